@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use std::fmt::Debug;
+
+/// Source of the current time used for Session expiry, renewal and cleanup.
+///
+/// Set it with `SessionConfig::with_clock`. The default `SystemClock` reads the system
+/// time while `test_util::MockClock` lets tests move time forward without sleeping.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::Clock;
+/// use chrono::{DateTime, Utc};
+///
+/// #[derive(Debug)]
+/// struct NtpClock { .. }
+///
+/// impl Clock for NtpClock {
+///     fn now(&self) -> DateTime<Utc> { .. }
+/// }
+/// ```
+///
+pub trait Clock: Debug + Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// `Clock` reading the system time. This is the Default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
@@ -0,0 +1,81 @@
+use crate::SessionError;
+use base64::{engine::general_purpose, Engine as _};
+
+/// Prefixed to compressed Session data so loading can tell it apart from plain data.
+/// Json and Base64 data can never start with this so older rows still load.
+pub(crate) const MAGIC: &str = "zstd:";
+
+/// Compression level used by zstd when compressing Session data.
+///
+/// # Examples
+/// ```rust
+/// use axum_session::{CompressionLevel, SessionConfig};
+///
+/// let config = SessionConfig::default().with_compression(CompressionLevel::Default, 4096);
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionLevel {
+    /// Fastest compression with the lowest ratio.
+    Fastest,
+    /// zstd's default level which is a good balance between speed and ratio.
+    Default,
+    /// Best compression ratio at the cost of speed.
+    Best,
+    /// A specific zstd level from 1 to 22.
+    Level(i32),
+}
+
+impl CompressionLevel {
+    /// Returns the zstd level.
+    ///
+    pub fn level(&self) -> i32 {
+        match self {
+            CompressionLevel::Fastest => 1,
+            CompressionLevel::Default => zstd::DEFAULT_COMPRESSION_LEVEL,
+            CompressionLevel::Best => 19,
+            CompressionLevel::Level(level) => *level,
+        }
+    }
+}
+
+/// Compresses the Session data if it is larger than threshold.
+pub(crate) fn compress(
+    value: String,
+    level: CompressionLevel,
+    threshold: usize,
+) -> Result<String, SessionError> {
+    if value.len() <= threshold {
+        return Ok(value);
+    }
+
+    let compressed = zstd::encode_all(value.as_bytes(), level.level())?;
+    let mut data = MAGIC.to_owned();
+    general_purpose::STANDARD.encode_string(&compressed, &mut data);
+
+    tracing::debug!(
+        "Session data compressed from {} to {} bytes",
+        value.len(),
+        data.len()
+    );
+
+    Ok(data)
+}
+
+/// Decompresses the Session data if it was compressed otherwise returns it as is.
+pub(crate) fn decompress(value: String) -> Result<String, SessionError> {
+    if let Some(data) = value.strip_prefix(MAGIC) {
+        let compressed = general_purpose::STANDARD.decode(data)?;
+        let data = String::from_utf8(zstd::decode_all(compressed.as_slice())?)?;
+
+        tracing::debug!(
+            "Session data decompressed from {} to {} bytes",
+            value.len(),
+            data.len()
+        );
+
+        Ok(data)
+    } else {
+        Ok(value)
+    }
+}
@@ -1,6 +1,51 @@
-use chrono::Duration;
+#[cfg(feature = "zstd")]
+use crate::CompressionLevel;
+#[cfg(not(feature = "rest_mode"))]
+use crate::HostWarning;
+use crate::{Clock, ConfigError, KeyProvider, SessionError, SessionSerializer, SystemClock};
+use base64::{engine::general_purpose, Engine as _};
+#[cfg(not(feature = "rest_mode"))]
+use chrono::Datelike;
+use chrono::{DateTime, Duration, Utc};
 pub use cookie::{Key, SameSite};
-use std::borrow::Cow;
+use hkdf::Hkdf;
+#[cfg(feature = "rest_mode")]
+use http::header::HeaderName;
+use http::HeaderMap;
+#[cfg(not(feature = "rest_mode"))]
+use http::Uri;
+use rand::RngCore;
+use sha2::Sha256;
+use std::{borrow::Cow, collections::HashMap, path::PathBuf, sync::Arc};
+use uuid::Uuid;
+
+/// Length in bytes of a cookie `Key`.
+const KEY_LEN: usize = 64;
+
+/// Minimum length in bytes of the master secret used by `SessionConfig::with_derived_key`.
+const MIN_SECRET_LEN: usize = 32;
+
+/// Largest rest_mode Header in bytes. Most servers and proxies reject Headers over 8KB
+/// in total so this leaves plenty of room for the app's own Headers.
+#[cfg(feature = "rest_mode")]
+pub(crate) const MAX_HEADER_LEN: usize = 4096;
+
+/// Function used to generate new Session ID's.
+pub type IdGenerator = Arc<dyn Fn() -> String + Send + Sync>;
+
+/// Function used to validate Session ID's given by the client.
+pub type IdValidator = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// Function used to migrate a Session value that failed to deserialize. It is given the
+/// key, the Session's schema version and the stored value and returns the migrated value.
+pub type DataMigrator = Arc<dyn Fn(&str, u32, String) -> Option<String> + Send + Sync>;
+
+/// Function used to upgrade a loaded Session's stored values by one schema version. It is
+/// given the Session's values, keyed like the Session, and the version they are at.
+pub type SchemaMigrator = Arc<dyn Fn(&mut HashMap<String, String>, u32) + Send + Sync>;
+
+/// Function used to pick the Cookie Domain from the Request Headers.
+pub type DomainResolver = Arc<dyn Fn(&HeaderMap) -> Option<String> + Send + Sync>;
 
 /// Mode at which the Session will function As.
 ///
@@ -70,6 +115,266 @@ impl SecurityMode {
     }
 }
 
+/// Which Cookie attributes are used to tell the browser when the Cookie expires.
+///
+/// # Examples
+/// ```rust
+/// use axum_session::{ExpiryAttr, SessionConfig};
+///
+/// let config = SessionConfig::default().with_cookie_expiry_attr(ExpiryAttr::Both);
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpiryAttr {
+    /// Emits only the `Max-Age` attribute.
+    MaxAge,
+    /// Emits only the `Expires` attribute.
+    Expires,
+    /// Emits both `Max-Age` and `Expires` for clients that only support one of them.
+    Both,
+}
+
+/// How Cookie values are encoded when read from and written to the Cookie Headers.
+///
+/// # Examples
+/// ```rust
+/// use axum_session::{CookieEncoding, SessionConfig};
+///
+/// let config = SessionConfig::default().with_cookie_encoding(CookieEncoding::Raw);
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CookieEncoding {
+    /// Values are percent-encoded when set and percent-decoded when read.
+    Encoded,
+    /// Values are set and read as is. Useful behind gateways that decode Cookies themselves.
+    Raw,
+}
+
+/// Prefix added to the front of Cookie names to have browsers enforce Cookie attributes.
+///
+/// # Examples
+/// ```rust
+/// use axum_session::{CookiePrefix, SessionConfig};
+///
+/// let config = SessionConfig::default()
+///     .with_secure(true)
+///     .with_cookie_prefix(CookiePrefix::Secure);
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CookiePrefix {
+    /// Cookie names are used as is.
+    #[default]
+    None,
+    /// `__Host-` Cookies must be secure, use the path `/` and have no domain so they are
+    /// not shared with sub domains.
+    Host,
+    /// `__Secure-` Cookies must be secure but can set a domain to be shared across sub domains.
+    Secure,
+}
+
+impl CookiePrefix {
+    /// Returns the prefix added to the Cookie names.
+    ///
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CookiePrefix::None => "",
+            CookiePrefix::Host => "__Host-",
+            CookiePrefix::Secure => "__Secure-",
+        }
+    }
+}
+
+/// Built in kinds of Session ID's set with `SessionConfig::with_id_kind`.
+///
+/// # Examples
+/// ```rust
+/// use axum_session::{IdKind, SessionConfig};
+///
+/// let config = SessionConfig::default().with_id_kind(IdKind::Random256);
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdKind {
+    /// UUIDv4 strings holding 122 random bits.
+    #[default]
+    Uuid,
+    /// 256 random bits encoded as unpadded base64url, which is 43 URL safe characters.
+    Random256,
+}
+
+impl IdKind {
+    /// Returns the function generating ID's of this kind.
+    ///
+    pub fn generator(&self) -> IdGenerator {
+        match self {
+            IdKind::Uuid => Arc::new(|| Uuid::new_v4().to_string()),
+            IdKind::Random256 => Arc::new(|| {
+                let mut bytes = [0u8; 32];
+                rand::thread_rng().fill_bytes(&mut bytes);
+                general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+            }),
+        }
+    }
+
+    /// Returns the function validating ID's of this kind given by the client.
+    ///
+    pub fn validator(&self) -> IdValidator {
+        match self {
+            IdKind::Uuid => Arc::new(|id| Uuid::parse_str(id).is_ok()),
+            IdKind::Random256 => Arc::new(|id| {
+                id.len() == 43
+                    && general_purpose::URL_SAFE_NO_PAD
+                        .decode(id)
+                        .is_ok_and(|bytes| bytes.len() == 32)
+            }),
+        }
+    }
+}
+
+/// AEAD Cipher used to encrypt the Header values when `rest_mode` is enabled.
+///
+/// # Examples
+/// ```rust
+/// use axum_session::{CipherSuite, SessionConfig};
+///
+/// let config = SessionConfig::default().with_cipher(CipherSuite::ChaCha20Poly1305);
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CipherSuite {
+    /// AES-256 in Galois/Counter Mode. Fastest on hardware with AES acceleration.
+    Aes256Gcm,
+    /// ChaCha20-Poly1305. Faster than AES on hardware without AES acceleration.
+    ChaCha20Poly1305,
+    /// XChaCha20-Poly1305. Uses a 24 byte nonce so random nonces will not collide.
+    XChaCha20Poly1305,
+}
+
+impl CipherSuite {
+    /// Returns the length of the Nonce in bytes for the Cipher.
+    ///
+    pub fn nonce_len(&self) -> usize {
+        match self {
+            CipherSuite::Aes256Gcm | CipherSuite::ChaCha20Poly1305 => 12,
+            CipherSuite::XChaCha20Poly1305 => 24,
+        }
+    }
+
+    /// Returns the length of the Authentication Tag in bytes for the Cipher.
+    ///
+    pub fn tag_len(&self) -> usize {
+        16
+    }
+}
+
+/// How database calls are retried when they fail with a transient error, like a timeout
+/// or a dropped connection. See `SessionError::is_transient`.
+///
+/// Each retry waits twice as long as the one before it, starting at base_delay.
+/// With jitter each wait is randomly shortened by up to half so clients retrying
+/// together do not hit the database at the same time.
+///
+/// # Examples
+/// ```rust
+/// use axum_session::{RetryPolicy, SessionConfig};
+/// use chrono::Duration;
+///
+/// let config = SessionConfig::default()
+///     .with_retry(Some(RetryPolicy::new(3, Duration::milliseconds(50))));
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// How many times a call is attempted in total, including the first attempt.
+    pub max_attempts: u32,
+    /// How long to wait before the first retry.
+    pub base_delay: Duration,
+    /// Randomly shortens each wait by up to half. Default is false.
+    pub jitter: bool,
+}
+
+impl RetryPolicy {
+    /// Creates a RetryPolicy making up to max_attempts attempts in total.
+    ///
+    pub fn new(max_attempts: u32, base_delay: Duration) -> Self {
+        Self {
+            max_attempts,
+            base_delay,
+            jitter: false,
+        }
+    }
+
+    /// Set's if each wait is randomly shortened by up to half.
+    ///
+    #[must_use]
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Returns how long to wait after the given failed attempt, starting at 1.
+    ///
+    pub(crate) fn delay(&self, attempt: u32) -> std::time::Duration {
+        let base = self.base_delay.to_std().unwrap_or_default();
+        let delay = base.saturating_mul(1 << attempt.saturating_sub(1).min(16));
+
+        if self.jitter {
+            let half = delay / 2;
+            half + half.mul_f64(rand::random::<f64>())
+        } else {
+            delay
+        }
+    }
+}
+
+/// The final Cookie or Header names built whenever a name or the Cookie prefix changes
+/// so they are not rebuilt on every Request.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SessionNames {
+    pub(crate) store: String,
+    pub(crate) session: String,
+    pub(crate) key: String,
+    #[cfg(not(feature = "rest_mode"))]
+    pub(crate) bootstrap: String,
+    /// Parsed store, session and key Header names. None if the name is not a legal Header name.
+    #[cfg(feature = "rest_mode")]
+    pub(crate) store_header: Option<HeaderName>,
+    #[cfg(feature = "rest_mode")]
+    pub(crate) session_header: Option<HeaderName>,
+    #[cfg(feature = "rest_mode")]
+    pub(crate) key_header: Option<HeaderName>,
+}
+
+impl SessionNames {
+    fn new(config: &SessionConfig) -> Self {
+        // Prefixes are only used by Cookies.
+        #[cfg(not(feature = "rest_mode"))]
+        let prefix = config.cookie_prefix.as_str();
+        #[cfg(feature = "rest_mode")]
+        let prefix = "";
+
+        let store = format!("{}{}", prefix, config.store_name);
+        let session = format!("{}{}", prefix, config.session_name);
+        let key = format!("{}{}", prefix, config.key_name);
+
+        Self {
+            #[cfg(feature = "rest_mode")]
+            store_header: HeaderName::from_bytes(store.as_bytes()).ok(),
+            #[cfg(feature = "rest_mode")]
+            session_header: HeaderName::from_bytes(session.as_bytes()).ok(),
+            #[cfg(feature = "rest_mode")]
+            key_header: HeaderName::from_bytes(key.as_bytes()).ok(),
+            #[cfg(not(feature = "rest_mode"))]
+            bootstrap: format!("{}{}", prefix, config.bootstrap_name),
+            store,
+            session,
+            key,
+        }
+    }
+}
+
 /// Configuration for how the Session and Cookies are used.
 ///
 /// # Examples
@@ -88,19 +393,35 @@ pub struct SessionConfig {
     pub(crate) session_name: Cow<'static, str>,
     /// Session key Cookie or Header name.
     pub(crate) key_name: Cow<'static, str>,
+    /// Lax bootstrap Cookie name. Only used when bootstrap_lifespan is set.
+    pub(crate) bootstrap_name: Cow<'static, str>,
+    /// How long the Lax bootstrap Cookie lives when cookie_same_site is Strict. Default is None to not use it.
+    pub(crate) bootstrap_lifespan: Option<Duration>,
     /// Session cookie domain.
     pub(crate) cookie_domain: Option<Cow<'static, str>>,
+    /// Hosts allowed to use the Session shared through cookie_domain. Default is empty to allow any host.
+    pub(crate) domain_list: Vec<Cow<'static, str>>,
+    /// Picks the Cookie Domain per Request over cookie_domain. Default is None.
+    pub(crate) domain_resolver: Option<DomainResolver>,
     /// Session cookie http only flag.
     pub(crate) cookie_http_only: bool,
     /// Session cookie max age None means the browser deletes cookie on close.
     /// Please make sure the Duration is longer than max_lifespan.
     pub(crate) cookie_max_age: Option<Duration>,
+    /// Which attributes are emitted from cookie_max_age. Default is `ExpiryAttr::Expires`.
+    pub(crate) cookie_expiry_attr: ExpiryAttr,
+    /// How Cookie values are encoded. Default is `CookieEncoding::Encoded`.
+    pub(crate) cookie_encoding: CookieEncoding,
     /// Session cookie path.
     pub(crate) cookie_path: Cow<'static, str>,
     /// Resticts how Cookies are sent cross-site. Default is `SameSite::Lax`.
     pub(crate) cookie_same_site: SameSite,
+    /// Ignores the Session Cookie on cross-site Requests that can change state. Default is false.
+    pub(crate) lax_get_only: bool,
     /// Session cookie secure flag.
     pub(crate) cookie_secure: bool,
+    /// Sets the secure flag per Request from if it arrived over TLS, ignoring cookie_secure. Default is false.
+    pub(crate) secure_auto: bool,
     /// Disables the need to avoid session saving.
     pub(crate) session_mode: SessionMode,
     /// Sessions the minimal lifespan a session can live in the database before expiring.
@@ -118,10 +439,24 @@ pub struct SessionConfig {
     /// this works fine since the data can stay in the database till its needed
     /// if not yet expired.
     pub(crate) memory_lifespan: Duration,
+    /// Keeps Sessions in memory between Requests. Default is true.
+    pub(crate) memory: bool,
     /// Session Database table name default is async_sessions
     pub(crate) table_name: Cow<'static, str>,
+    /// Creates the Session table if it does not exist within `SessionStore::new`. Default is true.
+    pub(crate) auto_migrate: bool,
+    /// Destroys Sessions whose stored data can not be read instead of reusing their ID. Default is false.
+    pub(crate) destroy_on_error: bool,
+    /// Saves Sessions and sends their Cookies or Headers on 5xx Responses. Default is true.
+    pub(crate) persist_on_error: bool,
+    /// Serves Sessions from memory while the database is failing. Default is false.
+    pub(crate) fallback_to_memory: bool,
+    /// File memory only stores write their Sessions to and how often. Default is None.
+    pub(crate) snapshot: Option<(PathBuf, Duration)>,
     /// Encyption Key used to encypt cookies for confidentiality, integrity, and authenticity.
     pub(crate) key: Option<Key>,
+    /// Rotating Encyption Keys used over key when set. Default is None.
+    pub(crate) key_provider: Option<Arc<dyn KeyProvider>>,
     /// Encyption Key used to encypt keys stored in the database for confidentiality.
     pub(crate) database_key: Option<Key>,
     /// Set how Secure you want SessionID's to be stored as.
@@ -139,42 +474,152 @@ pub struct SessionConfig {
     pub(crate) use_bloom_filters: bool,
     /// This is to be used when your handling multiple Parallel Sessions to prevent the next one from unloaded data.
     pub(crate) clear_check_on_load: bool,
-    /// This is used to append __Host- to the front of all Cookie names to prevent sub domain usage.
-    /// This will not append to Headers only Cookies. It is enabled by default.
-    pub(crate) prefix_with_host: bool,
+    /// Prefix appended to the front of all Cookie names. `__Host-` prevents sub domain usage.
+    /// This will not append to Headers only Cookies. Default is `CookiePrefix::None`.
+    pub(crate) cookie_prefix: CookiePrefix,
+    /// The AEAD Cipher used to encrypt Header values in rest_mode. Default is `CipherSuite::Aes256Gcm`.
+    pub(crate) cipher: CipherSuite,
+    /// Generates new Session ID's. Default is a UUIDv4 string.
+    pub(crate) id_generator: IdGenerator,
+    /// Validates Session ID's given by the client before they are used. Default checks for a UUID.
+    pub(crate) id_validator: IdValidator,
+    /// Migrates Session values that fail to deserialize. Default is None.
+    pub(crate) data_migrator: Option<DataMigrator>,
+    /// Upgrades loaded Sessions one schema version each, in order. Default is empty.
+    pub(crate) schema_migrators: Vec<SchemaMigrator>,
+    /// Format used to Serialize Session values and Sessions for database storage. Default is Json.
+    pub(crate) serializer: SessionSerializer,
+    /// Maximum duration a database call can take before it fails with `SessionError::Timeout`.
+    /// Default is None for no timeout.
+    pub(crate) db_timeout: Option<Duration>,
+    /// Destroys Sessions that were not accessed for this long. Default is None to not use it.
+    pub(crate) idle_timeout: Option<Duration>,
+    /// Renews Session ID's once they are this old. Default is None to not use it.
+    pub(crate) id_renewal_interval: Option<Duration>,
+    /// How long a Session ID renewed by the interval still leads to its Session. Default is 30 seconds.
+    pub(crate) id_renewal_grace: Duration,
+    /// Source of the current time. Default is `SystemClock`.
+    pub(crate) clock: Arc<dyn Clock>,
+    /// How long the database count from `SessionStore::count` is cached. Default is None to not cache.
+    pub(crate) count_cache_ttl: Option<Duration>,
+    /// How database calls are retried on transient errors. Default is None to not retry.
+    pub(crate) retry: Option<RetryPolicy>,
+    /// Maximum number of Sessions kept in memory. Default is None for no limit.
+    pub(crate) memory_limit: Option<usize>,
+    /// Number of shards the in memory Sessions are split into. Default is None for
+    /// DashMap's default, which scales with the number of CPUs.
+    pub(crate) memory_shards: Option<usize>,
+    /// Maximum approximate size of a Session's data in bytes. Default is None for no limit.
+    pub(crate) max_session_size: Option<usize>,
+    /// Maximum length of lists built with `Session::push`. Default is 100.
+    pub(crate) max_list_len: usize,
+    /// Only warns about an invalid config instead of failing `SessionStore::new`. Default is false.
+    pub(crate) lenient_validation: bool,
+    /// Responds with 401 to Requests with a Session ID that fails to decrypt or validate
+    /// instead of creating a new Session. Default is false.
+    pub(crate) reject_invalid_tokens: bool,
+    /// Accepts rest_mode store Headers that are not bound to the Session ID. Default is true.
+    pub(crate) accept_unbound_tokens: bool,
+    /// Postgres channel used to tell other app nodes to evict stored or destroyed Sessions
+    /// from memory. Default is None.
+    #[cfg(any(feature = "postgres-rustls", feature = "postgres-native"))]
+    pub(crate) invalidation_channel: Option<Cow<'static, str>>,
+    /// Compresses the Session data with the level if it is larger than the threshold in bytes.
+    #[cfg(feature = "zstd")]
+    pub(crate) compression: Option<(CompressionLevel, usize)>,
+    /// Encrypts the Session data with the config's Key before it is stored in the database.
+    /// Default is false.
+    pub(crate) encrypt_at_rest: bool,
+    /// Stores the Session to the database on a spawned task once the handler has returned
+    /// instead of before the Response is sent. Default is false.
+    pub(crate) deferred_save: bool,
+    /// Names with the Cookie prefix applied. Rebuilt by `refresh_names`.
+    pub(crate) names: SessionNames,
 }
 
 impl std::fmt::Debug for SessionConfig {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("SessionConfig")
+        let mut debug = f.debug_struct("SessionConfig");
+        debug
             .field("store_name", &self.store_name)
             .field("key_name", &self.key_name)
+            .field("bootstrap_name", &self.bootstrap_name)
+            .field("bootstrap_lifespan", &self.bootstrap_lifespan)
             .field("cookie_domain", &self.cookie_domain)
+            .field("domain_list", &self.domain_list)
+            .field(
+                "domain_resolver",
+                &self.domain_resolver.as_ref().map(|_| "fn hidden"),
+            )
             .field("cookie_http_only", &self.cookie_http_only)
             .field("cookie_max_age", &self.cookie_max_age)
+            .field("cookie_expiry_attr", &self.cookie_expiry_attr)
+            .field("cookie_encoding", &self.cookie_encoding)
             .field("session_name", &self.session_name)
             .field("cookie_path", &self.cookie_path)
             .field("cookie_same_site", &self.cookie_same_site)
+            .field("lax_get_only", &self.lax_get_only)
             .field("cookie_secure", &self.cookie_secure)
+            .field("secure_auto", &self.secure_auto)
             .field("session_mode", &self.session_mode)
             .field("lifespan", &self.lifespan)
             .field("max_lifespan", &self.max_lifespan)
             .field("memory_lifespan", &self.memory_lifespan)
+            .field("memory", &self.memory)
             .field("table_name", &self.table_name)
+            .field("auto_migrate", &self.auto_migrate)
+            .field("destroy_on_error", &self.destroy_on_error)
+            .field("persist_on_error", &self.persist_on_error)
+            .field("fallback_to_memory", &self.fallback_to_memory)
+            .field("snapshot", &self.snapshot)
             .field("security mode", &self.security_mode)
             .field("filter_expected_elements", &self.filter_expected_elements)
             .field("use_bloom_filters", &self.use_bloom_filters)
             .field("purge_update", &self.purge_update)
             .field("purge_database_update", &self.use_bloom_filters)
             .field("clear_check_on_load", &self.clear_check_on_load)
-            .field("prefix_with_host", &self.prefix_with_host)
+            .field("cookie_prefix", &self.cookie_prefix)
+            .field("cipher", &self.cipher)
+            .field("id_generator", &"fn hidden")
+            .field("id_validator", &"fn hidden")
+            .field(
+                "data_migrator",
+                &self.data_migrator.as_ref().map(|_| "fn hidden"),
+            )
+            .field("schema_migrators", &self.schema_migrators.len())
+            .field("serializer", &self.serializer)
+            .field("db_timeout", &self.db_timeout)
+            .field("idle_timeout", &self.idle_timeout)
+            .field("id_renewal_interval", &self.id_renewal_interval)
+            .field("id_renewal_grace", &self.id_renewal_grace)
+            .field("clock", &self.clock)
+            .field("count_cache_ttl", &self.count_cache_ttl)
+            .field("retry", &self.retry)
+            .field("memory_limit", &self.memory_limit)
+            .field("memory_shards", &self.memory_shards)
+            .field("max_session_size", &self.max_session_size)
+            .field("max_list_len", &self.max_list_len)
+            .field("lenient_validation", &self.lenient_validation)
+            .field("reject_invalid_tokens", &self.reject_invalid_tokens)
+            .field("accept_unbound_tokens", &self.accept_unbound_tokens)
             .field(
                 "filter_false_positive_probability",
                 &self.filter_false_positive_probability,
             )
             .field("key", &"key hidden")
-            .field("database_key", &"key hidden")
-            .finish()
+            .field("key_provider", &self.key_provider)
+            .field("database_key", &"key hidden");
+
+        #[cfg(any(feature = "postgres-rustls", feature = "postgres-native"))]
+        debug.field("invalidation_channel", &self.invalidation_channel);
+
+        #[cfg(feature = "zstd")]
+        debug.field("compression", &self.compression);
+
+        debug
+            .field("encrypt_at_rest", &self.encrypt_at_rest)
+            .field("deferred_save", &self.deferred_save);
+        debug.finish()
     }
 }
 
@@ -198,6 +643,7 @@ impl SessionConfig {
     #[must_use]
     pub fn with_store_name(mut self, name: impl Into<Cow<'static, str>>) -> Self {
         self.store_name = name.into();
+        self.refresh_names();
         self
     }
 
@@ -216,6 +662,143 @@ impl SessionConfig {
         self
     }
 
+    /// Set's the hosts that share the Session through the cookie domain.
+    ///
+    /// Setting a cookie domain sends the Cookies to every sub domain of it, including ones
+    /// run by other apps. With a domain list only Requests whose `Host` is in the list get
+    /// to use the Session. Requests from any other host have their Cookies ignored and
+    /// get a Session that is never stored, like cross-site Requests with
+    /// `with_cookie_same_site_lax_get`. Each host must be the cookie domain or a sub domain
+    /// of it. Ports are ignored.
+    ///
+    /// # Recipe
+    /// To share one login between `app.example.com` and `api.example.com`:
+    /// - Set the cookie domain to the registrable domain the hosts have in common, here
+    ///   `example.com`. Public suffixes like `com` or `co.uk` are rejected by `validate`.
+    /// - List every host that should see the Session. Leave out hosts running code you
+    ///   do not trust, though they are still sent the Cookies by browsers.
+    /// - Keep `CookiePrefix::None` or use `CookiePrefix::Secure`. `CookiePrefix::Host`
+    ///   Cookies can not have a Domain so they can never be shared.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default()
+    ///     .with_cookie_domain("example.com")
+    ///     .with_domain_list(["app.example.com", "api.example.com"]);
+    ///
+    /// assert!(config.validate().is_ok());
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_domain_list<I, S>(mut self, hosts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<Cow<'static, str>>,
+    {
+        self.domain_list = hosts.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Checks the Request's host is allowed by the domain list.
+    #[cfg(not(feature = "rest_mode"))]
+    pub(crate) fn allows_host(&self, uri: &Uri, headers: &HeaderMap) -> bool {
+        if self.domain_list.is_empty() {
+            return true;
+        }
+
+        // HTTP/2 Requests carry the host in the URI authority instead of a Host Header.
+        let host = headers
+            .get(http::header::HOST)
+            .and_then(|host| host.to_str().ok())
+            .or_else(|| uri.authority().map(|authority| authority.as_str()));
+
+        let Some(host) = host else {
+            return false;
+        };
+
+        let host = strip_port(host).trim_end_matches('.');
+
+        self.domain_list
+            .iter()
+            .any(|allowed| allowed.trim_end_matches('.').eq_ignore_ascii_case(host))
+    }
+
+    /// Set's the function used to pick the session's cookie's domain for each Request.
+    ///
+    /// The resolver is given the Request Headers so it can look at the `Host` to
+    /// serve multiple domains from one app. Returning None sets the Cookies without
+    /// a Domain. Removal Cookies use the same resolved domain so browsers delete them.
+    /// This is used over `with_cookie_domain` and can not be used with `CookiePrefix::Host`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use std::sync::Arc;
+    ///
+    /// let config = SessionConfig::default().with_domain_resolver(Arc::new(|headers| {
+    ///     let host = headers.get("host")?.to_str().ok()?;
+    ///     let host = host.split(':').next()?;
+    ///
+    ///     ["customer-a.com", "customer-b.io"]
+    ///         .into_iter()
+    ///         .find(|domain| host == *domain || host.ends_with(&format!(".{domain}")))
+    ///         .map(str::to_owned)
+    /// }));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_domain_resolver(mut self, resolver: DomainResolver) -> Self {
+        self.domain_resolver = Some(resolver);
+        self
+    }
+
+    /// Gets the Cookie Domain for the Request using the domain resolver if one is set.
+    #[cfg(not(feature = "rest_mode"))]
+    pub(crate) fn resolve_domain(&self, headers: &HeaderMap) -> Option<Cow<'static, str>> {
+        match &self.domain_resolver {
+            Some(resolver) => resolver(headers).map(Cow::Owned),
+            None => self.cookie_domain.clone(),
+        }
+    }
+
+    /// Gets the key to encrypt with, taken from the key provider if one is set.
+    pub(crate) fn current_key(&self) -> Option<Key> {
+        match &self.key_provider {
+            Some(provider) => Some(provider.current()),
+            None => self.key.clone(),
+        }
+    }
+
+    /// Gets the key to try when the current key fails to decrypt.
+    pub(crate) fn previous_key(&self) -> Option<Key> {
+        self.key_provider
+            .as_ref()
+            .and_then(|provider| provider.previous())
+    }
+
+    /// Returns if Sessions are removed from memory once no Request is using them.
+    #[inline]
+    pub(crate) fn unload_after_request(&self) -> bool {
+        !self.memory || self.memory_lifespan.is_zero()
+    }
+
+    /// Gets the Cookie secure flag for the Request, checking if it arrived over TLS when secure_auto is set.
+    #[cfg(not(feature = "rest_mode"))]
+    pub(crate) fn resolve_secure(&self, uri: &Uri, headers: &HeaderMap) -> bool {
+        if self.secure_auto {
+            crate::headers::is_tls_request(uri, headers)
+        } else {
+            self.cookie_secure
+        }
+    }
+
+    /// Rebuilds the final Cookie or Header names after a name or the Cookie prefix changed.
+    fn refresh_names(&mut self) {
+        self.names = SessionNames::new(self);
+    }
+
     /// Set's the session's Cookie or Header name.
     ///
     /// # Examples
@@ -228,6 +811,7 @@ impl SessionConfig {
     #[must_use]
     pub fn with_session_name(mut self, name: impl Into<Cow<'static, str>>) -> Self {
         self.session_name = name.into();
+        self.refresh_names();
         self
     }
 
@@ -243,6 +827,55 @@ impl SessionConfig {
     #[must_use]
     pub fn with_key_name(mut self, name: impl Into<Cow<'static, str>>) -> Self {
         self.key_name = name.into();
+        self.refresh_names();
+        self
+    }
+
+    /// Set's the session, store, key and bootstrap Cookie or Header names at once using a shared prefix.
+    ///
+    /// The names become `{prefix}_id`, `{prefix}_store`, `{prefix}_key` and `{prefix}_lax`. This helps prevent
+    /// collisions when running multiple apps behind one hostname.
+    ///
+    /// # Panics
+    /// Panics if the prefix is empty or is not a valid cookie token, such as one
+    /// containing `;`, `=` or whitespace.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_name_prefix("my_app");
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_name_prefix(mut self, prefix: &str) -> Self {
+        assert!(
+            is_cookie_token(prefix),
+            "Session name prefix {:?} is not a valid cookie token",
+            prefix
+        );
+
+        self.session_name = format!("{}_id", prefix).into();
+        self.store_name = format!("{}_store", prefix).into();
+        self.key_name = format!("{}_key", prefix).into();
+        self.bootstrap_name = format!("{}_lax", prefix).into();
+        self.refresh_names();
+        self
+    }
+
+    /// Set's the name of the Lax bootstrap Cookie used by `with_lax_bootstrap`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_bootstrap_name("my_lax_cookie");
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_bootstrap_name(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.bootstrap_name = name.into();
+        self.refresh_names();
         self
     }
 
@@ -280,6 +913,67 @@ impl SessionConfig {
         self
     }
 
+    /// Set's the session Cookies to `SameSite::Lax` so they are sent on top level GET navigations
+    /// from other sites, while the layer ignores them on cross-site Requests using any other method.
+    ///
+    /// SameSite applies per Cookie not per method, so the layer checks each Request itself. A Request
+    /// is cross-site if its `Sec-Fetch-Site` Header is `cross-site`, or without it, if its `Origin`
+    /// Header does not match its `Host`. GET, HEAD, OPTIONS and TRACE Requests are always allowed.
+    /// Other cross-site Requests get an empty Session that is not stored and no Cookies are set, so
+    /// the client's real Session is left untouched. Same-site Requests work as normal.
+    ///
+    /// Clients that send neither Header can not be told apart and are allowed, in which case only
+    /// the browser's own Lax handling applies. State changing GET routes are not protected, and
+    /// sibling subdomains count as same-site for `Sec-Fetch-Site`, so keep using CSRF tokens.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_cookie_same_site_lax_get(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_cookie_same_site_lax_get(mut self, enable: bool) -> Self {
+        self.lax_get_only = enable;
+
+        if enable {
+            self.cookie_same_site = SameSite::Lax;
+        }
+
+        self
+    }
+
+    /// Set's how long a short lived `SameSite=Lax` bootstrap Cookie lives alongside a `SameSite::Strict`
+    /// session Cookie. None disables it, which is the default.
+    ///
+    /// Strict Cookies are not sent on cross-site top level navigations, like the redirect back from an
+    /// external login provider, so the Session looks missing on that request. When enabled, a bootstrap
+    /// Cookie holding the same Session ID is set whenever the Session ID is created or renewed and the
+    /// Session is loaded from it when the Strict Cookie is missing.
+    ///
+    /// This only applies to Cookies when cookie_same_site is `SameSite::Strict`. While the bootstrap
+    /// Cookie lives cross-site navigations act like `SameSite::Lax`, so keep the lifespan just long
+    /// enough for the login round trip. If the window is not a concern simply using `SameSite::Lax`
+    /// is simpler. Calling `session.renew()` after login issues a fresh bootstrap Cookie.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use chrono::Duration;
+    /// use cookie::SameSite;
+    ///
+    /// let config = SessionConfig::default()
+    ///     .with_cookie_same_site(SameSite::Strict)
+    ///     .with_lax_bootstrap(Some(Duration::minutes(5)));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_lax_bootstrap(mut self, lifespan: Option<Duration>) -> Self {
+        self.bootstrap_lifespan = lifespan;
+        self
+    }
+
     /// Set's whether the session Persistantly stores data or on stores if storable.
     ///
     /// # Examples
@@ -347,6 +1041,42 @@ impl SessionConfig {
         self
     }
 
+    /// Set's which Cookie attributes are emitted from the cookies max_age.
+    ///
+    /// Some older clients only support `Expires` while others only support `Max-Age`.
+    /// `ExpiryAttr::Both` works with both at the cost of a slightly larger header.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{ExpiryAttr, SessionConfig};
+    ///
+    /// let config = SessionConfig::default().with_cookie_expiry_attr(ExpiryAttr::MaxAge);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_cookie_expiry_attr(mut self, attr: ExpiryAttr) -> Self {
+        self.cookie_expiry_attr = attr;
+        self
+    }
+
+    /// Set's if Cookie values are percent-encoded when set and percent-decoded when read.
+    ///
+    /// Use `CookieEncoding::Raw` behind gateways or proxies that decode Cookies themselves
+    /// so values are not decoded twice.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{CookieEncoding, SessionConfig};
+    ///
+    /// let config = SessionConfig::default().with_cookie_encoding(CookieEncoding::Raw);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_cookie_encoding(mut self, encoding: CookieEncoding) -> Self {
+        self.cookie_encoding = encoding;
+        self
+    }
+
     /// Set's the session's long term lifetime (expiration time) within database storage.
     ///
     /// # Examples
@@ -384,6 +1114,30 @@ impl SessionConfig {
         self
     }
 
+    /// Set's if Sessions are kept in memory between Requests. Default is true.
+    ///
+    /// When disabled every Request loads its Session from the database into memory of its
+    /// own, which is dropped with the Request, for data that must not stay in the process.
+    /// The store's shared memory is never used, so Requests running at the same time on the
+    /// same Session each load a copy and the last one saved wins. Deferred saves are stored
+    /// before the Response instead so the next Request never loads older data, read replica
+    /// writes are not cached and `with_fallback_to_memory` can not keep Sessions that failed
+    /// to save. Sessions from `SessionStore::load_or_create` only live as long as the returned
+    /// `Session`. Without a database Sessions only last for a single Request.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_memory(false);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_memory(mut self, enable: bool) -> Self {
+        self.memory = enable;
+        self
+    }
+
     /// This value represents the offset duration for how often session purge for memory is ran.
     ///
     /// # Examples
@@ -450,25 +1204,196 @@ impl SessionConfig {
         self
     }
 
-    /// Set's the session's database table name.
+    /// Set's the session's secure flag per Request from if it arrived over TLS instead of
+    /// using `with_secure`. Useful when the same app serves plain HTTP locally and HTTPS in
+    /// production.
+    ///
+    /// A Request counts as TLS if its URI has the `https` scheme, or the first
+    /// `X-Forwarded-Proto` value or `Forwarded` proto is `https`. Only enable this behind a
+    /// proxy that overwrites those Headers. Otherwise a client can send them to make the
+    /// Cookies Secure, or leave them out to get Cookies without the flag.
+    ///
+    /// As the flag is not always set this can not be used with `CookiePrefix::Host`,
+    /// `CookiePrefix::Secure` or `SameSite::None`, which browsers reject without it.
     ///
     /// # Examples
     /// ```rust
     /// use axum_session::SessionConfig;
     ///
-    /// let config = SessionConfig::default().with_table_name("my_table");
+    /// let config = SessionConfig::default().with_secure_auto(true);
     /// ```
     ///
     #[must_use]
-    pub fn with_table_name(mut self, table_name: impl Into<Cow<'static, str>>) -> Self {
-        self.table_name = table_name.into();
+    pub fn with_secure_auto(mut self, enable: bool) -> Self {
+        self.secure_auto = enable;
         self
     }
 
-    /// Set's the session's cookie encyption key enabling private cookies.
+    /// Set's the session's database table name.
     ///
-    /// When Set it will enforce Private cookies across all Sessions.
-    /// If you use Key::generate() it will make a new key each server reboot.
+    /// Lets several apps share one database using their own tables, or a `schema.table`
+    /// name per tenant. Only letters, digits, `_`, `.` and `:` are allowed as the name is
+    /// placed within the SQL statements. Default is `"sessions"`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_table_name("my_table");
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_table_name(mut self, table_name: impl Into<Cow<'static, str>>) -> Self {
+        self.table_name = table_name.into();
+        self
+    }
+
+    /// Set's if `SessionStore::new` creates the Session table when it does not exist.
+    ///
+    /// Disable this when the database user is not allowed to create tables and call
+    /// `SessionStore::migrate` from a user that is, or create the table yourself.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_auto_migrate(false);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_auto_migrate(mut self, auto_migrate: bool) -> Self {
+        self.auto_migrate = auto_migrate;
+        self
+    }
+
+    /// Set's if Sessions whose stored data can not be read at all, like after a deploy that
+    /// changed the data format, are destroyed and replaced by a new Session with a new ID.
+    /// A warning is logged each time. When disabled the unreadable data is ignored and a new
+    /// Session is started under the same ID, overwriting it on the next save.
+    ///
+    /// Default is false so unreadable data is not hidden while debugging.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_destroy_on_error(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_destroy_on_error(mut self, enable: bool) -> Self {
+        self.destroy_on_error = enable;
+        self
+    }
+
+    /// Set's if Sessions are saved and their Cookies or Headers sent when the handler
+    /// returns a 5xx Response.
+    ///
+    /// When disabled a 5xx Response skips saving the Session and setting any Cookies or
+    /// Headers, and the Session in memory is put back to how it was when the request loaded
+    /// it, so a handler failing halfway can not leave half changed data behind. Changes made
+    /// at the same time by parallel requests on the same Session are discarded with it.
+    /// Sessions created by the failed request are not kept. 4xx Responses are saved as usual.
+    ///
+    /// Only the copy in memory is put back. Anything the handler already wrote to the
+    /// database, like with `Session::save`, `SessionStore::destroy_session` or
+    /// `SessionStore::clear_store`, is not undone.
+    ///
+    /// Default is true.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_persist_on_error(false);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_persist_on_error(mut self, enable: bool) -> Self {
+        self.persist_on_error = enable;
+        self
+    }
+
+    /// Set's if Sessions are served from memory while the database is failing instead of
+    /// failing the Request. Each failure logs a warning and `SessionStore::is_degraded`
+    /// returns true until a load or save succeeds again.
+    ///
+    /// When a Session fails to load the client is given a new memory only Session under a
+    /// new ID, so the stored one is never overwritten once the database recovers. Sessions
+    /// that fail to save stay in memory, even past their memory lifespan, and are saved by
+    /// their next Request once the database is back.
+    ///
+    /// This comes with consistency caveats:
+    /// - Memory only Sessions are not shared with other app nodes and are lost on restart.
+    /// - Users whose Session failed to load start over with an empty Session.
+    /// - Destroyed or renewed Sessions can stay in the database until they expire.
+    /// - Memory grows with the Sessions waiting to be saved during an outage, though
+    ///   `with_memory_limit` still unloads them.
+    ///
+    /// `SessionStore::new` still fails if the table can not be created.
+    /// Default is false.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_fallback_to_memory(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_fallback_to_memory(mut self, enable: bool) -> Self {
+        self.fallback_to_memory = enable;
+        self
+    }
+
+    /// Set's a file that memory only stores write their unexpired Sessions to every interval,
+    /// giving best effort persistence without a database. `SessionStore::new` loads the file
+    /// if it exists, dropping expired Sessions. Corrupt files or ones written by another
+    /// version are ignored with a warning so the app still starts.
+    ///
+    /// Snapshots are taken during Requests once the interval passed and are written to a
+    /// temporary file that replaces the snapshot so it is never left half written. Sessions
+    /// changed since the last snapshot are lost on a crash. Call `SessionStore::snapshot`
+    /// during a graceful shutdown to keep them. Per-Session keys are not included.
+    /// This is ignored when the store has a database client.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use chrono::Duration;
+    ///
+    /// let config = SessionConfig::default().with_snapshot("sessions.json", Duration::minutes(5));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_snapshot(mut self, path: impl Into<PathBuf>, interval: Duration) -> Self {
+        self.snapshot = Some((path.into(), interval));
+        self
+    }
+
+    /// Set's the Postgres channel used to keep the in memory Sessions of multiple app nodes
+    /// in sync. Each store, save and removal sends a `NOTIFY` on the channel and
+    /// `SessionStore::spawn_invalidation_listener` evicts the Session on the other nodes
+    /// so they load the latest copy from the database.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_invalidation_channel("session_invalidation");
+    /// ```
+    ///
+    #[cfg(any(feature = "postgres-rustls", feature = "postgres-native"))]
+    #[must_use]
+    pub fn with_invalidation_channel(mut self, channel: impl Into<Cow<'static, str>>) -> Self {
+        self.invalidation_channel = Some(channel.into());
+        self
+    }
+
+    /// Set's the session's cookie encyption key enabling private cookies.
+    ///
+    /// When Set it will enforce Private cookies across all Sessions.
+    /// If you use Key::generate() it will make a new key each server reboot.
     /// To prevent this make and save a key to a config file for long term usage.
     /// For Extra Security Regenerate the key every so many months to a year.
     /// A new key will invalidate all old Sessions so it be wise to run session_store.clear_store() on reboot.
@@ -488,6 +1413,101 @@ impl SessionConfig {
         self
     }
 
+    /// Set's the session's cookie encyption key from a base64 encoded string, like one stored
+    /// within an environment variable. See `with_key`.
+    ///
+    /// The decoded key must be 64 bytes. One can be made with `openssl rand -base64 64`.
+    ///
+    /// # Errors
+    /// - ['SessionError::Decode'] is returned if the string is not valid base64.
+    /// - ['SessionError::KeyTooShort'] is returned if the decoded key is shorter than 64 bytes.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default()
+    ///     .with_key_from_base64(&std::env::var("SESSION_KEY").unwrap())
+    ///     .unwrap();
+    /// ```
+    ///
+    pub fn with_key_from_base64(mut self, encoded: &str) -> Result<Self, SessionError> {
+        let bytes = general_purpose::STANDARD.decode(encoded.trim())?;
+
+        if bytes.len() < KEY_LEN {
+            return Err(SessionError::KeyTooShort(bytes.len(), KEY_LEN));
+        }
+
+        self.key = Some(Key::from(&bytes));
+        Ok(self)
+    }
+
+    /// Set's a provider the session's cookie encyption keys are taken from so they can rotate
+    /// while the app is running. It is used over `with_key` and also encrypts the database rows
+    /// when `with_encrypt_at_rest` is enabled. The `with_database_key` key does not rotate.
+    ///
+    /// Everything is written with the provider's current key while anything it fails to
+    /// decrypt is tried again with the previous key. The previous key must be kept for at
+    /// least the cookie max age or Session lifespan, whichever is longer, and for as long as
+    /// any database row is still encrypted with it. See `KeyProvider` for the overlap window.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{Key, KeyRing, SessionConfig};
+    /// use std::sync::Arc;
+    ///
+    /// let config = SessionConfig::default()
+    ///     .with_key_rotation_schedule(Arc::new(KeyRing::new(Key::generate())));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_key_rotation_schedule(mut self, provider: Arc<dyn KeyProvider>) -> Self {
+        self.key_provider = Some(provider);
+        self
+    }
+
+    /// Set's the session's cookie encyption key by deriving it from a master secret with HKDF-SHA256.
+    /// See `with_key`.
+    ///
+    /// The same secret and context always derive the same key so it survives restarts.
+    /// Use a different context to derive unrelated keys from one secret, like per app or
+    /// to rotate the key without changing the secret.
+    ///
+    /// # Errors
+    /// - ['SessionError::KeyTooShort'] is returned if the master secret is shorter than 32 bytes.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let secret = [7u8; 32];
+    /// let config = SessionConfig::default()
+    ///     .with_derived_key(&secret, "my_app session v1")
+    ///     .unwrap();
+    /// ```
+    ///
+    pub fn with_derived_key(
+        mut self,
+        master_secret: &[u8],
+        context: &str,
+    ) -> Result<Self, SessionError> {
+        if master_secret.len() < MIN_SECRET_LEN {
+            return Err(SessionError::KeyTooShort(
+                master_secret.len(),
+                MIN_SECRET_LEN,
+            ));
+        }
+
+        let mut key = [0u8; KEY_LEN];
+        // Expand can only fail when asking for more than 255 * 32 bytes.
+        Hkdf::<Sha256>::new(None, master_secret)
+            .expand(context.as_bytes(), &mut key)
+            .expect("HKDF output length is valid");
+
+        self.key = Some(Key::from(&key));
+        Ok(self)
+    }
+
     /// Set's the session's database encyption key for per session key storage.
     ///
     /// Must be Set to Some() in order to use Security::PerSession or will panic if not.
@@ -606,6 +1626,19 @@ impl SessionConfig {
         self.store_name.to_string()
     }
 
+    /// Get's the session's Lax bootstrap Cookie name
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let name = SessionConfig::default().get_bootstrap_name();
+    /// ```
+    ///
+    pub fn get_bootstrap_name(&self) -> String {
+        self.bootstrap_name.to_string()
+    }
+
     /// Set's the session's loading to either true: unload data if checks fail or false: bypass.
     ///
     /// # Examples
@@ -622,6 +1655,7 @@ impl SessionConfig {
     }
 
     /// Set's the session's prefix_with_host to either true: __Host- gets prefixed to the cookie names false: __Host- does not get prepended.
+    /// This is a shorthand for `with_cookie_prefix(CookiePrefix::Host)` or `CookiePrefix::None`.
     ///
     /// __Host- prefix: Cookies with names starting with __Host- must be set with the secure flag, must be from a secure page (HTTPS),
     /// must not have a domain specified (and therefore, are not sent to subdomains), and the path must be /.
@@ -635,51 +1669,1088 @@ impl SessionConfig {
     ///
     #[must_use]
     pub fn with_prefix_with_host(mut self, enable: bool) -> Self {
-        self.prefix_with_host = enable;
+        self.cookie_prefix = if enable {
+            CookiePrefix::Host
+        } else {
+            CookiePrefix::None
+        };
+        self.refresh_names();
         self
     }
-}
 
-impl Default for SessionConfig {
-    fn default() -> Self {
-        Self {
-            // Set to a 6 hour default in Database Session stores unloading.
-            lifespan: Duration::hours(6),
-            session_name: "session".into(),
-            cookie_path: "/".into(),
-            cookie_max_age: Some(Duration::days(100)),
-            cookie_http_only: true,
-            cookie_secure: false,
-            cookie_domain: None,
-            cookie_same_site: SameSite::Lax,
-            store_name: "store".into(),
-            table_name: "sessions".into(),
-            // Unload memory after 60 minutes if it has not been accessed.
-            memory_lifespan: Duration::minutes(60),
-            // Unload long term session after 60 days if it has not been accessed.
-            max_lifespan: Duration::days(60),
-            // Default to purge old sessions every 5 hours.
-            purge_update: Duration::hours(1),
-            // Default to purge old sessions in the database every 5 hours per request.
-            purge_database_update: Duration::hours(5),
-            always_save: false,
-            session_mode: SessionMode::Persistent,
-            // Key is set to None so Private cookies are not used by default. Please set this if you want to use private cookies.
-            key: None,
-            // Database key is set to None it will panic if you attempt to use SecurityMode::PerSession.
-            database_key: None,
-            // Default cookie name for the Key Id.
-            key_name: "session_key".into(),
-            // Simple is the Default mode for compatibilty with older versions of the crate.
-            security_mode: SecurityMode::Simple,
-            filter_expected_elements: 100_000,
-            // The probability of how many allowable false positives you want to have based on the expected elements.
-            // 0.01 is a good starting point.
-            filter_false_positive_probability: 0.01,
-            // Always set to on.
-            use_bloom_filters: true,
-            clear_check_on_load: true,
-            prefix_with_host: false,
-        }
+    /// Set's the prefix appended to the front of the Cookie names.
+    ///
+    /// `CookiePrefix::Secure` requires secure to be set. `CookiePrefix::Host` also requires no
+    /// domain and the path `/`. Use `CookiePrefix::Secure` to share the Session across sub domains.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{CookiePrefix, SessionConfig};
+    ///
+    /// let config = SessionConfig::default()
+    ///     .with_secure(true)
+    ///     .with_cookie_domain("example.com")
+    ///     .with_cookie_prefix(CookiePrefix::Secure);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_cookie_prefix(mut self, prefix: CookiePrefix) -> Self {
+        self.cookie_prefix = prefix;
+        self.refresh_names();
+        self
+    }
+
+    /// Set's the AEAD Cipher used to encrypt the Header values when `rest_mode` is enabled.
+    ///
+    /// Changing the Cipher will invalidate all Headers encrypted with the previous Cipher
+    /// and those clients will be given a new Session.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{CipherSuite, SessionConfig};
+    ///
+    /// let config = SessionConfig::default().with_cipher(CipherSuite::XChaCha20Poly1305);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_cipher(mut self, cipher: CipherSuite) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
+    /// Set's the kind of Session ID's generated and accepted from clients, replacing the
+    /// generator and validator. Default is `IdKind::Uuid`.
+    ///
+    /// Changing the kind makes existing Session ID's fail validation so those users get
+    /// new Sessions.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{IdKind, SessionConfig};
+    ///
+    /// let config = SessionConfig::default().with_id_kind(IdKind::Random256);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_id_kind(mut self, kind: IdKind) -> Self {
+        self.id_generator = kind.generator();
+        self.id_validator = kind.validator();
+        self
+    }
+
+    /// Set's the function used to generate new Session ID's.
+    ///
+    /// Session ID's are treated as opaque strings so this can be used to create
+    /// time sortable ID's, longer random tokens or prefixed ID's.
+    /// Make sure to also set a matching validator using `with_id_validator`
+    /// or the default UUID validator will reject the generated ID's.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use std::sync::Arc;
+    /// use uuid::Uuid;
+    ///
+    /// let config = SessionConfig::default()
+    ///     .with_id_generator(Arc::new(|| format!("sess_{}", Uuid::new_v4().simple())))
+    ///     .with_id_validator(Arc::new(|id| id.starts_with("sess_")));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_id_generator(mut self, generator: IdGenerator) -> Self {
+        self.id_generator = generator;
+        self
+    }
+
+    /// Set's the function used to validate Session ID's given by the client.
+    ///
+    /// ID's that fail validation are ignored and a new Session is created.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use std::sync::Arc;
+    ///
+    /// let config = SessionConfig::default().with_id_validator(Arc::new(|id| id.len() == 64));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_id_validator(mut self, validator: IdValidator) -> Self {
+        self.id_validator = validator;
+        self
+    }
+
+    /// Set's the Serializer used for Session values and for storing Sessions in the database.
+    ///
+    /// Sessions stored with a different Serializer will fail to load and get treated as empty.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{SessionConfig, SessionSerializer};
+    ///
+    /// let config = SessionConfig::default().with_serializer(SessionSerializer::Json);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_serializer(mut self, serializer: SessionSerializer) -> Self {
+        self.serializer = serializer;
+        self
+    }
+
+    /// Set's the function used to migrate Session values that fail to deserialize, like after
+    /// the shape of a stored struct changed, instead of `get` returning None.
+    ///
+    /// The migrator is given the key, the Session's schema version set with
+    /// `Session::set_schema_version` and the stored value. If it returns the migrated value
+    /// it is used and stored in place of the old one.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use std::sync::Arc;
+    ///
+    /// let config = SessionConfig::default().with_data_migrator(Arc::new(|key, version, value| {
+    ///     // Version 0 stored the user as just their ID.
+    ///     (key == "user" && version == 0).then(|| format!("{{\"id\":{}}}", value))
+    /// }));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_data_migrator(mut self, migrator: DataMigrator) -> Self {
+        self.data_migrator = Some(migrator);
+        self
+    }
+
+    /// Adds a function used to upgrade loaded Sessions to the next schema version so the
+    /// shape of stored values can change without logging everyone out.
+    ///
+    /// The first migrator added upgrades version 0 to 1, the second 1 to 2 and so on, making
+    /// the current schema version the number of migrators. When a Session with an older
+    /// version is loaded, every migrator from its version onward is run over its values in
+    /// order. The Session is then marked with the current version and saved. New Sessions
+    /// start at the current version. Sessions with a newer version, like ones written by a
+    /// newer app node, are left as is.
+    ///
+    /// Migrators should be idempotent. The migrated Session is only stored if the Request
+    /// saves it, so the same stored values can be migrated again on a later load. Values
+    /// set by code that already uses the new shape before the migrator runs, like on an app
+    /// node that was not updated yet, must also be left untouched. Values are stored in
+    /// the config's serializer format.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use std::{collections::HashMap, sync::Arc};
+    ///
+    /// let config = SessionConfig::default().with_schema_migrator(Arc::new(
+    ///     |data: &mut HashMap<String, String>, _version: u32| {
+    ///         // Version 1 renamed "uid" to "user_id".
+    ///         if let Some(uid) = data.remove("uid") {
+    ///             data.entry("user_id".to_owned()).or_insert(uid);
+    ///         }
+    ///     },
+    /// ));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_schema_migrator(mut self, migrator: SchemaMigrator) -> Self {
+        self.schema_migrators.push(migrator);
+        self
+    }
+
+    /// Set's the maximum duration a database call can take before it fails with `SessionError::Timeout`.
+    ///
+    /// When loading times out a new Session is used for the request. When saving times out
+    /// `Session::save` returns the error and the Response fails with a 504 Gateway Timeout.
+    /// Deferred saves and saves made after a streamed body only log it.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use chrono::Duration;
+    ///
+    /// let config = SessionConfig::default().with_db_timeout(Some(Duration::seconds(2)));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_db_timeout(mut self, time: Option<Duration>) -> Self {
+        self.db_timeout = time;
+        self
+    }
+
+    /// Set's how long a Session can go without being accessed before it is destroyed,
+    /// like 15 minutes for banking apps. None disables it.
+    ///
+    /// The idle time is checked against the Session's last_accessed when it is loaded and
+    /// an idle Session is destroyed before the handler runs, which gets a new Session instead.
+    /// This is separate from the lifespans and the Cookie's max age which still apply, so
+    /// whichever runs out first ends the Session. Requests using `PeekSession` do not reset it.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use chrono::Duration;
+    ///
+    /// let config = SessionConfig::default().with_idle_timeout(Some(Duration::minutes(15)));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Set's how old a Session ID can get before it is renewed, rotating ID's periodically
+    /// and not only on login like OWASP recommends.
+    ///
+    /// The ID's age is checked when the Session is loaded, counting from when it was created
+    /// or last renewed. An old ID is renewed once the Request is done the same way
+    /// `session.renew()` does, keeping the data, removing the old row and sending the new ID.
+    ///
+    /// Requests already sent with the old ID still lead to the Session for the
+    /// `with_id_renewal_grace` window and are given the new ID. This is only known to the
+    /// app node that renewed it. Manual renews have no grace window as they often guard a
+    /// login against Session fixation.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use chrono::Duration;
+    ///
+    /// let config = SessionConfig::default().with_id_renewal_interval(Duration::minutes(15));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_id_renewal_interval(mut self, interval: Duration) -> Self {
+        self.id_renewal_interval = Some(interval);
+        self
+    }
+
+    /// Set's how long a Session ID renewed by `with_id_renewal_interval` still leads to
+    /// its Session so Requests sent before the client got the new ID are not lost.
+    /// Zero disables it. Default is 30 seconds.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use chrono::Duration;
+    ///
+    /// let config = SessionConfig::default()
+    ///     .with_id_renewal_interval(Duration::minutes(15))
+    ///     .with_id_renewal_grace(Duration::seconds(10));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_id_renewal_grace(mut self, grace: Duration) -> Self {
+        self.id_renewal_grace = grace;
+        self
+    }
+
+    /// Set's the clock the current time is read from for Session expiry, renewal and
+    /// cleanup. Useful within tests with `test_util::MockClock` to move time forward without
+    /// sleeping. Default is `SystemClock`.
+    ///
+    /// Databases still check expiry against their own time when loading or cleaning up
+    /// Sessions, and background tasks still wait in real time.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{SessionConfig, SystemClock};
+    /// use std::sync::Arc;
+    ///
+    /// let config = SessionConfig::default().with_clock(Arc::new(SystemClock));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Gets the current time from the configs clock.
+    #[inline]
+    pub(crate) fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// Set's how long the database count returned by `SessionStore::count` and
+    /// `Session::count` is cached for, so frequently polled status pages do not run a
+    /// full count every time. None disables the cache.
+    ///
+    /// The cached count may lag behind the database by up to the TTL. It is refreshed by
+    /// the first call after it runs out and failed counts are not cached. Counts of
+    /// Sessions in memory without a database are always exact.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use chrono::Duration;
+    ///
+    /// let config = SessionConfig::default().with_count_cache_ttl(Some(Duration::seconds(10)));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_count_cache_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.count_cache_ttl = ttl;
+        self
+    }
+
+    /// Set's how database calls are retried when they fail with a transient error like a
+    /// timeout or dropped connection. None disables retries, which is the default.
+    ///
+    /// Retries let a Request survive short network blips but add the backoff delays to
+    /// the Request's latency, so keep max_attempts and base_delay small. Errors caused by
+    /// the data itself, like failing to decode, are never retried. Once every attempt has
+    /// failed the last error is returned. Each attempt gets its own `db_timeout`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{RetryPolicy, SessionConfig};
+    /// use chrono::Duration;
+    ///
+    /// let config = SessionConfig::default()
+    ///     .with_retry(Some(RetryPolicy::new(3, Duration::milliseconds(50))));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_retry(mut self, policy: Option<RetryPolicy>) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Set's the maximum number of Sessions that are kept in memory.
+    ///
+    /// When the limit is exceeded the least recently accessed Sessions that are not
+    /// within a request get unloaded from memory until it is back down to 90% of the
+    /// limit, so the work is done in batches. Persistent Sessions will reload from
+    /// the database on their next request. When using `SessionNullPool` the unloaded
+    /// Sessions are gone for good.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_memory_limit(100_000);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_memory_limit(mut self, max_entries: usize) -> Self {
+        self.memory_limit = Some(max_entries);
+        self
+    }
+
+    /// Set's the number of shards the in memory Sessions are split into. Each shard has its
+    /// own lock so more shards lower contention on many cores while fewer save memory on
+    /// small instances. Must be a power of two greater than 1.
+    /// Default is None which uses 4 shards per CPU rounded up to a power of two.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_memory_shards(512);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_memory_shards(mut self, shards: usize) -> Self {
+        self.memory_shards = Some(shards);
+        self
+    }
+
+    /// Set's the maximum approximate size in bytes a Session's data can grow to.
+    ///
+    /// The size is the length of all the keys plus their serialized values.
+    /// `Session::try_set` returns `SessionError::SizeLimitExceeded` and leaves the Session
+    /// unchanged when the limit would be exceeded. Sessions loaded from the database
+    /// that are larger than the limit are treated as corrupt and are not loaded.
+    /// Use `Session::data_len` to see how large a Session currently is.
+    ///
+    /// Only the Session ID is sent within Cookies or Headers so the limit protects memory and
+    /// the database rather than the 4KB browser Cookie limit. Something around 64KB suits most
+    /// apps. Lower it if many Sessions are kept in memory or the database column is small.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_max_session_size(64 * 1024);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_max_session_size(mut self, bytes: usize) -> Self {
+        self.max_session_size = Some(bytes);
+        self
+    }
+
+    /// Set's the maximum length of the lists built with `Session::push`.
+    /// Pushing onto a full list drops its oldest values so lists like recently viewed
+    /// items can not grow forever. Default is 100.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_max_list_len(20);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_max_list_len(mut self, len: usize) -> Self {
+        self.max_list_len = len;
+        self
+    }
+
+    /// Set's the Session data to be compressed with zstd before being stored in the database
+    /// when the serialized Session is larger than threshold in bytes.
+    ///
+    /// Session data that was stored uncompressed will still load.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{CompressionLevel, SessionConfig};
+    ///
+    /// let config = SessionConfig::default().with_compression(CompressionLevel::Default, 4096);
+    /// ```
+    ///
+    #[cfg(feature = "zstd")]
+    #[must_use]
+    pub fn with_compression(mut self, level: CompressionLevel, threshold: usize) -> Self {
+        self.compression = Some((level, threshold));
+        self
+    }
+
+    /// Set's the Session data to be encrypted with the config's Key and Cipher before being
+    /// stored in the database so it stays private if the database is breached. The Session ID
+    /// is left as is so it can still be looked up. Requires a Key set with `with_key`.
+    ///
+    /// Session data that was stored unencrypted will still load and is encrypted the next time it
+    /// is stored. Changing the Key makes the stored Sessions fail to decrypt so they are treated
+    /// as new Sessions, which logs the users out. There is no fallback Key to decrypt them with,
+    /// so rotate the Key when logging everyone out is acceptable.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{Key, SessionConfig};
+    ///
+    /// let config = SessionConfig::default()
+    ///     .with_key(Key::generate())
+    ///     .with_encrypt_at_rest(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_encrypt_at_rest(mut self, encrypt: bool) -> Self {
+        self.encrypt_at_rest = encrypt;
+        self
+    }
+
+    /// Set's the Session to be stored to the database on a spawned task after the handler
+    /// returns so database latency is not added to the time before the Response is sent.
+    /// The Cookies or Headers are still set on the Response as usual.
+    ///
+    /// This trades durability for latency. If the app crashes or is shut down before the
+    /// task finishes, the last changes to the Session are lost. A Request that loads the
+    /// Session from the database before the task finishes, such as on another app node or
+    /// when memory_lifespan is zero, can also see the previous data. Only a limited number
+    /// of saves run at once and any over that limit are stored before the Response is sent.
+    /// Saves of the same Session run one at a time in Request order, skipping any a newer
+    /// save replaced while they waited. Failed saves are logged.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_deferred_save(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_deferred_save(mut self, enable: bool) -> Self {
+        self.deferred_save = enable;
+        self
+    }
+
+    /// Checks the config for combinations that would produce Cookies or Headers browsers reject.
+    /// This is called by `SessionStore::new`.
+    ///
+    /// # Errors
+    /// - ['ConfigError::InvalidName'] is returned if a Cookie or Header name is empty or not a valid token.
+    ///   In rest_mode it is also returned if a name can not be used as a Header name.
+    /// - ['ConfigError::InvalidDuration'] is returned if a lifespan is zero or negative.
+    /// - ['ConfigError::InvalidPath'] is returned if the cookie path does not start with `/` or has a `;`.
+    /// - ['ConfigError::HostPrefix'] is returned if `CookiePrefix::Host` is used with a domain, a path other than `/` or without secure.
+    /// - ['ConfigError::PublicSuffixDomain'] is returned if the cookie domain is a listed public suffix like `co.uk`.
+    /// - ['ConfigError::DomainListWithoutDomain'] is returned if a domain list is set without a cookie domain.
+    /// - ['ConfigError::HostOutsideDomain'] is returned if a host in the domain list is not within the cookie domain.
+    /// - ['ConfigError::SecurePrefix'] is returned if `CookiePrefix::Secure` is used without secure.
+    /// - ['ConfigError::SameSiteNoneInsecure'] is returned if `SameSite::None` is used without secure.
+    /// - ['ConfigError::InvalidRetryAttempts'] is returned if a RetryPolicy makes no attempts.
+    /// - ['ConfigError::InvalidMaxListLen'] is returned if max_list_len is 0.
+    /// - ['ConfigError::LaxGetNotLax'] is returned if `with_cookie_same_site_lax_get` is used without `SameSite::Lax`.
+    /// - ['ConfigError::MaxAgeTooLarge'] is returned if cookie_max_age can not be represented by `Expires`.
+    /// - ['ConfigError::EncryptAtRestWithoutKey'] is returned if `encrypt_at_rest` is set without a Key.
+    /// - ['ConfigError::HeaderTooLarge'] is returned in rest_mode if a generated Session ID makes a Header too large.
+    /// - ['ConfigError::InvalidHeaderValue'] is returned in rest_mode if a generated Session ID can not be sent unencrypted.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// assert!(SessionConfig::default().validate().is_ok());
+    /// ```
+    ///
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for name in [
+            &self.session_name,
+            &self.store_name,
+            &self.key_name,
+            &self.bootstrap_name,
+        ] {
+            if !is_cookie_token(name) {
+                return Err(ConfigError::InvalidName(name.to_string()));
+            }
+        }
+
+        // Catch names that would otherwise only fail once a Response Header is set.
+        #[cfg(feature = "rest_mode")]
+        for (name, header) in [
+            (&self.names.session, &self.names.session_header),
+            (&self.names.store, &self.names.store_header),
+            (&self.names.key, &self.names.key_header),
+        ] {
+            if header.is_none() {
+                return Err(ConfigError::InvalidName(name.clone()));
+            }
+        }
+
+        for (name, duration) in [
+            ("lifespan", Some(self.lifespan)),
+            ("max_lifespan", Some(self.max_lifespan)),
+            ("bootstrap_lifespan", self.bootstrap_lifespan),
+            ("cookie_max_age", self.cookie_max_age),
+            ("idle_timeout", self.idle_timeout),
+            ("id_renewal_interval", self.id_renewal_interval),
+            ("count_cache_ttl", self.count_cache_ttl),
+        ] {
+            if duration.is_some_and(|duration| duration <= Duration::zero()) {
+                return Err(ConfigError::InvalidDuration(name));
+            }
+        }
+
+        if let Some(policy) = self.retry {
+            if policy.max_attempts == 0 {
+                return Err(ConfigError::InvalidRetryAttempts);
+            }
+
+            if policy.base_delay < Duration::zero() {
+                return Err(ConfigError::InvalidDuration("retry base_delay"));
+            }
+        }
+
+        if !is_table_name(&self.table_name) {
+            return Err(ConfigError::InvalidTableName(self.table_name.to_string()));
+        }
+
+        // Zero is valid and means Sessions are not kept in memory between requests.
+        if self.memory_lifespan < Duration::zero() {
+            return Err(ConfigError::InvalidDuration("memory_lifespan"));
+        }
+
+        // Zero is valid and means renewed Session ID's stop working right away.
+        if self.id_renewal_grace < Duration::zero() {
+            return Err(ConfigError::InvalidDuration("id_renewal_grace"));
+        }
+
+        if let Some(shards) = self
+            .memory_shards
+            .filter(|shards| !is_shard_amount(*shards))
+        {
+            return Err(ConfigError::InvalidMemoryShards(shards));
+        }
+
+        if self.max_list_len == 0 {
+            return Err(ConfigError::InvalidMaxListLen);
+        }
+
+        if self.encrypt_at_rest && self.current_key().is_none() {
+            return Err(ConfigError::EncryptAtRestWithoutKey);
+        }
+
+        #[cfg(feature = "rest_mode")]
+        {
+            // Headers hold an ID either as is or encrypted and base64 encoded,
+            // so a generated ID shows how large they will get.
+            let id = (self.id_generator)();
+
+            let value_len = if self.current_key().is_some() {
+                let sealed = self.cipher.nonce_len() + id.len() + self.cipher.tag_len();
+                sealed.div_ceil(3) * 4
+            } else {
+                if http::HeaderValue::from_str(&id).is_err() {
+                    return Err(ConfigError::InvalidHeaderValue(id));
+                }
+
+                id.len()
+            };
+
+            for name in [&self.session_name, &self.key_name] {
+                if name.len() + value_len > MAX_HEADER_LEN {
+                    return Err(ConfigError::HeaderTooLarge(
+                        name.to_string(),
+                        name.len() + value_len,
+                        MAX_HEADER_LEN,
+                    ));
+                }
+            }
+        }
+
+        #[cfg(not(feature = "rest_mode"))]
+        {
+            if !self.cookie_path.starts_with('/')
+                || self
+                    .cookie_path
+                    .contains(|c: char| c == ';' || c.is_control())
+            {
+                return Err(ConfigError::InvalidPath(self.cookie_path.to_string()));
+            }
+
+            // secure_auto leaves the flag off for Requests that did not arrive over TLS.
+            let secure = self.cookie_secure && !self.secure_auto;
+
+            match self.cookie_prefix {
+                CookiePrefix::Host
+                    if self.cookie_domain.is_some()
+                        || self.domain_resolver.is_some()
+                        || self.cookie_path != "/"
+                        || !secure =>
+                {
+                    return Err(ConfigError::HostPrefix);
+                }
+                CookiePrefix::Secure if !secure => {
+                    return Err(ConfigError::SecurePrefix);
+                }
+                _ => {}
+            }
+
+            if let Some(domain) = &self.cookie_domain {
+                let domain = normalize_domain(domain);
+
+                // Only listed suffixes are rejected. Single labels, like `localhost` or an intranet
+                // name, are left to the browser and warned about by validate_against_host.
+                if domain.contains('.')
+                    && domain.parse::<std::net::IpAddr>().is_err()
+                    && registrable_domain(&domain).is_none()
+                {
+                    return Err(ConfigError::PublicSuffixDomain(domain));
+                }
+
+                for host in &self.domain_list {
+                    let host = normalize_domain(host);
+
+                    if host != domain && !host.ends_with(&format!(".{}", domain)) {
+                        return Err(ConfigError::HostOutsideDomain(host, domain));
+                    }
+                }
+            } else if !self.domain_list.is_empty() {
+                return Err(ConfigError::DomainListWithoutDomain);
+            }
+
+            if self.cookie_same_site == SameSite::None && !secure {
+                return Err(ConfigError::SameSiteNoneInsecure);
+            }
+
+            if self.lax_get_only && self.cookie_same_site != SameSite::Lax {
+                return Err(ConfigError::LaxGetNotLax);
+            }
+
+            if let Some(max_age) = self.cookie_max_age {
+                // Expires dates can only go up to the year 9999.
+                if self
+                    .now()
+                    .checked_add_signed(max_age)
+                    .filter(|expires| expires.year() <= 9999)
+                    .is_none()
+                {
+                    return Err(ConfigError::MaxAgeTooLarge);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the config like `validate` but only logs a warning if it is invalid.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// SessionConfig::default().validate_lenient();
+    /// ```
+    ///
+    pub fn validate_lenient(&self) {
+        if let Err(err) = self.validate() {
+            tracing::warn!("Session config is invalid: {}", err);
+        }
+    }
+
+    /// Checks the Cookie domain against the host the app is served from, like
+    /// `app.example.com`, for setups that let apps on the same parent domain mix up
+    /// their Sessions. The host may include a port. The domain resolver is given a
+    /// `Host` Header with the host if one is set.
+    ///
+    /// Returns a `HostWarning` for each problem found, which is none for host only Cookies:
+    /// - ['HostWarning::BroadDomain'] if the domain is a parent of the host, so the Cookies are
+    ///   shared with sibling apps, unless the Cookies use `CookiePrefix::Host`.
+    /// - ['HostWarning::DomainMismatch'] if the domain does not match the host at all.
+    /// - ['HostWarning::TopLevelDomain'] if the domain has a single label like `com`.
+    /// - ['HostWarning::PublicSuffix'] if the domain is a public suffix like `co.uk`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{HostWarning, SessionConfig};
+    ///
+    /// let config = SessionConfig::default().with_cookie_domain("example.com");
+    ///
+    /// for warning in config.validate_against_host("app.example.com") {
+    ///     eprintln!("{}", warning);
+    /// }
+    ///
+    /// assert!(matches!(
+    ///     config.validate_against_host("app.example.com")[..],
+    ///     [HostWarning::BroadDomain { .. }]
+    /// ));
+    /// ```
+    ///
+    #[cfg(not(feature = "rest_mode"))]
+    pub fn validate_against_host(&self, host: &str) -> Vec<HostWarning> {
+        let mut headers = HeaderMap::new();
+
+        if let Ok(value) = http::HeaderValue::from_str(host) {
+            headers.insert(http::header::HOST, value);
+        }
+
+        let Some(domain) = self.resolve_domain(&headers) else {
+            return Vec::new();
+        };
+
+        let domain = normalize_domain(&domain);
+        let host = strip_port(host).trim_end_matches('.').to_ascii_lowercase();
+        let mut warnings = Vec::new();
+
+        if domain == host {
+            return warnings;
+        }
+
+        // IP addresses only match themselves so they never have parent domains.
+        if host.parse::<std::net::IpAddr>().is_ok() {
+            warnings.push(HostWarning::DomainMismatch { domain, host });
+        } else if !domain.contains('.') {
+            warnings.push(HostWarning::TopLevelDomain(domain));
+        } else if registrable_domain(&domain).is_none() {
+            warnings.push(HostWarning::PublicSuffix(domain));
+        } else if host.ends_with(&format!(".{}", domain)) {
+            if self.cookie_prefix != CookiePrefix::Host {
+                warnings.push(HostWarning::BroadDomain { domain, host });
+            }
+        } else {
+            warnings.push(HostWarning::DomainMismatch { domain, host });
+        }
+
+        warnings
+    }
+
+    /// Set's `SessionStore::new` to only warn about an invalid config instead of failing.
+    /// See `validate`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_lenient_validation(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_lenient_validation(mut self, enable: bool) -> Self {
+        self.lenient_validation = enable;
+        self
+    }
+
+    /// Set's the Session layer to respond with `401 Unauthorized` when a Request sends a Session
+    /// ID that fails to decrypt or validate instead of starting a new Session. Useful for APIs
+    /// where a new anonymous Session is never wanted, so tampered tokens or a changed Key are
+    /// not mistaken for first time visitors. The handler is not called.
+    ///
+    /// In Cookie mode the 401 also removes the Session Cookies so the client can start over.
+    /// In rest_mode the client must drop its Session Headers itself. With
+    /// `SecurityMode::PerSession` a token is also rejected once its Per-Session Key expired.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_reject_invalid_tokens(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_reject_invalid_tokens(mut self, reject: bool) -> Self {
+        self.reject_invalid_tokens = reject;
+        self
+    }
+
+    /// Set's if rest_mode store Headers sealed before they were bound to the Session ID are
+    /// still accepted. Store Headers are now encrypted with the Session ID as associated data
+    /// so one can not be replayed on another Session. Older clients may still send unbound
+    /// Headers, so they are accepted by default until they had a chance to get new ones.
+    /// Set this to false to only accept bound store Headers.
+    ///
+    /// The key Header can not be bound as it is needed to decrypt the Session ID, which is
+    /// already bound to it with `SecurityMode::PerSession`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_accept_unbound_tokens(false);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_accept_unbound_tokens(mut self, accept: bool) -> Self {
+        self.accept_unbound_tokens = accept;
+        self
+    }
+}
+
+/// Removes the port from a host along with the brackets of IPv6 addresses.
+#[cfg(not(feature = "rest_mode"))]
+fn strip_port(host: &str) -> &str {
+    if let Some(rest) = host.strip_prefix('[') {
+        return rest.split_once(']').map_or(host, |(address, _)| address);
+    }
+
+    match host.rsplit_once(':') {
+        // More than one colon is an IPv6 address without brackets.
+        Some((name, port)) if !name.contains(':') && port.bytes().all(|c| c.is_ascii_digit()) => {
+            name
+        }
+        _ => host,
+    }
+}
+
+/// Multi label public suffixes, like `co.uk`, that sites are registered under.
+/// This is a short list of the common ones and not the full Public Suffix List.
+/// Every single label domain is treated as a public suffix.
+#[cfg(not(feature = "rest_mode"))]
+const PUBLIC_SUFFIXES: &[&str] = &[
+    "ac.uk",
+    "co.uk",
+    "gov.uk",
+    "ltd.uk",
+    "me.uk",
+    "net.uk",
+    "org.uk",
+    "plc.uk",
+    "com.au",
+    "net.au",
+    "org.au",
+    "co.nz",
+    "net.nz",
+    "org.nz",
+    "co.jp",
+    "ne.jp",
+    "or.jp",
+    "co.kr",
+    "co.in",
+    "co.za",
+    "com.ar",
+    "com.br",
+    "com.cn",
+    "com.hk",
+    "com.mx",
+    "com.sg",
+    "com.tr",
+    "com.tw",
+    "appspot.com",
+    "azurewebsites.net",
+    "cloudfront.net",
+    "firebaseapp.com",
+    "fly.dev",
+    "github.io",
+    "gitlab.io",
+    "herokuapp.com",
+    "netlify.app",
+    "onrender.com",
+    "pages.dev",
+    "vercel.app",
+    "web.app",
+    "workers.dev",
+];
+
+/// Lowercases a domain and removes its leading and trailing dots.
+#[cfg(not(feature = "rest_mode"))]
+fn normalize_domain(domain: &str) -> String {
+    domain
+        .trim_start_matches('.')
+        .trim_end_matches('.')
+        .to_ascii_lowercase()
+}
+
+/// Gets the public suffix of a normalized domain, like `co.uk` for `app.example.co.uk`.
+#[cfg(not(feature = "rest_mode"))]
+pub(crate) fn public_suffix(domain: &str) -> &str {
+    let suffix = std::iter::once(domain)
+        .chain(
+            domain
+                .match_indices('.')
+                .map(|(index, _)| &domain[index + 1..]),
+        )
+        .find(|suffix| PUBLIC_SUFFIXES.contains(suffix));
+
+    match suffix {
+        Some(suffix) => suffix,
+        None => domain.rsplit('.').next().unwrap_or(domain),
+    }
+}
+
+/// Gets the registrable domain of a normalized domain, which is its public suffix and
+/// one more label, like `example.co.uk` for `app.example.co.uk`. This is the broadest
+/// domain a Cookie can be scoped to. None is returned if the domain is a public suffix.
+#[cfg(not(feature = "rest_mode"))]
+pub(crate) fn registrable_domain(domain: &str) -> Option<&str> {
+    let suffix = public_suffix(domain);
+    let rest = domain.strip_suffix(suffix)?.strip_suffix('.')?;
+    let start = rest.rfind('.').map_or(0, |index| index + 1);
+
+    Some(&domain[start..])
+}
+
+/// Checks the shard amount is one DashMap accepts.
+pub(crate) fn is_shard_amount(shards: usize) -> bool {
+    shards > 1 && shards.is_power_of_two()
+}
+
+/// Checks the name is a valid RFC 6265 cookie token.
+pub(crate) fn is_cookie_token(name: &str) -> bool {
+    !name.is_empty()
+        && name.bytes().all(|c| {
+            c.is_ascii_graphic()
+                && !matches!(
+                    c,
+                    b'(' | b')'
+                        | b'<'
+                        | b'>'
+                        | b'@'
+                        | b','
+                        | b';'
+                        | b':'
+                        | b'\\'
+                        | b'"'
+                        | b'/'
+                        | b'['
+                        | b']'
+                        | b'?'
+                        | b'='
+                        | b'{'
+                        | b'}'
+                )
+        })
+}
+
+/// Checks the table name can be placed within the pools' statements and keys.
+/// `.` allows schema qualified names and `:` and `-` Redis or MongoDB prefixes like
+/// `my-app:sessions`. Empty is used by Redis for no key prefix. SQL pools also reject `-`
+/// through `DatabasePool::accepts_table_name`.
+pub(crate) fn is_table_name(name: &str) -> bool {
+    name.bytes()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, b'_' | b'.' | b':' | b'-'))
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        let mut config = Self {
+            // Set to a 6 hour default in Database Session stores unloading.
+            lifespan: Duration::hours(6),
+            session_name: "session".into(),
+            cookie_path: "/".into(),
+            cookie_max_age: Some(Duration::days(100)),
+            cookie_expiry_attr: ExpiryAttr::Expires,
+            cookie_encoding: CookieEncoding::Encoded,
+            cookie_http_only: true,
+            cookie_secure: false,
+            secure_auto: false,
+            cookie_domain: None,
+            domain_list: Vec::new(),
+            domain_resolver: None,
+            cookie_same_site: SameSite::Lax,
+            lax_get_only: false,
+            store_name: "store".into(),
+            table_name: "sessions".into(),
+            auto_migrate: true,
+            destroy_on_error: false,
+            persist_on_error: true,
+            fallback_to_memory: false,
+            snapshot: None,
+            // Unload memory after 60 minutes if it has not been accessed.
+            memory_lifespan: Duration::minutes(60),
+            memory: true,
+            // Unload long term session after 60 days if it has not been accessed.
+            max_lifespan: Duration::days(60),
+            // Default to purge old sessions every 5 hours.
+            purge_update: Duration::hours(1),
+            // Default to purge old sessions in the database every 5 hours per request.
+            purge_database_update: Duration::hours(5),
+            always_save: false,
+            session_mode: SessionMode::Persistent,
+            // Key is set to None so Private cookies are not used by default. Please set this if you want to use private cookies.
+            key: None,
+            key_provider: None,
+            // Database key is set to None it will panic if you attempt to use SecurityMode::PerSession.
+            database_key: None,
+            // Default cookie name for the Key Id.
+            key_name: "session_key".into(),
+            // Default cookie name for the Lax bootstrap cookie.
+            bootstrap_name: "session_lax".into(),
+            bootstrap_lifespan: None,
+            // Simple is the Default mode for compatibilty with older versions of the crate.
+            security_mode: SecurityMode::Simple,
+            filter_expected_elements: 100_000,
+            // The probability of how many allowable false positives you want to have based on the expected elements.
+            // 0.01 is a good starting point.
+            filter_false_positive_probability: 0.01,
+            // Always set to on.
+            use_bloom_filters: true,
+            clear_check_on_load: true,
+            cookie_prefix: CookiePrefix::None,
+            // AES is the Default for compatibilty with older versions of the crate.
+            cipher: CipherSuite::Aes256Gcm,
+            // UUIDv4 is the Default for compatibilty with older versions of the crate.
+            id_generator: IdKind::Uuid.generator(),
+            id_validator: IdKind::Uuid.validator(),
+            // Json is the Default for compatibilty with older versions of the crate.
+            data_migrator: None,
+            schema_migrators: Vec::new(),
+            serializer: SessionSerializer::Json,
+            db_timeout: None,
+            idle_timeout: None,
+            id_renewal_interval: None,
+            id_renewal_grace: Duration::seconds(30),
+            clock: Arc::new(SystemClock),
+            count_cache_ttl: None,
+            retry: None,
+            memory_limit: None,
+            memory_shards: None,
+            max_session_size: None,
+            max_list_len: 100,
+            lenient_validation: false,
+            reject_invalid_tokens: false,
+            accept_unbound_tokens: true,
+            #[cfg(any(feature = "postgres-rustls", feature = "postgres-native"))]
+            invalidation_channel: None,
+            #[cfg(feature = "zstd")]
+            compression: None,
+            encrypt_at_rest: false,
+            deferred_save: false,
+            names: SessionNames::default(),
+        };
+
+        config.refresh_names();
+        config
     }
 }
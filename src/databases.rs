@@ -36,9 +36,22 @@ mod surreal;
 #[cfg(feature = "surreal")]
 pub use self::surreal::*;
 
+#[cfg(feature = "sled")]
+mod sled;
+#[cfg(feature = "sled")]
+pub use self::sled::*;
+
+#[cfg(feature = "write-behind")]
+mod batching;
+#[cfg(feature = "write-behind")]
+pub use self::batching::*;
+
 mod any_db;
 pub use self::any_db::*;
 
+mod retry;
+pub use self::retry::*;
+
 mod database;
 mod null;
 
@@ -43,6 +43,10 @@ impl DatabasePool for SessionAnyPool {
         self.pool.count(table_name).await
     }
 
+    async fn count_active(&self, table_name: &str) -> Result<i64, SessionError> {
+        self.pool.count_active(table_name).await
+    }
+
     async fn store(
         &self,
         id: &str,
@@ -53,6 +57,14 @@ impl DatabasePool for SessionAnyPool {
         self.pool.store(id, session, expires, table_name).await
     }
 
+    async fn store_many(
+        &self,
+        sessions: &[(String, String, i64)],
+        table_name: &str,
+    ) -> Result<(), SessionError> {
+        self.pool.store_many(sessions, table_name).await
+    }
+
     async fn load(&self, id: &str, table_name: &str) -> Result<Option<String>, SessionError> {
         self.pool.load(id, table_name).await
     }
@@ -77,6 +89,14 @@ impl DatabasePool for SessionAnyPool {
         self.pool.get_ids(table_name).await
     }
 
+    async fn notify(&self, channel: &str, payload: &str) -> Result<(), SessionError> {
+        self.pool.notify(channel, payload).await
+    }
+
+    fn accepts_table_name(&self, table_name: &str) -> bool {
+        self.pool.accepts_table_name(table_name)
+    }
+
     fn auto_handles_expiry(&self) -> bool {
         self.pool.auto_handles_expiry()
     }
@@ -0,0 +1,313 @@
+use crate::{databases::DatabasePool, Session, SessionError, SessionStore};
+use async_trait::async_trait;
+use dashmap::DashMap;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{
+    sync::{mpsc, Mutex},
+    time::MissedTickBehavior,
+};
+
+///Batching Session Helper type for the DatabasePool.
+pub type SessionBatchingSession<P> = Session<BatchingPool<P>>;
+///Batching Session Store Helper type for the DatabasePool.
+pub type SessionBatchingSessionStore<P> = SessionStore<BatchingPool<P>>;
+
+/// A Session waiting to be stored keyed by its table name and ID.
+struct Pending {
+    session: String,
+    expires: i64,
+    /// Order the Session was queued in so a flush only clears what it stored.
+    seq: u64,
+}
+
+struct Shared<P> {
+    pool: P,
+    pending: DashMap<(String, String), Pending>,
+    seq: AtomicU64,
+    /// Held while storing or deleting so a delete can not be overwritten by a flush in progress.
+    flush_lock: Mutex<()>,
+}
+
+impl<P> Shared<P>
+where
+    P: DatabasePool + Send + Sync,
+{
+    async fn flush(&self) -> Result<(), SessionError> {
+        let _lock = self.flush_lock.lock().await;
+        let mut batches: HashMap<String, Vec<(String, String, i64)>> = HashMap::new();
+        let mut flushed = Vec::new();
+
+        for entry in self.pending.iter() {
+            let (table_name, id) = entry.key();
+            batches.entry(table_name.clone()).or_default().push((
+                id.clone(),
+                entry.session.clone(),
+                entry.expires,
+            ));
+            flushed.push((entry.key().clone(), entry.seq));
+        }
+
+        let mut result = Ok(());
+
+        for (table_name, sessions) in batches {
+            if let Err(err) = self.pool.store_many(&sessions, &table_name).await {
+                // Failed Sessions stay queued so they are retried on the next flush.
+                flushed.retain(|((table, _), _)| *table != table_name);
+                result = Err(err);
+            }
+        }
+
+        // Sessions queued again while storing are newer so they are kept.
+        for (key, seq) in flushed {
+            self.pending
+                .remove_if(&key, |_, pending| pending.seq == seq);
+        }
+
+        result
+    }
+}
+
+/// [BatchingPool] wraps a `DatabasePool` to queue Session stores and write them in batches
+/// using `DatabasePool::store_many`. Repeated stores to the same Session between flushes are
+/// coalesced keeping the newest so bursty traffic makes fewer database writes.
+///
+/// Queued Sessions are flushed every interval or as soon as max_batch Sessions are queued.
+/// Loads and exists check the queue first so a Session is never read back older than it
+/// was stored. Deletes remove the Session from the queue. Counts and `get_ids` flush first.
+///
+/// Stores return before the Session reaches the database so failed flushes are only logged
+/// and retried on the next flush. Anything still queued when the app crashes is lost. When the
+/// last clone of the pool is dropped the queue is flushed on a background task, but call
+/// `flush` during a graceful shutdown to be sure it finished before the runtime stops.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::{BatchingPool, SessionConfig, SessionPgPool, SessionStore};
+/// use std::time::Duration;
+///
+/// let pool = BatchingPool::new(SessionPgPool::from(pool), Duration::from_millis(250), 500);
+/// let session_store = SessionStore::new(Some(pool.clone()), SessionConfig::default()).await.unwrap();
+///
+/// // During shutdown.
+/// pool.flush().await.unwrap();
+/// ```
+///
+#[derive(Clone)]
+pub struct BatchingPool<P> {
+    shared: Arc<Shared<P>>,
+    max_batch: usize,
+    /// Wakes the flush task early. Once every clone is dropped the task flushes and stops.
+    trigger: mpsc::Sender<()>,
+}
+
+impl<P> BatchingPool<P>
+where
+    P: DatabasePool + Send + Sync + 'static,
+{
+    /// Creates a BatchingPool flushing every interval or once max_batch Sessions are queued.
+    ///
+    /// # Panics
+    /// Panics if called outside of a Tokio runtime or if interval is zero.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let pool = BatchingPool::new(pool, Duration::from_millis(250), 500);
+    /// ```
+    ///
+    pub fn new(pool: P, interval: Duration, max_batch: usize) -> Self {
+        let shared = Arc::new(Shared {
+            pool,
+            pending: DashMap::new(),
+            seq: AtomicU64::new(0),
+            flush_lock: Mutex::new(()),
+        });
+        let (trigger, mut receiver) = mpsc::channel(1);
+        let task_shared = shared.clone();
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        tokio::spawn(async move {
+            loop {
+                let open = tokio::select! {
+                    _ = ticker.tick() => true,
+                    message = receiver.recv() => message.is_some(),
+                };
+
+                if let Err(err) = task_shared.flush().await {
+                    tracing::error!("Batched Sessions failed to store: {}", err);
+                }
+
+                if !open {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            shared,
+            max_batch,
+            trigger,
+        }
+    }
+
+    /// Stores every queued Session now.
+    ///
+    /// # Errors
+    /// - ['SessionError'] is returned if the wrapped pool fails to store a batch. Those
+    ///   Sessions stay queued.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// pool.flush().await.unwrap();
+    /// ```
+    ///
+    pub async fn flush(&self) -> Result<(), SessionError> {
+        self.shared.flush().await
+    }
+
+    /// Returns how many Sessions are waiting to be stored.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let queued = pool.pending();
+    /// ```
+    ///
+    pub fn pending(&self) -> usize {
+        self.shared.pending.len()
+    }
+
+    fn get_pending(&self, id: &str, table_name: &str) -> Option<(String, i64)> {
+        self.shared
+            .pending
+            .get(&(table_name.to_owned(), id.to_owned()))
+            .map(|pending| (pending.session.clone(), pending.expires))
+    }
+}
+
+impl<P: Debug> Debug for BatchingPool<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BatchingPool")
+            .field("pool", &self.shared.pool)
+            .field("pending", &self.shared.pending.len())
+            .field("max_batch", &self.max_batch)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<P> DatabasePool for BatchingPool<P>
+where
+    P: DatabasePool + Send + Sync + 'static,
+{
+    async fn initiate(&self, table_name: &str) -> Result<(), SessionError> {
+        self.shared.pool.initiate(table_name).await
+    }
+
+    async fn count(&self, table_name: &str) -> Result<i64, SessionError> {
+        self.shared.flush().await?;
+        self.shared.pool.count(table_name).await
+    }
+
+    async fn count_active(&self, table_name: &str) -> Result<i64, SessionError> {
+        self.shared.flush().await?;
+        self.shared.pool.count_active(table_name).await
+    }
+
+    async fn store(
+        &self,
+        id: &str,
+        session: &str,
+        expires: i64,
+        table_name: &str,
+    ) -> Result<(), SessionError> {
+        let seq = self.shared.seq.fetch_add(1, Ordering::Relaxed);
+        self.shared.pending.insert(
+            (table_name.to_owned(), id.to_owned()),
+            Pending {
+                session: session.to_owned(),
+                expires,
+                seq,
+            },
+        );
+
+        if self.shared.pending.len() >= self.max_batch {
+            // A full channel means a flush is already on its way.
+            let _ = self.trigger.try_send(());
+        }
+
+        Ok(())
+    }
+
+    async fn store_many(
+        &self,
+        sessions: &[(String, String, i64)],
+        table_name: &str,
+    ) -> Result<(), SessionError> {
+        for (id, session, expires) in sessions {
+            self.store(id, session, *expires, table_name).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn load(&self, id: &str, table_name: &str) -> Result<Option<String>, SessionError> {
+        if let Some((session, _)) = self.get_pending(id, table_name) {
+            return Ok(Some(session));
+        }
+
+        self.shared.pool.load(id, table_name).await
+    }
+
+    async fn delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
+        let _lock = self.shared.flush_lock.lock().await;
+        self.shared
+            .pending
+            .remove(&(table_name.to_owned(), id.to_owned()));
+        self.shared.pool.delete_one_by_id(id, table_name).await
+    }
+
+    async fn exists(&self, id: &str, table_name: &str) -> Result<bool, SessionError> {
+        if let Some((_, expires)) = self.get_pending(id, table_name) {
+            return Ok(expires >= chrono::Utc::now().timestamp());
+        }
+
+        self.shared.pool.exists(id, table_name).await
+    }
+
+    async fn delete_by_expiry(&self, table_name: &str) -> Result<Vec<String>, SessionError> {
+        self.shared.pool.delete_by_expiry(table_name).await
+    }
+
+    async fn delete_all(&self, table_name: &str) -> Result<(), SessionError> {
+        let _lock = self.shared.flush_lock.lock().await;
+        self.shared
+            .pending
+            .retain(|(table, _), _| table != table_name);
+        self.shared.pool.delete_all(table_name).await
+    }
+
+    async fn get_ids(&self, table_name: &str) -> Result<Vec<String>, SessionError> {
+        self.shared.flush().await?;
+        self.shared.pool.get_ids(table_name).await
+    }
+
+    async fn notify(&self, channel: &str, payload: &str) -> Result<(), SessionError> {
+        self.shared.pool.notify(channel, payload).await
+    }
+
+    fn accepts_table_name(&self, table_name: &str) -> bool {
+        self.shared.pool.accepts_table_name(table_name)
+    }
+
+    fn auto_handles_expiry(&self) -> bool {
+        self.shared.pool.auto_handles_expiry()
+    }
+}
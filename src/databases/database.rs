@@ -14,6 +14,13 @@ pub trait DatabasePool {
     /// if an error occurs it should be propagated to the caller.
     async fn count(&self, table_name: &str) -> Result<i64, SessionError>;
 
+    /// This is called to receive the count of sessions that have not yet expired in the database
+    /// using the given table name. Defaults to `count` for databases that handle expiry themselves.
+    /// if an error occurs it should be propagated to the caller.
+    async fn count_active(&self, table_name: &str) -> Result<i64, SessionError> {
+        self.count(table_name).await
+    }
+
     /// This is called to store a session in the database using the given table name.
     /// The session is a string and should be stored in its own field.
     /// if an error occurs it should be propagated to the caller.
@@ -27,6 +34,22 @@ pub trait DatabasePool {
         table_name: &str,
     ) -> Result<(), SessionError>;
 
+    /// This is called to store many sessions at once in the database using the given table name.
+    /// Each session is given as its id, data and expires like `store`. Defaults to calling `store`
+    /// for each session, so databases that can write several rows in one statement should override it.
+    /// if an error occurs it should be propagated to the caller.
+    async fn store_many(
+        &self,
+        sessions: &[(String, String, i64)],
+        table_name: &str,
+    ) -> Result<(), SessionError> {
+        for (id, session, expires) in sessions {
+            self.store(id, session, *expires, table_name).await?;
+        }
+
+        Ok(())
+    }
+
     /// This is called to receive the session from the database using the given table name.
     /// if an error occurs it should be propagated to the caller.
     async fn load(&self, id: &str, table_name: &str) -> Result<Option<String>, SessionError>;
@@ -51,5 +74,21 @@ pub trait DatabasePool {
     /// if an error occurs it should be propagated to the caller.
     async fn get_ids(&self, table_name: &str) -> Result<Vec<String>, SessionError>;
 
+    /// This is called after a session was stored or deleted when an invalidation channel is set
+    /// so other app nodes can evict their in memory copy. Defaults to doing nothing for
+    /// databases that can not notify.
+    /// if an error occurs it should be propagated to the caller.
+    async fn notify(&self, _channel: &str, _payload: &str) -> Result<(), SessionError> {
+        Ok(())
+    }
+
+    /// This is called within `SessionStore::new` to check the table name can be used with the
+    /// database, on top of the characters `SessionConfig::validate` allows. Defaults to true.
+    /// Databases placing the table name unquoted within SQL statements should reject `-`
+    /// as `--` starts a comment.
+    fn accepts_table_name(&self, _table_name: &str) -> bool {
+        true
+    }
+
     fn auto_handles_expiry(&self) -> bool;
 }
@@ -94,6 +94,20 @@ impl DatabasePool for SessionMongoPool {
         })
     }
 
+    async fn count_active(&self, table_name: &str) -> Result<i64, SessionError> {
+        Ok(match &self.client.default_database() {
+            Some(db) => {
+                let filter = doc! {"expires":
+                    {"$gte": Utc::now().timestamp()}
+                };
+                db.collection::<MongoSessionData>(&table_name)
+                    .count_documents(filter, None)
+                    .await? as i64
+            }
+            None => 0,
+        })
+    }
+
     async fn store(
         &self,
         id: &str,
@@ -166,11 +180,17 @@ impl DatabasePool for SessionMongoPool {
 
     async fn exists(&self, id: &str, table_name: &str) -> Result<bool, SessionError> {
         Ok(match &self.client.default_database() {
-            Some(db) => db
-                .collection::<MongoSessionData>(&table_name)
-                .find_one(doc! {"id": id}, None)
-                .await?
-                .is_some(),
+            Some(db) => {
+                let filter = doc! {
+                    "id": id,
+                    "expires":
+                        {"$gte": Utc::now().timestamp()}
+                };
+                db.collection::<MongoSessionData>(&table_name)
+                    .find_one(filter, None)
+                    .await?
+                    .is_some()
+            }
             None => false,
         })
     }
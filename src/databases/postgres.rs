@@ -1,7 +1,7 @@
 use crate::{DatabasePool, Session, SessionError, SessionStore};
 use async_trait::async_trait;
 use chrono::Utc;
-use sqlx::{pool::Pool, PgPool, Postgres};
+use sqlx::{pool::Pool, postgres::PgListener, PgPool, Postgres};
 
 ///Postgres's Session Helper type for the DatabasePool.
 pub type SessionPgSession = Session<SessionPgPool>;
@@ -73,6 +73,21 @@ impl DatabasePool for SessionPgPool {
         return Ok(count);
     }
 
+    async fn count_active(&self, table_name: &str) -> Result<i64, SessionError> {
+        let (count,) = sqlx::query_as(
+            &r#"
+            SELECT COUNT(*) FROM %%TABLE_NAME%%
+            WHERE expires IS NULL OR expires > $1
+        "#
+            .replace("%%TABLE_NAME%%", table_name),
+        )
+        .bind(Utc::now().timestamp())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
     async fn store(
         &self,
         id: &str,
@@ -164,7 +179,65 @@ impl DatabasePool for SessionPgPool {
         Ok(result)
     }
 
+    async fn notify(&self, channel: &str, payload: &str) -> Result<(), SessionError> {
+        sqlx::query("SELECT pg_notify($1, $2)")
+            .bind(channel)
+            .bind(payload)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    fn accepts_table_name(&self, table_name: &str) -> bool {
+        !table_name.contains('-')
+    }
+
     fn auto_handles_expiry(&self) -> bool {
         false
     }
 }
+
+impl SessionStore<SessionPgPool> {
+    /// Spawns a task that `LISTEN`s on the config's invalidation channel and evicts Sessions
+    /// that other app nodes stored or destroyed from this node's memory, so the next request
+    /// loads them from the database.
+    ///
+    /// Returns None if no invalidation channel is set or the store is not persistent.
+    /// Notifications sent while the listener reconnects are lost.
+    ///
+    /// # Errors
+    /// - ['SessionError::Sqlx'] is returned if the listener failed to connect or `LISTEN`.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let config = SessionConfig::default().with_invalidation_channel("session_invalidation");
+    /// let session_store = SessionPgSessionStore::new(Some(pool.into()), config).await.unwrap();
+    /// let listener = session_store.spawn_invalidation_listener().await.unwrap();
+    /// ```
+    ///
+    pub async fn spawn_invalidation_listener(
+        &self,
+    ) -> Result<Option<tokio::task::JoinHandle<()>>, SessionError> {
+        let (Some(client), Some(channel)) = (&self.client, &self.config.invalidation_channel)
+        else {
+            return Ok(None);
+        };
+
+        let mut listener = PgListener::connect_with(&client.pool).await?;
+        listener.listen(channel).await?;
+
+        let store = self.clone();
+
+        Ok(Some(tokio::spawn(async move {
+            loop {
+                match listener.recv().await {
+                    Ok(notification) => store.evict_invalidated(notification.payload()),
+                    Err(err) => {
+                        tracing::warn!("Session invalidation listener failed: {}", err);
+                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                    }
+                }
+            }
+        })))
+    }
+}
@@ -0,0 +1,237 @@
+use crate::{databases::DatabasePool, RetryPolicy, Session, SessionError, SessionStore};
+use async_trait::async_trait;
+use chrono::Duration;
+use std::{
+    fmt::Debug,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+///Retry Session Helper type for the DatabasePool.
+pub type SessionRetrySession<P> = Session<RetryPool<P>>;
+///Retry Session Store Helper type for the DatabasePool.
+pub type SessionRetrySessionStore<P> = SessionStore<RetryPool<P>>;
+
+#[derive(Debug, Default)]
+struct RetryCounters {
+    retries: AtomicU64,
+    exhausted: AtomicU64,
+}
+
+/// [RetryPool] wraps a `DatabasePool` retrying its calls when they fail with a transient
+/// error, like a dropped connection, so network blips do not reach the Request.
+///
+/// Calls are retried following the RetryPolicy, using `SessionError::is_transient` to decide
+/// what can be retried. Other errors are returned right away. Each attempt can be given its
+/// own timeout which fails it with `SessionError::Timeout` so it is retried as well. Creating
+/// the table and notifying other app nodes are not retried.
+///
+/// When the `metrics` feature is enabled retries are also reported through the `metrics`
+/// crate as `axum_session_pool_retries_total` and calls that ran out of attempts as
+/// `axum_session_pool_retries_exhausted_total`.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::{RetryPolicy, RetryPool, SessionConfig, SessionPgPool, SessionStore};
+/// use chrono::Duration;
+///
+/// let policy = RetryPolicy::new(3, Duration::milliseconds(50)).with_jitter(true);
+/// let pool = RetryPool::new(SessionPgPool::from(pool), policy)
+///     .with_timeout(Some(Duration::seconds(2)));
+/// let session_store = SessionStore::new(Some(pool), SessionConfig::default()).await.unwrap();
+/// ```
+///
+#[derive(Clone)]
+pub struct RetryPool<P> {
+    pool: P,
+    policy: RetryPolicy,
+    timeout: Option<Duration>,
+    counters: Arc<RetryCounters>,
+}
+
+impl<P> RetryPool<P>
+where
+    P: DatabasePool + Send + Sync,
+{
+    /// Creates a RetryPool retrying the pool's calls with the policy.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let pool = RetryPool::new(pool, RetryPolicy::new(3, Duration::milliseconds(50)));
+    /// ```
+    ///
+    pub fn new(pool: P, policy: RetryPolicy) -> Self {
+        Self {
+            pool,
+            policy,
+            timeout: None,
+            counters: Default::default(),
+        }
+    }
+
+    /// Set's how long each attempt can take before it fails with `SessionError::Timeout`.
+    /// Default is None for no timeout.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let pool = RetryPool::new(pool, policy).with_timeout(Some(Duration::seconds(2)));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Returns how many times calls were retried.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let retries = pool.retries();
+    /// ```
+    ///
+    pub fn retries(&self) -> u64 {
+        self.counters.retries.load(Ordering::Relaxed)
+    }
+
+    /// Returns how many calls still failed with a transient error after every attempt.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let exhausted = pool.exhausted();
+    /// ```
+    ///
+    pub fn exhausted(&self) -> u64 {
+        self.counters.exhausted.load(Ordering::Relaxed)
+    }
+
+    async fn retry<R, Fut>(&self, mut call: impl FnMut() -> Fut) -> Result<R, SessionError>
+    where
+        Fut: Future<Output = Result<R, SessionError>>,
+    {
+        let mut attempt = 1;
+
+        loop {
+            let result = match self.timeout.and_then(|timeout| timeout.to_std().ok()) {
+                Some(timeout) => tokio::time::timeout(timeout, call())
+                    .await
+                    .unwrap_or(Err(SessionError::Timeout)),
+                None => call().await,
+            };
+
+            match result {
+                Err(err) if err.is_transient() && attempt < self.policy.max_attempts => {
+                    let delay = self.policy.delay(attempt);
+                    tracing::warn!(
+                        "Session database call failed, retrying in {:?}: {}",
+                        delay,
+                        err
+                    );
+
+                    self.counters.retries.fetch_add(1, Ordering::Relaxed);
+                    #[cfg(feature = "metrics")]
+                    ::metrics::counter!("axum_session_pool_retries_total").increment(1);
+
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(err) if err.is_transient() => {
+                    self.counters.exhausted.fetch_add(1, Ordering::Relaxed);
+                    #[cfg(feature = "metrics")]
+                    ::metrics::counter!("axum_session_pool_retries_exhausted_total").increment(1);
+
+                    return Err(err);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl<P: Debug> Debug for RetryPool<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryPool")
+            .field("pool", &self.pool)
+            .field("policy", &self.policy)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<P> DatabasePool for RetryPool<P>
+where
+    P: DatabasePool + Send + Sync,
+{
+    async fn initiate(&self, table_name: &str) -> Result<(), SessionError> {
+        self.pool.initiate(table_name).await
+    }
+
+    async fn count(&self, table_name: &str) -> Result<i64, SessionError> {
+        self.retry(|| self.pool.count(table_name)).await
+    }
+
+    async fn count_active(&self, table_name: &str) -> Result<i64, SessionError> {
+        self.retry(|| self.pool.count_active(table_name)).await
+    }
+
+    async fn store(
+        &self,
+        id: &str,
+        session: &str,
+        expires: i64,
+        table_name: &str,
+    ) -> Result<(), SessionError> {
+        self.retry(|| self.pool.store(id, session, expires, table_name))
+            .await
+    }
+
+    async fn store_many(
+        &self,
+        sessions: &[(String, String, i64)],
+        table_name: &str,
+    ) -> Result<(), SessionError> {
+        self.retry(|| self.pool.store_many(sessions, table_name))
+            .await
+    }
+
+    async fn load(&self, id: &str, table_name: &str) -> Result<Option<String>, SessionError> {
+        self.retry(|| self.pool.load(id, table_name)).await
+    }
+
+    async fn delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
+        self.retry(|| self.pool.delete_one_by_id(id, table_name))
+            .await
+    }
+
+    async fn exists(&self, id: &str, table_name: &str) -> Result<bool, SessionError> {
+        self.retry(|| self.pool.exists(id, table_name)).await
+    }
+
+    async fn delete_by_expiry(&self, table_name: &str) -> Result<Vec<String>, SessionError> {
+        self.retry(|| self.pool.delete_by_expiry(table_name)).await
+    }
+
+    async fn delete_all(&self, table_name: &str) -> Result<(), SessionError> {
+        self.retry(|| self.pool.delete_all(table_name)).await
+    }
+
+    async fn get_ids(&self, table_name: &str) -> Result<Vec<String>, SessionError> {
+        self.retry(|| self.pool.get_ids(table_name)).await
+    }
+
+    async fn notify(&self, channel: &str, payload: &str) -> Result<(), SessionError> {
+        self.pool.notify(channel, payload).await
+    }
+
+    fn accepts_table_name(&self, table_name: &str) -> bool {
+        self.pool.accepts_table_name(table_name)
+    }
+
+    fn auto_handles_expiry(&self) -> bool {
+        self.pool.auto_handles_expiry()
+    }
+}
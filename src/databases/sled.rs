@@ -0,0 +1,183 @@
+use crate::{DatabasePool, Session, SessionError, SessionStore};
+use async_trait::async_trait;
+use chrono::Utc;
+use sled::{Db, IVec, Tree};
+
+///Sled's Session Helper type for the DatabasePool.
+pub type SessionSledSession = Session<SessionSledPool>;
+///Sled's Session Store Helper type for the DatabasePool.
+pub type SessionSledSessionStore = SessionStore<SessionSledPool>;
+
+///Sled's Pool type for the DatabasePool. Needs an opened sled Db.
+///
+/// Each table name is stored as its own sled Tree with the Session ID as the key.
+/// Sled has no built in expiry so expired Sessions are ignored when loaded and are
+/// removed when the SessionStore runs its database purge.
+///
+/// Sled writes are only durable once flushed. Sled flushes in the background every
+/// 500ms by default which can be changed with `sled::Config::flush_every_ms`.
+/// The pool also flushes after purging and clearing the store. A crash can lose any
+/// Sessions written since the last flush which then simply get recreated.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::{SessionConfig, SessionSledPool, SessionStore};
+///
+/// let db = sled::open("sessions").unwrap();
+/// let config = SessionConfig::default();
+/// let session_store = SessionStore::<SessionSledPool>::new(Some(db.into()), config).await.unwrap();
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct SessionSledPool {
+    db: Db,
+}
+
+impl From<Db> for SessionSledPool {
+    fn from(db: Db) -> Self {
+        SessionSledPool { db }
+    }
+}
+
+impl SessionSledPool {
+    fn tree(&self, table_name: &str) -> Result<Tree, SessionError> {
+        Ok(self.db.open_tree(table_name)?)
+    }
+}
+
+/// Stores the expires timestamp in front of the Session.
+fn encode(session: &str, expires: i64) -> Vec<u8> {
+    let mut value = Vec::with_capacity(8 + session.len());
+    value.extend_from_slice(&expires.to_be_bytes());
+    value.extend_from_slice(session.as_bytes());
+    value
+}
+
+/// Splits the value into the expires timestamp and Session.
+fn decode(value: &IVec) -> Result<(i64, String), SessionError> {
+    if value.len() < 8 {
+        return Err(SessionError::GenericSelectError(
+            "Sled session value is missing its expiry".to_owned(),
+        ));
+    }
+
+    let (expires, session) = value.split_at(8);
+    let expires = i64::from_be_bytes(expires.try_into().unwrap_or_default());
+
+    Ok((expires, String::from_utf8(session.to_vec())?))
+}
+
+#[async_trait]
+impl DatabasePool for SessionSledPool {
+    async fn initiate(&self, table_name: &str) -> Result<(), SessionError> {
+        let _ = self.tree(table_name)?;
+        Ok(())
+    }
+
+    async fn delete_by_expiry(&self, table_name: &str) -> Result<Vec<String>, SessionError> {
+        let tree = self.tree(table_name)?;
+        let now = Utc::now().timestamp();
+        let mut ids: Vec<String> = Vec::new();
+
+        for item in tree.iter() {
+            let (key, value) = item?;
+            let expired = decode(&value).map_or(true, |(expires, _)| expires < now);
+
+            if expired {
+                let _ = tree.remove(&key)?;
+                ids.push(String::from_utf8_lossy(&key).into_owned());
+            }
+        }
+
+        tree.flush_async().await?;
+        Ok(ids)
+    }
+
+    async fn count(&self, table_name: &str) -> Result<i64, SessionError> {
+        Ok(self.tree(table_name)?.len() as i64)
+    }
+
+    async fn count_active(&self, table_name: &str) -> Result<i64, SessionError> {
+        let now = Utc::now().timestamp();
+        let mut count = 0;
+
+        for item in self.tree(table_name)?.iter() {
+            let (_, value) = item?;
+
+            if decode(&value).is_ok_and(|(expires, _)| expires >= now) {
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    async fn store(
+        &self,
+        id: &str,
+        session: &str,
+        expires: i64,
+        table_name: &str,
+    ) -> Result<(), SessionError> {
+        let _ = self
+            .tree(table_name)?
+            .insert(id.as_bytes(), encode(session, expires))?;
+        Ok(())
+    }
+
+    async fn load(&self, id: &str, table_name: &str) -> Result<Option<String>, SessionError> {
+        let tree = self.tree(table_name)?;
+
+        if let Some(value) = tree.get(id.as_bytes())? {
+            let (expires, session) = decode(&value)?;
+
+            if expires >= Utc::now().timestamp() {
+                return Ok(Some(session));
+            }
+
+            let _ = tree.remove(id.as_bytes())?;
+        }
+
+        Ok(None)
+    }
+
+    async fn delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
+        let _ = self.tree(table_name)?.remove(id.as_bytes())?;
+        Ok(())
+    }
+
+    async fn exists(&self, id: &str, table_name: &str) -> Result<bool, SessionError> {
+        if let Some(value) = self.tree(table_name)?.get(id.as_bytes())? {
+            let (expires, _) = decode(&value)?;
+            return Ok(expires >= Utc::now().timestamp());
+        }
+
+        Ok(false)
+    }
+
+    async fn delete_all(&self, table_name: &str) -> Result<(), SessionError> {
+        let tree = self.tree(table_name)?;
+        tree.clear()?;
+        tree.flush_async().await?;
+        Ok(())
+    }
+
+    async fn get_ids(&self, table_name: &str) -> Result<Vec<String>, SessionError> {
+        let now = Utc::now().timestamp();
+        let mut ids: Vec<String> = Vec::new();
+
+        for item in self.tree(table_name)?.iter() {
+            let (key, value) = item?;
+
+            if decode(&value).is_ok_and(|(expires, _)| expires >= now) {
+                ids.push(String::from_utf8_lossy(&key).into_owned());
+            }
+        }
+
+        Ok(ids)
+    }
+
+    fn auto_handles_expiry(&self) -> bool {
+        false
+    }
+}
@@ -73,6 +73,21 @@ impl DatabasePool for SessionSqlitePool {
         return Ok(count);
     }
 
+    async fn count_active(&self, table_name: &str) -> Result<i64, SessionError> {
+        let (count,) = sqlx::query_as(
+            &r#"
+            SELECT COUNT(*) FROM %%TABLE_NAME%%
+            WHERE expires IS NULL OR expires > $1
+        "#
+            .replace("%%TABLE_NAME%%", table_name),
+        )
+        .bind(Utc::now().timestamp())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
     async fn store(
         &self,
         id: &str,
@@ -164,6 +179,10 @@ impl DatabasePool for SessionSqlitePool {
         Ok(result)
     }
 
+    fn accepts_table_name(&self, table_name: &str) -> bool {
+        !table_name.contains('-')
+    }
+
     fn auto_handles_expiry(&self) -> bool {
         false
     }
@@ -88,6 +88,21 @@ impl<C: Connection> DatabasePool for SessionSurrealPool<C> {
         }
     }
 
+    async fn count_active(&self, table_name: &str) -> Result<i64, SessionError> {
+        let mut res = self
+            .connection
+            .query(
+                "SELECT count() AS amount FROM type::table($table_name)
+                WHERE sessionexpires = NONE OR sessionexpires > $expires GROUP BY amount;",
+            )
+            .bind(("table_name", table_name))
+            .bind(("expires", Utc::now().timestamp()))
+            .await?;
+
+        let response: Option<i64> = res.take("amount")?;
+        Ok(response.unwrap_or_default())
+    }
+
     async fn store(
         &self,
         id: &str,
@@ -1,5 +1,65 @@
+use axum_core::response::{IntoResponse, Response};
+use http::StatusCode;
 use thiserror::Error;
 
+/// Reasons a `SessionConfig` failed validation.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    #[error("{0:?} is not a valid Cookie or Header name.")]
+    InvalidName(String),
+    #[error("{0} must be greater than zero.")]
+    InvalidDuration(&'static str),
+    #[error("{0:?} is not a valid Cookie path. It must start with / and have no ;.")]
+    InvalidPath(String),
+    #[error("__Host- prefixed Cookies must be secure, use the path / and have no domain.")]
+    HostPrefix,
+    #[error("__Secure- prefixed Cookies must be secure.")]
+    SecurePrefix,
+    #[error("SameSite=None Cookies must be secure.")]
+    SameSiteNoneInsecure,
+    #[error("cookie_max_age is too large to be represented by the Expires attribute.")]
+    MaxAgeTooLarge,
+    #[error("RetryPolicy max_attempts must be at least 1.")]
+    InvalidRetryAttempts,
+    #[error("with_cookie_same_site_lax_get requires SameSite::Lax Cookies.")]
+    LaxGetNotLax,
+    #[error("encrypt_at_rest requires a Key set with with_key.")]
+    EncryptAtRestWithoutKey,
+    #[error("Header {0} would be {1} bytes which is larger than the {2} byte limit.")]
+    HeaderTooLarge(String, usize, usize),
+    #[error("Session ID {0:?} can not be sent as a Header value.")]
+    InvalidHeaderValue(String),
+    #[error("{0:?} is not a valid table name. Only letters, digits, _, ., : and - are allowed, without - for SQL databases.")]
+    InvalidTableName(String),
+    #[error("{0} memory shards is not a power of two greater than 1.")]
+    InvalidMemoryShards(usize),
+    #[error("max_list_len must be at least 1.")]
+    InvalidMaxListLen,
+    #[error("Cookie domain {0:?} is a public suffix so the Cookies would be sent to every site under it.")]
+    PublicSuffixDomain(String),
+    #[error("with_domain_list requires a Cookie domain set with with_cookie_domain.")]
+    DomainListWithoutDomain,
+    #[error("Host {0:?} in the domain list is not within the Cookie domain {1:?}.")]
+    HostOutsideDomain(String, String),
+}
+
+/// Problems found by `SessionConfig::validate_against_host` that can make apps sharing a
+/// parent domain mix up their Sessions or make browsers reject the Cookies.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HostWarning {
+    #[error("Cookie domain {domain:?} sends the Cookies to every sub domain of it, not only {host:?}, so other apps can shadow them. Remove the domain and use CookiePrefix::Host, or give the Cookies unique names.")]
+    BroadDomain { domain: String, host: String },
+    #[error(
+        "Cookie domain {domain:?} does not match {host:?} so browsers will reject the Cookies."
+    )]
+    DomainMismatch { domain: String, host: String },
+    #[error("Cookie domain {0:?} is a top level domain so browsers will reject the Cookies.")]
+    TopLevelDomain(String),
+    #[error("Cookie domain {0:?} is a public suffix so browsers will reject the Cookies or send them to every site under it.")]
+    PublicSuffix(String),
+}
+
 #[derive(Error, Debug)]
 pub enum SessionError {
     #[error(transparent)]
@@ -25,8 +85,28 @@ pub enum SessionError {
     #[cfg(feature = "mongodb")]
     #[error(transparent)]
     Mongodb(#[from] mongodb::error::Error),
+    #[cfg(feature = "sled")]
+    #[error(transparent)]
+    Sled(#[from] sled::Error),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
     #[error(transparent)]
     SerdeJson(#[from] serde_json::error::Error),
+    #[cfg(feature = "bincode")]
+    #[error(transparent)]
+    Bincode(#[from] bincode::Error),
+    #[cfg(feature = "msgpack")]
+    #[error(transparent)]
+    MessagePackEncode(#[from] rmp_serde::encode::Error),
+    #[cfg(feature = "msgpack")]
+    #[error(transparent)]
+    MessagePackDecode(#[from] rmp_serde::decode::Error),
+    #[cfg(feature = "argon2")]
+    #[error(transparent)]
+    PasswordHash(#[from] argon2::password_hash::Error),
+    #[cfg(feature = "argon2")]
+    #[error("Secret hashing task failed: {0}")]
+    HashTask(#[from] tokio::task::JoinError),
     #[error(transparent)]
     HTTP(#[from] http::Error),
     #[error(transparent)]
@@ -51,6 +131,26 @@ pub enum SessionError {
     GenericDeleteError(String),
     #[error("{0}")]
     GenericNotSupportedError(String),
+    #[error("Header value is too short to contain a nonce and tag.")]
+    DataTooShort,
+    #[error("Session Header {0} could not be set.")]
+    InvalidHeader(String),
+    #[error("Header value failed to decrypt. The key, cipher or value does not match.")]
+    Decrypt,
+    #[error("Database operation timed out.")]
+    Timeout,
+    #[error("Session would exceed the maximum size of {0} bytes.")]
+    SizeLimitExceeded(usize),
+    #[error("Key material is {0} bytes but at least {1} bytes are required.")]
+    KeyTooShort(usize, usize),
+    #[error("Session was destroyed within this request.")]
+    SessionDestroyed,
+    #[error("Session ID is not valid.")]
+    InvalidSessionID,
+    #[error("Session export is missing its header.")]
+    InvalidExportHeader,
+    #[error("Session export version {0} is not supported.")]
+    UnsupportedExportVersion(u32),
     #[error("Session was not found. Either the session was unloaded or was never created.")]
     NoSessionError,
     #[error(
@@ -59,3 +159,94 @@ pub enum SessionError {
     )]
     OldSessionError,
 }
+
+impl SessionError {
+    /// Returns the status code used when the error is returned from a handler.
+    ///
+    /// Errors caused by what the client sent like values that fail to deserialize are 4xx.
+    /// Database and config errors are 500 and timeouts are 504.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// assert_eq!(SessionError::Timeout.status_code(), StatusCode::GATEWAY_TIMEOUT);
+    /// ```
+    ///
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            SessionError::SerdeJson(_) => StatusCode::BAD_REQUEST,
+            #[cfg(feature = "bincode")]
+            SessionError::Bincode(_) => StatusCode::BAD_REQUEST,
+            #[cfg(feature = "msgpack")]
+            SessionError::MessagePackEncode(_) | SessionError::MessagePackDecode(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            SessionError::Decode(_)
+            | SessionError::DataTooShort
+            | SessionError::Decrypt
+            | SessionError::InvalidSessionID
+            | SessionError::SessionDestroyed
+            | SessionError::NoSessionError
+            | SessionError::OldSessionError => StatusCode::UNAUTHORIZED,
+            SessionError::SizeLimitExceeded(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            SessionError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Returns true if retrying the call could succeed, like for timeouts, dropped
+    /// connections or an exhausted connection pool. Errors caused by the data itself, like
+    /// failing to decode, and errors from unknown sources are not transient.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// assert!(SessionError::Timeout.is_transient());
+    /// ```
+    ///
+    pub fn is_transient(&self) -> bool {
+        match self {
+            SessionError::Timeout | SessionError::IO(_) => true,
+            #[cfg(any(
+                feature = "postgres-rustls",
+                feature = "postgres-native",
+                feature = "sqlite-rustls",
+                feature = "sqlite-native",
+                feature = "mysql-rustls",
+                feature = "mysql-native"
+            ))]
+            SessionError::Sqlx(err) => matches!(
+                err,
+                sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::WorkerCrashed
+            ),
+            #[cfg(any(feature = "redis-db", feature = "redis-clusterdb"))]
+            SessionError::Redis(err) => {
+                err.is_io_error()
+                    || err.is_timeout()
+                    || err.is_connection_dropped()
+                    || err.is_connection_refusal()
+            }
+            #[cfg(feature = "mongodb")]
+            SessionError::Mongodb(err) => {
+                matches!(*err.kind, mongodb::error::ErrorKind::Io(_))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Lets handlers return a `SessionError` using `?`.
+///
+/// The body only holds the status's reason so database and decryption details are never
+/// sent to the client. The full error is logged instead.
+impl IntoResponse for SessionError {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+
+        if status.is_server_error() {
+            tracing::error!("Session error: {}", self);
+        } else {
+            tracing::warn!("Session error: {}", self);
+        }
+
+        (status, status.canonical_reason().unwrap_or("Session Error")).into_response()
+    }
+}
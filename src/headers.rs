@@ -1,93 +1,126 @@
 #[cfg(feature = "rest_mode")]
-use crate::SessionError;
+use crate::session_data::constant_time_eq;
 use crate::{config::SecurityMode, DatabasePool, Session, SessionConfig, SessionKey, SessionStore};
-#[cfg(feature = "rest_mode")]
+use crate::{CipherSuite, SessionError};
+#[cfg(not(feature = "rest_mode"))]
+use crate::{CookieEncoding, CookiePrefix, ExpiryAttr};
 use aes_gcm::aead::{generic_array::GenericArray, Aead, AeadInPlace, KeyInit, Payload};
-#[cfg(feature = "rest_mode")]
 use aes_gcm::Aes256Gcm;
-#[cfg(feature = "rest_mode")]
 use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::{ChaCha20Poly1305, XChaCha20Poly1305};
+#[cfg(not(feature = "rest_mode"))]
+use chrono::Duration;
 use cookie::Key;
 #[cfg(not(feature = "rest_mode"))]
 use cookie::{Cookie, CookieJar};
-#[cfg(not(feature = "rest_mode"))]
-use http::header::{COOKIE, SET_COOKIE};
 use http::{self, HeaderMap};
 #[cfg(feature = "rest_mode")]
 use http::{header::HeaderName, HeaderValue};
-#[cfg(feature = "rest_mode")]
+#[cfg(not(feature = "rest_mode"))]
+use http::{
+    header::{COOKIE, FORWARDED, HOST, ORIGIN, SET_COOKIE},
+    uri::Scheme,
+    Method, Uri,
+};
 use rand::RngCore;
 #[cfg(feature = "rest_mode")]
 use std::collections::HashMap;
 use std::{
+    borrow::Cow,
     fmt::Debug,
     marker::{Send, Sync},
 };
-use uuid::Uuid;
-
-// Keep these in sync, and keep the key len synced with the `private` docs as
-// well as the `KEYS_INFO` const in secure::Key. from cookie-rs
-#[cfg(feature = "rest_mode")]
-pub(crate) const NONCE_LEN: usize = 12;
-#[cfg(feature = "rest_mode")]
-pub(crate) const TAG_LEN: usize = 16;
-#[cfg(feature = "rest_mode")]
-pub(crate) const KEY_LEN: usize = 32;
 
 enum NameType {
     Store,
     Data,
     Key,
+    #[cfg(not(feature = "rest_mode"))]
+    Bootstrap,
 }
 
 impl NameType {
+    /// Gets the Cookie or Header name with the Cookie prefix already applied.
     #[inline]
-    pub(crate) fn get_name(&self, config: &SessionConfig) -> String {
-        let name = match self {
-            NameType::Data => config.session_name.to_string(),
-            NameType::Store => config.store_name.to_string(),
-            NameType::Key => config.key_name.to_string(),
-        };
+    pub(crate) fn get_name<'a>(&self, config: &'a SessionConfig) -> &'a str {
+        match self {
+            NameType::Data => &config.names.session,
+            NameType::Store => &config.names.store,
+            NameType::Key => &config.names.key,
+            #[cfg(not(feature = "rest_mode"))]
+            NameType::Bootstrap => &config.names.bootstrap,
+        }
+    }
 
-        if config.prefix_with_host {
-            let mut prefixed = "__Host-".to_owned();
-            prefixed.push_str(&name);
-            prefixed
-        } else {
-            name
+    /// Gets the Header name. Header names are case-insensitive so lookups match whatever
+    /// case the client or a proxy used. Names are checked by `SessionConfig::validate`.
+    #[cfg(feature = "rest_mode")]
+    #[inline]
+    pub(crate) fn get_header_name<'a>(&self, config: &'a SessionConfig) -> Option<&'a HeaderName> {
+        match self {
+            NameType::Data => config.names.session_header.as_ref(),
+            NameType::Store => config.names.store_header.as_ref(),
+            NameType::Key => config.names.key_header.as_ref(),
         }
     }
 }
 
+/// Returns the Lax bootstrap Cookie's lifespan if it is enabled and the session Cookie is Strict.
+#[cfg(not(feature = "rest_mode"))]
+fn bootstrap_lifespan(config: &SessionConfig) -> Option<Duration> {
+    config
+        .bootstrap_lifespan
+        .filter(|_| config.cookie_same_site == cookie::SameSite::Strict)
+}
+
 #[cfg(not(feature = "rest_mode"))]
 pub async fn get_headers_and_key<T>(
     store: &SessionStore<T>,
     cookies: CookieJar,
-) -> (SessionKey, Option<Uuid>, bool)
+) -> (SessionKey, Option<String>, bool)
 where
     T: DatabasePool + Clone + Debug + Sync + Send + 'static,
 {
+    let config_key = store.config.current_key();
+    let previous_key = store.config.previous_key();
+
+    let name = NameType::Key.get_name(&store.config);
     let value = cookies
-        .get_cookie(&store.config.key_name, store.config.key.as_ref())
-        .and_then(|c| Uuid::parse_str(c.value()).ok());
+        .get_tracked_cookie(store, name, config_key.as_ref(), previous_key.as_ref())
+        .map(|c| c.value().to_owned())
+        .filter(|id| validate_tracked(store, name, id));
 
     let session_key = match store.config.security_mode {
         SecurityMode::PerSession => SessionKey::get_or_create(store, value).await,
         SecurityMode::Simple => SessionKey::new(),
     };
 
-    let key = match store.config.security_mode {
-        SecurityMode::PerSession => Some(&session_key.key),
-        SecurityMode::Simple => store.config.key.as_ref(),
+    // Per-Session keys never rotate so only the config's keys have a previous key.
+    let (key, previous) = match store.config.security_mode {
+        SecurityMode::PerSession => (Some(&session_key.key), None),
+        SecurityMode::Simple => (config_key.as_ref(), previous_key.as_ref()),
     };
 
+    let name = NameType::Data.get_name(&store.config);
     let value = cookies
-        .get_cookie(&store.config.session_name, key)
-        .and_then(|c| Uuid::parse_str(c.value()).ok());
-
+        .get_tracked_cookie(store, name, key, previous)
+        .map(|c| c.value().to_owned())
+        .filter(|id| validate_tracked(store, name, id))
+        .or_else(|| {
+            // Strict Cookies are not sent on cross-site navigations so fall back to the bootstrap Cookie.
+            bootstrap_lifespan(&store.config)?;
+            let name = NameType::Bootstrap.get_name(&store.config);
+            cookies
+                .get_tracked_cookie(store, name, key, previous)
+                .map(|c| c.value().to_owned())
+                .filter(|id| validate_tracked(store, name, id))
+        });
+
+    let name = NameType::Store.get_name(&store.config);
     let storable = cookies
-        .get_cookie(&store.config.store_name, key)
-        .map_or(false, |c| c.value().parse().unwrap_or(false));
+        .get_cookie(name, key)
+        .or_else(|| cookies.get_cookie(name, Some(previous?)))
+        .is_some_and(|c| c.value().parse().unwrap_or(false));
 
     (session_key, value, storable)
 }
@@ -95,64 +128,233 @@ where
 #[cfg(feature = "rest_mode")]
 pub async fn get_headers_and_key<T>(
     store: &SessionStore<T>,
-    headers: HashMap<String, String>,
-) -> (SessionKey, Option<Uuid>, bool)
+    headers: HashMap<HeaderName, String>,
+) -> (SessionKey, Option<String>, bool)
 where
     T: DatabasePool + Clone + Debug + Sync + Send + 'static,
 {
-    let name = store.config.key_name.to_string();
-    let value = headers
-        .get(&name)
+    let lookup = |name_type: NameType| {
+        name_type
+            .get_header_name(&store.config)
+            .and_then(|name| headers.get(name))
+    };
+
+    let config_key = store.config.current_key();
+    let previous_key = store.config.previous_key();
+
+    let name = NameType::Key.get_name(&store.config);
+    let value = lookup(NameType::Key)
         .and_then(|c| {
-            if let Some(key) = &store.config.key {
-                decrypt(&name, c, key).ok()
+            if let Some(key) = &config_key {
+                decrypt_tracked(store, name, name, c, key, previous_key.as_ref())
             } else {
                 Some(c.to_owned())
             }
         })
-        .and_then(|c| Uuid::parse_str(&c).ok());
+        .filter(|id| validate_tracked(store, name, id));
 
     let session_key = match store.config.security_mode {
         SecurityMode::PerSession => SessionKey::get_or_create(store, value).await,
         SecurityMode::Simple => SessionKey::new(),
     };
 
-    let key = match store.config.security_mode {
-        SecurityMode::PerSession => Some(&session_key.key),
-        SecurityMode::Simple => store.config.key.as_ref(),
+    // Per-Session keys never rotate so only the config's keys have a previous key.
+    let (key, previous) = match store.config.security_mode {
+        SecurityMode::PerSession => (Some(&session_key.key), None),
+        SecurityMode::Simple => (config_key.as_ref(), previous_key.as_ref()),
     };
 
-    let name = store.config.session_name.to_string();
-    let value = headers
-        .get(&name)
+    let name = NameType::Data.get_name(&store.config);
+    let value = lookup(NameType::Data)
         .and_then(|c| {
             if let Some(key) = key {
-                decrypt(&name, c, key).ok()
+                decrypt_tracked(store, name, name, c, key, previous)
             } else {
                 Some(c.to_owned())
             }
         })
-        .and_then(|c| Uuid::parse_str(&c).ok());
+        .filter(|id| validate_tracked(store, name, id));
 
-    let name = store.config.store_name.to_string();
-    let storable = headers
-        .get(&name)
+    let name = NameType::Store.get_name(&store.config);
+    let storable = lookup(NameType::Store)
         .and_then(|c| {
-            if let Some(key) = key {
-                decrypt(&name, c, key).ok()
-            } else {
-                Some(c.to_owned())
+            let Some(key) = key else {
+                return Some(c.to_owned());
+            };
+
+            // The store Header is bound to the Session ID so it can not be replayed on other Sessions.
+            let legacy = store.config.accept_unbound_tokens;
+            match value.as_deref().map(|id| bound_aad(name, id)) {
+                Some(aad) if legacy => decrypt_rotated(&aad, c, key, previous, store.config.cipher)
+                    .ok()
+                    .or_else(|| decrypt_tracked(store, name, name, c, key, previous)),
+                Some(aad) => decrypt_tracked(store, name, &aad, c, key, previous),
+                None if legacy => decrypt_tracked(store, name, name, c, key, previous),
+                None => None,
             }
         })
-        .map(|c| c.parse().unwrap_or(false));
+        .map(|c| constant_time_eq(&c, "true"));
 
     (session_key, value, storable.unwrap_or(false))
 }
 
+#[cfg(feature = "rest_mode")]
+/// Builds the associated data used to bind a Header to the Session ID.
+/// Header names can not hold a `:` so the name and ID can not run together.
+pub(crate) fn bound_aad(name: &str, id: &str) -> String {
+    format!("{}:{}", name, id)
+}
+
+/// Decrypts the Header value using aad as the associated data recording any failure
+/// within the store's metrics. Only the name is logged as aad can hold the Session ID.
+#[cfg(feature = "rest_mode")]
+fn decrypt_tracked<T>(
+    store: &SessionStore<T>,
+    name: &str,
+    aad: &str,
+    value: &str,
+    key: &Key,
+    previous: Option<&Key>,
+) -> Option<String>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    match decrypt_rotated(aad, value, key, previous, store.config.cipher) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            tracing::warn!("Session token {} rejected: {}", name, err);
+            store.metrics.decrypt_failure();
+            None
+        }
+    }
+}
+
+/// Checks the Session ID with the configs id validator recording any failure within the store's metrics.
+fn validate_tracked<T>(store: &SessionStore<T>, name: &str, id: &str) -> bool
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    let valid = (store.config.id_validator)(id);
+
+    if !valid {
+        tracing::warn!("Session token {} rejected: invalid Session ID", name);
+        store.metrics.invalid_id();
+    }
+
+    valid
+}
+
+#[cfg(not(feature = "rest_mode"))]
+/// Works out why a private Cookie failed to decrypt as the cookie crate does not say.
+/// Private Cookies are base64 encoded AES-256-GCM with a 12 byte nonce and 16 byte tag.
+fn cookie_failure_reason(value: &str) -> &'static str {
+    match general_purpose::STANDARD.decode(value) {
+        Err(_) => "not valid base64",
+        Ok(data) if data.len() < 12 + 16 => "too short to contain a nonce and tag",
+        Ok(_) => "failed to decrypt",
+    }
+}
+
+#[cfg(not(feature = "rest_mode"))]
+/// Returns true if the Request sent a Session ID Cookie, valid or not.
+pub(crate) fn has_session_token(config: &SessionConfig, cookies: &CookieJar) -> bool {
+    cookies.get(NameType::Data.get_name(config)).is_some()
+        || (bootstrap_lifespan(config).is_some()
+            && cookies.get(NameType::Bootstrap.get_name(config)).is_some())
+}
+
+#[cfg(feature = "rest_mode")]
+/// Returns true if the Request sent a Session ID Header, valid or not.
+pub(crate) fn has_session_token(
+    config: &SessionConfig,
+    headers: &HashMap<HeaderName, String>,
+) -> bool {
+    NameType::Data
+        .get_header_name(config)
+        .is_some_and(|name| headers.contains_key(name))
+}
+
+/// Cookie attributes resolved from the Request as the Response has no Host or scheme.
+#[derive(Debug, Default)]
+pub(crate) struct CookieAttrs {
+    pub(crate) domain: Option<Cow<'static, str>>,
+    pub(crate) secure: bool,
+}
+
+#[cfg(not(feature = "rest_mode"))]
+impl CookieAttrs {
+    pub(crate) fn new(config: &SessionConfig, uri: &Uri, headers: &HeaderMap) -> Self {
+        Self {
+            domain: config.resolve_domain(headers),
+            secure: config.resolve_secure(uri, headers),
+        }
+    }
+}
+
+#[cfg(not(feature = "rest_mode"))]
+/// Checks if the Request arrived over TLS using the URI scheme or the first
+/// `X-Forwarded-Proto` value or `Forwarded` proto set by a proxy.
+pub(crate) fn is_tls_request(uri: &Uri, headers: &HeaderMap) -> bool {
+    if uri.scheme() == Some(&Scheme::HTTPS) {
+        return true;
+    }
+
+    let forwarded_proto = headers
+        .get("x-forwarded-proto")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next());
+
+    let proto = forwarded_proto.or_else(|| {
+        headers
+            .get(FORWARDED)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').next())
+            .and_then(|element| {
+                element.split(';').find_map(|pair| {
+                    let (key, value) = pair.trim().split_once('=')?;
+                    key.eq_ignore_ascii_case("proto").then_some(value)
+                })
+            })
+    });
+
+    proto.is_some_and(|proto| proto.trim().trim_matches('"').eq_ignore_ascii_case("https"))
+}
+
+#[cfg(not(feature = "rest_mode"))]
+/// Adds removal Cookies for the Session Cookies so a client with a rejected Session ID starts fresh.
+pub(crate) fn remove_session_cookies(
+    config: &SessionConfig,
+    headers: &mut HeaderMap,
+    attrs: &CookieAttrs,
+) {
+    let mut cookies = CookieJar::new();
+
+    for name_type in [NameType::Key, NameType::Data, NameType::Store] {
+        cookies.add(remove_cookie(config, name_type, attrs));
+    }
+
+    if bootstrap_lifespan(config).is_some() {
+        cookies.add(remove_cookie(config, NameType::Bootstrap, attrs));
+    }
+
+    set_cookies(cookies, headers, config.cookie_encoding);
+}
+
 #[cfg(not(feature = "rest_mode"))]
 pub(crate) trait CookiesExt {
     fn get_cookie(&self, name: &str, key: Option<&Key>) -> Option<Cookie<'static>>;
     fn add_cookie(&mut self, cookie: Cookie<'static>, key: &Option<Key>);
+
+    /// Gets the Cookie, trying previous if key fails, recording any decryption failure within the store's metrics.
+    fn get_tracked_cookie<T>(
+        &self,
+        store: &SessionStore<T>,
+        name: &str,
+        key: Option<&Key>,
+        previous: Option<&Key>,
+    ) -> Option<Cookie<'static>>
+    where
+        T: DatabasePool + Clone + Debug + Sync + Send + 'static;
 }
 
 #[cfg(not(feature = "rest_mode"))]
@@ -172,42 +374,95 @@ impl CookiesExt for CookieJar {
             self.add(cookie)
         }
     }
+
+    fn get_tracked_cookie<T>(
+        &self,
+        store: &SessionStore<T>,
+        name: &str,
+        key: Option<&Key>,
+        previous: Option<&Key>,
+    ) -> Option<Cookie<'static>>
+    where
+        T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+    {
+        let cookie = self
+            .get_cookie(name, key)
+            .or_else(|| self.get_cookie(name, Some(previous?)));
+
+        if let (None, Some(_), Some(raw)) = (&cookie, key, self.get(name)) {
+            tracing::warn!(
+                "Session token {} rejected: {}",
+                name,
+                cookie_failure_reason(raw.value())
+            );
+            store.metrics.decrypt_failure();
+        }
+
+        cookie
+    }
 }
 
 #[cfg(not(feature = "rest_mode"))]
-fn create_cookie<'a>(config: &SessionConfig, value: String, cookie_type: NameType) -> Cookie<'a> {
-    let mut cookie_builder = Cookie::build((cookie_type.get_name(config), value))
+fn create_cookie<'a>(
+    config: &SessionConfig,
+    value: String,
+    cookie_type: NameType,
+    attrs: &CookieAttrs,
+    max_age: Option<Duration>,
+) -> Cookie<'a> {
+    let mut cookie_builder = Cookie::build((cookie_type.get_name(config).to_owned(), value))
         .path(config.cookie_path.clone())
-        .secure(config.cookie_secure)
+        .secure(attrs.secure)
         .http_only(config.cookie_http_only)
-        .same_site(config.cookie_same_site);
+        .same_site(match cookie_type {
+            NameType::Bootstrap => cookie::SameSite::Lax,
+            _ => config.cookie_same_site,
+        });
 
-    if let Some(domain) = &config.cookie_domain {
-        cookie_builder = cookie_builder.domain(domain.clone());
+    if let Some(domain) = attrs.domain.as_deref() {
+        cookie_builder = cookie_builder.domain(domain.to_owned());
     }
 
-    if let Some(max_age) = config.cookie_max_age {
-        let time_duration = max_age.to_std().expect("Max Age out of bounds");
-        cookie_builder =
-            cookie_builder.expires(Some((std::time::SystemTime::now() + time_duration).into()));
+    if let Some(max_age) = max_age {
+        if config.cookie_expiry_attr != ExpiryAttr::Expires {
+            cookie_builder =
+                cookie_builder.max_age(cookie::time::Duration::seconds(max_age.num_seconds()));
+        }
+
+        if config.cookie_expiry_attr != ExpiryAttr::MaxAge {
+            let expires = std::time::SystemTime::from(config.now() + max_age);
+            cookie_builder = cookie_builder.expires(Some(expires.into()));
+        }
     }
 
     cookie_builder.build()
 }
 
 #[cfg(not(feature = "rest_mode"))]
-fn remove_cookie<'a>(config: &SessionConfig, cookie_type: NameType) -> Cookie<'a> {
-    let mut cookie_builder = Cookie::build((cookie_type.get_name(config), ""))
+fn remove_cookie<'a>(
+    config: &SessionConfig,
+    cookie_type: NameType,
+    attrs: &CookieAttrs,
+) -> Cookie<'a> {
+    let mut cookie_builder = Cookie::build((cookie_type.get_name(config).to_owned(), ""))
         .path(config.cookie_path.clone())
         .http_only(config.cookie_http_only)
         .same_site(cookie::SameSite::None);
 
-    if let Some(domain) = &config.cookie_domain {
-        cookie_builder = cookie_builder.domain(domain.clone());
+    // __Host- cookies must be Secure, use the path / and have no Domain and
+    // __Secure- cookies must be Secure otherwise the browser will ignore the removal.
+    match config.cookie_prefix {
+        CookiePrefix::Host => cookie_builder = cookie_builder.secure(true).path("/"),
+        CookiePrefix::Secure => cookie_builder = cookie_builder.secure(true),
+        CookiePrefix::None => {}
     }
 
-    if let Some(domain) = &config.cookie_domain {
-        cookie_builder = cookie_builder.domain(domain.clone());
+    if let Some(domain) = attrs
+        .domain
+        .as_deref()
+        .filter(|_| config.cookie_prefix != CookiePrefix::Host)
+    {
+        cookie_builder = cookie_builder.domain(domain.to_owned());
     }
 
     let mut cookie = cookie_builder.build();
@@ -216,43 +471,125 @@ fn remove_cookie<'a>(config: &SessionConfig, cookie_type: NameType) -> Cookie<'a
 }
 
 #[cfg(not(feature = "rest_mode"))]
-/// This will get a CookieJar from the Headers.
-pub(crate) fn get_cookies(headers: &HeaderMap) -> CookieJar {
+/// Checks if the Request is cross-site and uses a method other than GET, HEAD, OPTIONS or TRACE.
+/// Uses `Sec-Fetch-Site` and falls back to comparing `Origin` with `Host`. Requests with
+/// neither Header are treated as same-site as they can not be told apart.
+pub(crate) fn is_cross_site_unsafe(method: &Method, headers: &HeaderMap) -> bool {
+    if method.is_safe() {
+        return false;
+    }
+
+    if let Some(site) = headers.get("sec-fetch-site") {
+        return site.as_bytes() == b"cross-site";
+    }
+
+    let Some(origin) = headers.get(ORIGIN) else {
+        return false;
+    };
+
+    let origin_host = origin
+        .to_str()
+        .ok()
+        .and_then(|origin| origin.split_once("://"))
+        .map(|(_, host)| host);
+
+    match (
+        origin_host,
+        headers.get(HOST).and_then(|host| host.to_str().ok()),
+    ) {
+        (Some(origin_host), Some(host)) => !origin_host.eq_ignore_ascii_case(host),
+        // Origin is `null` for opaque origins like sandboxed iframes.
+        _ => true,
+    }
+}
+
+#[cfg(not(feature = "rest_mode"))]
+/// This will get a CookieJar from the Headers decoding the values using the encoding.
+pub(crate) fn get_cookies(headers: &HeaderMap, encoding: CookieEncoding) -> CookieJar {
     let mut jar = CookieJar::new();
 
     let cookie_iter = headers
         .get_all(COOKIE)
         .into_iter()
         .filter_map(|value| value.to_str().ok())
-        .flat_map(|value| value.split(';'))
-        .filter_map(|cookie| Cookie::parse_encoded(cookie.to_owned()).ok());
+        .flat_map(split_cookie_header)
+        .filter_map(|pair| parse_cookie_pair(pair, encoding));
 
     for cookie in cookie_iter {
-        jar.add_original(cookie);
+        // Browsers send the Cookie with the most specific path first so later duplicates are ignored.
+        if jar.get(cookie.name()).is_none() {
+            jar.add_original(cookie);
+        }
     }
 
     jar
 }
 
+#[cfg(not(feature = "rest_mode"))]
+/// Splits a Cookie Header into its `name=value` pairs on any `;` outside of double quotes.
+fn split_cookie_header(header: &str) -> Vec<&str> {
+    let mut pairs = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+
+    for (i, c) in header.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => {
+                pairs.push(&header[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    pairs.push(&header[start..]);
+    pairs
+}
+
+#[cfg(not(feature = "rest_mode"))]
+/// Parses a single `name=value` pair splitting on the first `=` so padded base64 values
+/// stay whole. Double quotes around the value are removed. Malformed pairs return None.
+fn parse_cookie_pair(pair: &str, encoding: CookieEncoding) -> Option<Cookie<'static>> {
+    let (name, value) = pair.split_once('=')?;
+    let name = name.trim();
+
+    if name.is_empty() {
+        return None;
+    }
+
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value);
+
+    // Quoted values can hold a `;` which would cut the value short when parsed.
+    if value.contains(';') || encoding == CookieEncoding::Raw {
+        Some(Cookie::new(name.to_owned(), value.to_owned()))
+    } else {
+        Cookie::parse_encoded(format!("{}={}", name, value)).ok()
+    }
+}
+
 #[cfg(feature = "rest_mode")]
-/// This will get a Hashmap of all the headers that Exist.
+/// This will get a Hashmap of all the headers that Exist keyed by their case-insensitive names.
 pub(crate) fn get_headers<T>(
     store: &SessionStore<T>,
     headers: &HeaderMap,
-) -> HashMap<String, String>
+) -> HashMap<HeaderName, String>
 where
     T: DatabasePool + Clone + Debug + Sync + Send + 'static,
 {
     let mut map = HashMap::new();
 
-    for name in [
-        store.config.key_name.to_string(),
-        store.config.session_name.to_string(),
-        store.config.store_name.to_string(),
-    ] {
-        if let Some(value) = headers.get(&name) {
+    for name in [NameType::Key, NameType::Data, NameType::Store]
+        .iter()
+        .filter_map(|name| name.get_header_name(&store.config))
+    {
+        if let Some(value) = headers.get(name) {
             if let Ok(val) = value.to_str() {
-                map.insert(name, val.to_owned());
+                map.insert(name.clone(), val.to_owned());
             }
         }
     }
@@ -261,42 +598,74 @@ where
 }
 
 #[cfg(not(feature = "rest_mode"))]
-fn set_cookies(jar: CookieJar, headers: &mut HeaderMap) {
+fn set_cookies(jar: CookieJar, headers: &mut HeaderMap, encoding: CookieEncoding) {
     for cookie in jar.delta() {
-        if let Ok(header_value) = cookie.encoded().to_string().parse() {
+        let value = match encoding {
+            CookieEncoding::Encoded => cookie.encoded().to_string(),
+            CookieEncoding::Raw => cookie.to_string(),
+        };
+
+        if let Ok(header_value) = value.parse() {
             headers.append(SET_COOKIE, header_value);
         }
     }
 }
 
 /// Used to Set either the Header Values or the Cookie Values.
+/// The bootstrap Cookie is only set when bootstrap is true, meaning the Session ID was created or renewed.
+/// opt_in is the Session's resolved opt-in mode which may override the configs `SessionMode`.
+/// Cookies are created and removed using the domain and secure flag resolved for the Request.
+///
+/// # Errors
+/// - ['SessionError::InvalidHeader'] is returned in rest_mode if a Header could not be set.
+#[cfg_attr(feature = "rest_mode", allow(unused_variables))]
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn set_headers<T>(
     session: &Session<T>,
     session_key: &SessionKey,
     headers: &mut HeaderMap,
     destroy: bool,
     storable: bool,
-) where
+    opt_in: bool,
+    bootstrap: bool,
+    attrs: &CookieAttrs,
+) -> Result<(), SessionError>
+where
     T: DatabasePool + Clone + Debug + Sync + Send + 'static,
 {
     // Lets make a new jar as we only want to add our cookies to the Response cookie header.\
     #[cfg(not(feature = "rest_mode"))]
     {
         let mut cookies = CookieJar::new();
+        // An explicit Session expiry is used over the configs cookie max age.
+        let max_age = session
+            .store
+            .explicit_expiry(session.id.inner())
+            .map(|expiry| (expiry - session.store.config.now()).max(Duration::zero()))
+            .or(session.store.config.cookie_max_age);
+
+        // Everything is written with the current key so rotated clients move over to it.
+        let config_key = session.store.config.current_key();
 
         // Add Per-Session encryption KeyID
         let cookie_key = match session.store.config.security_mode {
             SecurityMode::PerSession => {
-                if (storable || !session.store.config.session_mode.is_opt_in()) && !destroy {
+                if (storable || !opt_in) && !destroy {
                     cookies.add_cookie(
-                        create_cookie(&session.store.config, session_key.id.inner(), NameType::Key),
-                        &session.store.config.key,
+                        create_cookie(
+                            &session.store.config,
+                            session_key.id.inner(),
+                            NameType::Key,
+                            attrs,
+                            max_age,
+                        ),
+                        &config_key,
                     );
                 } else {
                     //If not Storable we still remove the encryption key since there is no session.
                     cookies.add_cookie(
-                        remove_cookie(&session.store.config, NameType::Key),
-                        &session.store.config.key,
+                        remove_cookie(&session.store.config, NameType::Key, attrs),
+                        &config_key,
                     );
                 }
 
@@ -304,150 +673,261 @@ pub(crate) fn set_headers<T>(
             }
             SecurityMode::Simple => {
                 cookies.add_cookie(
-                    remove_cookie(&session.store.config, NameType::Key),
-                    &session.store.config.key,
+                    remove_cookie(&session.store.config, NameType::Key, attrs),
+                    &config_key,
                 );
-                session.store.config.key.clone()
+                config_key
             }
         };
 
         // Add SessionID
-        if (storable || !session.store.config.session_mode.is_opt_in()) && !destroy {
+        if (storable || !opt_in) && !destroy {
             cookies.add_cookie(
-                create_cookie(&session.store.config, session.id.inner(), NameType::Data),
+                create_cookie(
+                    &session.store.config,
+                    session.id.inner(),
+                    NameType::Data,
+                    attrs,
+                    max_age,
+                ),
                 &cookie_key,
             );
         } else {
             cookies.add_cookie(
-                remove_cookie(&session.store.config, NameType::Data),
+                remove_cookie(&session.store.config, NameType::Data, attrs),
                 &cookie_key,
             );
         }
 
+        // Add the Lax bootstrap SessionID for cross-site navigations.
+        if let Some(lifespan) = bootstrap_lifespan(&session.store.config) {
+            if (storable || !opt_in) && !destroy {
+                if bootstrap {
+                    cookies.add_cookie(
+                        create_cookie(
+                            &session.store.config,
+                            session.id.inner(),
+                            NameType::Bootstrap,
+                            attrs,
+                            Some(lifespan),
+                        ),
+                        &cookie_key,
+                    );
+                }
+            } else {
+                cookies.add_cookie(
+                    remove_cookie(&session.store.config, NameType::Bootstrap, attrs),
+                    &cookie_key,
+                );
+            }
+        }
+
         // Add Session Store Boolean
-        if session.store.config.session_mode.is_opt_in() && storable && !destroy {
+        if opt_in && storable && !destroy {
             cookies.add_cookie(
-                create_cookie(&session.store.config, storable.to_string(), NameType::Store),
+                create_cookie(
+                    &session.store.config,
+                    storable.to_string(),
+                    NameType::Store,
+                    attrs,
+                    max_age,
+                ),
                 &cookie_key,
             );
         } else {
             cookies.add_cookie(
-                remove_cookie(&session.store.config, NameType::Store),
+                remove_cookie(&session.store.config, NameType::Store, attrs),
                 &cookie_key,
             );
         }
 
-        set_cookies(cookies, headers);
+        set_cookies(cookies, headers, session.store.config.cookie_encoding);
     }
     #[cfg(feature = "rest_mode")]
     {
+        let config_key = session.store.config.current_key();
+
         // Add Per-Session encryption KeyID
         let cookie_key = match session.store.config.security_mode {
             SecurityMode::PerSession => {
-                if (storable || !session.store.config.session_mode.is_opt_in()) && !destroy {
+                if (storable || !opt_in) && !destroy {
                     let name = NameType::Key.get_name(&session.store.config);
-                    let value = if let Some(key) = session.store.config.key.as_ref() {
-                        encrypt(&name, &session_key.id.inner(), key)
+                    let value = if let Some(key) = config_key.as_ref() {
+                        encrypt(
+                            name,
+                            &session_key.id.inner(),
+                            key,
+                            session.store.config.cipher,
+                        )
                     } else {
                         session_key.id.inner()
                     };
 
-                    if let Ok(name) = HeaderName::from_bytes(name.as_bytes()) {
-                        if let Ok(value) = HeaderValue::from_str(&value) {
-                            headers.insert(name, value);
-                        }
-                    }
+                    insert_header(headers, name, &value)?;
                 }
 
                 Some(&session_key.key)
             }
-            SecurityMode::Simple => session.store.config.key.as_ref(),
+            SecurityMode::Simple => config_key.as_ref(),
         };
 
         // Add SessionID
-        if (storable || !session.store.config.session_mode.is_opt_in()) && !destroy {
+        if (storable || !opt_in) && !destroy {
             let name = NameType::Data.get_name(&session.store.config);
             let value = if let Some(key) = cookie_key {
-                encrypt(&name, &session.id.inner(), key)
+                encrypt(name, &session.id.inner(), key, session.store.config.cipher)
             } else {
                 session.id.inner()
             };
 
-            if let Ok(name) = HeaderName::from_bytes(name.as_bytes()) {
-                if let Ok(value) = HeaderValue::from_str(&value) {
-                    headers.insert(name, value);
-                }
-            }
+            insert_header(headers, name, &value)?;
         }
 
         // Add Session Store Boolean
-        if session.store.config.session_mode.is_opt_in() && storable && !destroy {
+        if opt_in && storable && !destroy {
             let name = NameType::Store.get_name(&session.store.config);
             let value = if let Some(key) = cookie_key {
-                encrypt(&name, &storable.to_string(), key)
+                encrypt(
+                    &bound_aad(name, &session.id.inner()),
+                    &storable.to_string(),
+                    key,
+                    session.store.config.cipher,
+                )
             } else {
                 storable.to_string()
             };
 
-            if let Ok(name) = HeaderName::from_bytes(name.as_bytes()) {
-                if let Ok(value) = HeaderValue::from_str(&value) {
-                    headers.insert(name, value);
-                }
-            }
+            insert_header(headers, name, &value)?;
         }
     }
+
+    Ok(())
 }
 
 #[cfg(feature = "rest_mode")]
-///Used to encrypt the Header Values and key values
-pub(crate) fn encrypt(name: &str, value: &str, key: &Key) -> String {
+/// Inserts the Header logging an error if the name or value is not valid.
+/// Headers only ever hold ID's so their size stays fixed no matter how large the Session is.
+///
+/// # Errors
+/// - ['SessionError::InvalidHeader'] is returned if the name or value is not valid.
+pub(crate) fn insert_header(
+    headers: &mut HeaderMap,
+    name: &str,
+    value: &str,
+) -> Result<(), SessionError> {
+    match (
+        HeaderName::from_bytes(name.as_bytes()),
+        HeaderValue::from_str(value),
+    ) {
+        (Ok(name), Ok(value)) => {
+            headers.insert(name, value);
+            Ok(())
+        }
+        _ => {
+            tracing::error!(
+                header = name,
+                value_len = value.len(),
+                "Session Header could not be set"
+            );
+            Err(SessionError::InvalidHeader(name.to_owned()))
+        }
+    }
+}
+
+///Used to encrypt the Header Values, key values and Session data stored with `encrypt_at_rest`.
+pub(crate) fn encrypt(name: &str, value: &str, key: &Key, cipher: CipherSuite) -> String {
     let val = value.as_bytes();
 
-    let mut data = vec![0; NONCE_LEN + val.len() + TAG_LEN];
-    let (nonce, in_out) = data.split_at_mut(NONCE_LEN);
+    let mut data = vec![0; cipher.nonce_len() + val.len() + cipher.tag_len()];
+    let (nonce, in_out) = data.split_at_mut(cipher.nonce_len());
     let (in_out, tag) = in_out.split_at_mut(val.len());
     in_out.copy_from_slice(val);
 
     let mut rng = rand::thread_rng();
     rng.try_fill_bytes(nonce)
         .expect("couldn't random fill nonce");
-    let nonce = GenericArray::clone_from_slice(nonce);
 
     // Use the UUID to preform actual cookie Sealing.
     let aad = name.as_bytes();
-    let aead = Aes256Gcm::new(GenericArray::from_slice(key.encryption()));
-    let aad_tag = aead
-        .encrypt_in_place_detached(&nonce, aad, in_out)
-        .expect("encryption failure!");
+    let aad_tag = match cipher {
+        CipherSuite::Aes256Gcm => seal::<Aes256Gcm>(key, nonce, aad, in_out),
+        CipherSuite::ChaCha20Poly1305 => seal::<ChaCha20Poly1305>(key, nonce, aad, in_out),
+        CipherSuite::XChaCha20Poly1305 => seal::<XChaCha20Poly1305>(key, nonce, aad, in_out),
+    };
 
-    tag.copy_from_slice(aad_tag.as_slice());
+    tag.copy_from_slice(&aad_tag);
 
     general_purpose::STANDARD.encode(&data)
 }
 
-#[cfg(feature = "rest_mode")]
-///Used to deencrypt the Header Values and key values.
-pub(crate) fn decrypt(name: &str, value: &str, key: &Key) -> Result<String, SessionError> {
+///Used to deencrypt the Header Values, key values and Session data stored with `encrypt_at_rest`.
+///
+/// # Errors
+/// - ['SessionError::Decode'] is returned if the value is not valid base64.
+/// - ['SessionError::DataTooShort'] is returned if the value can not hold a nonce and tag.
+/// - ['SessionError::Decrypt'] is returned if the value was sealed with a different key or cipher or was altered.
+pub(crate) fn decrypt(
+    name: &str,
+    value: &str,
+    key: &Key,
+    cipher: CipherSuite,
+) -> Result<String, SessionError> {
     let data = general_purpose::STANDARD.decode(value)?;
-    if data.len() <= NONCE_LEN {
-        return Err(SessionError::GenericNotSupportedError(
-            "length of decoded data is <= NONCE_LEN".to_owned(),
-        ));
+
+    if data.len() < cipher.nonce_len() + cipher.tag_len() {
+        return Err(SessionError::DataTooShort);
     }
 
-    let (nonce, cipher) = data.split_at(NONCE_LEN);
+    let (nonce, cipher_text) = data.split_at(cipher.nonce_len());
     let payload = Payload {
-        msg: cipher,
+        msg: cipher_text,
         aad: name.as_bytes(),
     };
 
-    let aead = Aes256Gcm::new(GenericArray::from_slice(key.encryption()));
-    Ok(String::from_utf8(
-        aead.decrypt(GenericArray::from_slice(nonce), payload)
-            .map_err(|_| {
-                SessionError::GenericNotSupportedError(
-                    "invalid key/nonce/value: bad seal".to_owned(),
-                )
-            })?,
-    )?)
+    let value = match cipher {
+        CipherSuite::Aes256Gcm => open::<Aes256Gcm>(key, nonce, payload),
+        CipherSuite::ChaCha20Poly1305 => open::<ChaCha20Poly1305>(key, nonce, payload),
+        CipherSuite::XChaCha20Poly1305 => open::<XChaCha20Poly1305>(key, nonce, payload),
+    }?;
+
+    Ok(String::from_utf8(value)?)
+}
+
+/// Decrypts the value with key, trying previous when key fails so values written before
+/// a key rotation still open. The error from key is returned if both fail.
+pub(crate) fn decrypt_rotated(
+    name: &str,
+    value: &str,
+    key: &Key,
+    previous: Option<&Key>,
+    cipher: CipherSuite,
+) -> Result<String, SessionError> {
+    decrypt(name, value, key, cipher).or_else(|err| match previous {
+        Some(previous) => decrypt(name, value, previous, cipher).map_err(|_| err),
+        None => Err(err),
+    })
+}
+
+/// Encrypts in_out in place with the given AEAD returning the detached tag.
+fn seal<A: AeadInPlace + KeyInit>(
+    key: &Key,
+    nonce: &[u8],
+    aad: &[u8],
+    in_out: &mut [u8],
+) -> Vec<u8> {
+    let aead = A::new(GenericArray::from_slice(key.encryption()));
+    aead.encrypt_in_place_detached(GenericArray::from_slice(nonce), aad, in_out)
+        .expect("encryption failure!")
+        .to_vec()
+}
+
+/// Decrypts the payload with the given AEAD.
+fn open<A: Aead + KeyInit>(
+    key: &Key,
+    nonce: &[u8],
+    payload: Payload,
+) -> Result<Vec<u8>, SessionError> {
+    let aead = A::new(GenericArray::from_slice(key.encryption()));
+    aead.decrypt(GenericArray::from_slice(nonce), payload)
+        .map_err(|_| SessionError::Decrypt)
 }
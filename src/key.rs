@@ -2,7 +2,7 @@ use crate::{DatabasePool, Session, SessionError, SessionID, SessionStore};
 use aes_gcm::aead::{generic_array::GenericArray, Aead, AeadInPlace, KeyInit, Payload};
 use aes_gcm::Aes256Gcm;
 use base64::{engine::general_purpose, Engine as _};
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Utc};
 pub use cookie::Key;
 use rand::RngCore;
 use std::fmt::{self, Debug, Formatter};
@@ -30,7 +30,7 @@ impl Debug for SessionKey {
 impl SessionKey {
     pub(crate) fn new() -> Self {
         Self {
-            id: SessionID(Uuid::default()),
+            id: SessionID(Uuid::default().to_string()),
             autoremove: Utc::now(),
             key: Key::generate(),
         }
@@ -39,7 +39,7 @@ impl SessionKey {
     /// Uses the Cookie Value to check if the key Exists or not.
     /// If the key does not Exist in the inner memory table then we load it from the database.
     /// if neither work then we make a new key.
-    pub(crate) async fn get_or_create<S>(store: &SessionStore<S>, value: Option<Uuid>) -> Self
+    pub(crate) async fn get_or_create<S>(store: &SessionStore<S>, value: Option<String>) -> Self
     where
         S: DatabasePool + Clone + Debug + Sync + Send + 'static,
     {
@@ -47,7 +47,7 @@ impl SessionKey {
             let id: SessionID = SessionID(v);
 
             if let Some(mut value) = store.keys.get_mut(&id.inner()) {
-                value.autoremove = Utc::now() + store.config.memory_lifespan;
+                value.autoremove = store.config.now() + store.config.memory_lifespan;
                 return value.clone();
             }
 
@@ -57,12 +57,12 @@ impl SessionKey {
             }
         }
 
-        let id = Session::generate_uuid(store).await;
+        let id = Session::generate_id(store).await;
         let key = Key::generate();
 
         let session_key = Self {
             id,
-            autoremove: Utc::now() + store.config.memory_lifespan,
+            autoremove: store.config.now() + store.config.memory_lifespan,
             key,
         };
 
@@ -84,7 +84,7 @@ impl SessionKey {
         // When we renew a SessionID we also should renew the Key SessionID and Key for extra Security.
         // This is the best time to do this as it doesnt disturb the force and loss data.
         // Switching the config Key and config Database Key however will invalidate everything.
-        self.id = Session::generate_uuid(store).await;
+        self.id = Session::generate_id(store).await;
         self.key = Key::generate();
 
         store.keys.insert(self.id.inner(), self.clone());
@@ -122,7 +122,7 @@ impl SessionKey {
         name: SessionID,
         value: &str,
         key: Key,
-        memory_life_span: Duration,
+        autoremove: DateTime<Utc>,
     ) -> Result<Self, SessionError> {
         let data = general_purpose::STANDARD.decode(value)?;
         if data.len() <= NONCE_LEN {
@@ -149,7 +149,7 @@ impl SessionKey {
 
         Ok(Self {
             id: name,
-            autoremove: Utc::now() + memory_life_span,
+            autoremove,
             key: Key::from(&key),
         })
     }
@@ -0,0 +1,112 @@
+use crate::Key;
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::{PoisonError, RwLock},
+};
+
+/// Supplies the cookie encryption keys so they can be rotated while the app is running.
+///
+/// Set it with `SessionConfig::with_key_rotation_schedule`. New Cookies, Headers and
+/// encrypted database rows are always written with `current()` while anything `current()`
+/// fails to decrypt is tried again with `previous()`. Cookies are re-sent on every
+/// Response so clients move to the new key on their next Request.
+///
+/// Both keys are asked for on every Request so they should be cached and refreshed in
+/// the background rather than fetched from a secrets manager each call.
+///
+/// # Overlap window
+/// A rotated out key must stay as `previous()` until nothing written with it is still in
+/// use, which is at least the cookie max age or the Session lifespan if that is longer.
+/// With `with_encrypt_at_rest` it must also outlive every row written with it, as rows
+/// only move to the new key when the Session is next saved. Rotating twice within the
+/// window drops the oldest key and the Sessions still using it.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::{Key, KeyProvider};
+///
+/// #[derive(Debug)]
+/// struct VaultKeys { .. }
+///
+/// impl KeyProvider for VaultKeys {
+///     fn current(&self) -> Key { .. }
+///
+///     fn previous(&self) -> Option<Key> { .. }
+/// }
+/// ```
+///
+pub trait KeyProvider: Debug + Send + Sync {
+    /// Key used to encrypt everything written from now on.
+    fn current(&self) -> Key;
+
+    /// Key that was current before the last rotation, tried when `current()` fails to decrypt.
+    fn previous(&self) -> Option<Key>;
+}
+
+/// In process `KeyProvider` holding the current key and the one it replaced.
+///
+/// # Examples
+/// ```rust
+/// use axum_session::{Key, KeyRing, SessionConfig};
+/// use std::sync::Arc;
+///
+/// let ring = Arc::new(KeyRing::new(Key::generate()));
+/// let config = SessionConfig::default().with_key_rotation_schedule(ring.clone());
+///
+/// // Later, like from a scheduled task.
+/// ring.rotate(Key::generate());
+/// ```
+///
+pub struct KeyRing {
+    keys: RwLock<(Key, Option<Key>)>,
+}
+
+impl Debug for KeyRing {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyRing")
+            .field("keys", &"keys hidden")
+            .finish()
+    }
+}
+
+impl KeyRing {
+    /// Creates a KeyRing using key as the current key.
+    ///
+    pub fn new(key: Key) -> Self {
+        Self {
+            keys: RwLock::new((key, None)),
+        }
+    }
+
+    /// Makes key the current key keeping the replaced one as the previous key.
+    ///
+    pub fn rotate(&self, key: Key) {
+        let mut keys = self.keys.write().unwrap_or_else(PoisonError::into_inner);
+        let replaced = std::mem::replace(&mut keys.0, key);
+        keys.1 = Some(replaced);
+    }
+
+    /// Drops the previous key once the overlap window has passed.
+    ///
+    pub fn retire_previous(&self) {
+        self.keys.write().unwrap_or_else(PoisonError::into_inner).1 = None;
+    }
+}
+
+impl KeyProvider for KeyRing {
+    fn current(&self) -> Key {
+        self.keys
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .0
+            .clone()
+    }
+
+    fn previous(&self) -> Option<Key> {
+        self.keys
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .1
+            .clone()
+    }
+}
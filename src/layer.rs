@@ -1,8 +1,37 @@
-use std::fmt;
+use std::{borrow::Cow, fmt, sync::Arc};
 
-use crate::{DatabasePool, SessionService, SessionStore};
+use crate::{
+    session_scoped::{insert_scoped, insert_session, SessionInserter},
+    DatabasePool, SessionError, SessionService, SessionStore,
+};
 use tower_layer::Layer;
 
+/// When the `SessionLayer` persists the Session relative to the Response body.
+///
+/// `Set-Cookie` and the rest of the Session Headers are always added when the handler
+/// returns the Response, as Headers can not be sent once the first body bytes are. Changes
+/// made to the Session while the body streams, like from within a Server-Sent Events
+/// stream, can be persisted with `AfterBody` but can not change the Cookies sent, so
+/// renewing or destroying the Session must happen before the handler returns.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::{CommitTiming, SessionLayer};
+///
+/// let layer = SessionLayer::new(session_store).with_commit_timing(CommitTiming::AfterBody);
+/// ```
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CommitTiming {
+    /// Persists the Session as soon as the handler returns, before any of the body is sent.
+    /// Long or endless streams do not hold back the save. This is the Default.
+    #[default]
+    AfterHandler,
+    /// Persists the Session once the body finished streaming or was dropped, like when the
+    /// client disconnects, so changes made while streaming are saved too.
+    AfterBody,
+}
+
 /// Sessions Layer used with Axum to activate the Service.
 ///
 /// # Examples
@@ -21,6 +50,8 @@ where
     T: DatabasePool + Clone + fmt::Debug + std::marker::Sync + std::marker::Send + 'static,
 {
     session_store: SessionStore<T>,
+    insert: SessionInserter<T>,
+    commit_timing: CommitTiming,
 }
 
 impl<T> SessionLayer<T>
@@ -41,7 +72,83 @@ where
     ///
     #[inline]
     pub fn new(session_store: SessionStore<T>) -> Self {
-        SessionLayer { session_store }
+        SessionLayer {
+            session_store,
+            insert: insert_session::<T>,
+            commit_timing: CommitTiming::AfterHandler,
+        }
+    }
+
+    /// Set's the layer to give its Session to handlers as `SessionScoped<T, Scope>`
+    /// instead of `Session<T>`.
+    ///
+    /// This lets layers with different configs be nested on the same Request, like a
+    /// short lived admin Session within the public site's Session, without one replacing
+    /// the other. Each layer's config must use different Cookie or Header names, like
+    /// with `SessionConfig::with_name_prefix`. The `SessionStore` and `ReadOnlySession`
+    /// extractors only see the unscoped layer's Session.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// struct Admin;
+    ///
+    /// let layer = SessionLayer::new(admin_store).with_scope::<Admin>();
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_scope<Scope: 'static>(mut self) -> Self {
+        self.insert = insert_scoped::<T, Scope>;
+        self
+    }
+
+    /// Set's when the Session is persisted relative to the Response body.
+    /// Default is `CommitTiming::AfterHandler`. See `CommitTiming`.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{CommitTiming, SessionLayer};
+    ///
+    /// let layer = SessionLayer::new(session_store).with_commit_timing(CommitTiming::AfterBody);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_commit_timing(mut self, timing: CommitTiming) -> Self {
+        self.commit_timing = timing;
+        self
+    }
+
+    /// Set's the cookie path for this layer only, overriding the config's cookie path.
+    ///
+    /// Useful when the app is mounted under a sub path like `/app` so the Cookies
+    /// do not leak to sibling apps on the same host. The config is validated again so
+    /// a `__Host-` prefixed Cookie can only use the path `/`.
+    ///
+    /// # Errors
+    /// - ['SessionError::Config'] is returned if the path is invalid for the config.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let app = Router::new().nest(
+    ///     "/app",
+    ///     app_routes().layer(SessionLayer::new(session_store).with_cookie_path("/app")?),
+    /// );
+    /// ```
+    ///
+    pub fn with_cookie_path(
+        mut self,
+        path: impl Into<Cow<'static, str>>,
+    ) -> Result<Self, SessionError> {
+        // Only this layer's copy of the config is changed.
+        let config = &mut Arc::make_mut(&mut self.session_store.shared).config;
+        config.cookie_path = path.into();
+
+        if config.lenient_validation {
+            config.validate_lenient();
+        } else {
+            config.validate()?;
+        }
+
+        Ok(self)
     }
 }
 
@@ -54,6 +161,8 @@ where
     fn layer(&self, inner: S) -> Self::Service {
         SessionService {
             session_store: self.session_store.clone(),
+            insert: self.insert,
+            commit_timing: self.commit_timing,
             inner,
         }
     }
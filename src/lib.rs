@@ -3,27 +3,66 @@
 #![warn(clippy::all, nonstandard_style, future_incompatible)]
 #![forbid(unsafe_code)]
 
+mod clock;
+#[cfg(feature = "zstd")]
+mod compression;
 mod config;
 pub mod databases;
 mod errors;
 pub(crate) mod headers;
 mod key;
+mod key_provider;
 mod layer;
+pub mod middleware;
+mod serializer;
 mod service;
 mod session;
+mod session_broadcast;
 mod session_data;
+mod session_export;
+mod session_memory;
+mod session_metrics;
+mod session_namespace;
+mod session_rate_limit;
+mod session_scoped;
+mod session_snapshot;
 mod session_store;
+mod session_watcher;
+#[cfg(feature = "test-util")]
+pub mod test_util;
 
-pub use config::{Key, SameSite, SecurityMode, SessionConfig, SessionMode};
+pub use clock::{Clock, SystemClock};
+#[cfg(feature = "zstd")]
+pub use compression::CompressionLevel;
+pub use config::{
+    CipherSuite, CookieEncoding, CookiePrefix, DataMigrator, DomainResolver, ExpiryAttr,
+    IdGenerator, IdKind, IdValidator, Key, RetryPolicy, SameSite, SchemaMigrator, SecurityMode,
+    SessionConfig, SessionMode,
+};
 pub use databases::*;
-pub use errors::SessionError;
+pub use errors::{ConfigError, HostWarning, SessionError};
 pub use key::SessionKey;
-pub use layer::SessionLayer;
-pub use session::{ReadOnlySession, Session};
+pub use key_provider::{KeyProvider, KeyRing};
+pub use layer::{CommitTiming, SessionLayer};
+pub use serializer::SessionSerializer;
+pub use session::{PeekSession, ReadOnlySession, Session};
+#[cfg(feature = "redis-db")]
+pub use session_broadcast::SessionRedisBroadcast;
+pub use session_broadcast::{
+    InvalidationEvent, InvalidationTarget, SessionBroadcast, SessionLocalBroadcast,
+};
+pub use session_data::SessionData;
+pub use session_metrics::{MetricsSnapshot, StoreStats};
+pub use session_namespace::SessionNamespace;
+pub use session_rate_limit::RateLimitDecision;
+pub use session_scoped::SessionScoped;
 pub use session_store::SessionStore;
+pub use session_watcher::{SessionEvent, SessionWatcher};
 
 pub(crate) use service::SessionService;
-pub(crate) use session_data::{SessionData, SessionID, SessionTimers};
+pub(crate) use session_data::{SessionID, SessionTimers};
+pub(crate) use session_memory::{MemoryStore, SessionMemory};
+pub(crate) use session_metrics::SessionMetrics;
 
 #[cfg(test)]
 mod tests {
@@ -31,6 +70,7 @@ mod tests {
     use super::*;
     use axum::{
         body::Body,
+        extract::Path,
         http::{header, Request},
         response::Redirect,
         routing::get,
@@ -143,4 +183,4672 @@ mod tests {
         let body = String::from_utf8(bytes).unwrap();
         assert_eq!(body, "Success");
     }
+
+    #[cfg(feature = "rest_mode")]
+    #[test]
+    fn cipher_suites_do_not_interop() {
+        use crate::headers::{decrypt, encrypt};
+
+        let key = Key::generate();
+        let suites = [
+            CipherSuite::Aes256Gcm,
+            CipherSuite::ChaCha20Poly1305,
+            CipherSuite::XChaCha20Poly1305,
+        ];
+
+        for sealed_with in suites {
+            let token = encrypt("session", "Hello World", &key, sealed_with);
+
+            for opened_with in suites {
+                let opened = decrypt("session", &token, &key, opened_with);
+
+                if sealed_with == opened_with {
+                    assert_eq!(opened.unwrap(), "Hello World");
+                } else {
+                    assert!(opened.is_err());
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn random_256_ids() {
+        use test_util::TestClient;
+
+        let config = SessionConfig::default().with_id_kind(IdKind::Random256);
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/set_session",
+                get(|session: Session<SessionNullPool>| async move {
+                    session.set("test", 42);
+                }),
+            )
+            .route(
+                "/test_session",
+                get(|session: Session<SessionNullPool>| async move {
+                    session.get::<u32>("test").unwrap_or_default().to_string()
+                }),
+            );
+
+        let mut client = TestClient::with_session_store(session_store.clone(), app);
+        client.get("/set_session").await;
+        let id = client.held("session").unwrap().to_owned();
+        assert_eq!(id.len(), 43);
+        assert!(id
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_'));
+        assert!(session_store.inner.contains_key(&id));
+
+        let response = client.get("/test_session").await;
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(bytes, "42");
+
+        let validator = IdKind::Random256.validator();
+        assert!(validator(&id));
+        assert!(!validator(&uuid::Uuid::new_v4().to_string()));
+        assert!(!validator(&format!("{}=", &id[..42])));
+        assert!(IdKind::Uuid.validator()(&IdKind::Uuid.generator()()));
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn custom_id_generator() {
+        use std::sync::Arc;
+
+        let config = SessionConfig::default()
+            .with_id_generator(Arc::new(|| {
+                format!("sess_{}", uuid::Uuid::new_v4().simple())
+            }))
+            .with_id_validator(Arc::new(|id| id.starts_with("sess_")));
+
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route("/set_session", get(set_session))
+            .route("/test_session", get(test_session))
+            .layer(SessionLayer::new(session_store));
+
+        async fn set_session(session: Session<SessionNullPool>) -> String {
+            session.set("test", 42);
+            session.get_session_id().inner()
+        }
+
+        async fn test_session(session: Session<SessionNullPool>) -> String {
+            session.get::<u32>("test").unwrap_or_default().to_string()
+        }
+
+        let request = Request::builder()
+            .uri("/set_session")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+
+        let cookies: Vec<_> = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .cloned()
+            .collect();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let id = String::from_utf8(bytes.to_vec()).unwrap();
+        assert!(id.starts_with("sess_"));
+
+        let mut request = Request::builder()
+            .uri("/test_session")
+            .body(Body::empty())
+            .unwrap();
+
+        for cookie in cookies {
+            request.headers_mut().append(header::COOKIE, cookie);
+        }
+
+        let response = app.clone().oneshot(request).await.unwrap();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(String::from_utf8(bytes.to_vec()).unwrap(), "42");
+
+        // A UUID is rejected by the validator so a new Session is created.
+        let request = Request::builder()
+            .uri("/test_session")
+            .header(header::COOKIE, format!("session={}", uuid::Uuid::new_v4()))
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(String::from_utf8(bytes.to_vec()).unwrap(), "0");
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn host_prefixed_removal_cookie() {
+        let config = SessionConfig::default()
+            .with_mode(SessionMode::OptIn)
+            .with_prefix_with_host(true)
+            .with_cookie_domain("example.com")
+            .with_lenient_validation(true);
+
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route("/", get(|| async { "" }))
+            .layer(SessionLayer::new(session_store));
+
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+
+        let removals: Vec<_> = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+            .collect();
+
+        assert!(!removals.is_empty());
+
+        for cookie in removals {
+            assert!(cookie.name().starts_with("__Host-"));
+            assert_eq!(cookie.domain(), None);
+            assert_eq!(cookie.secure(), Some(true));
+            assert_eq!(cookie.path(), Some("/"));
+        }
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn secure_auto_follows_request_scheme() {
+        let config = SessionConfig::default().with_secure_auto(true);
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route("/", get(|| async { "" }))
+            .layer(SessionLayer::new(session_store));
+
+        let requests = [
+            (Request::builder().uri("/"), false),
+            (
+                Request::builder()
+                    .uri("/")
+                    .header("x-forwarded-proto", "https, http"),
+                true,
+            ),
+            (
+                Request::builder()
+                    .uri("/")
+                    .header("x-forwarded-proto", "http"),
+                false,
+            ),
+            (
+                Request::builder()
+                    .uri("/")
+                    .header(header::FORWARDED, "for=192.0.2.60;Proto=\"https\""),
+                true,
+            ),
+            (Request::builder().uri("https://example.com/"), true),
+        ];
+
+        for (request, secure) in requests {
+            let request = request.body(Body::empty()).unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            let cookie = response
+                .headers()
+                .get_all(header::SET_COOKIE)
+                .iter()
+                .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+                .find(|cookie| cookie.name() == "session")
+                .unwrap();
+
+            assert_eq!(cookie.secure().unwrap_or(false), secure);
+        }
+
+        // Prefixes need the flag on every Request.
+        assert_eq!(
+            SessionConfig::default()
+                .with_secure(true)
+                .with_secure_auto(true)
+                .with_cookie_prefix(CookiePrefix::Secure)
+                .validate(),
+            Err(ConfigError::SecurePrefix)
+        );
+    }
+
+    #[cfg(feature = "zstd")]
+    #[tokio::test]
+    async fn compression_round_trip() {
+        let config = SessionConfig::default().with_compression(CompressionLevel::Default, 1024);
+        let session_store = SessionStore::<SessionNullPool>::new(None, config.clone())
+            .await
+            .unwrap();
+
+        let mut small = SessionData::new("small".to_owned(), true, &config);
+        small.set("test", "Hello World").unwrap();
+
+        let data = session_store.encode_session(&small).unwrap();
+        assert!(!data.starts_with(compression::MAGIC));
+
+        let loaded = session_store.decode_session("small", data).unwrap();
+        assert_eq!(loaded.get::<String>("test").unwrap(), "Hello World");
+
+        let mut large = SessionData::new("large".to_owned(), true, &config);
+        large.set("test", "Hello World".repeat(1000)).unwrap();
+
+        let data = session_store.encode_session(&large).unwrap();
+        assert!(data.starts_with(compression::MAGIC));
+        assert!(data.len() < "Hello World".len() * 1000);
+
+        let loaded = session_store.decode_session("large", data).unwrap();
+        assert_eq!(
+            loaded.get::<String>("test").unwrap(),
+            "Hello World".repeat(1000)
+        );
+    }
+
+    #[tokio::test]
+    async fn encrypt_at_rest_round_trip() {
+        let config = SessionConfig::default().with_encrypt_at_rest(true);
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::EncryptAtRestWithoutKey)
+        ));
+
+        let config = config.with_key(Key::generate());
+        let session_store = SessionStore::<SessionNullPool>::new(None, config.clone())
+            .await
+            .unwrap();
+
+        let mut session = SessionData::new("encrypted".to_owned(), true, &config);
+        session.set("secret", "Hello World").unwrap();
+
+        let data = session_store.encode_session(&session).unwrap();
+        assert!(data.starts_with(session_store::ENCRYPTED_MAGIC));
+        assert!(!data.contains("Hello World"));
+
+        let loaded = session_store
+            .decode_session("encrypted", data.clone())
+            .unwrap();
+        assert_eq!(loaded.get::<String>("secret").unwrap(), "Hello World");
+
+        // Data copied to another Session's row fails to decrypt.
+        assert!(session_store.decode_session("other", data).is_err());
+
+        // Rows stored before encryption was enabled still load.
+        let plain = config.serializer.serialize(&session).unwrap();
+        let loaded = session_store.decode_session("encrypted", plain).unwrap();
+        assert_eq!(loaded.get::<String>("secret").unwrap(), "Hello World");
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn cookie_expiry_attr() {
+        for attr in [ExpiryAttr::MaxAge, ExpiryAttr::Expires, ExpiryAttr::Both] {
+            let config = SessionConfig::default().with_cookie_expiry_attr(attr);
+            let session_store = SessionStore::<SessionNullPool>::new(None, config)
+                .await
+                .unwrap();
+
+            let app = Router::new()
+                .route("/", get(|| async { "" }))
+                .layer(SessionLayer::new(session_store));
+
+            let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+            let response = app.oneshot(request).await.unwrap();
+
+            let cookie = response
+                .headers()
+                .get_all(header::SET_COOKIE)
+                .iter()
+                .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+                .find(|cookie| cookie.name() == "session")
+                .unwrap();
+
+            assert_eq!(cookie.max_age().is_some(), attr != ExpiryAttr::Expires);
+            assert_eq!(cookie.expires().is_some(), attr != ExpiryAttr::MaxAge);
+        }
+    }
+
+    #[cfg(all(feature = "test-util", not(feature = "rest_mode")))]
+    #[tokio::test]
+    async fn cookie_expires_follows_clock() {
+        let now = chrono::DateTime::parse_from_rfc3339("2030-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let config = SessionConfig::default()
+            .with_cookie_expiry_attr(ExpiryAttr::Expires)
+            .with_clock(std::sync::Arc::new(test_util::MockClock::at(now)));
+        let max_age = config.cookie_max_age.unwrap();
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route("/", get(|| async { "" }))
+            .layer(SessionLayer::new(session_store));
+
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+
+        let cookie = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+            .find(|cookie| cookie.name() == "session")
+            .unwrap();
+
+        assert_eq!(
+            cookie.expires_datetime().unwrap().unix_timestamp(),
+            (now + max_age).timestamp()
+        );
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn cookie_encoding() {
+        let mut headers = http::HeaderMap::new();
+        headers.insert(header::COOKIE, "session=a%2Fb".parse().unwrap());
+        let encoded = headers::get_cookies(&headers, CookieEncoding::Encoded);
+        assert_eq!(encoded.get("session").unwrap().value(), "a/b");
+        let raw = headers::get_cookies(&headers, CookieEncoding::Raw);
+        assert_eq!(raw.get("session").unwrap().value(), "a%2Fb");
+
+        for encoding in [CookieEncoding::Encoded, CookieEncoding::Raw] {
+            let config = SessionConfig::default().with_cookie_encoding(encoding);
+            let session_store = SessionStore::<SessionNullPool>::new(None, config)
+                .await
+                .unwrap();
+
+            let app = Router::new()
+                .route(
+                    "/",
+                    get(|session: Session<SessionNullPool>| async move {
+                        let count: usize = session.get("count").unwrap_or(0) + 1;
+                        session.set("count", count);
+                        count.to_string()
+                    }),
+                )
+                .layer(SessionLayer::new(session_store));
+
+            let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+
+            // Cookies are sent back exactly as they were set.
+            let cookies: Vec<String> = response
+                .headers()
+                .get_all(header::SET_COOKIE)
+                .iter()
+                .map(|value| {
+                    let value = value.to_str().unwrap();
+                    value.split(';').next().unwrap().to_owned()
+                })
+                .collect();
+
+            let request = Request::builder()
+                .uri("/")
+                .header(header::COOKIE, cookies.join("; "))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, "2");
+        }
+    }
+
+    #[tokio::test]
+    async fn max_session_size() {
+        // "key" plus the Json string "\"value\"" is 3 + 7 bytes.
+        let config = SessionConfig::default().with_max_session_size(10);
+        let mut session = SessionData::new("size".to_owned(), true, &config);
+
+        session.set("key", "value").unwrap();
+        assert_eq!(session.size(), 10);
+
+        // Replacing a value only counts the new value.
+        session.set("key", "other").unwrap();
+        assert_eq!(session.get::<String>("key").unwrap(), "other");
+
+        let err = session.set("key", "value1").unwrap_err();
+        assert!(matches!(err, SessionError::SizeLimitExceeded(10)));
+        assert_eq!(session.get::<String>("key").unwrap(), "other");
+
+        let err = session.try_set("a", "").unwrap_err();
+        assert!(matches!(err, SessionError::SizeLimitExceeded(10)));
+        assert_eq!(session.size(), 10);
+
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+        session_store.inner.insert("size".to_owned(), session);
+        let (session, _) = Session::new(session_store, Some("size".to_owned())).await;
+        assert_eq!(session.data_len(), 10);
+    }
+
+    #[cfg(any(feature = "sqlite-rustls", feature = "sqlite-native"))]
+    #[tokio::test]
+    async fn memory_limit_eviction() {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+
+        let config = SessionConfig::default().with_memory_limit(3);
+        let session_store = SessionStore::<SessionSqlitePool>::new(Some(pool.into()), config)
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|session: Session<SessionSqlitePool>| async move {
+                    let count: u32 = session.get("count").unwrap_or(0) + 1;
+                    session.set("count", count);
+                    count.to_string()
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let mut ids = Vec::new();
+
+        for _ in 0..5 {
+            let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            let cookie = response
+                .headers()
+                .get_all(header::SET_COOKIE)
+                .iter()
+                .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+                .find(|cookie| cookie.name() == "session")
+                .unwrap();
+
+            ids.push(cookie.value().to_owned());
+        }
+
+        assert_eq!(session_store.inner.len(), 3);
+        assert!(ids[..2]
+            .iter()
+            .all(|id| !session_store.inner.contains_key(id)));
+        assert!(ids[2..]
+            .iter()
+            .all(|id| session_store.inner.contains_key(id)));
+
+        // The evicted Session reloads from the database.
+        let request = Request::builder()
+            .uri("/")
+            .header(header::COOKIE, format!("session={}", ids[0]))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"2");
+        assert_eq!(session_store.inner.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn memory_limit_evicts_oldest() {
+        let config = SessionConfig::default().with_memory_limit(4);
+        let session_store = SessionStore::<SessionNullPool>::new(None, config.clone())
+            .await
+            .unwrap();
+
+        // Inserted out of order so the oldest are not simply the first ones.
+        for age in [7, 2, 9, 0, 5, 3, 8, 1, 6, 4] {
+            let mut data = SessionData::new(age.to_string(), true, &config);
+            data.last_accessed = chrono::Utc::now() - chrono::Duration::minutes(age);
+            data.requests = 0;
+            session_store.inner.insert(age.to_string(), data);
+        }
+
+        session_store.evict_memory().await;
+        assert_eq!(session_store.inner.len(), 4);
+        assert!((0..4).all(|age| session_store.inner.contains_key(&age.to_string())));
+
+        // Under the limit nothing is unloaded.
+        session_store.evict_memory().await;
+        assert_eq!(session_store.inner.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn memory_limit_evicts_in_batches() {
+        let config = SessionConfig::default().with_memory_limit(20);
+        let session_store = SessionStore::<SessionNullPool>::new(None, config.clone())
+            .await
+            .unwrap();
+
+        for age in 0..21 {
+            let mut data = SessionData::new(age.to_string(), true, &config);
+            data.last_accessed = chrono::Utc::now() - chrono::Duration::minutes(age);
+            data.requests = 0;
+            session_store.inner.insert(age.to_string(), data);
+        }
+
+        // Going one over the limit unloads down to 90% of it.
+        session_store.evict_memory().await;
+        assert_eq!(session_store.inner.len(), 18);
+        assert!((0..18).all(|age| session_store.inner.contains_key(&age.to_string())));
+    }
+
+    #[tokio::test]
+    async fn for_each_session_lists_memory_sessions() {
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::default())
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|session: Session<SessionNullPool>| async move {
+                    session.set("visited", true);
+                    session.get_session_id().inner()
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let mut ids = Vec::new();
+
+        for _ in 0..2 {
+            let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            ids.push(String::from_utf8(body.to_vec()).unwrap());
+        }
+
+        let mut seen = Vec::new();
+        session_store
+            .for_each_session(|id, data| {
+                assert!(data.last_accessed() <= chrono::Utc::now());
+                seen.push((id.to_owned(), data.get::<bool>("visited")));
+            })
+            .unwrap();
+
+        seen.sort();
+        ids.sort();
+        assert_eq!(
+            seen,
+            ids.into_iter()
+                .map(|id| (id, Some(true)))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn store_metrics() {
+        let config = SessionConfig::default().with_key(Key::generate());
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route("/", get(|| async { "" }))
+            .route(
+                "/destroy",
+                get(|session: Session<SessionNullPool>| async move { session.destroy() }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let cookies: Vec<String> = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|value| {
+                let cookie = cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap();
+                format!("{}={}", cookie.name(), cookie.value())
+            })
+            .collect();
+
+        let metrics = session_store.metrics();
+        assert_eq!(metrics.created, 1);
+        assert_eq!(metrics.loaded_memory, 0);
+
+        let request = Request::builder()
+            .uri("/destroy")
+            .header(header::COOKIE, cookies.join("; "))
+            .body(Body::empty())
+            .unwrap();
+        app.clone().oneshot(request).await.unwrap();
+
+        let metrics = session_store.metrics();
+        assert_eq!(metrics.created, 1);
+        assert_eq!(metrics.loaded_memory, 1);
+        assert_eq!(metrics.destroyed, 1);
+
+        let request = Request::builder()
+            .uri("/")
+            .header(header::COOKIE, "session=tampered")
+            .body(Body::empty())
+            .unwrap();
+        app.oneshot(request).await.unwrap();
+
+        let metrics = session_store.metrics();
+        assert_eq!(metrics.created, 2);
+        assert_eq!(metrics.decrypt_failures, 1);
+        assert_eq!(metrics.db_calls, 0);
+    }
+
+    #[tokio::test]
+    async fn reject_invalid_tokens() {
+        let config = SessionConfig::default().with_reject_invalid_tokens(true);
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route("/", get(|| async { "called" }))
+            .layer(SessionLayer::new(session_store.clone()));
+
+        // First time visitors still get a Session.
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        #[cfg(not(feature = "rest_mode"))]
+        let tampered = (header::COOKIE, "session=tampered");
+        #[cfg(feature = "rest_mode")]
+        let tampered = (http::HeaderName::from_static("session"), "tampered");
+
+        let request = Request::builder()
+            .uri("/")
+            .header(tampered.0, tampered.1)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
+
+        #[cfg(not(feature = "rest_mode"))]
+        assert!(response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+            .any(|cookie| cookie.name() == "session" && cookie.value().is_empty()));
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(body.is_empty());
+
+        let metrics = session_store.metrics();
+        assert_eq!(metrics.decrypt_failures + metrics.invalid_ids, 1);
+        assert_eq!(metrics.rejected, 1);
+        assert_eq!(metrics.created, 1);
+    }
+
+    #[tokio::test]
+    async fn tracing_spans() {
+        use std::sync::{Arc, Mutex};
+        use tracing_subscriber::{layer::SubscriberExt, registry::LookupSpan, Layer};
+
+        #[derive(Clone, Default)]
+        struct SpanNames(Arc<Mutex<Vec<&'static str>>>);
+
+        impl<S: tracing::Subscriber + for<'a> LookupSpan<'a>> Layer<S> for SpanNames {
+            fn on_new_span(
+                &self,
+                attrs: &tracing::span::Attributes<'_>,
+                _id: &tracing::span::Id,
+                _ctx: tracing_subscriber::layer::Context<'_, S>,
+            ) {
+                self.0.lock().unwrap().push(attrs.metadata().name());
+            }
+        }
+
+        let names = SpanNames::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(names.clone()));
+
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::default())
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route("/", get(|| async { "" }))
+            .layer(SessionLayer::new(session_store));
+
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        app.oneshot(request).await.unwrap();
+
+        let names = names.0.lock().unwrap();
+        for name in ["session", "parse_headers", "call_inner", "set_headers"] {
+            assert!(names.contains(&name), "missing span {}", name);
+        }
+    }
+
+    #[cfg(feature = "rest_mode")]
+    #[test]
+    fn decrypt_failure_reasons() {
+        use base64::{engine::general_purpose, Engine as _};
+
+        let key = Key::generate();
+        let cipher = CipherSuite::Aes256Gcm;
+
+        let err = headers::decrypt("session", "not base64!", &key, cipher).unwrap_err();
+        assert!(matches!(err, SessionError::Decode(_)));
+
+        let short = general_purpose::STANDARD.encode([0u8; 27]);
+        let err = headers::decrypt("session", &short, &key, cipher).unwrap_err();
+        assert!(matches!(err, SessionError::DataTooShort));
+
+        let sealed = headers::encrypt("session", "value", &Key::generate(), cipher);
+        let err = headers::decrypt("session", &sealed, &key, cipher).unwrap_err();
+        assert!(matches!(err, SessionError::Decrypt));
+
+        let sealed = headers::encrypt("session", "", &key, cipher);
+        assert_eq!(
+            headers::decrypt("session", &sealed, &key, cipher).unwrap(),
+            ""
+        );
+    }
+
+    #[cfg(any(feature = "sqlite-rustls", feature = "sqlite-native"))]
+    #[tokio::test]
+    async fn store_stats() {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+
+        let config = SessionConfig::default();
+        let session_store =
+            SessionStore::<SessionSqlitePool>::new(Some(pool.into()), config.clone())
+                .await
+                .unwrap();
+
+        let stats = session_store.stats().await;
+        assert_eq!(stats.memory_sessions, 0);
+        assert_eq!(stats.database_sessions, Some(0));
+        assert_eq!(stats.database_healthy, Some(true));
+
+        let session = SessionData::new("stats".to_owned(), true, &config);
+        session_store.store_session(&session).await.unwrap();
+        session_store.inner.insert(session.id.clone(), session);
+
+        // The database count is cached so the new Session is not counted yet.
+        let stats = session_store.stats().await;
+        assert_eq!(stats.memory_sessions, 1);
+        assert_eq!(stats.database_sessions, Some(0));
+        assert_eq!(session_store.count().await.unwrap(), 1);
+
+        let json = serde_json::to_string(&stats).unwrap();
+        assert_eq!(serde_json::from_str::<StoreStats>(&json).unwrap(), stats);
+    }
+
+    #[test]
+    fn name_prefix() {
+        let config = SessionConfig::default().with_name_prefix("my_app");
+        assert_eq!(config.session_name, "my_app_id");
+        assert_eq!(config.store_name, "my_app_store");
+        assert_eq!(config.key_name, "my_app_key");
+
+        for prefix in ["", "my app", "my;app", "my=app", "my\tapp"] {
+            let result =
+                std::panic::catch_unwind(|| SessionConfig::default().with_name_prefix(prefix));
+            assert!(result.is_err(), "{:?} should be rejected", prefix);
+        }
+    }
+
+    #[cfg(any(feature = "sqlite-rustls", feature = "sqlite-native"))]
+    #[tokio::test]
+    async fn count_active_sessions() {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+
+        let config = SessionConfig::default();
+        let session_store =
+            SessionStore::<SessionSqlitePool>::new(Some(pool.into()), config.clone())
+                .await
+                .unwrap();
+
+        let live = SessionData::new("live".to_owned(), true, &config);
+        let mut expired = SessionData::new("expired".to_owned(), true, &config);
+        expired.expires = chrono::Utc::now() - chrono::Duration::hours(1);
+
+        session_store.store_session(&live).await.unwrap();
+        session_store.store_session(&expired).await.unwrap();
+
+        assert_eq!(session_store.count().await.unwrap(), 2);
+        assert_eq!(session_store.count_active().await.unwrap(), 1);
+
+        // Memory only stores filter on the Sessions expires.
+        let memory_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+        memory_store.inner.insert(live.id.clone(), live);
+        memory_store.inner.insert(expired.id.clone(), expired);
+
+        assert_eq!(memory_store.count_sessions().await, 2);
+        assert_eq!(memory_store.count_active_sessions().await, 1);
+    }
+
+    #[test]
+    fn csrf_token() {
+        let config = SessionConfig::default();
+        let mut session = SessionData::new("csrf".to_owned(), true, &config);
+        assert!(!session.verify_csrf(""));
+
+        let token = session.csrf_token();
+        assert_eq!(session.csrf_token(), token);
+        assert!(session.verify_csrf(&token));
+        assert!(!session.verify_csrf("wrong"));
+        assert!(!session.verify_csrf(&token[1..]));
+
+        session.renew();
+        assert!(!session.verify_csrf(&token));
+        let renewed = session.csrf_token();
+        assert_ne!(renewed, token);
+
+        session.destroy();
+        assert!(!session.verify_csrf(&renewed));
+    }
+
+    #[cfg(all(feature = "test-util", not(feature = "rest_mode")))]
+    #[tokio::test]
+    async fn session_timestamps() {
+        let clock = test_util::MockClock::new();
+        let config = SessionConfig::default().with_clock(std::sync::Arc::new(clock.clone()));
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|session: Session<SessionNullPool>| async move {
+                    assert!(session.remaining() > chrono::Duration::hours(5));
+                    format!(
+                        "{} {}",
+                        session.created_at().timestamp_micros(),
+                        session.last_accessed().timestamp_micros()
+                    )
+                }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let cookie = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+            .find(|cookie| cookie.name() == "session")
+            .unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let first: Vec<i64> = std::str::from_utf8(&body)
+            .unwrap()
+            .split(' ')
+            .map(|value| value.parse().unwrap())
+            .collect();
+
+        clock.advance(chrono::Duration::seconds(1));
+
+        let request = Request::builder()
+            .uri("/")
+            .header(header::COOKIE, format!("session={}", cookie.value()))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let second: Vec<i64> = std::str::from_utf8(&body)
+            .unwrap()
+            .split(' ')
+            .map(|value| value.parse().unwrap())
+            .collect();
+
+        assert_eq!(first[0], second[0]);
+        assert_eq!(second[1] - first[1], 1_000_000);
+
+        // Sessions stored before the timestamps existed default to now.
+        let old: SessionData = serde_json::from_str(r#"{"data":{},"longterm":false}"#).unwrap();
+        assert!(chrono::Utc::now() - old.created_at < chrono::Duration::seconds(5));
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn load_or_create_outside_request() {
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::default())
+            .await
+            .unwrap();
+
+        assert!(session_store.load_or_create("not-a-uuid").await.is_err());
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let session = session_store.load_or_create(&id).await.unwrap();
+        assert!(session.is_new());
+        session.set("notification", true);
+        session.save().await.unwrap();
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|session: Session<SessionNullPool>| async move {
+                    session
+                        .get::<bool>("notification")
+                        .unwrap_or(false)
+                        .to_string()
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let request = Request::builder()
+            .uri("/")
+            .header(header::COOKIE, format!("session={}", id))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "true");
+
+        let session = session_store.load_or_create(&id).await.unwrap();
+        assert!(!session.is_new());
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn explicit_expiry() {
+        let config = SessionConfig::default().with_cookie_expiry_attr(ExpiryAttr::MaxAge);
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+        let expiry = chrono::Utc::now() + chrono::Duration::days(30);
+
+        let app = Router::new()
+            .route(
+                "/set",
+                get(move |session: Session<SessionNullPool>| async move {
+                    session.set_expiry(expiry);
+                }),
+            )
+            .route(
+                "/get",
+                get(|session: Session<SessionNullPool>| async move {
+                    session.expires().timestamp().to_string()
+                }),
+            )
+            .route(
+                "/shrink",
+                get(|session: Session<SessionNullPool>| async move {
+                    session.extend_expiry(-chrono::Duration::days(60));
+                }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        let session_cookie = |response: &axum::response::Response<Body>| {
+            response
+                .headers()
+                .get_all(header::SET_COOKIE)
+                .iter()
+                .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+                .find(|cookie| cookie.name() == "session")
+                .unwrap()
+        };
+
+        let request = Request::builder().uri("/set").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let cookie = session_cookie(&response);
+        let max_age = cookie.max_age().unwrap().whole_days();
+        assert!((29..=30).contains(&max_age));
+
+        // Requests do not roll the explicit expiry forward.
+        let request = Request::builder()
+            .uri("/get")
+            .header(header::COOKIE, format!("session={}", cookie.value()))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, expiry.timestamp().to_string());
+
+        // Shrinking the expiry into the past destroys the Session.
+        let request = Request::builder()
+            .uri("/shrink")
+            .header(header::COOKIE, format!("session={}", cookie.value()))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(session_cookie(&response).value(), "");
+    }
+
+    #[tokio::test]
+    async fn store_extractor_destroys_session() {
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::default())
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/login",
+                get(|session: Session<SessionNullPool>| async move {
+                    session.set("user_id", 5);
+                    session.get_session_id().inner()
+                }),
+            )
+            .route(
+                "/admin/destroy/:id",
+                get(
+                    |store: SessionStore<SessionNullPool>, Path(id): Path<String>| async move {
+                        store.destroy_session(&id).await.unwrap();
+                    },
+                ),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let request = Request::builder()
+            .uri("/login")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let id = String::from_utf8(body.to_vec()).unwrap();
+
+        let data = session_store.get_session_data(&id).await.unwrap().unwrap();
+        assert_eq!(data.get::<i64>("user_id"), Some(5));
+
+        let request = Request::builder()
+            .uri(format!("/admin/destroy/{}", id))
+            .body(Body::empty())
+            .unwrap();
+        app.oneshot(request).await.unwrap();
+
+        assert!(session_store.get_session_data(&id).await.unwrap().is_none());
+        assert!(!session_store.exists(&id).await);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn lax_bootstrap_cookie() {
+        let config = SessionConfig::default()
+            .with_cookie_same_site(cookie::SameSite::Strict)
+            .with_lax_bootstrap(Some(chrono::Duration::minutes(5)));
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|session: Session<SessionNullPool>| async move {
+                    let count: usize = session.get("count").unwrap_or(0) + 1;
+                    session.set("count", count);
+                    count.to_string()
+                }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        let cookies = |response: &axum::response::Response<Body>| {
+            response
+                .headers()
+                .get_all(header::SET_COOKIE)
+                .iter()
+                .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let set = cookies(&response);
+        let session = set.iter().find(|c| c.name() == "session").unwrap();
+        let bootstrap = set.iter().find(|c| c.name() == "session_lax").unwrap();
+        assert_eq!(session.same_site(), Some(cookie::SameSite::Strict));
+        assert_eq!(bootstrap.same_site(), Some(cookie::SameSite::Lax));
+        assert_eq!(session.value(), bootstrap.value());
+
+        // A cross-site navigation only carries the Lax bootstrap Cookie.
+        let request = Request::builder()
+            .uri("/")
+            .header(header::COOKIE, format!("session_lax={}", bootstrap.value()))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let set = cookies(&response);
+        assert!(set.iter().all(|c| c.name() != "session_lax"));
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "2");
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn destroy_takes_effect_immediately() {
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::default())
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/login",
+                get(|session: Session<SessionNullPool>| async move {
+                    session.set("user_id", 5);
+                }),
+            )
+            .route(
+                "/logout",
+                get(|session: Session<SessionNullPool>| async move {
+                    session.destroy();
+                    assert!(session.is_destroyed());
+                    assert_eq!(session.get::<i64>("user_id"), None);
+
+                    session.set("user_id", 6);
+                    assert!(matches!(
+                        session.try_set("user_id", 6),
+                        Err(SessionError::SessionDestroyed)
+                    ));
+                    assert_eq!(session.get::<i64>("user_id"), None);
+                }),
+            )
+            .route(
+                "/",
+                get(|session: Session<SessionNullPool>| async move {
+                    format!("{:?}", session.get::<i64>("user_id"))
+                }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        let request = Request::builder()
+            .uri("/login")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let cookie = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+            .find(|cookie| cookie.name() == "session")
+            .unwrap();
+
+        for uri in ["/logout", "/"] {
+            let request = Request::builder()
+                .uri(uri)
+                .header(header::COOKIE, format!("session={}", cookie.value()))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+
+            if uri == "/" {
+                assert_eq!(body, "None");
+            }
+        }
+    }
+
+    #[test]
+    fn key_helpers() {
+        use base64::{engine::general_purpose, Engine as _};
+
+        let key = Key::generate();
+        let encoded = general_purpose::STANDARD.encode(key.master());
+        let config = SessionConfig::default()
+            .with_key_from_base64(&encoded)
+            .unwrap();
+        assert_eq!(config.key.unwrap().master(), key.master());
+
+        let short = general_purpose::STANDARD.encode([0u8; 32]);
+        assert!(matches!(
+            SessionConfig::default().with_key_from_base64(&short),
+            Err(SessionError::KeyTooShort(32, 64))
+        ));
+        assert!(SessionConfig::default()
+            .with_key_from_base64("not base64!")
+            .is_err());
+
+        let secret = [7u8; 32];
+        let derive = |context| {
+            SessionConfig::default()
+                .with_derived_key(&secret, context)
+                .unwrap()
+                .key
+                .unwrap()
+        };
+        assert_eq!(derive("app v1").master(), derive("app v1").master());
+        assert_ne!(derive("app v1").master(), derive("app v2").master());
+        assert!(matches!(
+            SessionConfig::default().with_derived_key(&[0u8; 16], "app"),
+            Err(SessionError::KeyTooShort(16, 32))
+        ));
+    }
+
+    #[cfg(all(
+        not(feature = "rest_mode"),
+        any(feature = "sqlite-rustls", feature = "sqlite-native")
+    ))]
+    #[tokio::test]
+    async fn logout_removes_session_immediately() {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+
+        let session_store =
+            SessionStore::<SessionSqlitePool>::new(Some(pool.into()), SessionConfig::default())
+                .await
+                .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/login",
+                get(|session: Session<SessionSqlitePool>| async move {
+                    session.set("user_id", 5);
+                }),
+            )
+            .route(
+                "/logout",
+                get(|session: Session<SessionSqlitePool>| async move {
+                    session.logout().await.unwrap();
+                    session.logout().await.unwrap();
+                    session.get_store().count().await.unwrap().to_string()
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let request = Request::builder()
+            .uri("/login")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let cookie = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+            .find(|cookie| cookie.name() == "session")
+            .unwrap();
+        assert_eq!(session_store.count().await.unwrap(), 1);
+
+        let request = Request::builder()
+            .uri("/logout")
+            .header(header::COOKIE, format!("session={}", cookie.value()))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let removal = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+            .find(|cookie| cookie.name() == "session")
+            .unwrap();
+        assert_eq!(removal.value(), "");
+
+        // The row was already gone while the handler was still running.
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "0");
+        assert!(session_store.inner.is_empty());
+    }
+
+    #[cfg(all(
+        not(feature = "rest_mode"),
+        any(feature = "sqlite-rustls", feature = "sqlite-native")
+    ))]
+    #[tokio::test]
+    async fn custom_table_name() {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+
+        let config = SessionConfig::default().with_table_name("tenant_sessions");
+        let session_store =
+            SessionStore::<SessionSqlitePool>::new(Some(pool.clone().into()), config)
+                .await
+                .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/login",
+                get(|session: Session<SessionSqlitePool>| async move {
+                    session.set("user_id", 5);
+                }),
+            )
+            .route(
+                "/user",
+                get(|session: Session<SessionSqlitePool>| async move {
+                    session
+                        .get::<i64>("user_id")
+                        .unwrap_or_default()
+                        .to_string()
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let request = Request::builder()
+            .uri("/login")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let cookie = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+            .find(|cookie| cookie.name() == "session")
+            .unwrap();
+
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tenant_sessions")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rows, 1);
+
+        // Loads the Session back from the custom table.
+        session_store.inner.clear();
+        let request = Request::builder()
+            .uri("/user")
+            .header(header::COOKIE, format!("session={}", cookie.value()))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "5");
+    }
+
+    #[test]
+    fn config_validation() {
+        assert_eq!(SessionConfig::default().validate(), Ok(()));
+
+        let invalid = [
+            (
+                SessionConfig::default().with_session_name(""),
+                ConfigError::InvalidName("".to_owned()),
+            ),
+            (
+                SessionConfig::default().with_key_name("my key"),
+                ConfigError::InvalidName("my key".to_owned()),
+            ),
+            (
+                SessionConfig::default().with_lifetime(chrono::Duration::zero()),
+                ConfigError::InvalidDuration("lifespan"),
+            ),
+            (
+                SessionConfig::default().with_max_lifetime(chrono::Duration::hours(-1)),
+                ConfigError::InvalidDuration("max_lifespan"),
+            ),
+            (
+                SessionConfig::default().with_memory_lifetime(chrono::Duration::hours(-1)),
+                ConfigError::InvalidDuration("memory_lifespan"),
+            ),
+        ];
+
+        for (config, err) in invalid {
+            assert_eq!(config.validate(), Err(err));
+        }
+
+        #[cfg(not(feature = "rest_mode"))]
+        {
+            let host = SessionConfig::default()
+                .with_prefix_with_host(true)
+                .with_secure(true);
+            assert_eq!(host.clone().validate(), Ok(()));
+
+            let invalid = [
+                (
+                    host.clone().with_cookie_domain("example.com"),
+                    ConfigError::HostPrefix,
+                ),
+                (
+                    host.clone().with_cookie_path("/app"),
+                    ConfigError::HostPrefix,
+                ),
+                (host.with_secure(false), ConfigError::HostPrefix),
+                (
+                    SessionConfig::default().with_cookie_prefix(CookiePrefix::Secure),
+                    ConfigError::SecurePrefix,
+                ),
+                (
+                    SessionConfig::default().with_cookie_same_site(cookie::SameSite::None),
+                    ConfigError::SameSiteNoneInsecure,
+                ),
+                (
+                    SessionConfig::default()
+                        .with_cookie_same_site_lax_get(true)
+                        .with_cookie_same_site(cookie::SameSite::Strict),
+                    ConfigError::LaxGetNotLax,
+                ),
+                (
+                    SessionConfig::default().with_max_age(Some(chrono::Duration::days(3_000_000))),
+                    ConfigError::MaxAgeTooLarge,
+                ),
+            ];
+
+            for (config, err) in invalid {
+                assert_eq!(config.validate(), Err(err));
+            }
+        }
+
+        for name in ["tenant_1.sessions", "app:sessions", "my-app:sessions", ""] {
+            assert_eq!(
+                SessionConfig::default().with_table_name(name).validate(),
+                Ok(())
+            );
+        }
+
+        for name in ["sessions; DROP TABLE users", "\"sessions\"", "sessions/*"] {
+            assert_eq!(
+                SessionConfig::default().with_table_name(name).validate(),
+                Err(ConfigError::InvalidTableName(name.to_owned()))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn config_validation_on_store() {
+        let config = SessionConfig::default().with_session_name("");
+        assert!(matches!(
+            SessionStore::<SessionNullPool>::new(None, config.clone()).await,
+            Err(SessionError::Config(ConfigError::InvalidName(_)))
+        ));
+
+        let config = config.with_lenient_validation(true);
+        assert!(SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .is_ok());
+
+        // SQL databases place the table name unquoted so `-` could start a comment.
+        #[cfg(any(feature = "postgres-rustls", feature = "postgres-native"))]
+        {
+            let pool = PgPoolOptions::new().connect_lazy_with(PgConnectOptions::new());
+            let config = SessionConfig::default().with_table_name("sessions--");
+            assert!(matches!(
+                SessionStore::<SessionPgPool>::new(Some(pool.into()), config).await,
+                Err(SessionError::Config(ConfigError::InvalidTableName(_)))
+            ));
+        }
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn prefixed_cookies_round_trip() {
+        let configs = [
+            (
+                SessionConfig::default().with_cookie_prefix(CookiePrefix::Host),
+                "__Host-session",
+            ),
+            (
+                SessionConfig::default()
+                    .with_cookie_prefix(CookiePrefix::Secure)
+                    .with_cookie_domain("example.com"),
+                "__Secure-session",
+            ),
+        ];
+
+        for (config, name) in configs {
+            let config = config.with_secure(true).with_key(Key::generate());
+            let session_store = SessionStore::<SessionNullPool>::new(None, config)
+                .await
+                .unwrap();
+
+            let app = Router::new()
+                .route(
+                    "/",
+                    get(|session: Session<SessionNullPool>| async move {
+                        let count: usize = session.get("count").unwrap_or(0) + 1;
+                        session.set("count", count);
+                        count.to_string()
+                    }),
+                )
+                .layer(SessionLayer::new(session_store));
+
+            let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            let cookie = response
+                .headers()
+                .get_all(header::SET_COOKIE)
+                .iter()
+                .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+                .find(|cookie| cookie.name() == name)
+                .unwrap();
+            assert_eq!(cookie.secure(), Some(true));
+
+            // The prefixed Cookie name must also be used to read the Cookie back.
+            let request = Request::builder()
+                .uri("/")
+                .header(header::COOKIE, format!("{}={}", name, cookie.value()))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, "2");
+        }
+    }
+
+    #[test]
+    fn names_follow_builder_order() {
+        // The prefix can be set before or after the names and still apply to all of them.
+        let config = SessionConfig::default()
+            .with_cookie_prefix(CookiePrefix::Secure)
+            .with_name_prefix("my_app")
+            .with_session_name("my_id");
+
+        #[cfg(not(feature = "rest_mode"))]
+        {
+            assert_eq!(config.names.session, "__Secure-my_id");
+            assert_eq!(config.names.store, "__Secure-my_app_store");
+            assert_eq!(config.names.key, "__Secure-my_app_key");
+            assert_eq!(config.names.bootstrap, "__Secure-my_app_lax");
+
+            let config = config.with_cookie_prefix(CookiePrefix::None);
+            assert_eq!(config.names.session, "my_id");
+        }
+
+        // Headers are never prefixed and are parsed once.
+        #[cfg(feature = "rest_mode")]
+        {
+            assert_eq!(config.names.session, "my_id");
+            assert_eq!(
+                config.names.session_header,
+                Some(http::HeaderName::from_static("my_id"))
+            );
+            assert_eq!(
+                config.names.key_header,
+                Some(http::HeaderName::from_static("my_app_key"))
+            );
+        }
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn test_util_session() {
+        async fn handler(session: Session<SessionNullPool>) -> String {
+            session.get::<usize>("count").unwrap_or(0).to_string()
+        }
+
+        let session = Session::new_test(test_util::test_store().await).await;
+        assert!(session.is_new());
+
+        session.set("count", 5);
+        assert_eq!(handler(session.clone()).await, "5");
+        assert!(
+            session
+                .get_store()
+                .exists(&session.get_session_id().inner())
+                .await
+        );
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn count_cache_ttl() {
+        let clock = test_util::MockClock::new();
+        let pool = test_util::TestPool::new();
+        let config = SessionConfig::default()
+            .with_count_cache_ttl(Some(chrono::Duration::seconds(10)))
+            .with_clock(std::sync::Arc::new(clock.clone()));
+        let session_store = SessionStore::new(Some(pool.clone()), config).await.unwrap();
+
+        for id in ["a", "b"] {
+            let data = SessionData::new(id.to_owned(), true, &session_store.config);
+            session_store.store_session(&data).await.unwrap();
+
+            // Cached until the TTL runs out.
+            assert_eq!(session_store.count().await.unwrap(), 1);
+        }
+
+        clock.advance(chrono::Duration::seconds(10));
+        assert_eq!(session_store.count().await.unwrap(), 2);
+
+        // Stats reads the same cache.
+        let data = SessionData::new("c".to_owned(), true, &session_store.config);
+        session_store.store_session(&data).await.unwrap();
+        assert_eq!(session_store.stats().await.database_sessions, Some(2));
+
+        // Without a TTL every call counts the database.
+        let session_store = SessionStore::new(Some(pool), SessionConfig::default())
+            .await
+            .unwrap();
+        let data = SessionData::new("d".to_owned(), true, &session_store.config);
+        session_store.store_session(&data).await.unwrap();
+        assert_eq!(session_store.count().await.unwrap(), 4);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn retry_transient_errors() {
+        let pool = test_util::TestPool::new();
+        let config = SessionConfig::default()
+            .with_retry(Some(RetryPolicy::new(3, chrono::Duration::milliseconds(1))));
+        let session_store = SessionStore::new(Some(pool.clone()), config).await.unwrap();
+        let data = SessionData::new("retry".to_owned(), true, &session_store.config);
+
+        // Two failures are retried away within three attempts.
+        pool.fail_next_stores(2);
+        session_store.store_session(&data).await.unwrap();
+        assert_eq!(pool.len(), 1);
+
+        pool.fail_next_loads(2);
+        assert!(session_store
+            .load_session("retry".to_owned())
+            .await
+            .unwrap()
+            .is_some());
+
+        // Once every attempt fails the last error is returned.
+        pool.fail_next_loads(3);
+        let err = session_store
+            .load_session("retry".to_owned())
+            .await
+            .unwrap_err();
+        assert!(err.is_transient());
+
+        // Without a RetryPolicy the first failure is returned.
+        let session_store = SessionStore::new(Some(pool.clone()), SessionConfig::default())
+            .await
+            .unwrap();
+        pool.fail_next_loads(1);
+        assert!(session_store
+            .load_session("retry".to_owned())
+            .await
+            .is_err());
+
+        assert!(SessionError::Timeout.is_transient());
+        assert!(!SessionError::Decrypt.is_transient());
+        assert!(!SessionError::GenericSelectError("no such table".to_owned()).is_transient());
+        assert_eq!(
+            SessionConfig::default()
+                .with_retry(Some(RetryPolicy::new(0, chrono::Duration::zero())))
+                .validate(),
+            Err(ConfigError::InvalidRetryAttempts)
+        );
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn read_pool_serves_recent_writes() {
+        let clock = test_util::MockClock::new();
+        let primary = test_util::TestPool::new();
+        let replica = test_util::TestPool::new();
+        let config = SessionConfig::default().with_clock(std::sync::Arc::new(clock.clone()));
+        let session_store = SessionStore::new(Some(primary.clone()), config)
+            .await
+            .unwrap()
+            .with_read_pool(replica.clone(), chrono::Duration::seconds(1));
+
+        let mut data = SessionData::new("replica".to_owned(), true, &session_store.config);
+        data.set("user_id", 42).unwrap();
+        session_store.store_session(&data).await.unwrap();
+        assert!(primary.stored("replica").is_some());
+        assert!(replica.is_empty());
+
+        // The replica has not caught up so the fresh write is served from memory.
+        let loaded = session_store
+            .load_session("replica".to_owned())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.get::<i64>("user_id"), Some(42));
+        assert_eq!(session_store.count().await.unwrap(), 0);
+
+        // Once the lag has passed loads go to the replica.
+        clock.advance(chrono::Duration::seconds(1));
+        assert!(session_store
+            .load_session("replica".to_owned())
+            .await
+            .unwrap()
+            .is_none());
+
+        let stored = primary.stored("replica").unwrap();
+        replica
+            .store("replica", &stored, data.expires.timestamp(), "sessions")
+            .await
+            .unwrap();
+        assert!(session_store
+            .load_session("replica".to_owned())
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(session_store.count().await.unwrap(), 1);
+
+        // Destroyed Sessions are not loaded back from the lagging replica.
+        session_store.destroy_session("replica").await.unwrap();
+        assert!(primary.stored("replica").is_none());
+        assert!(session_store
+            .load_session("replica".to_owned())
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn snapshot_survives_restart() {
+        let clock = test_util::MockClock::new();
+        let path = std::env::temp_dir().join(format!("axum_session_{}.json", uuid::Uuid::new_v4()));
+        let config = SessionConfig::default()
+            .with_snapshot(&path, chrono::Duration::minutes(5))
+            .with_clock(std::sync::Arc::new(clock.clone()));
+
+        let session_store = SessionStore::<SessionNullPool>::new(None, config.clone())
+            .await
+            .unwrap();
+        let mut data = SessionData::new("kept".to_owned(), true, &session_store.config);
+        data.set("user_id", 42).unwrap();
+        session_store.inner.insert("kept".to_owned(), data);
+
+        let mut expired = SessionData::new("expired".to_owned(), true, &session_store.config);
+        expired.expires = clock.now() + chrono::Duration::minutes(1);
+        session_store.inner.insert("expired".to_owned(), expired);
+        session_store.snapshot().await.unwrap();
+
+        // Sessions that expired after the snapshot was written are dropped on load.
+        clock.advance(chrono::Duration::minutes(1));
+        let session_store = SessionStore::<SessionNullPool>::new(None, config.clone())
+            .await
+            .unwrap();
+        assert!(!session_store.inner.contains_key("expired"));
+        assert_eq!(
+            session_store.get::<i64>("kept".to_owned(), "user_id"),
+            Some(42)
+        );
+
+        // Corrupt and other version files are ignored instead of failing startup.
+        for contents in ["{ not json", r#"{"version":999,"sessions":"changed"}"#] {
+            std::fs::write(&path, contents).unwrap();
+            let session_store = SessionStore::<SessionNullPool>::new(None, config.clone())
+                .await
+                .unwrap();
+            assert!(session_store.inner.is_empty());
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn broadcast_evicts_on_other_nodes() {
+        let pool = test_util::TestPool::new();
+        let broadcast = SessionLocalBroadcast::new();
+        let node_a = SessionStore::new(Some(pool.clone()), SessionConfig::default())
+            .await
+            .unwrap()
+            .with_broadcast(broadcast.clone());
+        let node_b = SessionStore::new(Some(pool.clone()), SessionConfig::default())
+            .await
+            .unwrap()
+            .with_broadcast(broadcast);
+        let _listener_a = node_a.spawn_broadcast_listener().await.unwrap().unwrap();
+        let _listener_b = node_b.spawn_broadcast_listener().await.unwrap().unwrap();
+
+        // Both nodes hold the Session in memory.
+        for id in ["a", "b"] {
+            let data = SessionData::new(id.to_owned(), true, &node_a.config);
+            node_a.store_session(&data).await.unwrap();
+            node_a.inner.insert(id.to_owned(), data.clone());
+            node_b.inner.insert(id.to_owned(), data);
+        }
+
+        let mut destroyed = node_b.subscribe("a");
+        node_a.destroy_session("a").await.unwrap();
+        assert_eq!(destroyed.recv().await.unwrap(), SessionEvent::Destroyed);
+        assert!(!node_b.inner.contains_key("a"));
+        assert!(node_b.inner.contains_key("b"));
+
+        // A node ignores its own events so Sessions it created afterwards are kept.
+        node_a.inner.insert(
+            "c".to_owned(),
+            SessionData::new("c".to_owned(), true, &node_a.config),
+        );
+
+        node_a.clear_store().await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(node_b.inner.is_empty());
+        assert!(node_a.inner.contains_key("c"));
+        assert!(pool.is_empty());
+    }
+
+    #[cfg(all(feature = "test-util", not(feature = "rest_mode")))]
+    #[tokio::test]
+    async fn broadcast_evicts_request_destroys() {
+        use test_util::{TestClient, TestPool};
+
+        let pool = TestPool::new();
+        let broadcast = SessionLocalBroadcast::new();
+        let node_a = SessionStore::new(Some(pool.clone()), SessionConfig::default())
+            .await
+            .unwrap()
+            .with_broadcast(broadcast.clone());
+        let node_b = SessionStore::new(Some(pool), SessionConfig::default())
+            .await
+            .unwrap()
+            .with_broadcast(broadcast);
+        let _listener_b = node_b.spawn_broadcast_listener().await.unwrap().unwrap();
+
+        let app = Router::new()
+            .route(
+                "/login",
+                get(|session: Session<TestPool>| async move {
+                    session.set("user_id", 1);
+                }),
+            )
+            .route(
+                "/renew",
+                get(|session: Session<TestPool>| async move {
+                    session.renew();
+                }),
+            )
+            .route(
+                "/logout",
+                get(|session: Session<TestPool>| async move {
+                    session.destroy();
+                }),
+            );
+
+        let mut client = TestClient::with_session_store(node_a.clone(), app);
+        client.get("/login").await;
+
+        // Node b loaded the Session too, then the handler renews its ID on node a.
+        let old_id = client.held("session").unwrap().to_owned();
+        let data = node_a.inner.get(&old_id).unwrap().clone();
+        node_b.inner.insert(old_id.clone(), data);
+
+        client.get("/renew").await;
+        let id = client.held("session").unwrap().to_owned();
+        assert_ne!(id, old_id);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!node_b.inner.contains_key(&old_id));
+
+        // Logging out through a handler evicts the Session on node b as well.
+        let data = node_a.inner.get(&id).unwrap().clone();
+        node_b.inner.insert(id.clone(), data);
+        let mut destroyed = node_b.subscribe(&id);
+
+        client.get("/logout").await;
+        assert_eq!(destroyed.recv().await.unwrap(), SessionEvent::Destroyed);
+        assert!(!node_b.inner.contains_key(&id));
+    }
+
+    #[cfg(all(feature = "write-behind", feature = "test-util"))]
+    #[tokio::test]
+    async fn batching_pool_coalesces_writes() {
+        let inner = test_util::TestPool::new();
+        let pool = BatchingPool::new(inner.clone(), std::time::Duration::from_secs(3600), 3);
+
+        // Repeated stores to one Session are coalesced keeping the newest.
+        for data in ["first", "second", "third"] {
+            pool.store("a", data, i64::MAX, "sessions").await.unwrap();
+        }
+        assert_eq!(pool.pending(), 1);
+        assert!(inner.is_empty());
+
+        // Reads see the queued Session before it reaches the database.
+        assert_eq!(
+            pool.load("a", "sessions").await.unwrap().as_deref(),
+            Some("third")
+        );
+        assert!(pool.exists("a", "sessions").await.unwrap());
+
+        pool.flush().await.unwrap();
+        assert_eq!(pool.pending(), 0);
+        assert_eq!(inner.stored("a").as_deref(), Some("third"));
+
+        // Failed flushes keep the Sessions queued.
+        pool.store("b", "queued", i64::MAX, "sessions")
+            .await
+            .unwrap();
+        inner.fail_next_stores(1);
+        assert!(pool.flush().await.is_err());
+        assert_eq!(pool.pending(), 1);
+
+        // Deletes drop queued Sessions so a later flush can not bring them back.
+        pool.delete_one_by_id("b", "sessions").await.unwrap();
+        assert_eq!(pool.pending(), 0);
+        assert_eq!(pool.load("b", "sessions").await.unwrap(), None);
+
+        // Reaching max_batch flushes without waiting for the interval.
+        for id in ["c", "d", "e"] {
+            pool.store(id, "batched", i64::MAX, "sessions")
+                .await
+                .unwrap();
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(pool.pending(), 0);
+        assert!(inner.stored("e").is_some());
+
+        // Dropping the last clone flushes what is still queued.
+        pool.store("f", "dropped", i64::MAX, "sessions")
+            .await
+            .unwrap();
+        drop(pool);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(inner.stored("f").as_deref(), Some("dropped"));
+    }
+
+    #[cfg(all(feature = "test-util", not(feature = "rest_mode")))]
+    #[tokio::test]
+    async fn retry_pool_hides_transient_errors() {
+        use test_util::{TestClient, TestPool};
+
+        let inner = TestPool::new();
+        let pool = RetryPool::new(
+            inner.clone(),
+            RetryPolicy::new(3, chrono::Duration::milliseconds(1)).with_jitter(true),
+        );
+        let session_store = SessionStore::new(Some(pool.clone()), SessionConfig::default())
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/login",
+                get(|session: Session<RetryPool<TestPool>>| async move {
+                    session.set("user_id", 42);
+                }),
+            )
+            .route(
+                "/account",
+                get(|session: Session<RetryPool<TestPool>>| async move {
+                    session.get::<i64>("user_id").unwrap_or(0).to_string()
+                }),
+            );
+
+        let mut client = TestClient::with_session_store(session_store.clone(), app);
+        client.get("/login").await;
+
+        // Two failed loads are retried away before the Request sees them.
+        session_store.inner.clear();
+        inner.fail_next_loads(2);
+        let response = client.get("/account").await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "42");
+        assert_eq!(pool.retries(), 2);
+        assert_eq!(pool.exhausted(), 0);
+
+        // Once every attempt fails the last error is returned.
+        inner.fail_next_loads(3);
+        assert!(pool.load("missing", "sessions").await.is_err());
+        assert_eq!(pool.retries(), 4);
+        assert_eq!(pool.exhausted(), 1);
+    }
+
+    #[cfg(all(feature = "test-util", not(feature = "rest_mode")))]
+    #[tokio::test]
+    async fn test_pool_faults_and_client() {
+        use test_util::{TestClient, TestPool};
+
+        let pool = TestPool::new();
+        let session_store = SessionStore::new(Some(pool.clone()), SessionConfig::default())
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/login",
+                get(|session: Session<TestPool>| async move {
+                    session.set("user_id", 42);
+                }),
+            )
+            .route(
+                "/account",
+                get(|session: Session<TestPool>| async move {
+                    session.get::<i64>("user_id").unwrap_or(0).to_string()
+                }),
+            );
+
+        let mut client = TestClient::with_session_store(session_store.clone(), app);
+        client.get("/login").await;
+        let session_id = client.held("session").unwrap().to_owned();
+        assert!(pool.stored(&session_id).is_some());
+
+        // The client carries the Cookie so the Session is found again.
+        let response = client.get("/account").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "42");
+
+        // Corrupted rows load as a new Session once unloaded from memory.
+        session_store.inner.clear();
+        pool.corrupt_loads(true);
+        let response = client.get("/account").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "0");
+        pool.corrupt_loads(false);
+
+        pool.fail_next_stores(1);
+        let err = session_store
+            .store_session(&SessionData::new(
+                "a".to_owned(),
+                true,
+                &session_store.config,
+            ))
+            .await;
+        assert!(err.is_err());
+        assert!(session_store
+            .store_session(&SessionData::new(
+                "a".to_owned(),
+                true,
+                &session_store.config
+            ))
+            .await
+            .is_ok());
+
+        let serde_json::Value::Object(data) = serde_json::json!({ "user_id": 7 }) else {
+            unreachable!()
+        };
+        let session = Session::new_test_with_data(session_store, data)
+            .await
+            .unwrap();
+        assert_eq!(session.get::<i64>("user_id"), Some(7));
+    }
+
+    #[cfg(all(feature = "test-util", not(feature = "rest_mode")))]
+    #[tokio::test]
+    async fn destroy_on_error_replaces_corrupt_sessions() {
+        use test_util::{TestClient, TestPool};
+
+        let pool = TestPool::new();
+        let config = SessionConfig::default().with_destroy_on_error(true);
+        let session_store = SessionStore::new(Some(pool.clone()), config).await.unwrap();
+
+        let app = Router::new()
+            .route(
+                "/login",
+                get(|session: Session<TestPool>| async move {
+                    session.set("user_id", 42);
+                }),
+            )
+            .route(
+                "/account",
+                get(|session: Session<TestPool>| async move {
+                    session.get::<i64>("user_id").unwrap_or(0).to_string()
+                }),
+            );
+
+        let mut client = TestClient::with_session_store(session_store.clone(), app);
+        client.get("/login").await;
+        let corrupt_id = client.held("session").unwrap().to_owned();
+
+        // Unreadable data is destroyed and the client is moved to a new Session.
+        session_store.inner.clear();
+        pool.corrupt_loads(true);
+        let response = client.get("/account").await;
+        pool.corrupt_loads(false);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "0");
+
+        let session_id = client.held("session").unwrap().to_owned();
+        assert_ne!(session_id, corrupt_id);
+        assert!(pool.stored(&corrupt_id).is_none());
+        assert!(pool.stored(&session_id).is_some());
+        assert!(!session_store.inner.contains_key(&corrupt_id));
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn new_and_loaded_sessions() {
+        use test_util::{TestClient, TestPool};
+
+        let app = Router::new().route(
+            "/",
+            get(|session: Session<TestPool>| async move {
+                session.set("visited", true);
+                format!(
+                    "{} {}",
+                    session.is_new(),
+                    session.was_loaded_from_database()
+                )
+            }),
+        );
+
+        let pool = TestPool::new();
+        let session_store = SessionStore::new(Some(pool.clone()), SessionConfig::default())
+            .await
+            .unwrap();
+        let mut client = TestClient::with_session_store(session_store.clone(), app.clone());
+
+        // No Cookie.
+        let response = client.get("/").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "true false");
+        let session_id = client.held("session").unwrap().to_owned();
+
+        // Returning Session still in memory.
+        let response = client.get("/").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "false false");
+
+        // Returning Session loaded from the database.
+        session_store.inner.clear();
+        let response = client.get("/").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "false true");
+
+        // Expired Session.
+        session_store.inner.clear();
+        let data = pool.stored(&session_id).unwrap();
+        pool.store(&session_id, &data, 0, "sessions").await.unwrap();
+        let response = client.get("/").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "true false");
+
+        // Unknown Session.
+        session_store.destroy_session(&session_id).await.unwrap();
+        let response = client.get("/").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "true false");
+
+        // Anonymous opt-in Sessions are never stored so they are new every time.
+        let config = SessionConfig::default().with_mode(SessionMode::OptIn);
+        let session_store = SessionStore::new(Some(TestPool::new()), config)
+            .await
+            .unwrap();
+        let mut client = TestClient::with_session_store(session_store, app);
+
+        for _ in 0..2 {
+            let response = client.get("/").await;
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, "true false");
+        }
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn key_rotation_keeps_sessions() {
+        use test_util::{TestClient, TestPool};
+
+        let app = Router::new().route(
+            "/",
+            get(|session: Session<TestPool>| async move {
+                session.set("visited", true);
+                session.get_session_id().inner()
+            }),
+        );
+
+        let ring = std::sync::Arc::new(KeyRing::new(Key::generate()));
+        let config = SessionConfig::default()
+            .with_key_rotation_schedule(ring.clone())
+            .with_encrypt_at_rest(true);
+        let session_store = SessionStore::new(Some(TestPool::new()), config)
+            .await
+            .unwrap();
+        let mut client = TestClient::with_session_store(session_store.clone(), app);
+
+        let response = client.get("/").await;
+        let session_id = response.into_body().collect().await.unwrap().to_bytes();
+        let old_cookie = client.held("session").unwrap().to_owned();
+
+        // The old Cookie and database row still open and the Cookie moves to the new key.
+        ring.rotate(Key::generate());
+        session_store.inner.clear();
+        let response = client.get("/").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, session_id);
+        assert_ne!(client.held("session").unwrap(), old_cookie);
+
+        // Once the previous key is retired only the re-issued Cookie still opens.
+        ring.retire_previous();
+        let response = client.get("/").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, session_id);
+
+        ring.rotate(Key::generate());
+        ring.retire_previous();
+        let response = client.get("/").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_ne!(body, session_id);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn id_renewal_interval_renews_old_ids() {
+        use chrono::Duration;
+        use test_util::{MockClock, TestClient, TestPool};
+
+        let app = Router::new().route(
+            "/",
+            get(|session: Session<TestPool>| async move {
+                let visits = session.get::<u32>("visits").unwrap_or(0) + 1;
+                session.set("visits", visits);
+                format!("{} {}", session.get_session_id().inner(), visits)
+            }),
+        );
+
+        let clock = MockClock::new();
+        let pool = TestPool::new().with_clock(std::sync::Arc::new(clock.clone()));
+        let config = SessionConfig::default()
+            .with_id_renewal_interval(Duration::minutes(15))
+            .with_clock(std::sync::Arc::new(clock.clone()));
+        let session_store = SessionStore::new(Some(pool.clone()), config).await.unwrap();
+        let mut client = TestClient::with_session_store(session_store.clone(), app);
+
+        client.get("/").await;
+        let old_id = client.held("session").unwrap().to_owned();
+
+        // Just under the interval the ID is kept.
+        clock.advance(Duration::minutes(15) - Duration::seconds(1));
+        let response = client.get("/").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, format!("{} 2", old_id));
+        assert_eq!(client.held("session").unwrap(), old_id);
+
+        // Once it is reached the Request is served and then given a new ID.
+        let (mut racing, mut late) = (client.clone(), client.clone());
+        clock.advance(Duration::seconds(1));
+        let response = client.get("/").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, format!("{} 3", old_id));
+        let new_id = client.held("session").unwrap().to_owned();
+        assert_ne!(new_id, old_id);
+        assert!(pool.stored(&old_id).is_none());
+        assert!(pool.stored(&new_id).is_some());
+
+        // Requests still sent with the old ID lead to the renewed Session within the grace window.
+        let response = racing.get("/").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, format!("{} 4", new_id));
+        assert_eq!(racing.held("session").unwrap(), new_id);
+
+        // The renewed ID is not renewed again right away.
+        let response = client.get("/").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, format!("{} 5", new_id));
+
+        // After the grace window the old ID leads nowhere.
+        clock.advance(Duration::seconds(30));
+        let response = late.get("/").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(!body.starts_with(&new_id));
+        assert!(body.ends_with(" 1"));
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn fallback_to_memory_while_database_fails() {
+        use test_util::{TestClient, TestPool};
+
+        let pool = TestPool::new();
+        let config = SessionConfig::default().with_fallback_to_memory(true);
+        let session_store = SessionStore::new(Some(pool.clone()), config).await.unwrap();
+
+        let app = Router::new()
+            .route(
+                "/login",
+                get(|session: Session<TestPool>| async move {
+                    session.set("user_id", 42);
+                }),
+            )
+            .route(
+                "/account",
+                get(|session: Session<TestPool>| async move {
+                    session.set("visited", true);
+                    session.get::<i64>("user_id").unwrap_or(0).to_string()
+                }),
+            );
+
+        let mut client = TestClient::with_session_store(session_store.clone(), app);
+        client.get("/login").await;
+        let stored_id = client.held("session").unwrap().to_owned();
+        let stored = pool.stored(&stored_id);
+
+        // The database goes down so the client gets a memory only Session under a new ID.
+        session_store.inner.clear();
+        pool.fail_next_loads(1);
+        pool.fail_next_stores(1);
+        let response = client.get("/account").await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "0");
+        assert!(session_store.is_degraded());
+
+        let memory_id = client.held("session").unwrap().to_owned();
+        assert_ne!(memory_id, stored_id);
+        assert!(pool.stored(&memory_id).is_none());
+        assert!(session_store.inner.get(&memory_id).unwrap().update);
+        assert_eq!(pool.stored(&stored_id), stored);
+
+        // Once the database recovers the memory only Session is saved instead of dropped.
+        client.get("/login").await;
+        assert!(!session_store.is_degraded());
+        assert!(pool.stored(&memory_id).is_some());
+        assert_eq!(pool.stored(&stored_id), stored);
+    }
+
+    #[cfg(all(feature = "test-util", not(feature = "rest_mode")))]
+    #[tokio::test]
+    async fn persist_on_error_discards_failed_requests() {
+        use test_util::{TestClient, TestPool};
+
+        let pool = TestPool::new();
+        let config = SessionConfig::default().with_persist_on_error(false);
+        let session_store = SessionStore::new(Some(pool.clone()), config).await.unwrap();
+
+        let app = Router::new()
+            .route(
+                "/login",
+                get(|session: Session<TestPool>| async move {
+                    session.set("value", 1);
+                }),
+            )
+            .route(
+                "/fail",
+                get(|session: Session<TestPool>| async move {
+                    session.set("value", 2);
+                    http::StatusCode::INTERNAL_SERVER_ERROR
+                }),
+            )
+            .route(
+                "/reject",
+                get(|session: Session<TestPool>| async move {
+                    session.set("value", 3);
+                    http::StatusCode::BAD_REQUEST
+                }),
+            )
+            .route(
+                "/value",
+                get(|session: Session<TestPool>| async move {
+                    session.get::<i64>("value").unwrap_or(0).to_string()
+                }),
+            );
+
+        let mut client = TestClient::with_session_store(session_store.clone(), app);
+
+        // Sessions created by a failed Request are dropped without sending Cookies.
+        let response = client.get("/fail").await;
+        assert_eq!(response.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(response.headers().get(header::SET_COOKIE).is_none());
+        assert!(session_store.inner.is_empty());
+        assert!(pool.is_empty());
+
+        client.get("/login").await;
+        let session_id = client.held("session").unwrap().to_owned();
+        let stored = pool.stored(&session_id);
+        assert!(stored.is_some());
+
+        let response = client.get("/fail").await;
+        assert!(response.headers().get(header::SET_COOKIE).is_none());
+        assert_eq!(pool.stored(&session_id), stored);
+
+        let response = client.get("/value").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "1");
+
+        // 4xx Responses are still saved.
+        let response = client.get("/reject").await;
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+        assert_ne!(pool.stored(&session_id), stored);
+
+        let response = client.get("/value").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "3");
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn memory_disabled_matches_memory_enabled() {
+        use test_util::{TestClient, TestPool};
+
+        for memory in [true, false] {
+            let pool = TestPool::new();
+            let config = SessionConfig::default()
+                .with_memory(memory)
+                .with_deferred_save(true);
+            let session_store = SessionStore::new(Some(pool.clone()), config).await.unwrap();
+            let shared = session_store.clone();
+
+            // Reports how many Sessions the store's shared memory holds while the handler runs.
+            let app = Router::new()
+                .route(
+                    "/visit",
+                    get(|session: Session<TestPool>| async move {
+                        session.push("pages", "/visit".to_owned());
+                        let visits = session.increment("visits", 1);
+                        format!("{} {}", visits, shared.inner.len())
+                    }),
+                )
+                .route(
+                    "/logout",
+                    get(|session: Session<TestPool>| async move {
+                        session.destroy();
+                    }),
+                );
+
+            let mut client = TestClient::with_session_store(session_store.clone(), app);
+
+            for visits in 1..=3 {
+                let response = client.get("/visit").await;
+                let body = response.into_body().collect().await.unwrap().to_bytes();
+                assert_eq!(body, format!("{} {}", visits, usize::from(memory)));
+
+                // Without memory the Session only lives in the database between Requests
+                // so saves are never deferred.
+                assert_eq!(session_store.inner.is_empty(), !memory);
+                if !memory {
+                    assert_eq!(pool.len(), 1);
+                }
+            }
+
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            assert_eq!(session_store.count().await.unwrap(), 1);
+
+            client.get("/logout").await;
+            assert!(session_store.inner.is_empty());
+            assert!(pool.is_empty());
+
+            let response = client.get("/visit").await;
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, format!("1 {}", usize::from(memory)));
+
+            // Sessions loaded outside of a Request only stay in memory if it is enabled.
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            let id = client.held("session").unwrap().to_owned();
+            let session = session_store.load_or_create(&id).await.unwrap();
+            session.push("pages", "/job".to_owned());
+            session.save().await.unwrap();
+            drop(session);
+            assert_eq!(session_store.inner.is_empty(), !memory);
+
+            let data = session_store.get_session_data(&id).await.unwrap().unwrap();
+            assert_eq!(
+                data.get::<Vec<String>>("pages"),
+                Some(vec!["/visit".to_owned(), "/job".to_owned()])
+            );
+        }
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[test]
+    fn validate_against_host_warnings() {
+        let broad = HostWarning::BroadDomain {
+            domain: "example.com".to_owned(),
+            host: "app.example.com".to_owned(),
+        };
+
+        assert!(SessionConfig::default()
+            .validate_against_host("app.example.com")
+            .is_empty());
+
+        let config = SessionConfig::default().with_cookie_domain(".Example.com");
+        assert_eq!(
+            config.validate_against_host("app.example.com:8443"),
+            std::slice::from_ref(&broad)
+        );
+        assert!(config.validate_against_host("example.com.").is_empty());
+        assert_eq!(
+            config.validate_against_host("notexample.com"),
+            [HostWarning::DomainMismatch {
+                domain: "example.com".to_owned(),
+                host: "notexample.com".to_owned(),
+            }]
+        );
+        assert_eq!(
+            config.validate_against_host("[::1]:3000"),
+            [HostWarning::DomainMismatch {
+                domain: "example.com".to_owned(),
+                host: "::1".to_owned(),
+            }]
+        );
+
+        let config = SessionConfig::default().with_cookie_domain("com");
+        assert_eq!(
+            config.validate_against_host("example.com"),
+            [HostWarning::TopLevelDomain("com".to_owned())]
+        );
+
+        let config = SessionConfig::default().with_cookie_domain("co.uk");
+        assert_eq!(
+            config.validate_against_host("example.co.uk"),
+            [HostWarning::PublicSuffix("co.uk".to_owned())]
+        );
+
+        let config =
+            SessionConfig::default().with_domain_resolver(std::sync::Arc::new(|headers| {
+                let host = headers.get(header::HOST)?.to_str().ok()?;
+                host.strip_prefix("app.").map(str::to_owned)
+            }));
+        assert_eq!(config.validate_against_host("app.example.com"), [broad]);
+        assert!(config.validate_against_host("example.com").is_empty());
+    }
+
+    #[cfg(all(feature = "test-util", not(feature = "rest_mode")))]
+    #[tokio::test]
+    async fn deferred_save_after_response() {
+        use test_util::{TestClient, TestPool};
+
+        let pool = TestPool::new();
+        let config = SessionConfig::default().with_deferred_save(true);
+        let session_store = SessionStore::new(Some(pool.clone()), config).await.unwrap();
+
+        let app = Router::new().route(
+            "/",
+            get(|session: Session<TestPool>| async move {
+                session.set("user_id", 42);
+            }),
+        );
+
+        let mut client = TestClient::with_session_store(session_store, app);
+        pool.set_latency(Some(std::time::Duration::from_millis(200)));
+        let response = client.get("/").await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        // The Cookie went out with the Response while the slow store is still running.
+        let session_id = client.held("session").unwrap().to_owned();
+        assert!(pool.stored(&session_id).is_none());
+
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        assert!(pool.stored(&session_id).is_some());
+    }
+
+    #[cfg(all(feature = "test-util", not(feature = "rest_mode")))]
+    #[tokio::test]
+    async fn save_timeouts_are_returned() {
+        use test_util::{TestClient, TestPool};
+
+        let pool = TestPool::new();
+        let config =
+            SessionConfig::default().with_db_timeout(Some(chrono::Duration::milliseconds(50)));
+        let session_store = SessionStore::new(Some(pool.clone()), config).await.unwrap();
+
+        let app = Router::new().route(
+            "/",
+            get(|session: Session<TestPool>| async move {
+                session.set("user_id", 42);
+                "saved"
+            }),
+        );
+
+        let mut client = TestClient::with_session_store(session_store.clone(), app);
+        pool.set_latency(Some(std::time::Duration::from_millis(200)));
+        let response = client.get("/").await;
+        assert_eq!(response.status(), http::StatusCode::GATEWAY_TIMEOUT);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(body.is_empty());
+
+        let id = uuid::Uuid::new_v4().to_string();
+        pool.set_latency(None);
+        let session = session_store.load_or_create(&id).await.unwrap();
+        session.set("user_id", 42);
+        pool.set_latency(Some(std::time::Duration::from_millis(200)));
+        assert!(matches!(session.save().await, Err(SessionError::Timeout)));
+
+        // Deferred saves have no Response to fail.
+        let config = SessionConfig::default()
+            .with_db_timeout(Some(chrono::Duration::milliseconds(50)))
+            .with_deferred_save(true);
+        let session_store = SessionStore::new(Some(pool.clone()), config).await.unwrap();
+        let app = Router::new().route(
+            "/",
+            get(|session: Session<TestPool>| async move {
+                session.set("user_id", 42);
+            }),
+        );
+
+        let mut client = TestClient::with_session_store(session_store, app);
+        assert_eq!(client.get("/").await.status(), http::StatusCode::OK);
+    }
+
+    #[cfg(all(feature = "test-util", not(feature = "rest_mode")))]
+    #[tokio::test]
+    async fn deferred_saves_keep_request_order() {
+        use test_util::{TestClient, TestPool};
+
+        let pool = TestPool::new();
+        let config = SessionConfig::default().with_deferred_save(true);
+        let session_store = SessionStore::new(Some(pool.clone()), config).await.unwrap();
+
+        let app = Router::new().route(
+            "/:step",
+            get(
+                |session: Session<TestPool>, Path(step): Path<u32>| async move {
+                    session.set("step", step);
+                },
+            ),
+        );
+
+        let mut client = TestClient::with_session_store(session_store.clone(), app);
+
+        // The first save is slow so the second one would finish before it.
+        pool.set_latency(Some(std::time::Duration::from_millis(200)));
+        client.get("/1").await;
+        tokio::task::yield_now().await;
+        pool.set_latency(None);
+        client.get("/2").await;
+
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        let session_id = client.held("session").unwrap().to_owned();
+        session_store.inner.remove(&session_id);
+        let data = session_store
+            .get_session_data(&session_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(data.get::<u32>("step"), Some(2));
+        assert!(session_store.deferred_order.is_empty());
+    }
+
+    #[cfg(all(feature = "test-util", not(feature = "rest_mode")))]
+    #[tokio::test]
+    async fn create_test_session_tokens() {
+        use axum::Extension;
+        use middleware::require_key;
+        use std::collections::HashMap;
+
+        for mode in [SecurityMode::Simple, SecurityMode::PerSession] {
+            let config = SessionConfig::default()
+                .with_key(Key::generate())
+                .with_security_mode(mode);
+            let session_store = SessionStore::<SessionNullPool>::new(None, config)
+                .await
+                .unwrap();
+
+            let app = Router::new()
+                .route(
+                    "/account",
+                    get(|Extension(user_id): Extension<i64>| async move { user_id.to_string() }),
+                )
+                .route_layer(require_key::<SessionNullPool, i64>("user_id"))
+                .layer(SessionLayer::new(session_store.clone()));
+
+            let token = session_store
+                .create_test_session(HashMap::from([("user_id", 42)]))
+                .await
+                .unwrap();
+
+            let request = token
+                .apply(Request::builder().uri("/account"))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), http::StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, "42");
+
+            let request = Request::builder()
+                .uri("/account")
+                .body(Body::empty())
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn domain_resolver_per_host() {
+        let config =
+            SessionConfig::default().with_domain_resolver(std::sync::Arc::new(|headers| {
+                let host = headers.get(header::HOST)?.to_str().ok()?;
+
+                ["customer-a.com", "customer-b.io"]
+                    .into_iter()
+                    .find(|domain| host.ends_with(domain))
+                    .map(str::to_owned)
+            }));
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route("/", get(|| async { "" }))
+            .route(
+                "/logout",
+                get(|session: Session<SessionNullPool>| async move { session.destroy() }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        for (host, domain) in [
+            ("app.customer-a.com", "customer-a.com"),
+            ("www.customer-b.io", "customer-b.io"),
+        ] {
+            for uri in ["/", "/logout"] {
+                let request = Request::builder()
+                    .uri(uri)
+                    .header(header::HOST, host)
+                    .body(Body::empty())
+                    .unwrap();
+                let response = app.clone().oneshot(request).await.unwrap();
+
+                let cookies: Vec<_> = response
+                    .headers()
+                    .get_all(header::SET_COOKIE)
+                    .iter()
+                    .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+                    .collect();
+
+                assert!(cookies.iter().any(|c| c.name() == "session"));
+                assert!(cookies.iter().all(|c| c.domain() == Some(domain)));
+            }
+        }
+
+        // Unknown hosts get host only Cookies.
+        let request = Request::builder()
+            .uri("/")
+            .header(header::HOST, "localhost")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert!(response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .all(|value| !value.to_str().unwrap().contains("Domain")));
+    }
+
+    #[tokio::test]
+    async fn session_error_into_response() {
+        use axum::response::IntoResponse;
+
+        let response =
+            SessionError::GenericSelectError("connection refused".to_owned()).into_response();
+        assert_eq!(response.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "Internal Server Error");
+
+        assert_eq!(
+            SessionError::Timeout.status_code(),
+            http::StatusCode::GATEWAY_TIMEOUT
+        );
+        assert_eq!(
+            SessionError::Decrypt.status_code(),
+            http::StatusCode::UNAUTHORIZED
+        );
+
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::default())
+            .await
+            .unwrap();
+        async fn handler(session: Session<SessionNullPool>) -> Result<String, SessionError> {
+            session.set("count", "not a number");
+            let count: Option<usize> = session.take("count")?;
+            Ok(count.unwrap_or(0).to_string())
+        }
+
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(SessionLayer::new(session_store));
+
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "Bad Request");
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_reads_and_writes() {
+        let session = Session::new_test(test_util::test_store().await).await;
+        session.set("count", 0);
+
+        let writers = (0..4).map(|_| {
+            let session = session.clone();
+            tokio::spawn(async move {
+                for _ in 0..250 {
+                    session.increment("count", 1);
+                    tokio::task::yield_now().await;
+                }
+            })
+        });
+
+        let readers = (0..8).map(|_| {
+            let session = ReadOnlySession::from(session.clone());
+            tokio::spawn(async move {
+                let mut last = 0;
+
+                for _ in 0..1000 {
+                    // Reads never see a partial write or go backwards.
+                    let count = session.get::<i64>("count").unwrap();
+                    assert!(count >= last);
+                    last = count;
+
+                    assert!(session.contains_key("count"));
+                    assert_eq!(session.keys(), vec!["count".to_owned()]);
+                    tokio::task::yield_now().await;
+                }
+            })
+        });
+
+        let tasks: Vec<_> = writers.chain(readers).collect();
+
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(session.get::<i64>("count").unwrap(), 1000);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn memory_stress() {
+        assert_eq!(
+            SessionConfig::default().with_memory_shards(3).validate(),
+            Err(ConfigError::InvalidMemoryShards(3))
+        );
+
+        // Two shards put every task on the same locks.
+        let config = SessionConfig::default().with_memory_shards(2);
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+
+        let tasks: Vec<_> = (0..8usize)
+            .map(|task| {
+                let session_store = session_store.clone();
+                tokio::spawn(async move {
+                    for round in 0..200usize {
+                        let session = Session::new_test(session_store.clone()).await;
+                        let id = session.id.inner();
+
+                        session.set("owner", (task, round));
+                        assert_eq!(session.get("owner"), Some((task, round)));
+                        assert!(session_store.inner.contains_key(&id));
+
+                        if round % 2 == 0 {
+                            session_store.destroy_session(&id).await.unwrap();
+                            assert!(!session_store.inner.contains_key(&id));
+                            assert_eq!(session.get::<(usize, usize)>("owner"), None);
+                        }
+
+                        tokio::task::yield_now().await;
+                    }
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        // Every other Session was destroyed and none of the others were lost or mixed up.
+        assert_eq!(session_store.inner.len(), 8 * 100);
+        session_store.inner.for_each(|id, session| {
+            let (_, round) = session.get::<(usize, usize)>("owner").unwrap();
+            assert_eq!(&session.id, id);
+            assert_eq!(round % 2, 1);
+        });
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_increments() {
+        let session = Session::new_test(test_util::test_store().await).await;
+
+        let tasks: Vec<_> = (0..100)
+            .map(|_| {
+                let session = session.clone();
+                tokio::spawn(async move {
+                    session.increment("count", 1);
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(session.get::<i64>("count").unwrap(), 100);
+        assert_eq!(session.decrement("count", 10), 90);
+
+        session.set("count", "not a number");
+        assert_eq!(session.increment("count", 2), 2);
+
+        assert_eq!(session.increment_with_max("attempts", 1, 3), (1, false));
+        assert_eq!(session.increment_with_max("attempts", 1, 3), (2, false));
+        assert_eq!(session.increment_with_max("attempts", 1, 3), (3, true));
+        assert_eq!(session.increment_with_max("attempts", 1, 3), (3, true));
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn list_helpers() {
+        let config = SessionConfig::default().with_max_list_len(50);
+        let store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+        let session = Session::new_test(store).await;
+
+        assert!(session.list::<u32>("viewed").is_empty());
+        assert_eq!(session.pop::<u32>("viewed"), None);
+
+        let tasks: Vec<_> = (0..40)
+            .map(|i| {
+                let session = session.clone();
+                tokio::spawn(async move {
+                    session.push("viewed", i);
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(session.list_len("viewed"), 40);
+
+        // Pushing past the max drops the oldest values.
+        session.set("viewed", Vec::<u32>::new());
+        for i in 0..60u32 {
+            session.push("viewed", i);
+        }
+
+        assert_eq!(session.list::<u32>("viewed"), (10..60).collect::<Vec<_>>());
+        assert_eq!(session.pop::<u32>("viewed"), Some(59));
+        assert_eq!(session.list_len("viewed"), 49);
+
+        session.set("viewed", "not a list");
+        assert_eq!(session.list_len("viewed"), 0);
+        session.push("viewed", 1u32);
+        assert_eq!(session.list::<u32>("viewed"), vec![1]);
+
+        assert_eq!(
+            SessionConfig::default().with_max_list_len(0).validate(),
+            Err(ConfigError::InvalidMaxListLen)
+        );
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn rate_limit_windows() {
+        use chrono::Duration;
+        use middleware::rate_limit;
+        use test_util::{MockClock, TestClient};
+
+        let clock = MockClock::new();
+        let config = SessionConfig::default().with_clock(std::sync::Arc::new(clock.clone()));
+        let store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+        let session = Session::new_test(store.clone()).await;
+
+        // Concurrent attempts are all counted so only the max get through.
+        let tasks: Vec<_> = (0..20)
+            .map(|_| {
+                let session = session.clone();
+                tokio::spawn(async move {
+                    session
+                        .rate_limit("password", 5, Duration::minutes(15))
+                        .allowed
+                })
+            })
+            .collect();
+
+        let mut allowed = 0;
+        for task in tasks {
+            allowed += u32::from(task.await.unwrap());
+        }
+        assert_eq!(allowed, 5);
+
+        clock.advance(Duration::minutes(10));
+        let decision = session.rate_limit("password", 5, Duration::minutes(15));
+        assert!(!decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        assert_eq!(decision.retry_after, Duration::minutes(5));
+        assert_eq!(decision.retry_after_secs(), 300);
+
+        // Once the window ends a new one starts.
+        clock.advance(Duration::minutes(5));
+        let decision = session.rate_limit("password", 5, Duration::minutes(15));
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 4);
+        assert_eq!(decision.reset, clock.now() + Duration::minutes(15));
+
+        assert!(!session.rate_limit("none", 0, Duration::minutes(1)).allowed);
+        assert!(!session.contains_key("none"));
+
+        let app = Router::new()
+            .route("/login", get(|| async { "login" }))
+            .route("/login/otp", get(|| async { "otp" }))
+            .route("/", get(|| async { "home" }))
+            .layer(
+                rate_limit::<SessionNullPool>("login", 2, Duration::seconds(90))
+                    .with_paths(["/login"]),
+            );
+        let mut client = TestClient::with_session_store(store, app);
+
+        assert_eq!(client.get("/login").await.status(), http::StatusCode::OK);
+        assert_eq!(
+            client.get("/login/otp").await.status(),
+            http::StatusCode::OK
+        );
+
+        let response = client.get("/login").await;
+        assert_eq!(response.status(), http::StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "90");
+
+        // Other paths are not limited.
+        assert_eq!(client.get("/").await.status(), http::StatusCode::OK);
+
+        clock.advance(Duration::seconds(90));
+        assert_eq!(client.get("/login").await.status(), http::StatusCode::OK);
+    }
+
+    #[cfg(all(feature = "test-util", not(feature = "rest_mode")))]
+    #[tokio::test]
+    async fn commit_timing_around_streamed_bodies() {
+        use std::convert::Infallible;
+        use test_util::TestPool;
+
+        let handler = |session: Session<TestPool>| async move {
+            session.set("step", "from-handler");
+            let stream = futures::stream::once(async move {
+                session.set("step", "from-stream");
+                Ok::<_, Infallible>(bytes::Bytes::from("streamed"))
+            });
+
+            Body::from_stream(stream)
+        };
+
+        for timing in [CommitTiming::AfterHandler, CommitTiming::AfterBody] {
+            let pool = TestPool::new();
+            let session_store = SessionStore::new(Some(pool.clone()), SessionConfig::default())
+                .await
+                .unwrap();
+            let app = Router::new()
+                .route("/", get(handler))
+                .layer(SessionLayer::new(session_store).with_commit_timing(timing));
+
+            let request = || Request::builder().uri("/").body(Body::empty()).unwrap();
+            let response = app.clone().oneshot(request()).await.unwrap();
+
+            // Set-Cookie is sent with the headers before any of the body.
+            let cookie = response
+                .headers()
+                .get_all(header::SET_COOKIE)
+                .iter()
+                .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+                .find(|cookie| cookie.name() == "session")
+                .unwrap();
+            let session_id = cookie.value().to_owned();
+
+            match timing {
+                CommitTiming::AfterHandler => {
+                    assert!(pool.stored(&session_id).unwrap().contains("from-handler"))
+                }
+                CommitTiming::AfterBody => assert!(pool.stored(&session_id).is_none()),
+            }
+
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, "streamed");
+
+            let stored = pool.stored(&session_id).unwrap();
+            match timing {
+                CommitTiming::AfterHandler => assert!(stored.contains("from-handler")),
+                CommitTiming::AfterBody => assert!(stored.contains("from-stream")),
+            }
+
+            // Bodies dropped before they finished, like on a disconnect, still commit.
+            if timing == CommitTiming::AfterBody {
+                let response = app.oneshot(request()).await.unwrap();
+                let session_id = response
+                    .headers()
+                    .get_all(header::SET_COOKIE)
+                    .iter()
+                    .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+                    .find(|cookie| cookie.name() == "session")
+                    .unwrap()
+                    .value()
+                    .to_owned();
+                drop(response);
+
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                assert!(pool.stored(&session_id).unwrap().contains("from-handler"));
+            }
+        }
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn with_mutates_once() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Address {
+            city: String,
+            zip: String,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Profile {
+            address: Address,
+            visits: u32,
+        }
+
+        let session = Session::new_test(test_util::test_store().await).await;
+        session.set(
+            "profile",
+            Profile {
+                address: Address {
+                    city: "Paris".to_owned(),
+                    zip: "75001".to_owned(),
+                },
+                visits: 1,
+            },
+        );
+
+        let id = session.get_session_id().inner();
+        session.store.inner.get_mut(&id).unwrap().update = false;
+
+        // Reading without changing anything does not mark the Session for an update.
+        let city = session.with("profile", |profile: &mut Profile| {
+            profile.address.city.clone()
+        });
+        assert_eq!(city.as_deref(), Some("Paris"));
+        assert!(!session.store.inner.get(&id).unwrap().update);
+
+        let visits = session.with("profile", |profile: &mut Profile| {
+            profile.address.city = "Berlin".to_owned();
+            profile.address.zip = "10115".to_owned();
+            profile.visits += 1;
+            profile.visits
+        });
+        assert_eq!(visits, Some(2));
+        assert!(session.store.inner.get(&id).unwrap().update);
+        assert_eq!(
+            session.get::<Profile>("profile"),
+            Some(Profile {
+                address: Address {
+                    city: "Berlin".to_owned(),
+                    zip: "10115".to_owned(),
+                },
+                visits: 2,
+            })
+        );
+
+        // Missing and unparsable values are left alone.
+        session.set("count", "not a number");
+        assert_eq!(session.with("missing", |value: &mut i64| *value), None);
+        assert_eq!(session.with("count", |value: &mut i64| *value), None);
+        assert_eq!(
+            session.get::<String>("count").as_deref(),
+            Some("not a number")
+        );
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn touch_marks_activity() {
+        let session = Session::new_test(test_util::test_store().await).await;
+        session.set("user", "alice");
+
+        let id = session.get_session_id().inner();
+        let idle = chrono::Utc::now() - chrono::Duration::minutes(30);
+
+        if let Some(mut sess) = session.store.inner.get_mut(&id) {
+            sess.last_accessed = idle;
+            sess.update = false;
+        }
+
+        session.touch().await;
+        assert!(session.last_accessed() > idle);
+        assert!(session.store.inner.get(&id).unwrap().update);
+        assert_eq!(session.get::<String>("user").as_deref(), Some("alice"));
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn presence_checks() {
+        let session = Session::new_test(test_util::test_store().await).await;
+        session.set("user", "alice");
+        session.set("count", "not a number");
+
+        let id = session.get_session_id().inner();
+        session.store.inner.get_mut(&id).unwrap().update = false;
+
+        let read_only = ReadOnlySession::from(session.clone());
+        assert!(session.contains_key("user"));
+        assert!(read_only.contains_key("count"));
+        assert!(!session.contains_key("missing"));
+
+        // The Json string "\"alice\"" is 7 bytes.
+        assert_eq!(session.value_len("user"), Some(7));
+        assert_eq!(read_only.value_len("user"), Some(7));
+        assert_eq!(session.value_len("missing"), None);
+
+        // Presence checks do not deserialize or mark the Session for saving.
+        assert!(session.get::<u32>("count").is_none());
+        assert!(session.contains_key("count"));
+        assert!(!session.store.inner.get(&id).unwrap().update);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn keys_with_prefix() {
+        let session = Session::new_test(test_util::test_store().await).await;
+        session.set("cart:item:123", 1);
+        session.set("cart:item:456", 2);
+        session.set("cart:coupon", "SAVE10");
+        session.set("cartography", true);
+
+        let mut items = session.keys_with_prefix("cart:item:");
+        items.sort();
+        assert_eq!(
+            items,
+            vec!["cart:item:123".to_owned(), "cart:item:456".to_owned()]
+        );
+        assert_eq!(
+            ReadOnlySession::from(session.clone())
+                .keys_with_prefix("cart:")
+                .len(),
+            3
+        );
+        assert!(session.keys_with_prefix("wishlist:").is_empty());
+
+        assert_eq!(session.remove_prefix("cart:"), 3);
+        assert_eq!(session.keys(), vec!["cartography".to_owned()]);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn namespaces_do_not_collide() {
+        let session = Session::new_test(test_util::test_store().await).await;
+        let cart = session.namespace("cart");
+        let wishlist = session.namespace("wishlist");
+
+        session.set("items", 0);
+        cart.set("items", vec![1, 2]);
+        cart.set("coupon", "SAVE10");
+        wishlist.set("items", vec![3]);
+
+        assert_eq!(cart.name(), "cart");
+        assert_eq!(cart.get::<Vec<u32>>("items").unwrap(), vec![1, 2]);
+        assert_eq!(wishlist.get::<Vec<u32>>("items").unwrap(), vec![3]);
+        assert_eq!(session.get::<u32>("items").unwrap(), 0);
+        assert_eq!(session.get::<Vec<u32>>("cart:items").unwrap(), vec![1, 2]);
+
+        let mut keys = cart.keys();
+        keys.sort();
+        assert_eq!(keys, vec!["coupon".to_owned(), "items".to_owned()]);
+        assert_eq!(wishlist.keys(), vec!["items".to_owned()]);
+
+        wishlist.remove("items");
+        assert!(wishlist.get::<Vec<u32>>("items").is_none());
+        assert!(cart.get::<Vec<u32>>("items").is_some());
+
+        wishlist.set("items", vec![3]);
+        cart.clear();
+        assert!(cart.keys().is_empty());
+        assert_eq!(wishlist.get::<Vec<u32>>("items").unwrap(), vec![3]);
+        assert_eq!(session.get::<u32>("items").unwrap(), 0);
+    }
+
+    #[test]
+    fn set_reports_serialize_errors() {
+        use std::sync::{Arc, Mutex};
+        use tracing_subscriber::{layer::SubscriberExt, Layer};
+
+        struct Unserializable;
+
+        impl Serialize for Unserializable {
+            fn serialize<S: serde::Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+                Err(serde::ser::Error::custom("always fails"))
+            }
+        }
+
+        #[derive(Clone, Default)]
+        struct ErrorEvents(Arc<Mutex<Vec<String>>>);
+
+        impl<S: tracing::Subscriber> Layer<S> for ErrorEvents {
+            fn on_event(
+                &self,
+                event: &tracing::Event<'_>,
+                _ctx: tracing_subscriber::layer::Context<'_, S>,
+            ) {
+                struct Message<'a>(&'a mut String);
+
+                impl tracing::field::Visit for Message<'_> {
+                    fn record_debug(
+                        &mut self,
+                        _field: &tracing::field::Field,
+                        value: &dyn std::fmt::Debug,
+                    ) {
+                        self.0.push_str(&format!("{:?}", value));
+                    }
+                }
+
+                if *event.metadata().level() == tracing::Level::ERROR {
+                    let mut message = String::new();
+                    event.record(&mut Message(&mut message));
+                    self.0.lock().unwrap().push(message);
+                }
+            }
+        }
+
+        let events = ErrorEvents::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(events.clone()));
+
+        let config = SessionConfig::default();
+        let mut data = SessionData::new("serialize".to_owned(), true, &config);
+        data.set("user", "alice").unwrap();
+        data.update = false;
+
+        assert!(matches!(
+            data.try_set("user", Unserializable),
+            Err(SessionError::SerdeJson(_))
+        ));
+        assert!(events.0.lock().unwrap().is_empty());
+
+        assert!(data.set("user", Unserializable).is_err());
+        let events = events.0.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(events[0].contains("\"user\""), "{}", events[0]);
+
+        // The previous value is kept instead of being replaced by an empty String.
+        assert_eq!(data.get::<String>("user").unwrap(), "alice");
+        assert!(!data.update);
+    }
+
+    #[test]
+    fn bulk_mutations() {
+        enum Value {
+            Number(i64),
+            Broken,
+        }
+
+        impl Serialize for Value {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                match self {
+                    Value::Number(number) => serializer.serialize_i64(*number),
+                    Value::Broken => Err(serde::ser::Error::custom("always fails")),
+                }
+            }
+        }
+
+        let config = SessionConfig::default();
+        let mut data = SessionData::new("bulk".to_owned(), true, &config);
+        data.set_many([("a", 1), ("ab", 2), ("a:b", 3), ("ba", 4), ("b", 5)])
+            .unwrap();
+        assert!(data.update);
+
+        // A batch with a value that fails to serialize is not applied at all.
+        data.update = false;
+        assert!(data
+            .set_many([("a", Value::Number(10)), ("c", Value::Broken)])
+            .is_err());
+        assert!(!data.update);
+        assert_eq!(data.get::<i64>("a").unwrap(), 1);
+        assert!(data.get::<i64>("c").is_none());
+
+        assert_eq!(data.remove_many(&["b", "missing"]), 1);
+        assert!(data.update);
+        data.update = false;
+        assert_eq!(data.remove_many(&["missing"]), 0);
+        assert!(!data.update);
+
+        // Prefixes match as plain strings so "ab" does not match "a" or "ba".
+        assert_eq!(data.remove_prefix("ab").len(), 1);
+        assert_eq!(data.get::<i64>("a").unwrap(), 1);
+        assert_eq!(data.get::<i64>("ba").unwrap(), 4);
+
+        let mut removed = data.remove_prefix("a");
+        removed.sort();
+        assert_eq!(removed, vec!["a".to_owned(), "a:b".to_owned()]);
+        assert_eq!(data.get::<i64>("ba").unwrap(), 4);
+
+        data.update = false;
+        assert!(data.remove_prefix("z").is_empty());
+        assert!(!data.update);
+
+        // An empty prefix matches every Key.
+        assert_eq!(data.remove_prefix("").len(), 1);
+        assert!(data.export().is_empty());
+    }
+
+    #[test]
+    fn get_remove_keeps_invalid_values() {
+        let config = SessionConfig::default();
+        let mut data = SessionData::new("get_remove".to_owned(), true, &config);
+        data.set("count", "not a number").unwrap();
+        data.update = false;
+
+        assert!(data.get_remove::<usize>("count").is_none());
+        assert!(!data.update);
+        assert_eq!(data.get::<String>("count").unwrap(), "not a number");
+
+        assert!(data.get_remove::<usize>("missing").is_none());
+        assert!(!data.update);
+
+        assert_eq!(data.get_remove::<String>("count").unwrap(), "not a number");
+        assert!(data.update);
+        assert!(data.get::<String>("count").is_none());
+    }
+
+    #[test]
+    fn take_reports_parse_errors() {
+        let config = SessionConfig::default();
+        let mut data = SessionData::new("take".to_owned(), true, &config);
+        data.set("nonce", "abc123").unwrap();
+        data.set("count", "not a number").unwrap();
+
+        assert_eq!(data.take::<String>("nonce").unwrap().unwrap(), "abc123");
+        assert!(data.take::<String>("nonce").unwrap().is_none());
+
+        assert!(matches!(
+            data.take::<usize>("count"),
+            Err(SessionError::SerdeJson(_))
+        ));
+        assert!(data.take::<String>("count").unwrap().is_none());
+
+        data.destroy();
+        assert!(matches!(
+            data.take::<String>("nonce"),
+            Err(SessionError::SessionDestroyed)
+        ));
+    }
+
+    #[test]
+    fn merge_sets_all_values() {
+        let config = SessionConfig::default().with_max_session_size(128);
+        let mut data = SessionData::new("merge".to_owned(), true, &config);
+        data.set("theme", "light").unwrap();
+        data.set("count", 1).unwrap();
+        data.update = false;
+
+        let serde_json::Value::Object(map) = serde_json::json!({ "theme": "dark", "lang": "en" })
+        else {
+            unreachable!()
+        };
+        data.merge(map).unwrap();
+
+        assert!(data.update);
+        assert_eq!(data.get::<String>("theme").unwrap(), "dark");
+        assert_eq!(data.get::<String>("lang").unwrap(), "en");
+        assert_eq!(data.get::<usize>("count").unwrap(), 1);
+
+        // Nothing is set if the merged Session would be too large.
+        let serde_json::Value::Object(map) =
+            serde_json::json!({ "theme": "blue", "big": "x".repeat(128) })
+        else {
+            unreachable!()
+        };
+        assert!(matches!(
+            data.merge(map),
+            Err(SessionError::SizeLimitExceeded(128))
+        ));
+        assert_eq!(data.get::<String>("theme").unwrap(), "dark");
+        assert!(data.get::<String>("big").is_none());
+    }
+
+    #[test]
+    fn export_values_into_another_session() {
+        let config = SessionConfig::default();
+        let mut guest = SessionData::new("guest".to_owned(), true, &config);
+        guest.set("cart", vec![1, 2, 3]).unwrap();
+        guest.set("theme", "dark").unwrap();
+        guest.data.insert("raw".to_owned(), "not json".to_owned());
+        guest.update = false;
+
+        let values = guest.export();
+        assert!(!guest.update);
+        assert_eq!(values["cart"], serde_json::json!([1, 2, 3]));
+        assert_eq!(values["raw"], serde_json::json!("not json"));
+
+        let mut user = SessionData::new("user".to_owned(), true, &config);
+        user.set("theme", "light").unwrap();
+        user.merge(values).unwrap();
+        assert_eq!(user.get::<Vec<usize>>("cart").unwrap(), vec![1, 2, 3]);
+        assert_eq!(user.get::<String>("theme").unwrap(), "dark");
+    }
+
+    #[tokio::test]
+    async fn opt_in_override() {
+        // Removal Cookies are still sent when the Session is not stored.
+        #[cfg(not(feature = "rest_mode"))]
+        let has_session_cookie = |response: &axum::response::Response| {
+            response
+                .headers()
+                .get_all(header::SET_COOKIE)
+                .iter()
+                .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+                .any(|cookie| cookie.name() == "session" && !cookie.value().is_empty())
+        };
+
+        for mode in [SessionMode::Persistent, SessionMode::OptIn] {
+            let config = SessionConfig::default().with_mode(mode);
+            let session_store = SessionStore::<SessionNullPool>::new(None, config)
+                .await
+                .unwrap();
+
+            let app = Router::new()
+                .route(
+                    "/decline",
+                    get(|session: Session<SessionNullPool>| async move {
+                        session.set_opt_in_override(true);
+                    }),
+                )
+                .route(
+                    "/accept",
+                    get(|session: Session<SessionNullPool>| async move {
+                        session.set_opt_in_override(false);
+                    }),
+                )
+                .layer(SessionLayer::new(session_store.clone()));
+
+            let request = Request::builder()
+                .uri("/decline")
+                .body(Body::empty())
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(session_store.inner.len(), 0);
+            #[cfg(not(feature = "rest_mode"))]
+            assert!(!has_session_cookie(&response));
+            #[cfg(feature = "rest_mode")]
+            assert!(response.headers().get("session").is_none());
+
+            let request = Request::builder()
+                .uri("/accept")
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(session_store.inner.len(), 1);
+            #[cfg(not(feature = "rest_mode"))]
+            assert!(has_session_cookie(&response));
+            #[cfg(feature = "rest_mode")]
+            assert!(response.headers().get("session").is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn scoped_layers_coexist() {
+        struct Admin;
+
+        let public_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::default())
+            .await
+            .unwrap();
+        let admin_config = SessionConfig::default()
+            .with_name_prefix("admin")
+            .with_lifetime(chrono::Duration::minutes(15))
+            .with_security_mode(SecurityMode::PerSession);
+        let admin_store = SessionStore::<SessionNullPool>::new(None, admin_config)
+            .await
+            .unwrap();
+
+        let admin = Router::new()
+            .route(
+                "/",
+                get(
+                    |public: Session<SessionNullPool>,
+                     admin: SessionScoped<SessionNullPool, Admin>| async move {
+                        assert_ne!(
+                            public.get_session_id().inner(),
+                            admin.get_session_id().inner()
+                        );
+                        public.set("page", "admin");
+                        admin.set("admin", true);
+                    },
+                ),
+            )
+            .layer(SessionLayer::new(admin_store.clone()).with_scope::<Admin>());
+
+        let app = Router::new()
+            .nest("/admin", admin)
+            .layer(SessionLayer::new(public_store.clone()));
+
+        let request = Request::builder()
+            .uri("/admin")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        assert_eq!(public_store.inner.len(), 1);
+        assert_eq!(admin_store.inner.len(), 1);
+
+        #[cfg(not(feature = "rest_mode"))]
+        {
+            let names: Vec<String> = response
+                .headers()
+                .get_all(header::SET_COOKIE)
+                .iter()
+                .map(|value| {
+                    let cookie = cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap();
+                    cookie.name().to_owned()
+                })
+                .collect();
+            assert!(names.contains(&"session".to_owned()));
+            assert!(names.contains(&"admin_id".to_owned()));
+        }
+        #[cfg(feature = "rest_mode")]
+        {
+            assert!(response.headers().get("session").is_some());
+            assert!(response.headers().get("admin_id").is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn forget_skips_headers() {
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::default())
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|session: Session<SessionNullPool>| async move {
+                    let count: usize = session.get("count").unwrap_or(0) + 1;
+                    session.set("count", count);
+                    count.to_string()
+                }),
+            )
+            .route(
+                "/download",
+                get(|session: Session<SessionNullPool>| async move {
+                    session.forget();
+                    session.set("downloaded", true);
+                }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let headers = response.headers().clone();
+
+        #[cfg(not(feature = "rest_mode"))]
+        let session = headers
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+            .find(|cookie| cookie.name() == "session")
+            .map(|cookie| (header::COOKIE, format!("session={}", cookie.value())))
+            .unwrap();
+        #[cfg(feature = "rest_mode")]
+        let session = (
+            http::HeaderName::from_static("session"),
+            headers.get("session").unwrap().to_str().unwrap().to_owned(),
+        );
+
+        let request = Request::builder()
+            .uri("/download")
+            .header(session.0.clone(), session.1.clone())
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(
+            response
+                .headers()
+                .get_all(header::SET_COOKIE)
+                .iter()
+                .count(),
+            0
+        );
+        assert!(response.headers().get("session").is_none());
+
+        let request = Request::builder()
+            .uri("/")
+            .header(session.0, session.1)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "2");
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn lax_get_ignores_cross_site_posts() {
+        let config = SessionConfig::default()
+            .with_cookie_same_site(cookie::SameSite::Strict)
+            .with_cookie_same_site_lax_get(true);
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+
+        let handler = |session: Session<SessionNullPool>| async move {
+            session.get::<String>("user").unwrap_or_default()
+        };
+        let app = Router::new()
+            .route(
+                "/login",
+                get(|session: Session<SessionNullPool>| async move {
+                    session.set("user", "alice");
+                }),
+            )
+            .route("/", get(handler).post(handler))
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/login")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let cookie = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+            .find(|cookie| cookie.name() == "session")
+            .unwrap();
+        assert_eq!(cookie.same_site(), Some(cookie::SameSite::Lax));
+
+        let request = |method: &str, headers: &[(&str, &str)]| {
+            let mut builder = Request::builder()
+                .method(method)
+                .uri("/")
+                .header(header::HOST, "example.com")
+                .header(header::COOKIE, format!("session={}", cookie.value()));
+
+            for (name, value) in headers {
+                builder = builder.header(*name, *value);
+            }
+
+            builder.body(Body::empty()).unwrap()
+        };
+
+        let cases = [
+            ("GET", vec![("sec-fetch-site", "cross-site")], "alice"),
+            ("POST", vec![("sec-fetch-site", "same-origin")], "alice"),
+            ("POST", vec![("origin", "https://example.com")], "alice"),
+            ("POST", vec![], "alice"),
+            ("POST", vec![("sec-fetch-site", "cross-site")], ""),
+            ("POST", vec![("origin", "https://evil.example")], ""),
+            ("POST", vec![("origin", "null")], ""),
+        ];
+
+        for (method, headers, expected) in cases {
+            let response = app
+                .clone()
+                .oneshot(request(method, &headers))
+                .await
+                .unwrap();
+
+            if expected.is_empty() {
+                assert!(response.headers().get(header::SET_COOKIE).is_none());
+            }
+
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, expected, "{method} {headers:?}");
+        }
+
+        // The ignored Requests do not leave Sessions behind.
+        assert_eq!(session_store.count_sessions().await, 1);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn domain_list_shares_sessions() {
+        use crate::config::registrable_domain;
+
+        assert_eq!(
+            registrable_domain("app.example.co.uk"),
+            Some("example.co.uk")
+        );
+        assert_eq!(registrable_domain("example.com"), Some("example.com"));
+        assert_eq!(registrable_domain("co.uk"), None);
+        assert_eq!(registrable_domain("com"), None);
+
+        let config = SessionConfig::default()
+            .with_cookie_domain(".example.com")
+            .with_domain_list(["app.example.com", "API.example.com"]);
+
+        assert_eq!(
+            SessionConfig::default()
+                .with_cookie_domain("co.uk")
+                .validate(),
+            Err(ConfigError::PublicSuffixDomain("co.uk".to_owned()))
+        );
+        for domain in ["localhost", "intranet", "127.0.0.1"] {
+            assert_eq!(
+                SessionConfig::default()
+                    .with_cookie_domain(domain)
+                    .validate(),
+                Ok(())
+            );
+        }
+        assert_eq!(
+            SessionConfig::default()
+                .with_domain_list(["app.example.com"])
+                .validate(),
+            Err(ConfigError::DomainListWithoutDomain)
+        );
+        assert_eq!(
+            config
+                .clone()
+                .with_domain_list(["app.example.org"])
+                .validate(),
+            Err(ConfigError::HostOutsideDomain(
+                "app.example.org".to_owned(),
+                "example.com".to_owned()
+            ))
+        );
+        assert_eq!(
+            config
+                .clone()
+                .with_secure(true)
+                .with_cookie_prefix(CookiePrefix::Host)
+                .validate(),
+            Err(ConfigError::HostPrefix)
+        );
+
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+        let app = Router::new()
+            .route(
+                "/login",
+                get(|session: Session<SessionNullPool>| async move {
+                    session.set("user", "alice");
+                }),
+            )
+            .route(
+                "/",
+                get(|session: Session<SessionNullPool>| async move {
+                    session.get::<String>("user").unwrap_or_default()
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/login")
+                    .header(header::HOST, "app.example.com")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let cookie = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+            .find(|cookie| cookie.name() == "session")
+            .unwrap();
+        assert_eq!(cookie.domain(), Some("example.com"));
+
+        for (host, expected) in [
+            ("api.example.com:8443", "alice"),
+            ("evil.example.com", ""),
+            ("example.com", ""),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri("/")
+                        .header(header::HOST, host)
+                        .header(header::COOKIE, format!("session={}", cookie.value()))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            if expected.is_empty() {
+                assert!(response.headers().get(header::SET_COOKIE).is_none());
+            }
+
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, expected, "{host}");
+        }
+
+        assert_eq!(session_store.count_sessions().await, 1);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn idle_timeout_destroys_session() {
+        let config =
+            SessionConfig::default().with_idle_timeout(Some(chrono::Duration::milliseconds(50)));
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/set",
+                get(|session: Session<SessionNullPool>| async move {
+                    session.set("status", "ready");
+                }),
+            )
+            .route(
+                "/get",
+                get(|session: Session<SessionNullPool>| async move {
+                    session.get::<String>("status").unwrap_or_default()
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/set").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let cookie = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+            .find(|cookie| cookie.name() == "session")
+            .unwrap();
+        let get = |id: &str| {
+            Request::builder()
+                .uri("/get")
+                .header(header::COOKIE, format!("session={}", id))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        // Accessed within the idle timeout so the Session is kept.
+        let response = app.clone().oneshot(get(cookie.value())).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "ready");
+
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        // Idle for too long so the handler gets a new Session.
+        let response = app.clone().oneshot(get(cookie.value())).await.unwrap();
+        let new_cookie = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+            .find(|cookie| cookie.name() == "session")
+            .unwrap();
+        assert_ne!(new_cookie.value(), cookie.value());
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "");
+        assert!(session_store
+            .get_session_data(cookie.value())
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn peek_does_not_extend_session() {
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::default())
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/set",
+                get(|session: Session<SessionNullPool>| async move {
+                    session.set("status", "ready");
+                }),
+            )
+            .route(
+                "/poll",
+                get(|session: PeekSession<SessionNullPool>| async move {
+                    session.get::<String>("status").unwrap_or_default()
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/set").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let cookie = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+            .find(|cookie| cookie.name() == "session")
+            .unwrap();
+        let before = session_store
+            .get_session_data(cookie.value())
+            .await
+            .unwrap()
+            .unwrap();
+
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+
+        let request = Request::builder()
+            .uri("/poll")
+            .header(header::COOKIE, format!("session={}", cookie.value()))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert!(response.headers().get(header::SET_COOKIE).is_none());
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "ready");
+
+        let after = session_store
+            .get_session_data(cookie.value())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(before.last_accessed, after.last_accessed);
+        assert_eq!(before.expires, after.expires);
+        assert_eq!(before.autoremove, after.autoremove);
+
+        // Peeking without a Session does not create one.
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/poll").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert!(response.headers().get(header::SET_COOKIE).is_none());
+        assert_eq!(session_store.inner.len(), 1);
+    }
+
+    #[cfg(any(feature = "postgres-rustls", feature = "postgres-native"))]
+    #[tokio::test]
+    async fn invalidation_evicts_other_nodes() {
+        let config = SessionConfig::default();
+        let session_store = SessionStore::<SessionNullPool>::new(None, config.clone())
+            .await
+            .unwrap();
+
+        for id in ["idle", "busy"] {
+            let mut sess = SessionData::new(id.to_owned(), true, &config);
+            sess.requests = usize::from(id == "busy");
+            session_store.inner.insert(id.to_owned(), sess);
+        }
+
+        // Notifications sent by this node are ignored.
+        session_store.evict_invalidated(&format!("{}:idle", session_store.node_id));
+        assert!(session_store.inner.contains_key("idle"));
+
+        // Sessions in use are evicted too so the running request can not save a stale copy.
+        session_store.evict_invalidated("other-node:idle");
+        session_store.evict_invalidated("other-node:busy");
+        assert!(!session_store.inner.contains_key("idle"));
+        assert!(!session_store.inner.contains_key("busy"));
+    }
+
+    #[tokio::test]
+    async fn watcher_fires_on_destroy() {
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::default())
+            .await
+            .unwrap();
+        let watchers = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let shared = watchers.clone();
+
+        let app = Router::new()
+            .route(
+                "/ws",
+                get(move |session: Session<SessionNullPool>| async move {
+                    shared.lock().unwrap().push(session.watch());
+                }),
+            )
+            .route(
+                "/logout",
+                get(|session: Session<SessionNullPool>| async move { session.destroy() }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        let request = Request::builder().uri("/ws").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+
+        #[cfg(not(feature = "rest_mode"))]
+        let session = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+            .find(|cookie| cookie.name() == "session")
+            .map(|cookie| (header::COOKIE, format!("session={}", cookie.value())))
+            .unwrap();
+        #[cfg(feature = "rest_mode")]
+        let session = (
+            http::HeaderName::from_static("session"),
+            response
+                .headers()
+                .get("session")
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_owned(),
+        );
+
+        let mut watcher = watchers.lock().unwrap().pop().unwrap();
+        let timeout = std::time::Duration::from_millis(50);
+        assert!(tokio::time::timeout(timeout, watcher.destroyed())
+            .await
+            .is_err());
+
+        let request = Request::builder()
+            .uri("/logout")
+            .header(session.0, session.1)
+            .body(Body::empty())
+            .unwrap();
+        app.oneshot(request).await.unwrap();
+
+        assert!(tokio::time::timeout(timeout, watcher.destroyed())
+            .await
+            .is_ok());
+        assert!(watcher.session_id().is_none());
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn layer_cookie_path() {
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::default())
+            .await
+            .unwrap();
+
+        let app = Router::new().route("/", get(|| async { "" })).layer(
+            SessionLayer::new(session_store.clone())
+                .with_cookie_path("/app")
+                .unwrap(),
+        );
+
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+
+        for value in response.headers().get_all(header::SET_COOKIE) {
+            let cookie = cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap();
+            assert_eq!(cookie.path(), Some("/app"));
+        }
+
+        // The store keeps its own config.
+        assert_eq!(session_store.config.cookie_path, "/");
+        assert!(matches!(
+            SessionLayer::new(session_store).with_cookie_path("app"),
+            Err(SessionError::Config(ConfigError::InvalidPath(_)))
+        ));
+
+        let config = SessionConfig::default()
+            .with_cookie_prefix(CookiePrefix::Host)
+            .with_secure(true);
+        let host_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+        assert!(matches!(
+            SessionLayer::new(host_store).with_cookie_path("/app"),
+            Err(SessionError::Config(ConfigError::HostPrefix))
+        ));
+    }
+
+    #[tokio::test]
+    async fn export_import_round_trip() {
+        let config = SessionConfig::default();
+        let session_store = SessionStore::<SessionNullPool>::new(None, config.clone())
+            .await
+            .unwrap();
+
+        let live = uuid::Uuid::new_v4().to_string();
+        let expired = uuid::Uuid::new_v4().to_string();
+
+        for id in [&live, &expired] {
+            let mut sess = SessionData::new(id.clone(), true, &config);
+            sess.set("user", id).unwrap();
+            session_store.inner.insert(id.clone(), sess);
+        }
+
+        let mut export = Vec::new();
+        assert_eq!(session_store.export(&mut export).unwrap(), 2);
+
+        // Expire one of the Sessions after it was exported so import has to drop it.
+        let mut lines: Vec<String> = String::from_utf8(export)
+            .unwrap()
+            .lines()
+            .map(str::to_owned)
+            .collect();
+        for line in lines.iter_mut().filter(|line| line.contains(&expired)) {
+            let mut record: serde_json::Value = serde_json::from_str(line).unwrap();
+            record["expires"] =
+                serde_json::to_value(chrono::Utc::now() - chrono::Duration::hours(1)).unwrap();
+            *line = record.to_string();
+        }
+        let export = lines.join("\n");
+
+        let restored = SessionStore::<SessionNullPool>::new(None, config.clone())
+            .await
+            .unwrap();
+        assert_eq!(restored.import(export.as_bytes()).await.unwrap(), 1);
+        let data = restored.get_session_data(&live).await.unwrap().unwrap();
+        assert_eq!(data.get::<String>("user").unwrap(), live);
+        assert!(!restored.inner.contains_key(&expired));
+
+        // Importing again skips the ID's already in memory.
+        assert_eq!(restored.import(export.as_bytes()).await.unwrap(), 0);
+
+        let future = export.replacen("\"version\":1", "\"version\":2", 1);
+        assert!(matches!(
+            restored.import(future.as_bytes()).await,
+            Err(SessionError::UnsupportedExportVersion(2))
+        ));
+        assert!(matches!(
+            restored.import("".as_bytes()).await,
+            Err(SessionError::InvalidExportHeader)
+        ));
+    }
+
+    #[tokio::test]
+    async fn subscribe_to_session_events() {
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::default())
+            .await
+            .unwrap();
+        let id = uuid::Uuid::new_v4().to_string();
+        let mut events = session_store.subscribe(&id);
+
+        // Changes from a background job reach the subscriber.
+        let session = session_store.load_or_create(&id).await.unwrap();
+        session.set("count", 1);
+        session.remove("count");
+        session.save().await.unwrap();
+        session.destroy();
+
+        assert_eq!(
+            events.recv().await.unwrap(),
+            SessionEvent::Updated("count".to_owned())
+        );
+        assert_eq!(
+            events.recv().await.unwrap(),
+            SessionEvent::Updated("count".to_owned())
+        );
+        assert_eq!(events.recv().await.unwrap(), SessionEvent::Saved);
+        assert_eq!(events.recv().await.unwrap(), SessionEvent::Destroyed);
+
+        session_store.destroy_session(&id).await.unwrap();
+        assert_eq!(events.recv().await.unwrap(), SessionEvent::Destroyed);
+        assert!(matches!(
+            events.recv().await,
+            Err(tokio::sync::broadcast::error::RecvError::Closed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn data_migrator_upgrades_old_values() {
+        #[derive(Deserialize, Serialize, Debug, PartialEq)]
+        struct User {
+            id: u64,
+            name: String,
+        }
+
+        let config = SessionConfig::default().with_data_migrator(std::sync::Arc::new(
+            |key, version, value| {
+                // Version 0 stored the user as just their ID.
+                (key == "user" && version == 0)
+                    .then(|| format!("{{\"id\":{},\"name\":\"unknown\"}}", value))
+            },
+        ));
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+        let id = uuid::Uuid::new_v4().to_string();
+        let session = session_store.load_or_create(&id).await.unwrap();
+
+        session.set("user", 5);
+        assert_eq!(session.schema_version(), 0);
+        assert_eq!(
+            session.get::<User>("user"),
+            Some(User {
+                id: 5,
+                name: "unknown".to_owned()
+            })
+        );
+        // The migrated value was stored.
+        assert_eq!(session.get::<u64>("user"), None);
+
+        session.set("user", 6);
+        session.set_schema_version(1);
+        assert_eq!(session.get::<User>("user"), None);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn schema_migrators_upgrade_on_load() {
+        use std::collections::HashMap;
+
+        let pool = test_util::TestPool::new();
+        let session_store = SessionStore::new(Some(pool.clone()), SessionConfig::default())
+            .await
+            .unwrap();
+
+        let mut data = SessionData::new("schema".to_owned(), true, &session_store.config);
+        data.set("uid", 5).unwrap();
+        assert_eq!(data.schema_version, 0);
+        session_store.store_session(&data).await.unwrap();
+
+        let migrators: [SchemaMigrator; 2] = [
+            std::sync::Arc::new(|data: &mut HashMap<String, String>, version: u32| {
+                assert_eq!(version, 0);
+                if let Some(uid) = data.remove("uid") {
+                    data.entry("user_id".to_owned()).or_insert(uid);
+                }
+            }),
+            std::sync::Arc::new(|data: &mut HashMap<String, String>, version: u32| {
+                assert_eq!(version, 1);
+                data.entry("theme".to_owned())
+                    .or_insert_with(|| "\"light\"".to_owned());
+            }),
+        ];
+        let config = migrators
+            .into_iter()
+            .fold(SessionConfig::default(), |config, migrator| {
+                config.with_schema_migrator(migrator)
+            });
+        let session_store = SessionStore::new(Some(pool.clone()), config).await.unwrap();
+
+        // Both migrators ran and the Session is saved at the current version.
+        let mut loaded = session_store
+            .load_session("schema".to_owned())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.schema_version, 2);
+        assert!(loaded.update);
+        assert_eq!(loaded.get::<u64>("uid"), None);
+        assert_eq!(loaded.get::<u64>("user_id"), Some(5));
+        assert_eq!(loaded.get::<String>("theme"), Some("light".to_owned()));
+
+        // Current Sessions are not migrated again and new Sessions start current.
+        // The expiry is not serialized so it is refreshed before saving like the layer does.
+        loaded.refresh_expires(&session_store.config);
+        session_store.store_session(&loaded).await.unwrap();
+        let mut reloaded = session_store
+            .load_session("schema".to_owned())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!reloaded.migrate_schema(&session_store.config.schema_migrators));
+        assert_eq!(
+            SessionData::new("new".to_owned(), true, &session_store.config).schema_version,
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_session_secrets() {
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::default())
+            .await
+            .unwrap();
+        let id = uuid::Uuid::new_v4().to_string();
+        let session = session_store.load_or_create(&id).await.unwrap();
+
+        session.set("api-token", "secret-token");
+        session.set("count", 5);
+        assert!(session.verify_secret("api-token", "secret-token"));
+        assert!(!session.verify_secret("api-token", "secret-tokem"));
+        assert!(!session.verify_secret("api-token", "secret"));
+        assert!(!session.verify_secret("count", "5"));
+        assert!(!session.verify_secret("missing", ""));
+
+        #[cfg(feature = "argon2")]
+        {
+            session
+                .set_hashed_secret("hashed", "secret-token")
+                .await
+                .unwrap();
+            assert!(!session
+                .get::<String>("hashed")
+                .unwrap()
+                .contains("secret-token"));
+            assert!(session.verify_hashed_secret("hashed", "secret-token").await);
+            assert!(!session.verify_hashed_secret("hashed", "secret-tokem").await);
+            assert!(
+                !session
+                    .verify_hashed_secret("api-token", "secret-token")
+                    .await
+            );
+        }
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[test]
+    fn cookie_header_parsing() {
+        let jar = |value: &str| {
+            let mut headers = http::HeaderMap::new();
+            headers.insert(header::COOKIE, value.parse().unwrap());
+            headers::get_cookies(&headers, CookieEncoding::Encoded)
+        };
+
+        // Base64 padding stays part of the value.
+        let cookies = jar("session=YWJjZA==; key=YQ==");
+        assert_eq!(cookies.get("session").unwrap().value(), "YWJjZA==");
+        assert_eq!(cookies.get("key").unwrap().value(), "YQ==");
+
+        // The first of duplicate names is the most specific path.
+        let cookies = jar("session=first; session=second");
+        assert_eq!(cookies.get("session").unwrap().value(), "first");
+
+        // Malformed fragments between valid Cookies are skipped.
+        let cookies = jar("session=abc; garbage; =novalue; store=true");
+        assert_eq!(cookies.get("session").unwrap().value(), "abc");
+        assert_eq!(cookies.get("store").unwrap().value(), "true");
+        assert_eq!(cookies.iter().count(), 2);
+
+        // Quoted values keep embedded semicolons.
+        let cookies = jar("other=\"a;b\"; session=\"abc\"");
+        assert_eq!(cookies.get("other").unwrap().value(), "a;b");
+        assert_eq!(cookies.get("session").unwrap().value(), "abc");
+
+        // Whitespace variations.
+        let cookies = jar("  session = abc ;store=true;;\tkey=\tdef  ");
+        assert_eq!(cookies.get("session").unwrap().value(), "abc");
+        assert_eq!(cookies.get("store").unwrap().value(), "true");
+        assert_eq!(cookies.get("key").unwrap().value(), "def");
+    }
+
+    #[cfg(feature = "rest_mode")]
+    #[tokio::test]
+    async fn rest_headers_case_insensitive() {
+        let config = SessionConfig::default()
+            .with_session_name("X-Session-Id")
+            .with_key_name("X-Session-Key")
+            .with_store_name("X-Session-Store");
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|session: Session<SessionNullPool>| async move {
+                    let count: usize = session.get("count").unwrap_or(0) + 1;
+                    session.set("count", count);
+                    count.to_string()
+                }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let headers: Vec<(String, String)> = response
+            .headers()
+            .iter()
+            .filter(|(name, _)| name.as_str().starts_with("x-session-"))
+            .map(|(name, value)| (name.to_string(), value.to_str().unwrap().to_owned()))
+            .collect();
+        assert!(!headers.is_empty());
+
+        // Mixed-case config names must match whatever case the client sends.
+        let cases: [fn(&str) -> String; 2] =
+            [|name| name.to_lowercase(), |name| name.to_uppercase()];
+
+        for (expected, case) in ["2", "3"].into_iter().zip(cases) {
+            let mut request = Request::builder().uri("/");
+
+            for (name, value) in &headers {
+                request = request.header(case(name), value);
+            }
+
+            let response = app
+                .clone()
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, expected);
+        }
+    }
+
+    #[cfg(feature = "rest_mode")]
+    #[tokio::test]
+    async fn rest_invalid_headers_fail() {
+        let config =
+            SessionConfig::default().with_id_generator(std::sync::Arc::new(|| "x".repeat(5000)));
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::HeaderTooLarge(_, _, config::MAX_HEADER_LEN))
+        ));
+
+        let mut headers = http::HeaderMap::new();
+        assert!(matches!(
+            headers::insert_header(&mut headers, "bad name", "value"),
+            Err(SessionError::InvalidHeader(name)) if name == "bad name"
+        ));
+        assert!(headers.is_empty());
+
+        // Lenient validation lets the invalid name through so the Response fails instead
+        // of being sent without its Session.
+        let config = SessionConfig::default()
+            .with_session_name("bad name")
+            .with_lenient_validation(true);
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|session: Session<SessionNullPool>| async move {
+                    session.set("count", 1);
+                    "secret page"
+                }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(response.headers().get(http::header::CONTENT_TYPE).is_none());
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(body.is_empty());
+    }
+
+    #[cfg(feature = "rest_mode")]
+    #[tokio::test]
+    async fn store_header_bound_to_session() {
+        let key = Key::generate();
+        let cipher = CipherSuite::Aes256Gcm;
+        let (id_a, id_b) = (
+            uuid::Uuid::new_v4().to_string(),
+            uuid::Uuid::new_v4().to_string(),
+        );
+
+        let bound_a = headers::encrypt(&headers::bound_aad("store", &id_a), "true", &key, cipher);
+        let unbound = headers::encrypt("store", "true", &key, cipher);
+
+        for accept_unbound in [true, false] {
+            let config = SessionConfig::default()
+                .with_key(key.clone())
+                .with_security_mode(SecurityMode::Simple)
+                .with_accept_unbound_tokens(accept_unbound);
+            let session_store = SessionStore::<SessionNullPool>::new(None, config)
+                .await
+                .unwrap();
+
+            let storable = |id: &str, store: &str| {
+                let mut map = std::collections::HashMap::new();
+                map.insert(
+                    http::HeaderName::from_static("session"),
+                    headers::encrypt("session", id, &key, cipher),
+                );
+                map.insert(http::HeaderName::from_static("store"), store.to_owned());
+                headers::get_headers_and_key(&session_store, map)
+            };
+
+            assert!(storable(&id_a, &bound_a).await.2);
+            // A store Header replayed from another Session is ignored.
+            assert!(!storable(&id_b, &bound_a).await.2);
+            assert_eq!(storable(&id_b, &unbound).await.2, accept_unbound);
+        }
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn require_key_protects_routes() {
+        use axum::Extension;
+        use middleware::require_key;
+
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::default())
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/account",
+                get(|Extension(user_id): Extension<i64>| async move { user_id.to_string() })
+                    .route_layer(
+                        require_key::<SessionNullPool, i64>("user_id").with_redirect("/login"),
+                    ),
+            )
+            .route(
+                "/admin",
+                get(|Extension(user_id): Extension<i64>| async move { user_id.to_string() })
+                    .route_layer(
+                        require_key::<SessionNullPool, i64>("user_id")
+                            .with_status(http::StatusCode::FORBIDDEN),
+                    ),
+            )
+            .route(
+                "/login",
+                get(|session: Session<SessionNullPool>| async move {
+                    session.set("user_id", 5);
+                }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/account?tab=1")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::SEE_OTHER);
+        assert_eq!(
+            response.headers().get(header::LOCATION).unwrap(),
+            "/login?redirect=%2Faccount%3Ftab%3D1"
+        );
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/admin")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::FORBIDDEN);
+
+        // The login route is not protected.
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/login")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        let cookie = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|value| cookie::Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+            .find(|cookie| cookie.name() == "session")
+            .unwrap();
+
+        for uri in ["/account", "/admin"] {
+            let request = Request::builder()
+                .uri(uri)
+                .header(header::COOKIE, format!("session={}", cookie.value()))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), http::StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, "5");
+        }
+    }
 }
@@ -0,0 +1,481 @@
+//! Middleware built on top of the `SessionLayer`.
+
+use crate::{DatabasePool, Session};
+use axum_core::response::Response;
+use chrono::Duration;
+use futures::future::BoxFuture;
+use http::{self, header, HeaderValue, Request, StatusCode};
+use serde::de::DeserializeOwned;
+use std::{
+    borrow::Cow,
+    convert::Infallible,
+    fmt::{self, Debug, Formatter},
+    marker::{PhantomData, Send, Sync},
+    task::{Context, Poll},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Query parameter used to pass the original URI to the login page.
+pub(crate) const DEFAULT_REDIRECT_PARAM: &str = "redirect";
+
+/// What to respond with when the Session is missing the required key.
+#[derive(Clone, Debug)]
+enum Rejection {
+    Status(StatusCode),
+    Redirect {
+        to: Cow<'static, str>,
+        param: Cow<'static, str>,
+    },
+}
+
+/// Requires the Session to hold `key` as a `V` before the Request reaches the handler.
+///
+/// The value is inserted into the Request's extensions so handlers can extract it
+/// with `Extension<V>` instead of loading it from the Session again. Requests whose
+/// Session is missing the key, or whose value fails to deserialize as `V`, are
+/// rejected with `401 Unauthorized` unless set otherwise with `with_status` or
+/// `with_redirect`.
+///
+/// Must be added within a `SessionLayer` as it reads the unscoped `Session<T>`.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::middleware::require_key;
+///
+/// let app = Router::new()
+///     .route("/account", get(|Extension(user_id): Extension<i64>| async move {
+///         format!("Hello user {}", user_id)
+///     }))
+///     .route_layer(require_key::<SessionPgPool, i64>("user_id").with_redirect("/login"))
+///     .route("/login", get(login))
+///     .layer(SessionLayer::new(session_store));
+/// ```
+///
+pub fn require_key<T, V>(key: &'static str) -> RequireKeyLayer<T, V>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+    V: DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    RequireKeyLayer {
+        key,
+        rejection: Rejection::Status(StatusCode::UNAUTHORIZED),
+        phantom: PhantomData,
+    }
+}
+
+/// Layer created by `require_key`.
+pub struct RequireKeyLayer<T, V> {
+    key: &'static str,
+    rejection: Rejection,
+    /// Uses a fn pointer so `V` does not need to be Send, Sync or Clone for the layer to be.
+    phantom: PhantomData<fn() -> (T, V)>,
+}
+
+impl<T, V> RequireKeyLayer<T, V> {
+    /// Set's the Status Code returned when the key is missing. Default is `401 Unauthorized`.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::middleware::require_key;
+    ///
+    /// let layer = require_key::<SessionPgPool, i64>("user_id").with_status(StatusCode::FORBIDDEN);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        self.rejection = Rejection::Status(status);
+        self
+    }
+
+    /// Set's the layer to redirect to a login page when the key is missing.
+    ///
+    /// The original path and query are added to the redirect as the `redirect` query
+    /// parameter so the login page can send the user back once they logged in.
+    /// Responds with `303 See Other`.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::middleware::require_key;
+    ///
+    /// // Redirects "/account?tab=1" to "/login?redirect=%2Faccount%3Ftab%3D1".
+    /// let layer = require_key::<SessionPgPool, i64>("user_id").with_redirect("/login");
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_redirect(mut self, to: impl Into<Cow<'static, str>>) -> Self {
+        self.rejection = Rejection::Redirect {
+            to: to.into(),
+            param: Cow::Borrowed(DEFAULT_REDIRECT_PARAM),
+        };
+        self
+    }
+
+    /// Set's the layer to redirect to a login page, passing the original URI as `param`.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::middleware::require_key;
+    ///
+    /// let layer = require_key::<SessionPgPool, i64>("user_id")
+    ///     .with_redirect_param("/login", "next");
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_redirect_param(
+        mut self,
+        to: impl Into<Cow<'static, str>>,
+        param: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        self.rejection = Rejection::Redirect {
+            to: to.into(),
+            param: param.into(),
+        };
+        self
+    }
+}
+
+impl<T, V> Clone for RequireKeyLayer<T, V> {
+    fn clone(&self) -> Self {
+        Self {
+            key: self.key,
+            rejection: self.rejection.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, V> Debug for RequireKeyLayer<T, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequireKeyLayer")
+            .field("key", &self.key)
+            .field("rejection", &self.rejection)
+            .field("value", &std::any::type_name::<V>())
+            .finish()
+    }
+}
+
+impl<S, T, V> Layer<S> for RequireKeyLayer<T, V> {
+    type Service = RequireKeyService<S, T, V>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequireKeyService {
+            layer: self.clone(),
+            inner,
+        }
+    }
+}
+
+/// Service created by `RequireKeyLayer`.
+pub struct RequireKeyService<S, T, V> {
+    layer: RequireKeyLayer<T, V>,
+    inner: S,
+}
+
+impl<S: Clone, T, V> Clone for RequireKeyService<S, T, V> {
+    fn clone(&self) -> Self {
+        Self {
+            layer: self.layer.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S: Debug, T, V> Debug for RequireKeyService<S, T, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequireKeyService")
+            .field("layer", &self.layer)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<S, T, V, ReqBody, ResBody> Service<Request<ReqBody>> for RequireKeyService<S, T, V>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+    ReqBody: Send + 'static,
+    ResBody: Default + Send + 'static,
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+    V: DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    type Response = Response<ResBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let not_ready_inner = self.inner.clone();
+        let mut ready_inner = std::mem::replace(&mut self.inner, not_ready_inner);
+
+        let Some(session) = req.extensions().get::<Session<T>>() else {
+            tracing::error!("`require_key` was used without a `SessionLayer` around it.");
+            let mut response = Response::new(ResBody::default());
+            *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+            return Box::pin(async move { Ok(response) });
+        };
+
+        match session.get::<V>(self.layer.key) {
+            Some(value) => {
+                req.extensions_mut().insert(value);
+                Box::pin(ready_inner.call(req))
+            }
+            None => {
+                let response = reject(&self.layer.rejection, req.uri());
+                Box::pin(async move { Ok(response) })
+            }
+        }
+    }
+}
+
+/// Limits how often a Session may make Requests using `Session::rate_limit`.
+///
+/// Every Request counts an attempt against the fixed window stored under `key`.
+/// Requests over `max` within the `window` are rejected with `429 Too Many Requests`
+/// and a `Retry-After` Header holding the seconds until the window resets. The handler
+/// is not called for them. Use `with_paths` to only count some paths.
+///
+/// Must be added within a `SessionLayer` as it reads the unscoped `Session<T>`.
+///
+/// Like `Session::rate_limit` the limit resets whenever the client drops its Session, so
+/// it does not stop brute force attempts without a per account or per IP limit as well.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::middleware::rate_limit;
+///
+/// let app = Router::new()
+///     .route("/login", post(login))
+///     .route("/reset-password", post(reset_password))
+///     .layer(
+///         rate_limit::<SessionPgPool>("auth-attempts", 5, Duration::minutes(15))
+///             .with_paths(["/login", "/reset-password"]),
+///     )
+///     .layer(SessionLayer::new(session_store));
+/// ```
+///
+pub fn rate_limit<T>(
+    key: impl Into<Cow<'static, str>>,
+    max: u32,
+    window: Duration,
+) -> RateLimitLayer<T>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    RateLimitLayer {
+        key: key.into(),
+        max,
+        window,
+        paths: Vec::new(),
+        phantom: PhantomData,
+    }
+}
+
+/// Layer created by `rate_limit`.
+pub struct RateLimitLayer<T> {
+    key: Cow<'static, str>,
+    max: u32,
+    window: Duration,
+    paths: Vec<Cow<'static, str>>,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> RateLimitLayer<T> {
+    /// Set's the paths the limit applies to. Default is empty to apply it to every path.
+    ///
+    /// A path matches itself and every path below it, so `/login` also matches
+    /// `/login/otp` but not `/login-help`.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::middleware::rate_limit;
+    ///
+    /// let layer = rate_limit::<SessionPgPool>("login-attempts", 5, Duration::minutes(15))
+    ///     .with_paths(["/login"]);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_paths<I, P>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<Cow<'static, str>>,
+    {
+        self.paths = paths.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Checks the limit applies to the path.
+    fn matches(&self, path: &str) -> bool {
+        self.paths.is_empty()
+            || self.paths.iter().any(|matched| {
+                let matched = matched.trim_end_matches('/');
+
+                path.strip_prefix(matched)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+    }
+}
+
+impl<T> Clone for RateLimitLayer<T> {
+    fn clone(&self) -> Self {
+        Self {
+            key: self.key.clone(),
+            max: self.max,
+            window: self.window,
+            paths: self.paths.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> Debug for RateLimitLayer<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimitLayer")
+            .field("key", &self.key)
+            .field("max", &self.max)
+            .field("window", &self.window)
+            .field("paths", &self.paths)
+            .finish()
+    }
+}
+
+impl<S, T> Layer<S> for RateLimitLayer<T> {
+    type Service = RateLimitService<S, T>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            layer: self.clone(),
+            inner,
+        }
+    }
+}
+
+/// Service created by `RateLimitLayer`.
+pub struct RateLimitService<S, T> {
+    layer: RateLimitLayer<T>,
+    inner: S,
+}
+
+impl<S: Clone, T> Clone for RateLimitService<S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            layer: self.layer.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S: Debug, T> Debug for RateLimitService<S, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimitService")
+            .field("layer", &self.layer)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<S, T, ReqBody, ResBody> Service<Request<ReqBody>> for RateLimitService<S, T>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+    ReqBody: Send + 'static,
+    ResBody: Default + Send + 'static,
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    type Response = Response<ResBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let not_ready_inner = self.inner.clone();
+        let mut ready_inner = std::mem::replace(&mut self.inner, not_ready_inner);
+
+        if !self.layer.matches(req.uri().path()) {
+            return Box::pin(ready_inner.call(req));
+        }
+
+        let Some(session) = req.extensions().get::<Session<T>>() else {
+            tracing::error!("`rate_limit` was used without a `SessionLayer` around it.");
+            let mut response = Response::new(ResBody::default());
+            *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+            return Box::pin(async move { Ok(response) });
+        };
+
+        let decision = session.rate_limit(&self.layer.key, self.layer.max, self.layer.window);
+
+        if decision.allowed {
+            return Box::pin(ready_inner.call(req));
+        }
+
+        let mut response = Response::new(ResBody::default());
+        *response.status_mut() = StatusCode::TOO_MANY_REQUESTS;
+        response.headers_mut().insert(
+            header::RETRY_AFTER,
+            HeaderValue::from(decision.retry_after_secs()),
+        );
+
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+/// Builds the Response for a Request missing the required key.
+fn reject<ResBody: Default>(rejection: &Rejection, uri: &http::Uri) -> Response<ResBody> {
+    let mut response = Response::new(ResBody::default());
+
+    match rejection {
+        Rejection::Status(status) => *response.status_mut() = *status,
+        Rejection::Redirect { to, param } => {
+            let original = uri.path_and_query().map_or("/", |path| path.as_str());
+            let separator = if to.contains('?') { '&' } else { '?' };
+            let location = format!(
+                "{}{}{}={}",
+                to,
+                separator,
+                encode_query_value(param),
+                encode_query_value(original)
+            );
+
+            match HeaderValue::from_str(&location) {
+                Ok(location) => {
+                    *response.status_mut() = StatusCode::SEE_OTHER;
+                    response.headers_mut().insert(header::LOCATION, location);
+                }
+                Err(err) => {
+                    tracing::error!("Redirect location {} is not a valid header: {}", to, err);
+                    *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+                }
+            }
+        }
+    }
+
+    response
+}
+
+/// Percent encodes everything but unreserved characters so the value is safe in a query.
+fn encode_query_value(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
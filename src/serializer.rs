@@ -0,0 +1,78 @@
+use crate::SessionError;
+#[cfg(any(feature = "bincode", feature = "msgpack"))]
+use base64::{engine::general_purpose, Engine as _};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Format used to Serialize the Session's values and the Session itself for database storage.
+///
+/// Binary formats are Base64 encoded so they can be stored in the same String fields
+/// as Json. Changing the Serializer will make any Sessions stored with the previous
+/// Serializer fail to load and they will be treated as empty Sessions.
+///
+/// # Examples
+/// ```rust
+/// use axum_session::{SessionConfig, SessionSerializer};
+///
+/// let config = SessionConfig::default().with_serializer(SessionSerializer::Json);
+/// ```
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SessionSerializer {
+    /// Uses serde_json. This is the Default.
+    #[default]
+    Json,
+    /// Uses bincode. Requires the `bincode` feature.
+    #[cfg(feature = "bincode")]
+    Bincode,
+    /// Uses rmp-serde MessagePack. Requires the `msgpack` feature.
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+}
+
+impl SessionSerializer {
+    /// Serializes the value into a String based on the Serializer.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionSerializer;
+    ///
+    /// let value = SessionSerializer::Json.serialize(&5).unwrap();
+    /// ```
+    ///
+    pub fn serialize<T: Serialize + ?Sized>(&self, value: &T) -> Result<String, SessionError> {
+        Ok(match self {
+            SessionSerializer::Json => serde_json::to_string(value)?,
+            #[cfg(feature = "bincode")]
+            SessionSerializer::Bincode => {
+                general_purpose::STANDARD.encode(bincode::serialize(value)?)
+            }
+            #[cfg(feature = "msgpack")]
+            SessionSerializer::MessagePack => {
+                general_purpose::STANDARD.encode(rmp_serde::to_vec(value)?)
+            }
+        })
+    }
+
+    /// Deserializes the String into a value based on the Serializer.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionSerializer;
+    ///
+    /// let value: u32 = SessionSerializer::Json.deserialize("5").unwrap();
+    /// ```
+    ///
+    pub fn deserialize<T: DeserializeOwned>(&self, value: &str) -> Result<T, SessionError> {
+        Ok(match self {
+            SessionSerializer::Json => serde_json::from_str(value)?,
+            #[cfg(feature = "bincode")]
+            SessionSerializer::Bincode => {
+                bincode::deserialize(&general_purpose::STANDARD.decode(value)?)?
+            }
+            #[cfg(feature = "msgpack")]
+            SessionSerializer::MessagePack => {
+                rmp_serde::from_slice(&general_purpose::STANDARD.decode(value)?)?
+            }
+        })
+    }
+}
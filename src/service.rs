@@ -1,20 +1,25 @@
-use crate::{config::SecurityMode, headers::*, DatabasePool, Session, SessionData, SessionStore};
-use axum_core::{response::Response, BoxError};
+use crate::{
+    config::SecurityMode, headers::*, session_scoped::SessionInserter,
+    session_store::LoadedSession, CommitTiming, DatabasePool, MemoryStore, Session, SessionData,
+    SessionID, SessionStore,
+};
+use axum_core::{body::Body, response::Response, BoxError, Error};
 use bytes::Bytes;
-use chrono::Utc;
 #[cfg(feature = "key-store")]
 use fastbloom_rs::Deletable;
 use futures::future::BoxFuture;
-use http::{self, Request};
-use http_body::Body as HttpBody;
+use http::{self, header, Request, StatusCode};
+use http_body::{Body as HttpBody, Frame, SizeHint};
 use std::{
     boxed::Box,
     convert::Infallible,
     fmt::{self, Debug, Formatter},
     marker::{Send, Sync},
-    task::{Context, Poll},
+    pin::Pin,
+    task::{ready, Context, Poll},
 };
 use tower_service::Service;
+use tracing::Instrument;
 
 #[derive(Clone)]
 pub struct SessionService<S, T>
@@ -22,6 +27,10 @@ where
     T: DatabasePool + Clone + Debug + Sync + Send + 'static,
 {
     pub(crate) session_store: SessionStore<T>,
+    /// Inserts the Session as `Session<T>` or as a `SessionScoped` for scoped layers.
+    pub(crate) insert: SessionInserter<T>,
+    /// When the Session is persisted relative to the Response body.
+    pub(crate) commit_timing: CommitTiming,
     pub(crate) inner: S,
 }
 
@@ -38,7 +47,7 @@ where
     ResBody::Error: Into<BoxError>,
     T: DatabasePool + Clone + Debug + Sync + Send + 'static,
 {
-    type Response = Response<ResBody>;
+    type Response = Response<Body>;
     type Error = Infallible;
     type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
 
@@ -48,309 +57,723 @@ where
 
     fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
         let store = self.session_store.clone();
+        let insert = self.insert;
+        let commit_timing = self.commit_timing;
         let not_ready_inner = self.inner.clone();
         let mut ready_inner = std::mem::replace(&mut self.inner, not_ready_inner);
+        let span = tracing::debug_span!(
+            "session",
+            session_id = tracing::field::Empty,
+            security_mode = ?store.config.security_mode,
+            db_hit = false,
+        );
+
+        Box::pin(
+            async move {
+                // Cross-site Requests that can change state do not get to use the Session.
+                #[cfg(not(feature = "rest_mode"))]
+                let cross_site =
+                    store.config.lax_get_only && is_cross_site_unsafe(req.method(), req.headers());
+
+                // Neither do Requests from hosts outside the domain list.
+                #[cfg(not(feature = "rest_mode"))]
+                let foreign_host = !store.config.allows_host(req.uri(), req.headers());
+
+                #[cfg(not(feature = "rest_mode"))]
+                let cookies = if cross_site || foreign_host {
+                    Default::default()
+                } else {
+                    get_cookies(req.headers(), store.config.cookie_encoding)
+                };
 
-        Box::pin(async move {
-            #[cfg(not(feature = "rest_mode"))]
-            let cookies = get_cookies(req.headers());
-
-            #[cfg(not(feature = "rest_mode"))]
-            let (mut session_key, session_uuid, storable) =
-                get_headers_and_key(&store, cookies).await;
-
-            #[cfg(feature = "rest_mode")]
-            let headers = get_headers(&store, req.headers());
-
-            #[cfg(feature = "rest_mode")]
-            let (mut session_key, session_uuid, storable) =
-                get_headers_and_key(&store, headers).await;
-
-            let (mut session, is_new) = Session::new(store, session_uuid).await;
-
-            // Check if the session id exists if not lets check if it exists in the database or generate a new session.
-            // If manual mode is enabled then do not check for a Session unless the UUID is not new.
-            let check_database: bool = if is_new && !session.store.config.session_mode.is_manual() {
-                let sess = SessionData::new(session.id.0, storable, &session.store.config);
-                session.store.inner.insert(session.id.inner(), sess);
-                false
-            } else if !is_new || !session.store.config.session_mode.is_manual() {
-                !session.store.service_session_data(&session)
-            } else {
-                false
-            };
-
-            if check_database {
-                let mut sess = session
-                    .store
-                    .load_session(session.id.inner())
-                    .await
-                    .ok()
-                    .flatten()
-                    .unwrap_or_else(|| {
-                        SessionData::new(session.id.0, storable, &session.store.config)
-                    });
+                // The Cookie Domain and secure flag are resolved from the Request as the Response has no Host.
+                #[cfg(not(feature = "rest_mode"))]
+                let cookie_attrs = CookieAttrs::new(&store.config, req.uri(), req.headers());
 
-                sess.autoremove = Utc::now() + session.store.config.memory_lifespan;
-                sess.store = storable;
-                sess.update = true;
-                sess.requests = 1;
-                session.store.inner.insert(session.id.inner(), sess);
-            }
+                #[cfg(feature = "rest_mode")]
+                let cookie_attrs = CookieAttrs::default();
 
-            let (last_sweep, last_database_sweep) = {
-                let timers = session.store.timers.read().await;
-                (timers.last_expiry_sweep, timers.last_database_expiry_sweep)
-            };
-
-            // This branch runs less often, and we already have write access,
-            // let's check if any sessions expired. We don't want to hog memory
-            // forever by abandoned sessions (e.g. when a client lost their cookie)
-            // throttle by memory lifespan - e.g. sweep every hour
-            let current_time = Utc::now();
-
-            if last_sweep <= current_time && !session.store.config.memory_lifespan.is_zero() {
-                // Only unload these from filter if the Client is None as this means no database.
-                // Otherwise only unload from the filter if removed from the Database.
-                #[cfg(feature = "key-store")]
-                if session.store.is_persistent()
-                    && session.store.auto_handles_expiry()
-                    && session.store.config.use_bloom_filters
-                {
-                    let mut filter = session.store.filter.write().await;
-                    session
-                        .store
-                        .inner
-                        .iter()
-                        .filter(|r| r.autoremove < current_time)
-                        .for_each(|r| filter.remove(r.key().as_bytes()));
+                #[cfg(not(feature = "rest_mode"))]
+                let has_token = has_session_token(&store.config, &cookies);
 
-                    session
-                        .store
-                        .keys
-                        .iter()
-                        .filter(|r| r.autoremove < current_time)
-                        .for_each(|r| filter.remove(r.key().as_bytes()));
+                #[cfg(not(feature = "rest_mode"))]
+                let (mut session_key, session_id, storable) = get_headers_and_key(&store, cookies)
+                    .instrument(tracing::debug_span!("parse_headers"))
+                    .await;
+
+                #[cfg(feature = "rest_mode")]
+                let headers = get_headers(&store, req.headers());
+
+                #[cfg(feature = "rest_mode")]
+                let has_token = has_session_token(&store.config, &headers);
+
+                #[cfg(feature = "rest_mode")]
+                let (mut session_key, session_id, storable) = get_headers_and_key(&store, headers)
+                    .instrument(tracing::debug_span!("parse_headers"))
+                    .await;
+
+                // A Session ID was sent but failed to decrypt or validate.
+                if store.config.reject_invalid_tokens && has_token && session_id.is_none() {
+                    store.metrics.rejected();
+                    // Keys created for the rejected Request are never sent to the client.
+                    store.keys.remove(&session_key.id.inner());
+
+                    let mut response = Response::new(Body::empty());
+                    *response.status_mut() = StatusCode::UNAUTHORIZED;
+
+                    #[cfg(not(feature = "rest_mode"))]
+                    remove_session_cookies(&store.config, response.headers_mut(), &cookie_attrs);
+
+                    return Ok(response);
                 }
 
-                session
+                // Requests sent with an ID renewed by the id_renewal_interval still lead to its Session.
+                let session_id = session_id.map(|id| store.renamed_to(&id).unwrap_or(id));
+
+                // Without memory the Session is only held by this Request's extensions.
+                let store = if store.config.memory {
+                    store
+                } else {
+                    store.scoped()
+                };
+
+                let (mut session, mut is_new) = Session::new(store, session_id).await;
+                // Only part of the ID is recorded so full Session ID's do not end up in logs.
+                let short_id: String = session.id.inner().chars().take(8).collect();
+                tracing::Span::current().record("session_id", short_id.as_str());
+
+                // Peeking keeps the throwaway Session from being stored or sending Cookies.
+                #[cfg(not(feature = "rest_mode"))]
+                if cross_site {
+                    tracing::debug!("Ignoring Session Cookies on a cross-site Request");
+                    session.peek();
+                } else if foreign_host {
+                    tracing::debug!("Ignoring Session Cookies from a host outside the domain list");
+                    session.peek();
+                }
+
+                // Kept so a peeked Request can put the Session's activity timers back.
+                let previous = session
                     .store
                     .inner
-                    .retain(|_k, v| v.autoremove > current_time);
-                session
-                    .store
-                    .keys
-                    .retain(|_k, v| v.autoremove > current_time);
-                session.store.timers.write().await.last_expiry_sweep =
-                    Utc::now() + session.store.config.purge_update;
-            }
+                    .get(&session.id.inner())
+                    .map(|sess| (sess.last_accessed, sess.expires, sess.autoremove));
+                let mut previous_accessed = previous.map(|(last_accessed, _, _)| last_accessed);
+                let mut previous_timers =
+                    previous.map(|(_, expires, autoremove)| (expires, autoremove));
+
+                // Check if the session id exists if not lets check if it exists in the database or generate a new session.
+                // If manual mode is enabled then do not check for a Session unless the UUID is not new.
+                let check_database: bool =
+                    if is_new && !session.store.config.session_mode.is_manual() {
+                        let sess =
+                            SessionData::new(session.id.inner(), storable, &session.store.config);
+                        session.store.inner.insert(session.id.inner(), sess);
+                        session.store.metrics.created();
+                        false
+                    } else if !is_new || !session.store.config.session_mode.is_manual() {
+                        !session.store.service_session_data(&session)
+                    } else {
+                        false
+                    };
+
+                if check_database {
+                    tracing::Span::current().record("db_hit", session.store.is_persistent());
+
+                    let loaded = session
+                        .store
+                        .try_load_session(session.id.inner())
+                        .instrument(tracing::debug_span!("load_session"))
+                        .await;
+
+                    // Unreadable data is destroyed so the user starts fresh under a new ID.
+                    // Memory only Sessions also get a new ID so they never overwrite the
+                    // stored Session once the database recovers.
+                    let replace = match &loaded {
+                        Ok(LoadedSession::Corrupt) if session.store.config.destroy_on_error => {
+                            tracing::warn!(
+                                "Session data could not be read, destroying the Session"
+                            );
+
+                            if let Err(err) =
+                                session.store.destroy_session(&session.id.inner()).await
+                            {
+                                tracing::warn!("Corrupt Session failed to be destroyed: {}", err);
+                            }
+
+                            true
+                        }
+                        Err(err) if session.store.config.fallback_to_memory => {
+                            session.store.database_failed(err);
+                            true
+                        }
+                        Err(err) => {
+                            tracing::warn!("Session data failed to load: {}", err);
+                            false
+                        }
+                        Ok(_) => false,
+                    };
+
+                    if replace {
+                        let store = session.store.clone();
+                        session = Session::new(store, None).await.0;
+                        is_new = true;
+                        previous_accessed = None;
+                        previous_timers = None;
+                        tracing::Span::current().record(
+                            "session_id",
+                            session
+                                .id
+                                .inner()
+                                .chars()
+                                .take(8)
+                                .collect::<String>()
+                                .as_str(),
+                        );
+                    }
+
+                    let loaded = match loaded {
+                        Ok(LoadedSession::Found(sess)) => Some(*sess),
+                        _ => None,
+                    };
+
+                    // The database keeps its own expiry so only last_accessed needs to be kept.
+                    if let Some(sess) = &loaded {
+                        previous_accessed = Some(sess.last_accessed);
+                        session.loaded_from_database = true;
+                    } else {
+                        // A Session ID that named no Session still starts a new one.
+                        session.is_new = true;
+                    }
+
+                    let mut sess = loaded.unwrap_or_else(|| {
+                        if !is_new {
+                            tracing::debug!("Session not found or expired, creating a new one");
+                        }
+
+                        session.store.metrics.created();
+                        SessionData::new(session.id.inner(), storable, &session.store.config)
+                    });
 
-            // Throttle by database lifespan - e.g. sweep every 6 hours
-            if last_database_sweep <= current_time && session.store.is_persistent() {
-                //Remove any old keys that expired and Remove them from our loaded filter.
-                #[cfg(feature = "key-store")]
-                let expired = session.store.cleanup().await.unwrap();
-                #[cfg(not(feature = "key-store"))]
-                let _ = session.store.cleanup().await.unwrap();
-
-                #[cfg(feature = "key-store")]
-                if !session.store.auto_handles_expiry() {
-                    let mut filter = session.store.filter.write().await;
-                    expired.iter().for_each(|id| filter.remove(id.as_bytes()));
+                    sess.autoremove =
+                        session.store.config.now() + session.store.config.memory_lifespan;
+                    sess.last_accessed = session.store.config.now();
+                    sess.refresh_expires(&session.store.config);
+                    sess.store = storable;
+                    sess.update = true;
+                    sess.requests = 1;
+                    session.store.inner.insert(session.id.inner(), sess);
                 }
 
-                session
-                    .store
-                    .timers
-                    .write()
-                    .await
-                    .last_database_expiry_sweep =
-                    Utc::now() + session.store.config.purge_database_update;
-            }
+                // Idle Sessions are destroyed and replaced before the handler can use them.
+                if let (Some(idle_timeout), Some(last_accessed)) =
+                    (session.store.config.idle_timeout, previous_accessed)
+                {
+                    if session.store.config.now() - last_accessed > idle_timeout {
+                        tracing::debug!("Session idle for longer than idle_timeout, destroying it");
+
+                        if let Err(err) = session.store.destroy_session(&session.id.inner()).await {
+                            tracing::warn!("Idle Session failed to be destroyed: {}", err);
+                        }
 
-            // Sets a clone of the Store in the Extensions for Direct usage and sets the Session for Direct usage
-            //req.extensions_mut().insert(store.clone());
-            req.extensions_mut().insert(session.clone());
+                        let store = session.store.clone();
+                        session = Session::new(store, None).await.0;
+                        is_new = true;
+                        previous_accessed = None;
+                        previous_timers = None;
+
+                        if !session.store.config.session_mode.is_manual() {
+                            let sess = SessionData::new(
+                                session.id.inner(),
+                                storable,
+                                &session.store.config,
+                            );
+                            session.store.inner.insert(session.id.inner(), sess);
+                            session.store.metrics.created();
+                        }
+
+                        tracing::Span::current().record(
+                            "session_id",
+                            session
+                                .id
+                                .inner()
+                                .chars()
+                                .take(8)
+                                .collect::<String>()
+                                .as_str(),
+                        );
+                    }
+                }
+
+                // Session ID's older than the id_renewal_interval are renewed once the Request is done.
+                if session.store.config.id_renewal_interval.is_some() {
+                    if let Some(mut sess) = session.store.inner.get_mut(&session.id.inner()) {
+                        sess.schedule_renewal(&session.store.config);
+                    }
+                }
 
-            let mut response = ready_inner.call(req).await?;
+                session.store.evict_memory().await;
 
-            let (renew, storable, renew_key, destroy, loaded) =
-                if let Some(session_data) = session.store.inner.get(&session.id.inner()) {
+                let (last_sweep, last_database_sweep, last_snapshot) = {
+                    let timers = session.store.timers.read().await;
                     (
-                        session_data.renew,
-                        session_data.store,
-                        session_data.renew_key,
-                        session_data.destroy,
-                        true,
+                        timers.last_expiry_sweep,
+                        timers.last_database_expiry_sweep,
+                        timers.last_snapshot,
                     )
-                } else {
-                    (false, false, false, false, false)
                 };
 
-            if !destroy && (!session.store.config.session_mode.is_manual() || loaded) {
-                if renew {
-                    // Lets change the Session ID and destory the old Session from the database.
-                    let session_id = Session::generate_uuid(&session.store).await;
-
-                    // Lets remove it from the database first.
-                    if session.store.is_persistent() {
-                        session
-                            .store
-                            .database_remove_session(session.id.inner())
-                            .await
-                            .unwrap();
-                    }
+                // This branch runs less often, and we already have write access,
+                // let's check if any sessions expired. We don't want to hog memory
+                // forever by abandoned sessions (e.g. when a client lost their cookie)
+                // throttle by memory lifespan - e.g. sweep every hour
+                let current_time = session.store.config.now();
 
-                    //lets remove it from the filter. if the bottom fails just means it did not exist or was already unloaded.
+                if last_sweep <= current_time && !session.store.config.memory_lifespan.is_zero() {
+                    // Only unload these from filter if the Client is None as this means no database.
+                    // Otherwise only unload from the filter if removed from the Database.
                     #[cfg(feature = "key-store")]
-                    if session.store.config.use_bloom_filters {
+                    if session.store.is_persistent()
+                        && session.store.auto_handles_expiry()
+                        && session.store.config.use_bloom_filters
+                    {
                         let mut filter = session.store.filter.write().await;
-                        filter.remove(session.id.inner().as_bytes());
-                    }
+                        session.store.inner.for_each(|id, r| {
+                            if r.autoremove < current_time && !session.store.keeps_unsaved(r) {
+                                filter.remove(id.as_bytes());
+                            }
+                        });
 
-                    // Lets remove update and reinsert.
-                    if let Some((_, mut session_data)) =
-                        session.store.inner.remove(&session.id.inner())
-                    {
-                        session_data.id = session_id.0;
-                        session_data.renew = false;
-                        session.id = session_id;
-                        session.store.inner.insert(session.id.inner(), session_data);
+                        session
+                            .store
+                            .keys
+                            .iter()
+                            .filter(|r| r.autoremove < current_time)
+                            .for_each(|r| filter.remove(r.key().as_bytes()));
                     }
+
+                    // Sessions waiting for the database to recover are kept till they are saved.
+                    session.store.inner.retain(|_k, v| {
+                        v.autoremove > current_time || session.store.keeps_unsaved(v)
+                    });
+                    session
+                        .store
+                        .keys
+                        .retain(|_k, v| v.autoremove > current_time);
+                    session
+                        .store
+                        .watchers
+                        .retain(|_k, v| v.receiver_count() > 0);
+                    session.store.events.retain(|_k, v| v.receiver_count() > 0);
+                    session
+                        .store
+                        .recent_writes
+                        .retain(|_k, v| v.0 > current_time);
+                    session.store.renamed.retain(|_k, v| v.1 > current_time);
+                    session.store.timers.write().await.last_expiry_sweep =
+                        session.store.config.now() + session.store.config.purge_update;
                 }
 
-                if renew_key && session.store.config.security_mode == SecurityMode::PerSession {
-                    // Lets remove it from the database first.
-                    if session.store.is_persistent() {
-                        session
-                            .store
-                            .database_remove_session(session_key.id.inner())
-                            .await
-                            .unwrap();
+                // Memory only stores write their Sessions to the snapshot file every interval.
+                if let Some((_, interval)) = &session.store.config.snapshot {
+                    if last_snapshot <= current_time && !session.store.is_persistent() {
+                        session.store.timers.write().await.last_snapshot =
+                            session.store.config.now() + *interval;
+
+                        let store = session.store.clone();
+                        tokio::spawn(async move {
+                            if let Err(err) = store.snapshot().await {
+                                tracing::warn!("Session snapshot failed to write: {}", err);
+                            }
+                        });
                     }
+                }
 
-                    // Lets remove update and reinsert.
+                // Throttle by database lifespan - e.g. sweep every 6 hours
+                if last_database_sweep <= current_time && session.store.is_persistent() {
+                    //Remove any old keys that expired and Remove them from our loaded filter.
                     #[cfg(feature = "key-store")]
-                    let old_id = session_key.renew(&session.store).await.unwrap();
-
+                    let expired = session.store.or_fallback(session.store.cleanup().await);
                     #[cfg(not(feature = "key-store"))]
-                    let _ = session_key.renew(&session.store).await.unwrap();
+                    let _ = session.store.or_fallback(session.store.cleanup().await);
 
                     #[cfg(feature = "key-store")]
-                    if session.store.config.use_bloom_filters {
+                    if !session.store.auto_handles_expiry() {
                         let mut filter = session.store.filter.write().await;
-                        filter.remove(old_id.as_bytes());
+                        expired.iter().for_each(|id| filter.remove(id.as_bytes()));
                     }
+
+                    let mut timers = session.store.timers.write().await;
+                    timers.last_database_expiry_sweep =
+                        session.store.config.now() + session.store.config.purge_database_update;
+                    timers.last_database_cleanup = Some(session.store.config.now());
                 }
-            }
 
-            // Add the Session ID so it can link back to a Session if one exists.
-            if (!session.store.config.session_mode.is_opt_in() || storable)
-                && session.store.is_persistent()
-                && !destroy
-            {
-                let clone_session =
-                    if let Some(mut sess) = session.store.inner.get_mut(&session.id.inner()) {
-                        // Check if Database needs to be updated or not. TODO: Make updatable based on a timer for in memory only.
-                        if session.store.config.always_save || sess.update || !sess.validate() {
-                            if sess.longterm {
-                                sess.expires = Utc::now() + session.store.config.max_lifespan;
-                            } else {
-                                sess.expires = Utc::now() + session.store.config.lifespan;
-                            };
+                // Sets a clone of the Store in the Extensions for Direct usage and sets the Session for Direct usage
+                //req.extensions_mut().insert(store.clone());
+                insert(req.extensions_mut(), session.clone());
 
-                            sess.update = false;
+                // Kept so a failed Request can put the Session back to how it was loaded.
+                let loaded_session = if session.store.config.persist_on_error {
+                    None
+                } else {
+                    session
+                        .store
+                        .inner
+                        .get(&session.id.inner())
+                        .map(|sess| sess.clone())
+                };
 
-                            Some(sess.clone())
-                        } else {
-                            None
+                let mut response = ready_inner
+                    .call(req)
+                    .instrument(tracing::debug_span!("call_inner"))
+                    .await?
+                    .map(Body::new);
+
+                let failed =
+                    !session.store.config.persist_on_error && response.status().is_server_error();
+
+                if failed {
+                    tracing::debug!("Discarding Session changes made by a failed Request");
+
+                    if let Some(loaded_session) = loaded_session {
+                        if let Some(mut sess) = session.store.inner.get_mut(&session.id.inner()) {
+                            let requests = sess.requests;
+                            *sess = loaded_session;
+                            sess.requests = requests;
                         }
+                    }
+                }
+
+                // Another Request renewed the Session ID while this one ran so the new ID is used.
+                if !session.store.inner.contains_key(&session.id.inner()) {
+                    if let Some(new_id) = session.store.renamed_to(&session.id.inner()) {
+                        session.id = SessionID::new(new_id);
+                    }
+                }
+
+                let (renew, scheduled, storable, opt_in, renew_key, destroy, loaded) =
+                    if let Some(session_data) = session.store.inner.get(&session.id.inner()) {
+                        (
+                            session_data.renew || session_data.renew_scheduled,
+                            // Manual renews get no grace window as they often guard a login.
+                            session_data.renew_scheduled && !session_data.renew,
+                            session_data.store,
+                            session_data.is_opt_in(&session.store.config),
+                            session_data.renew_key,
+                            session_data.destroy,
+                            true,
+                        )
                     } else {
-                        None
+                        let opt_in = session.store.config.session_mode.is_opt_in();
+                        (false, false, false, opt_in, false, false, false)
                     };
 
-                if let Some(sess) = clone_session {
-                    session.store.store_session(&sess).await.unwrap();
+                // Peeked Requests do not count as activity so the timers are put back.
+                let peek = session.is_peeked();
 
-                    if session.store.config.security_mode == SecurityMode::PerSession {
-                        session
-                            .store
-                            .store_key(&session_key, sess.expires.timestamp())
-                            .await
-                            .unwrap();
+                if peek {
+                    if let Some(mut sess) = session.store.inner.get_mut(&session.id.inner()) {
+                        if let Some(last_accessed) = previous_accessed {
+                            sess.last_accessed = last_accessed;
+                        }
+
+                        if let Some((expires, autoremove)) = previous_timers {
+                            sess.expires = expires;
+                            sess.autoremove = autoremove;
+                        }
                     }
                 }
-            }
 
-            //lets tell the system we can unload this request now.
-            //If there are still more left the bottom wont unload anything.
-            session.remove_request();
+                // Forgotten, peeked and failed Responses leave the Session as is for the next request.
+                let forget = session.is_forgotten() || peek || failed;
 
-            if ((session.store.config.session_mode.is_opt_in() && !storable) || destroy)
-                && !session.is_parallel()
-            {
-                if session.store.config.security_mode == SecurityMode::PerSession {
-                    #[cfg(feature = "key-store")]
-                    if session.store.config.use_bloom_filters {
-                        let mut filter = session.store.filter.write().await;
-                        filter.remove(session_key.id.inner().as_bytes());
+                if !destroy && !forget && (!session.store.config.session_mode.is_manual() || loaded)
+                {
+                    if renew {
+                        // Lets change the Session ID and destory the old Session from the database.
+                        let session_id = Session::generate_id(&session.store).await;
+
+                        // Lets remove it from the database first.
+                        if session.store.is_persistent() {
+                            let result = session
+                                .store
+                                .database_remove_session(session.id.inner())
+                                .await;
+                            session.store.or_fallback(result);
+                        }
+
+                        //lets remove it from the filter. if the bottom fails just means it did not exist or was already unloaded.
+                        #[cfg(feature = "key-store")]
+                        if session.store.config.use_bloom_filters {
+                            let mut filter = session.store.filter.write().await;
+                            filter.remove(session.id.inner().as_bytes());
+                        }
+
+                        // Lets remove update and reinsert.
+                        if let Some((_, mut session_data)) =
+                            session.store.inner.remove(&session.id.inner())
+                        {
+                            session_data.id = session_id.inner();
+                            session_data.renew = false;
+                            session_data.renew_scheduled = false;
+                            session_data.last_renewed = Some(session.store.config.now());
+                            session
+                                .store
+                                .notify_renewed(&session.id.inner(), session_id.inner());
+
+                            if scheduled {
+                                session
+                                    .store
+                                    .record_renamed(session.id.inner(), session_id.inner());
+                            }
+                            session.id = session_id;
+                            session.store.inner.insert(session.id.inner(), session_data);
+                        }
                     }
 
-                    let _ = session.store.keys.remove(&session_key.id.inner());
+                    if renew_key && session.store.config.security_mode == SecurityMode::PerSession {
+                        // Lets remove it from the database first.
+                        if session.store.is_persistent() {
+                            let result = session
+                                .store
+                                .database_remove_session(session_key.id.inner())
+                                .await;
+                            session.store.or_fallback(result);
+                        }
 
-                    if session.store.is_persistent() {
-                        session
-                            .store
-                            .database_remove_session(session_key.id.inner())
-                            .await
-                            .unwrap();
+                        // Lets remove update and reinsert.
+                        #[cfg(feature = "key-store")]
+                        let old_id = session_key.renew(&session.store).await.unwrap();
+
+                        #[cfg(not(feature = "key-store"))]
+                        let _ = session_key.renew(&session.store).await.unwrap();
+
+                        #[cfg(feature = "key-store")]
+                        if session.store.config.use_bloom_filters {
+                            let mut filter = session.store.filter.write().await;
+                            filter.remove(old_id.as_bytes());
+                        }
                     }
                 }
 
-                #[cfg(feature = "key-store")]
-                if session.store.config.use_bloom_filters {
-                    let mut filter = session.store.filter.write().await;
-                    filter.remove(session.id.inner().as_bytes());
+                if !forget {
+                    let result = tracing::debug_span!("set_headers").in_scope(|| {
+                        set_headers(
+                            &session,
+                            &session_key,
+                            response.headers_mut(),
+                            destroy,
+                            storable,
+                            opt_in,
+                            is_new || renew,
+                            &cookie_attrs,
+                        )
+                    });
+
+                    // The client would silently lose its Session so fail the Response instead.
+                    if let Err(err) = result {
+                        tracing::error!("Session headers failed to set: {}", err);
+                        fail_response(&mut response, StatusCode::INTERNAL_SERVER_ERROR);
+                    }
                 }
 
-                let _ = session.store.inner.remove(&session.id.inner());
+                // Persisting is left to the body when it streams so changes made while
+                // streaming are saved. Set-Cookie has to go out with the headers either way.
+                let commit = async move {
+                    let mut saved = Ok(());
 
-                if session.store.is_persistent() {
-                    session
-                        .store
-                        .database_remove_session(session.id.inner())
-                        .await
-                        .unwrap();
-                }
-            }
+                    // Add the Session ID so it can link back to a Session if one exists.
+                    if (!opt_in || storable) && session.store.is_persistent() && !destroy && !forget
+                    {
+                        let clone_session = if let Some(mut sess) =
+                            session.store.inner.get_mut(&session.id.inner())
+                        {
+                            // Check if Database needs to be updated or not. TODO: Make updatable based on a timer for in memory only.
+                            if session.store.config.always_save
+                                || sess.update
+                                || !sess.validate(session.store.config.now())
+                            {
+                                sess.refresh_expires(&session.store.config);
+                                sess.update = false;
+
+                                Some(sess.clone())
+                            } else {
+                                None
+                            }
+                        } else {
+                            None
+                        };
+
+                        if let Some(sess) = clone_session {
+                            let store = session.store.clone();
+                            let key = session_key.clone();
+                            let persist = async move {
+                                if let Err(err) = store.store_session(&sess).await {
+                                    if store.config.fallback_to_memory {
+                                        store.keep_unsaved(sess.clone(), &err);
+                                    } else {
+                                        return Err(err);
+                                    }
+                                }
+
+                                if store.config.security_mode == SecurityMode::PerSession {
+                                    store.store_key(&key, sess.expires.timestamp()).await?;
+                                }
+
+                                Ok(())
+                            }
+                            .instrument(tracing::debug_span!(
+                                "persist",
+                                payload_size = tracing::field::Empty
+                            ));
+
+                            // Deferred saves over the limit are stored now so they can not pile up.
+                            let config = &session.store.config;
+                            let permit = (config.deferred_save && config.memory)
+                                .then(|| {
+                                    session
+                                        .store
+                                        .deferred_saves
+                                        .clone()
+                                        .try_acquire_owned()
+                                        .ok()
+                                })
+                                .flatten();
+
+                            if !config.deferred_save {
+                                saved = persist.await;
+                            } else {
+                                // Deferred saves have no Response left to fail so their errors are only logged.
+                                let save =
+                                    session.store.queue_save(session.id.inner(), async move {
+                                        if let Err(err) = persist.await {
+                                            tracing::error!("Session data failed to save: {}", err);
+                                        }
+                                    });
+
+                                if let Some(permit) = permit {
+                                    tokio::spawn(async move {
+                                        save.await;
+                                        drop(permit);
+                                    });
+                                } else {
+                                    // Ordered too so it never lands before a deferred save still running.
+                                    save.await;
+                                }
+                            }
+                        }
+                    }
 
-            // We will Deleted the data in memory as it should be stored in the database instead.
-            // if user is using this without a database then it will only work as a per request data store.
-            if session.store.config.memory_lifespan.is_zero() && !session.is_parallel() {
-                #[cfg(feature = "key-store")]
-                if !session.store.is_persistent() && session.store.config.use_bloom_filters {
-                    let mut filter = session.store.filter.write().await;
-                    filter.remove(session.id.inner().as_bytes());
-                    filter.remove(session_key.id.inner().as_bytes());
-                }
+                    //lets tell the system we can unload this request now.
+                    //If there are still more left the bottom wont unload anything.
+                    session.remove_request();
 
-                session.store.inner.remove(&session.id.inner());
-                session.store.keys.remove(&session_key.id.inner());
-            }
+                    if ((opt_in && !storable) || destroy) && !session.is_parallel() {
+                        if destroy {
+                            session.store.metrics.destroyed();
+                            session.store.notify_destroyed(&session.id.inner());
+                        }
+
+                        if session.store.config.security_mode == SecurityMode::PerSession {
+                            #[cfg(feature = "key-store")]
+                            if session.store.config.use_bloom_filters {
+                                let mut filter = session.store.filter.write().await;
+                                filter.remove(session_key.id.inner().as_bytes());
+                            }
+
+                            let _ = session.store.keys.remove(&session_key.id.inner());
+
+                            if session.store.is_persistent() {
+                                let result = session
+                                    .store
+                                    .database_remove_session(session_key.id.inner())
+                                    .await;
+                                session.store.or_fallback(result);
+                            }
+                        }
+
+                        #[cfg(feature = "key-store")]
+                        if session.store.config.use_bloom_filters {
+                            let mut filter = session.store.filter.write().await;
+                            filter.remove(session.id.inner().as_bytes());
+                        }
+
+                        let _ = session.store.inner.remove(&session.id.inner());
 
-            set_headers(
-                &session,
-                &session_key,
-                response.headers_mut(),
-                destroy,
-                storable,
-            );
+                        if session.store.is_persistent() {
+                            let result = session
+                                .store
+                                .database_remove_session(session.id.inner())
+                                .await;
+                            session.store.or_fallback(result);
+                        }
+                    }
+
+                    // We will Deleted the data in memory as it should be stored in the database instead.
+                    // if user is using this without a database then it will only work as a per request data store.
+                    if session.store.config.unload_after_request() && !session.is_parallel() {
+                        #[cfg(feature = "key-store")]
+                        if !session.store.is_persistent() && session.store.config.use_bloom_filters
+                        {
+                            let mut filter = session.store.filter.write().await;
+                            filter.remove(session.id.inner().as_bytes());
+                            filter.remove(session_key.id.inner().as_bytes());
+                        }
 
-            Ok(response)
-        })
+                        session
+                            .store
+                            .inner
+                            .remove_if(&session.id.inner(), |_, sess| {
+                                !session.store.keeps_unsaved(sess)
+                            });
+                        session.store.keys.remove(&session_key.id.inner());
+                    }
+
+                    // Sessions created by a peeked or failed Request are not kept.
+                    if (peek || failed) && previous_accessed.is_none() && !session.is_parallel() {
+                        session.store.inner.remove(&session.id.inner());
+                    }
+
+                    saved
+                }
+                .instrument(tracing::Span::current());
+
+                match commit_timing {
+                    CommitTiming::AfterHandler => {
+                        if let Err(err) = commit.await {
+                            tracing::error!("Session data failed to save: {}", err);
+                            fail_response(&mut response, err.status_code());
+                        }
+
+                        Ok(response)
+                    }
+                    CommitTiming::AfterBody => {
+                        // The headers are already sent once the body ends so errors are only logged.
+                        let commit = async move {
+                            if let Err(err) = commit.await {
+                                tracing::error!("Session data failed to save: {}", err);
+                            }
+                        };
+
+                        Ok(response.map(|body| Body::new(CommitBody::new(body, Box::pin(commit)))))
+                    }
+                }
+            }
+            .instrument(span),
+        )
     }
 }
 
+/// Replaces the Response with an empty one carrying the status so the handler's output
+/// is never sent without its Session.
+fn fail_response(response: &mut Response<Body>, status: StatusCode) {
+    *response.status_mut() = status;
+    *response.body_mut() = Body::empty();
+    response.headers_mut().remove(header::CONTENT_LENGTH);
+    response.headers_mut().remove(header::CONTENT_TYPE);
+}
+
 impl<S, T> Debug for SessionService<S, T>
 where
     S: Debug,
@@ -359,7 +782,85 @@ where
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("SessionService")
             .field("session_store", &self.session_store)
+            .field("commit_timing", &self.commit_timing)
             .field("inner", &self.inner)
             .finish()
     }
 }
+
+/// Response body that persists the Session once it finished streaming.
+///
+/// The commit runs before the end of the body is passed on so the Session is saved by the
+/// time the client has the whole Response. If the body is dropped early, like when the
+/// client disconnects, the commit is spawned instead so the Session is still saved.
+struct CommitBody {
+    inner: Body,
+    commit: Option<BoxFuture<'static, ()>>,
+    /// What the inner body ended with, held while the commit runs.
+    ended: Option<Option<Result<Frame<Bytes>, Error>>>,
+}
+
+impl CommitBody {
+    fn new(inner: Body, commit: BoxFuture<'static, ()>) -> Self {
+        Self {
+            inner,
+            commit: Some(commit),
+            ended: None,
+        }
+    }
+}
+
+impl HttpBody for CommitBody {
+    type Data = Bytes;
+    type Error = Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = &mut *self;
+
+        if this.commit.is_some() && this.ended.is_none() {
+            match ready!(Pin::new(&mut this.inner).poll_frame(cx)) {
+                Some(Ok(frame)) => return Poll::Ready(Some(Ok(frame))),
+                // Bodies that fail part way are committed like ones the client dropped.
+                ended => this.ended = Some(ended),
+            }
+        }
+
+        if let Some(commit) = &mut this.commit {
+            ready!(commit.as_mut().poll(cx));
+            this.commit = None;
+        }
+
+        match this.ended.take() {
+            Some(ended) => Poll::Ready(ended),
+            None => Pin::new(&mut this.inner).poll_frame(cx),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.commit.is_none() && self.ended.is_none() && self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for CommitBody {
+    fn drop(&mut self) {
+        let Some(commit) = self.commit.take() else {
+            return;
+        };
+
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(commit);
+            }
+            Err(_) => tracing::warn!(
+                "Session could not be saved as the Response body was dropped outside of a runtime"
+            ),
+        }
+    }
+}
@@ -1,8 +1,10 @@
-#[cfg(feature = "advanced")]
-use crate::SessionError;
-use crate::{DatabasePool, SessionData, SessionID, SessionStore};
+use crate::{
+    DatabasePool, MemoryStore, RateLimitDecision, SessionData, SessionError, SessionID,
+    SessionNamespace, SessionStore, SessionWatcher,
+};
 use async_trait::async_trait;
 use axum_core::extract::FromRequestParts;
+use chrono::{DateTime, Duration, Utc};
 
 #[cfg(feature = "key-store")]
 use fastbloom_rs::Membership;
@@ -12,8 +14,12 @@ use std::{
     convert::From,
     fmt::Debug,
     marker::{Send, Sync},
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
-use uuid::Uuid;
 
 /// A Session Store.
 ///
@@ -29,6 +35,14 @@ where
     pub(crate) store: SessionStore<T>,
     /// The Sessions current ID for lookng up its store.
     pub(crate) id: SessionID,
+    /// If the Session was created during this request.
+    pub(crate) is_new: bool,
+    /// If the Session was loaded from the database during this request.
+    pub(crate) loaded_from_database: bool,
+    /// If the Response should skip setting headers and persisting. Shared by the request's clones.
+    pub(crate) forget: Arc<AtomicBool>,
+    /// If the request should not count as Session activity. Shared by the request's clones.
+    pub(crate) peek: Arc<AtomicBool>,
 }
 
 /// Adds FromRequestParts<B> for Session
@@ -55,10 +69,10 @@ where
     S: DatabasePool + Clone + Debug + Sync + Send + 'static,
 {
     #[allow(clippy::needless_pass_by_ref_mut)]
-    pub(crate) async fn new(store: SessionStore<S>, value: Option<Uuid>) -> (Self, bool) {
+    pub(crate) async fn new(store: SessionStore<S>, value: Option<String>) -> (Self, bool) {
         let (id, is_new) = match value {
             Some(v) => (SessionID(v), false),
-            None => (Self::generate_uuid(&store).await, true),
+            None => (Self::generate_id(&store).await, true),
         };
 
         #[cfg(feature = "key-store")]
@@ -74,27 +88,35 @@ where
             }
         }
 
-        (Self { id, store }, is_new)
+        (
+            Self {
+                id,
+                store,
+                is_new,
+                loaded_from_database: false,
+                forget: Arc::new(AtomicBool::new(false)),
+                peek: Arc::new(AtomicBool::new(false)),
+            },
+            is_new,
+        )
     }
 
     #[cfg(feature = "key-store")]
-    pub(crate) async fn generate_uuid(store: &SessionStore<S>) -> SessionID {
+    pub(crate) async fn generate_id(store: &SessionStore<S>) -> SessionID {
         loop {
-            let token = Uuid::new_v4();
+            let token = (store.config.id_generator)();
 
             if (!store.config.use_bloom_filters || store.auto_handles_expiry())
-                && !store.inner.contains_key(&token.to_string())
-                && !store.keys.contains_key(&token.to_string())
+                && !store.inner.contains_key(&token)
+                && !store.keys.contains_key(&token)
             {
                 //This fixes an already used but in database issue.
                 if let Some(client) = &store.client {
-                    // Unwrap should be safe to use as we would want it to crash if there was a major database error.
+                    // We want it to crash if there was a major database error unless falling back to memory.
                     // This would mean the database no longer is online or the table missing etc.
-                    if !client
-                        .exists(&token.to_string(), &store.config.table_name)
-                        .await
-                        .unwrap()
-                    {
+                    let exists = client.exists(&token, &store.config.table_name).await;
+
+                    if !store.or_fallback(exists) {
                         return SessionID(token);
                     }
                 } else {
@@ -103,7 +125,7 @@ where
             } else {
                 let filter = store.filter.read().await;
 
-                if !filter.contains(token.to_string().as_bytes()) {
+                if !filter.contains(token.as_bytes()) {
                     return SessionID(token);
                 }
             }
@@ -111,22 +133,18 @@ where
     }
 
     #[cfg(not(feature = "key-store"))]
-    pub(crate) async fn generate_uuid(store: &SessionStore<S>) -> SessionID {
+    pub(crate) async fn generate_id(store: &SessionStore<S>) -> SessionID {
         loop {
-            let token = Uuid::new_v4();
+            let token = (store.config.id_generator)();
 
-            if !store.inner.contains_key(&token.to_string())
-                && !store.keys.contains_key(&token.to_string())
-            {
+            if !store.inner.contains_key(&token) && !store.keys.contains_key(&token) {
                 //This fixes an already used but in database issue.
                 if let Some(client) = &store.client {
-                    // Unwrap should be safe to use as we would want it to crash if there was a major database error.
+                    // We want it to crash if there was a major database error unless falling back to memory.
                     // This would mean the database no longer is online or the table missing etc.
-                    if !client
-                        .exists(&token.to_string(), &store.config.table_name)
-                        .await
-                        .unwrap()
-                    {
+                    let exists = client.exists(&token, &store.config.table_name).await;
+
+                    if !store.or_fallback(exists) {
                         return SessionID(token);
                     }
                 } else {
@@ -135,6 +153,7 @@ where
             }
         }
     }
+
     /// Sets the Session to create the SessionData based on the current Session ID.
     /// You can only use this if SessionMode::Manual is set or it will Panic.
     /// This will also set the store to true similair to session.set_store(true);
@@ -152,8 +171,9 @@ where
                 as the Session data is created already."
             );
         }
-        let sess = SessionData::new(self.id.0, true, &self.store.config);
+        let sess = SessionData::new(self.id.inner(), true, &self.store.config);
         self.store.inner.insert(self.id.inner(), sess);
+        self.store.metrics.created();
     }
 
     /// Checks if the SessionData was created or not.
@@ -201,6 +221,47 @@ where
         self.store.update(self.id.inner());
     }
 
+    /// Marks the Session as active now without changing its data and sets it to update
+    /// the database so the refreshed activity is kept.
+    ///
+    /// This is the explicit counterpart to the touch every request does when it loads the
+    /// Session, for long lived connections like WebSockets that keep a user active without
+    /// sending new requests. It keeps the Session within its idle timeout and slides its
+    /// expiry forward once saved. Outside of a request follow it with `save`.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// // Within a WebSocket keepalive.
+    /// session.touch().await;
+    /// session.save().await?;
+    /// ```
+    ///
+    #[inline]
+    pub async fn touch(&self) {
+        self.store.touch(self.id.inner());
+    }
+
+    /// Saves the Session to the database right away instead of waiting on the Response.
+    /// Mostly useful for Sessions gotten from `SessionStore::load_or_create` outside of a request.
+    /// If the Session was destroyed it is removed from memory and the database instead.
+    ///
+    /// Does nothing to the database if the SessionStore is not persistent.
+    ///
+    /// # Errors
+    /// - ['SessionError::NoSessionError'] is returned if the Session was unloaded.
+    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
+    /// - ['SessionError::Timeout'] is returned if the database call took longer than the db_timeout.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.save().await.unwrap();
+    /// ```
+    ///
+    #[inline]
+    pub async fn save(&self) -> Result<(), SessionError> {
+        self.store.save(self.id.inner()).await
+    }
+
     /// Sets the Session to renew its Session Key ID and Encryption Key.
     /// This Deletes Session key data from the database
     /// associated with the old Key UUID. This helps to enhance
@@ -223,7 +284,10 @@ where
     }
 
     /// Sets the Current Session to be Destroyed.
-    /// This will Deleted the Session and Cookies upon Response Phase.
+    /// The Session's data is cleared right away and the Session and Cookies are Deleted upon Response Phase.
+    ///
+    /// For the rest of the request `get` returns None and `set` is ignored with a warning.
+    /// `try_set` returns `SessionError::SessionDestroyed`.
     ///
     /// # Examples
     /// ```rust ignore
@@ -235,376 +299,1534 @@ where
         self.store.destroy(self.id.inner());
     }
 
-    /// Sets the Current Session to a long term expiration. Useful for Remember Me setups.
-    /// This will also update the database on Response Phase.
+    /// Logs out the Session by destroying it and deleting it from the database right away.
+    /// This is the recommended way to log out over `destroy` as the Session is gone even if
+    /// the Response Phase does not complete, like with streaming responses.
+    ///
+    /// The Session stays marked as destroyed in memory so the removal Cookies are still sent
+    /// on Response Phase. Calling this more than once is safe.
+    ///
+    /// # Errors
+    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
     ///
     /// # Examples
     /// ```rust ignore
-    /// session.set_longterm(true);
+    /// session.logout().await?;
     /// ```
     ///
     #[inline]
-    pub fn set_longterm(&self, longterm: bool) {
-        self.store.set_longterm(self.id.inner(), longterm);
+    pub async fn logout(&self) -> Result<(), SessionError> {
+        self.destroy();
+        self.store.database_remove_session(self.id.inner()).await
     }
 
-    /// Allows the Current Session to store.
-    /// This will also update the database on Response Phase.
+    /// Checks if the Session was created during this request.
     ///
-    /// This is only used when `SessionMode` is Manual or OptIn.
-    /// This will allow the Session to be stored if true.
-    /// This will delete and not allow a session to be stored if false.
+    /// This is true when no valid Session ID was sent by the client, or the Session it named
+    /// was not found in memory or the database, like after it expired. Anonymous opt-in
+    /// Sessions that are never stored are new on every request.
+    /// It only reflects creation during this request and not the overall age of the Session.
     ///
     /// # Examples
     /// ```rust ignore
-    /// session.set_store(true);
+    /// if session.is_new() {
+    ///     println!("Session was created this request");
+    /// }
     /// ```
     ///
     #[inline]
-    pub fn set_store(&self, can_store: bool) {
-        self.store.set_store(self.id.inner(), can_store);
+    pub fn is_new(&self) -> bool {
+        self.is_new
     }
 
-    /// Gets data from the Session's HashMap
-    ///
-    /// Provides an Option<T> that returns the requested data from the Sessions store.
-    /// Returns None if Key does not exist or if serdes_json failed to deserialize.
+    /// Checks if the Session was loaded from the database during this request, so it was
+    /// not already in memory. False for new Sessions and stores without a database.
     ///
     /// # Examples
     /// ```rust ignore
-    /// let id = session.get("user-id").unwrap_or(0);
+    /// if session.was_loaded_from_database() {
+    ///     println!("Returning visitor whose Session was not in memory");
+    /// }
     /// ```
     ///
-    ///Used to get data stored within SessionDatas hashmap from a key value.
-    ///
     #[inline]
-    pub fn get<T: serde::de::DeserializeOwned>(&self, key: &str) -> Option<T> {
-        self.store.get(self.id.inner(), key)
+    pub fn was_loaded_from_database(&self) -> bool {
+        self.loaded_from_database
     }
 
-    /// Removes a Key from the Current Session's HashMap returning it.
+    /// Sets the Response to not set or remove any Session Cookies or Headers and to skip
+    /// saving the Session to the database. Useful for responses that must stay cacheable
+    /// or embeddable like OAuth callbacks or signed downloads.
     ///
-    /// Provides an Option<T> that returns the requested data from the Sessions store.
-    /// Returns None if Key does not exist or if serdes_json failed to deserialize.
+    /// Unlike `destroy` the Session is kept, so the next request with the same Cookie
+    /// still gets the same Session. Changes made during this request stay in memory and
+    /// are saved on the next request that is not forgotten. Pending renews are also
+    /// delayed till then. This only affects the current request.
     ///
     /// # Examples
     /// ```rust ignore
-    /// let id = session.get_remove("user-id").unwrap_or(0);
+    /// session.forget();
     /// ```
     ///
-    /// Used to get data stored within SessionDatas hashmap from a key value.
-    ///
     #[inline]
-    pub fn get_remove<T: serde::de::DeserializeOwned>(&self, key: &str) -> Option<T> {
-        self.store.get_remove(self.id.inner(), key)
+    pub fn forget(&self) {
+        self.forget.store(true, Ordering::Relaxed);
     }
 
-    /// Sets data to the Current Session's HashMap.
-    /// This will also update the database on Response Phase.
+    /// Checks if the Current Response was set to skip the Session's Cookies or Headers using `forget`.
     ///
     /// # Examples
     /// ```rust ignore
-    /// session.set("user-id", 1);
+    /// if session.is_forgotten() {
+    ///     println!("No Session Cookies will be sent");
+    /// }
     /// ```
     ///
     #[inline]
-    pub fn set(&self, key: &str, value: impl Serialize) {
-        self.store.set(self.id.inner(), key, value);
+    pub fn is_forgotten(&self) -> bool {
+        self.forget.load(Ordering::Relaxed)
     }
 
-    /// Removes a Key from the Current Session's HashMap.
-    /// Does not process the String into a Type, Just removes it.
-    /// This will also update the database on Response Phase.
+    /// Sets the Current Request to not count as Session activity, like for health checks,
+    /// prefetches or background polling which should not keep a Session alive forever.
+    /// `PeekSession` does this when extracted.
+    ///
+    /// The Session's last_accessed, expires and in memory timeout are put back to what they
+    /// were before the Request and like `forget` no Cookies or Headers are sent and the
+    /// Session is not saved. Changes made during the Request stay in memory and are saved
+    /// by the next Request that is not peeked. Sessions created by a peeked Request are not kept.
     ///
     /// # Examples
     /// ```rust ignore
-    /// let _ = session.remove("user-id");
+    /// session.peek();
     /// ```
     ///
     #[inline]
-    pub fn remove(&self, key: &str) {
-        self.store.remove(self.id.inner(), key);
+    pub fn peek(&self) {
+        self.peek.store(true, Ordering::Relaxed);
     }
 
-    /// Clears all data from the Current Session's HashMap instantly.
-    /// This will also update the database on Response Phase.
+    /// Checks if the Current Request was set to not count as Session activity using `peek`.
     ///
     /// # Examples
     /// ```rust ignore
-    /// session.clear();
+    /// if session.is_peeked() {
+    ///     println!("The Session will not be extended");
+    /// }
     /// ```
     ///
     #[inline]
-    pub fn clear(&self) {
-        self.store.clear_session_data(self.id.inner());
+    pub fn is_peeked(&self) -> bool {
+        self.peek.load(Ordering::Relaxed)
     }
 
-    /// Returns a i64 count of how many Sessions exist.
+    /// Gets a `SessionWatcher` which resolves once the Session is destroyed or expires.
     ///
-    /// If the Session is persistant it will return all sessions within the database.
-    /// If the Session is not persistant it will return a count within SessionStore.
+    /// The Session and watcher can both be moved into a websocket's `on_upgrade` closure
+    /// as they do not borrow the request. Use the watcher to close the socket on logout.
     ///
     /// # Examples
     /// ```rust ignore
-    /// let count = session.count().await;
+    /// let mut watcher = session.watch();
+    ///
+    /// ws.on_upgrade(move |socket| async move {
+    ///     tokio::select! {
+    ///         _ = handle_socket(socket, session) => {}
+    ///         _ = watcher.destroyed() => {}
+    ///     }
+    /// })
     /// ```
     ///
     #[inline]
-    pub async fn count(&self) -> i64 {
-        self.store.count_sessions().await
+    pub fn watch(&self) -> SessionWatcher<S> {
+        self.store.watch(self.id.inner())
     }
 
-    /// Returns the SessionID for this Session.
-    ///
-    /// The SessionID contains the Uuid generated at the beginning of this Session.
+    /// Returns a Future which resolves once the Session is destroyed or expires.
+    /// This is a shortcut for `watch` followed by `SessionWatcher::destroyed`.
     ///
     /// # Examples
     /// ```rust ignore
-    /// let session_id = session.get_session_id();
+    /// let destroyed = session.subscribe_destroyed();
+    ///
+    /// tokio::spawn(async move {
+    ///     destroyed.await;
+    ///     println!("Session ended");
+    /// });
     /// ```
     ///
     #[inline]
-    pub fn get_session_id(&self) -> SessionID {
-        self.id
+    pub fn subscribe_destroyed(&self) -> impl std::future::Future<Output = ()> + Send + 'static {
+        let mut watcher = self.watch();
+        async move { watcher.destroyed().await }
     }
 
-    /// Returns the store for this Session.
-    ///
-    /// The store contains everything that all sessions need.
+    /// Gets the schema version of the Current Session's stored values.
+    /// This is given to the config's data migrator. Default is 0.
     ///
     /// # Examples
     /// ```rust ignore
-    /// let store = session.get_store();
+    /// let version = session.schema_version();
     /// ```
     ///
     #[inline]
-    pub fn get_store(&self) -> &SessionStore<S> {
-        &self.store
+    pub fn schema_version(&self) -> u32 {
+        self.store.schema_version(self.id.inner())
     }
 
-    /// Returns a mutable store for this Session.
-    ///
-    /// The store contains everything that all sessions need.
+    /// Sets the schema version of the Current Session's stored values, like after
+    /// storing values in a new shape. This will also update the database on Response Phase.
     ///
     /// # Examples
     /// ```rust ignore
-    /// let store = session.get_store_mut();
+    /// session.set_schema_version(1);
     /// ```
     ///
     #[inline]
-    pub fn get_mut_store(&mut self) -> &mut SessionStore<S> {
-        &mut self.store
+    pub fn set_schema_version(&self, version: u32) {
+        self.store.set_schema_version(self.id.inner(), version);
     }
 
-    /// Removes a Request from the request counter
-    /// used to deturmine if parallel requests exist.
-    /// prevents data deletion until requests == 0.
+    /// Subscribes to the changes made to the Current Session on this app node,
+    /// like from a background job using `SessionStore::load_or_create`.
+    /// See `SessionStore::subscribe` for how lagging receivers behave.
     ///
     /// # Examples
     /// ```rust ignore
-    /// session.remove_request();
+    /// let mut events = session.subscribe();
+    ///
+    /// while let Ok(event) = events.recv().await {
+    ///     if let SessionEvent::Updated(key) = event {
+    ///         println!("{} changed", key);
+    ///     }
+    /// }
     /// ```
     ///
     #[inline]
-    pub(crate) fn remove_request(&self) {
-        self.store.remove_session_request(self.id.inner());
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<crate::SessionEvent> {
+        self.store.subscribe(&self.id.inner())
     }
 
-    /// Removes a Request from the request counter
-    /// used to deturmine if parallel requests exist.
-    /// prevents data deletion until requests == 0.
+    /// Checks if the Current Session is set to be Destroyed upon Response Phase.
     ///
     /// # Examples
     /// ```rust ignore
-    /// session.set_request();
+    /// if session.is_destroyed() {
+    ///     println!("Session will be destroyed");
+    /// }
     /// ```
     ///
     #[inline]
-    pub(crate) fn set_request(&self) {
-        self.store.set_session_request(self.id.inner());
+    pub fn is_destroyed(&self) -> bool {
+        self.store.is_destroyed(self.id.inner())
     }
 
-    /// checks if a session has more than one request.
+    /// Sets the Current Session to a long term expiration. Useful for Remember Me setups.
+    /// This will also update the database on Response Phase.
     ///
     /// # Examples
     /// ```rust ignore
-    /// session.is_parallel();
+    /// session.set_longterm(true);
     /// ```
     ///
     #[inline]
-    pub(crate) fn is_parallel(&self) -> bool {
-        self.store.is_session_parallel(self.id.inner())
+    pub fn set_longterm(&self, longterm: bool) {
+        self.store.set_longterm(self.id.inner(), longterm);
     }
 
-    /// checks if a session exists and if it is outdated.
+    /// Sets an explicit expiry for the Session, like for "keep me signed in for 30 days" choices.
+    /// The expiry is persisted and used over the configs lifespan and max_lifespan for both
+    /// the database and the Cookie. Sessions are no longer extended on each request once set.
+    ///
+    /// If the expiry is not in the future the Session is destroyed.
+    /// This will also update the database on Response Phase.
     ///
     /// # Examples
     /// ```rust ignore
-    /// session.verify();
+    /// session.set_expiry(Utc::now() + Duration::days(30));
     /// ```
     ///
-    #[cfg(feature = "advanced")]
     #[inline]
-    pub fn verify(&self) -> Result<(), SessionError> {
-        self.store.verify(self.id.inner())
+    pub fn set_expiry(&self, expiry: DateTime<Utc>) {
+        self.store.set_expiry(self.id.inner(), expiry);
     }
 
-    /// Updates the sessions stored database expire time.
-    /// Use this before forcing a update to the database store.
-    /// will update the database expires based on
-    /// if the session is longterm then configs max_lifespan.
-    /// if not then configs lifespan.
-    ///
-    /// THIS WILL NOT UPDATE THE DATABASE SIDE.
+    /// Extends the Sessions explicit expiry by the duration, or its current expiry if none was set.
+    /// A negative duration shrinks the expiry and destroys the Session if it is no longer in the future.
+    /// See `set_expiry`.
     ///
     /// # Examples
     /// ```rust ignore
-    /// session.update_database_expires();
+    /// session.extend_expiry(Duration::days(7));
     /// ```
     ///
-    #[cfg(feature = "advanced")]
     #[inline]
-    pub fn update_database_expires(&self) -> Result<(), SessionError> {
-        self.store.update_database_expires(self.id.inner())
+    pub fn extend_expiry(&self, duration: Duration) {
+        self.store.extend_expiry(self.id.inner(), duration);
     }
 
-    /// Updates the Sessions In memory auto remove timer.
-    /// Will prevent it from being removed for the configs set memory_lifespan.
+    /// Allows the Current Session to store.
+    /// This will also update the database on Response Phase.
+    ///
+    /// This is only used when `SessionMode` is Manual or OptIn.
+    /// This will allow the Session to be stored if true.
+    /// This will delete and not allow a session to be stored if false.
     ///
     /// # Examples
     /// ```rust ignore
-    /// session.update_memory_expires();
+    /// session.set_store(true);
     /// ```
     ///
-    #[cfg(feature = "advanced")]
     #[inline]
-    pub fn update_memory_expires(&self) -> Result<(), SessionError> {
-        self.store.update_memory_expires(self.id.inner())
+    pub fn set_store(&self, can_store: bool) {
+        self.store.set_store(self.id.inner(), can_store);
     }
 
-    /// forces a update to the databases stored data for the session.
-    /// Make sure to update the databases expire time before running this or
-    /// the data could be unloaded by a request checking for outdated sessions.
+    /// Overrides the configs `SessionMode` for the Current Session's save decision.
+    /// This is kept with the Session's data so it lasts across Requests.
+    ///
+    /// True makes the Session opt-in, so it is only stored and sent to the client once
+    /// `set_store(true)` is called. False always stores it like `SessionMode::Persistent`.
+    /// Useful to keep most users stored while letting visitors opt out of persistence,
+    /// which removes their Session at the end of the Request unless `set_store(true)` was called.
     ///
     /// # Examples
     /// ```rust ignore
-    /// session.force_database_update().await;
+    /// // The visitor declined persistent cookies.
+    /// session.set_opt_in_override(true);
     /// ```
     ///
-    #[cfg(feature = "advanced")]
     #[inline]
-    pub async fn force_database_update(&self) -> Result<(), SessionError> {
-        self.store.force_database_update(self.id.inner()).await
+    pub fn set_opt_in_override(&self, opt_in: bool) {
+        self.store.set_opt_in_override(self.id.inner(), opt_in);
     }
 
-    /// Removes the session from the memory store if it is not parallel.
-    /// If it is parallel then each parallel session will need to call this once.
-    /// when all parallel sessions are dead this gets unloaded.
-    ///
-    /// THIS DOES NOT CLEAR THE KEY STORE.
+    /// Returns when the Session was first created.
     ///
     /// # Examples
     /// ```rust ignore
-    /// session.memory_remove_session();
+    /// let created_at = session.created_at();
     /// ```
     ///
-    #[cfg(feature = "advanced")]
     #[inline]
-    pub fn memory_remove_session(&self) -> Result<(), SessionError> {
-        self.store.memory_remove_session(self.id.inner())
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.store.created_at(self.id.inner())
     }
 
-    /// Removes the session from the Database store.
-    ///
-    /// THIS DOES NOT REMOVE THE KEY STORE.
+    /// Returns when the Session was last accessed. This is updated at the start of every request.
     ///
     /// # Examples
     /// ```rust ignore
-    /// session.database_remove_session().await;
+    /// let last_accessed = session.last_accessed();
     /// ```
     ///
-    #[cfg(feature = "advanced")]
     #[inline]
-    pub async fn database_remove_session(&self) -> Result<(), SessionError> {
-        self.store.database_remove_session(self.id.inner()).await
+    pub fn last_accessed(&self) -> DateTime<Utc> {
+        self.store.last_accessed(self.id.inner())
     }
-}
-
-#[derive(Debug, Clone)]
-pub struct ReadOnlySession<T>
-where
-    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
-{
-    pub(crate) store: SessionStore<T>,
-    pub(crate) id: SessionID,
-}
 
-impl<T> From<Session<T>> for ReadOnlySession<T>
-where
-    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
-{
-    fn from(session: Session<T>) -> Self {
-        ReadOnlySession {
-            store: session.store,
-            id: session.id,
-        }
+    /// Returns when the Session will expire within the database unless it is accessed again.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let expires = session.expires();
+    /// ```
+    ///
+    #[inline]
+    pub fn expires(&self) -> DateTime<Utc> {
+        self.store.expires(self.id.inner())
     }
-}
-
-/// Adds FromRequestParts<B> for Session
-///
-/// Returns the Session from Axums request extensions state.
-#[async_trait]
-impl<T, S> FromRequestParts<S> for ReadOnlySession<T>
-where
-    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
-    S: Send + Sync,
-{
-    type Rejection = (http::StatusCode, &'static str);
-
-    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        let session = parts.extensions.get::<Session<T>>().cloned().ok_or((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Can't extract Axum `Session`. Is `SessionLayer` enabled?",
-        ))?;
 
-        Ok(session.into())
+    /// Returns how long until the Session expires. Returns a zero Duration if already expired.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// if session.remaining() < Duration::minutes(5) {
+    ///     println!("Your session expires soon");
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn remaining(&self) -> Duration {
+        (self.expires() - self.store.config.now()).max(Duration::zero())
     }
-}
 
-impl<S> ReadOnlySession<S>
-where
-    S: DatabasePool + Clone + Debug + Sync + Send + 'static,
-{
-    /// Gets data from the Session's HashMap
+    /// Returns all of the Session's values as a Json Map without changing the Session.
+    /// Pass it to `Session::merge` to carry values over to another Session, like a guest's
+    /// cart into a new account. Every key set by the app is included, so filter out the
+    /// ones that should not be carried over. The CSRF token is kept outside of the values
+    /// and is never included.
     ///
-    /// Provides an Option<T> that returns the requested data from the Sessions store.
-    /// Returns None if Key does not exist or if serdes_json failed to deserialize.
+    /// Values that can not be read back as Json, like ones stored using bincode, are
+    /// returned as the stored String.
     ///
     /// # Examples
     /// ```rust ignore
-    /// let id = session.get("user-id").unwrap_or(0);
+    /// let cart = guest_session.export();
     /// ```
     ///
-    ///Used to get data stored within SessionDatas hashmap from a key value.
-    ///
     #[inline]
-    pub fn get<T: serde::de::DeserializeOwned>(&self, key: &str) -> Option<T> {
-        self.store.get(self.id.inner(), key)
+    pub fn export(&self) -> serde_json::Map<String, serde_json::Value> {
+        self.store.export_data(self.id.inner())
     }
 
-    /// Returns a i64 count of how many Sessions exist.
-    ///
-    /// If the Session is persistant it will return all sessions within the database.
-    /// If the Session is not persistant it will return a count within SessionStore.
+    /// Returns the approximate size in bytes of the Session's keys and serialized values.
+    /// This is the size checked against the configs max_session_size.
     ///
     /// # Examples
     /// ```rust ignore
-    /// let count = session.count().await;
+    /// let bytes = session.data_len();
     /// ```
     ///
     #[inline]
-    pub async fn count(&self) -> i64 {
-        self.store.count_sessions().await
+    pub fn data_len(&self) -> usize {
+        self.store.data_len(self.id.inner())
     }
+
+    /// Returns the Session's anti-CSRF token generating and storing one if none exists.
+    ///
+    /// This follows the synchronizer token pattern. The token must still be rendered into
+    /// your forms or sent as a header and then checked with `verify_csrf` on submission.
+    /// The token is rotated when the Session is renewed or destroyed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let token = session.csrf_token();
+    /// ```
+    ///
+    #[inline]
+    pub fn csrf_token(&self) -> String {
+        self.store.csrf_token(self.id.inner())
+    }
+
+    /// Checks the presented token against the Session's anti-CSRF token in constant time.
+    ///
+    /// Returns false if no token was generated for the Session yet.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// if !session.verify_csrf(&form.csrf_token) {
+    ///     return StatusCode::FORBIDDEN;
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn verify_csrf(&self, presented: &str) -> bool {
+        self.store.verify_csrf(self.id.inner(), presented)
+    }
+
+    /// Checks the presented secret against the String stored under the key in constant time,
+    /// like for session bound API tokens. Use this over comparing with `==` which can leak
+    /// the secret through timing.
+    ///
+    /// Returns false if the key does not exist or is not a String.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// if !session.verify_secret("api-token", &presented) {
+    ///     return StatusCode::UNAUTHORIZED;
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn verify_secret(&self, key: &str, presented: &str) -> bool {
+        self.store.verify_secret(self.id.inner(), key, presented)
+    }
+
+    /// Checks the presented secret against the Argon2 hash stored under the key
+    /// using `Session::set_hashed_secret`. Hashing runs on a blocking thread.
+    ///
+    /// Returns false if the key does not exist, does not hold a valid hash or the
+    /// hashing thread failed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// if !session.verify_hashed_secret("api-token", &presented).await {
+    ///     return StatusCode::UNAUTHORIZED;
+    /// }
+    /// ```
+    ///
+    #[cfg(feature = "argon2")]
+    pub async fn verify_hashed_secret(&self, key: &str, presented: &str) -> bool {
+        verify_hashed(self.get::<String>(key), presented).await
+    }
+
+    /// Stores an Argon2 hash of the secret under the key so the raw secret is never stored.
+    /// Use `verify_hashed_secret` to check a presented secret. Hashing runs on a blocking thread.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Errors
+    /// - ['SessionError::PasswordHash'] is returned if the secret failed to hash.
+    /// - ['SessionError::HashTask'] is returned if the blocking hashing thread failed.
+    /// - ['SessionError::SizeLimitExceeded'] is returned if the Session would grow larger than max_session_size.
+    /// - ['SessionError::SessionDestroyed'] is returned if the Session was destroyed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set_hashed_secret("api-token", &token).await?;
+    /// ```
+    ///
+    #[cfg(feature = "argon2")]
+    pub async fn set_hashed_secret(&self, key: &str, secret: &str) -> Result<(), SessionError> {
+        let secret = secret.to_owned();
+        let hash = tokio::task::spawn_blocking(move || hash_secret(&secret)).await??;
+
+        self.try_set(key, hash)
+    }
+
+    /// Gets data from the Session's HashMap
+    ///
+    /// Provides an Option<T> that returns the requested data from the Sessions store.
+    /// Returns None if Key does not exist or if serdes_json failed to deserialize.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let id = session.get("user-id").unwrap_or(0);
+    /// ```
+    ///
+    ///Used to get data stored within SessionDatas hashmap from a key value.
+    ///
+    #[inline]
+    pub fn get<T: serde::de::DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.store.get(self.id.inner(), key)
+    }
+
+    /// Checks if the Key exists within the Session without deserializing its value,
+    /// like checking if a user is logged in without reading their whole profile.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let logged_in = session.contains_key("user");
+    /// ```
+    ///
+    #[inline]
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.store.contains_key(self.id.inner(), key)
+    }
+
+    /// Returns all of the Session's Keys without deserializing their values.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let keys = session.keys();
+    /// ```
+    ///
+    #[inline]
+    pub fn keys(&self) -> Vec<String> {
+        self.store.keys(self.id.inner())
+    }
+
+    /// Returns the Keys starting with the prefix, like every `cart:item:` Key, so callers
+    /// do not need to keep their own index of Keys. The Keys are returned with the prefix.
+    ///
+    /// The prefix is matched as a plain string like `remove_prefix`.
+    /// This is a linear scan of the Session's Keys.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let items = session.keys_with_prefix("cart:item:");
+    /// ```
+    ///
+    #[inline]
+    pub fn keys_with_prefix(&self, prefix: &str) -> Vec<String> {
+        self.store.keys_with_prefix(self.id.inner(), prefix)
+    }
+
+    /// Returns the length in bytes of the Key's serialized value without deserializing it.
+    /// Returns None if the Key does not exist.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let bytes = session.value_len("profile").unwrap_or(0);
+    /// ```
+    ///
+    #[inline]
+    pub fn value_len(&self, key: &str) -> Option<usize> {
+        self.store.value_len(self.id.inner(), key)
+    }
+
+    /// Removes a Key from the Current Session's HashMap returning it.
+    ///
+    /// Provides an Option<T> that returns the requested data from the Sessions store.
+    /// Returns None if Key does not exist or if serdes_json failed to deserialize.
+    /// A value that fails to deserialize is kept and the database is only updated if the
+    /// Key was removed. Use `take` to get the deserialize error.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let id = session.get_remove("user-id").unwrap_or(0);
+    /// ```
+    ///
+    /// Used to get data stored within SessionDatas hashmap from a key value.
+    ///
+    #[inline]
+    pub fn get_remove<T: serde::de::DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.store.get_remove(self.id.inner(), key)
+    }
+
+    /// Removes a Key from the Current Session's HashMap returning it.
+    /// This will also update the database on Response Phase.
+    ///
+    /// Returns Ok(None) if the Key does not exist. Unlike `get_remove` a value that fails to
+    /// deserialize is an error, which suits consume once values like password reset nonces.
+    /// The value is removed either way.
+    ///
+    /// # Errors
+    /// - ['SessionError::SerdeJson'] or the matching serializer error is returned if the value failed to deserialize.
+    /// - ['SessionError::SessionDestroyed'] is returned if the Session was destroyed.
+    /// - ['SessionError::NoSessionError'] is returned if the Session was unloaded.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let nonce: Option<String> = session.take("reset-nonce")?;
+    /// ```
+    ///
+    #[inline]
+    pub fn take<T: serde::de::DeserializeOwned>(
+        &self,
+        key: &str,
+    ) -> Result<Option<T>, SessionError> {
+        self.store.take(self.id.inner(), key)
+    }
+
+    /// Sets data to the Current Session's HashMap.
+    /// This will also update the database on Response Phase.
+    ///
+    /// If the value fails to Serialize or can not be set the error is logged with the Key
+    /// and the Session is left unchanged. Use `try_set` to handle the error instead.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set("user-id", 1);
+    /// ```
+    ///
+    #[inline]
+    pub fn set(&self, key: &str, value: impl Serialize) {
+        self.store.set(self.id.inner(), key, value);
+    }
+
+    /// Sets data to the Current Session's HashMap returning an error if it could not be set.
+    /// The Session is left unchanged on error.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Errors
+    /// - ['SessionError::SizeLimitExceeded'] is returned if the Session would grow larger than
+    ///   `SessionConfig::with_max_session_size`.
+    /// - ['SessionError::SerdeJson'] is returned if the value failed to Serialize.
+    /// - ['SessionError::NoSessionError'] is returned if the Session was unloaded.
+    /// - ['SessionError::SessionDestroyed'] is returned if the Session was destroyed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.try_set("user-id", 1)?;
+    /// ```
+    ///
+    #[inline]
+    pub fn try_set(&self, key: &str, value: impl Serialize) -> Result<(), SessionError> {
+        self.store.try_set(self.id.inner(), key, value)
+    }
+
+    /// Sets every entry of the Map to the Current Session's HashMap at once, overwriting
+    /// existing keys. Useful to restore state from another source or apply a template.
+    /// Nothing is set if any value fails or the Session would grow too large.
+    /// This will also update the database on Response Phase if anything was set.
+    ///
+    /// # Errors
+    /// - ['SessionError::SizeLimitExceeded'] is returned if the Session would grow larger than
+    ///   `SessionConfig::with_max_session_size`.
+    /// - ['SessionError::SerdeJson'] is returned if a value failed to Serialize.
+    /// - ['SessionError::NoSessionError'] is returned if the Session was unloaded.
+    /// - ['SessionError::SessionDestroyed'] is returned if the Session was destroyed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let serde_json::Value::Object(map) = serde_json::json!({ "theme": "dark", "lang": "en" }) else {
+    ///     unreachable!()
+    /// };
+    /// session.merge(map)?;
+    /// ```
+    ///
+    #[inline]
+    pub fn merge(
+        &self,
+        map: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<(), SessionError> {
+        self.store.merge(self.id.inner(), map)
+    }
+
+    /// Adds to the number stored under the Key returning the new value.
+    /// A missing or non numeric value counts as 0.
+    ///
+    /// The value is read and written under the Session's lock so concurrent
+    /// Requests never lose an increment, unlike a `get` followed by a `set`.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let views = session.increment("page-views", 1);
+    /// ```
+    ///
+    #[inline]
+    pub fn increment(&self, key: &str, by: i64) -> i64 {
+        self.store.increment(self.id.inner(), key, by, None).0
+    }
+
+    /// Subtracts from the number stored under the Key returning the new value.
+    /// A missing or non numeric value counts as 0. See `increment`.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let remaining = session.decrement("credits", 1);
+    /// ```
+    ///
+    #[inline]
+    pub fn decrement(&self, key: &str, by: i64) -> i64 {
+        self.store
+            .increment(self.id.inner(), key, by.saturating_neg(), None)
+            .0
+    }
+
+    /// Adds to the number stored under the Key clamping it to the max.
+    /// Returns the new value and if the max was reached, like for throttling login attempts.
+    /// A missing or non numeric value counts as 0. See `increment`.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let (attempts, locked) = session.increment_with_max("login-attempts", 1, 5);
+    ///
+    /// if locked {
+    ///     return Err(StatusCode::TOO_MANY_REQUESTS);
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn increment_with_max(&self, key: &str, by: i64, max: i64) -> (i64, bool) {
+        self.store.increment(self.id.inner(), key, by, Some(max))
+    }
+
+    /// Counts an attempt against a fixed window rate limit stored under the Key, like
+    /// allowing 5 password attempts per 15 minutes. Returns if the attempt is allowed along
+    /// with the attempts remaining and when the window resets. Denied attempts are not
+    /// counted. Attempts are also denied if the window could not be stored, which is logged.
+    ///
+    /// The window is read and written under the Session's lock so concurrent Requests can
+    /// not get past the limit. Time is taken from `SessionConfig::with_clock`.
+    /// The window is persisted with the Session on Response Phase. For a limit on every
+    /// Request to some paths see `middleware::rate_limit`.
+    ///
+    /// This is not a brute force defence on its own. The limit lives in the Session, so a
+    /// client can start over by dropping its Cookie or Header and getting a new Session.
+    /// Pair it with a limit kept per account or per IP address outside of the Session.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let decision = session.rate_limit("password-attempts", 5, Duration::minutes(15));
+    ///
+    /// if !decision.allowed {
+    ///     return Err(format!("Try again in {} seconds", decision.retry_after_secs()));
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn rate_limit(&self, key: &str, max: u32, window: Duration) -> RateLimitDecision {
+        self.store.rate_limit(self.id.inner(), key, max, window)
+    }
+
+    /// Deserializes the Key's value and lets f change it in place, returning what f returns.
+    /// The value is written back once, and only if f changed it, so several fields of a
+    /// stored struct can be changed without a `get`, clone and `set`.
+    /// Returns None if the Key does not exist or failed to deserialize, or if the changed
+    /// value could not be set, which is logged with the Key.
+    ///
+    /// The Session stays locked while f runs so concurrent Requests never lose a change.
+    /// f must not use the Session itself or it will deadlock.
+    /// This will also update the database on Response Phase if the value changed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// #[derive(Serialize, Deserialize)]
+    /// struct Address {
+    ///     city: String,
+    ///     zip: String,
+    /// }
+    ///
+    /// #[derive(Serialize, Deserialize)]
+    /// struct Profile {
+    ///     address: Address,
+    ///     visits: u32,
+    /// }
+    ///
+    /// let visits = session.with("profile", |profile: &mut Profile| {
+    ///     profile.address.city = "Berlin".to_owned();
+    ///     profile.address.zip = "10115".to_owned();
+    ///     profile.visits += 1;
+    ///     profile.visits
+    /// });
+    /// ```
+    ///
+    #[inline]
+    pub fn with<V, R, F>(&self, key: &str, f: F) -> Option<R>
+    where
+        V: Serialize + serde::de::DeserializeOwned,
+        F: FnOnce(&mut V) -> R,
+    {
+        self.store.with(self.id.inner(), key, f)
+    }
+
+    /// Appends the value to the list stored under the Key, like recently viewed items.
+    /// A missing or non list value counts as an empty list. Once the list is longer than
+    /// `SessionConfig::with_max_list_len` its oldest values are dropped.
+    ///
+    /// The list is read and written under the Session's lock so concurrent Requests never
+    /// lose a value, unlike a `get` followed by a `set`. Failures are logged with the Key.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.push("recently-viewed", product_id);
+    /// ```
+    ///
+    #[inline]
+    pub fn push<T>(&self, key: &str, value: T)
+    where
+        T: Serialize + serde::de::DeserializeOwned,
+    {
+        self.store.push(self.id.inner(), key, value);
+    }
+
+    /// Removes and returns the newest value of the list stored under the Key.
+    /// Returns None if the list is empty, missing or failed to deserialize.
+    /// This will also update the database on Response Phase if a value was removed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let last: Option<u64> = session.pop("recently-viewed");
+    /// ```
+    ///
+    #[inline]
+    pub fn pop<T>(&self, key: &str) -> Option<T>
+    where
+        T: Serialize + serde::de::DeserializeOwned,
+    {
+        self.store.pop(self.id.inner(), key)
+    }
+
+    /// Gets the list stored under the Key from oldest to newest.
+    /// Returns an empty list if it is missing or failed to deserialize.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let viewed: Vec<u64> = session.list("recently-viewed");
+    /// ```
+    ///
+    #[inline]
+    pub fn list<T: serde::de::DeserializeOwned>(&self, key: &str) -> Vec<T> {
+        self.store.list(self.id.inner(), key)
+    }
+
+    /// Gets the length of the list stored under the Key without deserializing its values.
+    /// Returns 0 if it is missing or not a list.
+    ///
+    /// The length is read without knowing the values' type which `SessionSerializer::Bincode`
+    /// can not do, so use `list(key).len()` with it instead.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let viewed = session.list_len("recently-viewed");
+    /// ```
+    ///
+    #[inline]
+    pub fn list_len(&self, key: &str) -> usize {
+        self.store.list_len(self.id.inner(), key)
+    }
+
+    /// Sets every Key and value to the Current Session's HashMap at once, overwriting
+    /// existing keys while only locking the Session once.
+    /// Nothing is set if any value fails or the Session would grow too large.
+    /// This will also update the database on Response Phase if anything was set.
+    ///
+    /// # Errors
+    /// - ['SessionError::SizeLimitExceeded'] is returned if the Session would grow larger than
+    ///   `SessionConfig::with_max_session_size`.
+    /// - ['SessionError::SerdeJson'] is returned if a value failed to Serialize.
+    /// - ['SessionError::NoSessionError'] is returned if the Session was unloaded.
+    /// - ['SessionError::SessionDestroyed'] is returned if the Session was destroyed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set_many([("wizard:step", 2), ("wizard:total", 5)])?;
+    /// ```
+    ///
+    #[inline]
+    pub fn set_many<K: Into<String>, V: Serialize>(
+        &self,
+        values: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<(), SessionError> {
+        self.store.set_many(self.id.inner(), values)
+    }
+
+    /// Removes each of the Keys from the Current Session's HashMap at once returning
+    /// how many existed.
+    /// This will also update the database on Response Phase if anything was removed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let removed = session.remove_many(&["wizard:step", "wizard:total"]);
+    /// ```
+    ///
+    #[inline]
+    pub fn remove_many(&self, keys: &[&str]) -> usize {
+        self.store.remove_many(self.id.inner(), keys)
+    }
+
+    /// Removes every Key starting with the prefix at once returning how many were removed.
+    ///
+    /// The prefix is matched as a plain string, so `"a"` matches `"a"` and `"ab"` but not
+    /// `"ba"`. Include the separator, like `"wizard:"`, to only match a namespace.
+    /// An empty prefix removes every Key. This is a linear scan of the Session's Keys.
+    /// This will also update the database on Response Phase if anything was removed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let removed = session.remove_prefix("wizard:");
+    /// ```
+    ///
+    #[inline]
+    pub fn remove_prefix(&self, prefix: &str) -> usize {
+        self.store.remove_prefix(self.id.inner(), prefix)
+    }
+
+    /// Removes a Key from the Current Session's HashMap.
+    /// Does not process the String into a Type, Just removes it.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let _ = session.remove("user-id");
+    /// ```
+    ///
+    #[inline]
+    pub fn remove(&self, key: &str) {
+        self.store.remove(self.id.inner(), key);
+    }
+
+    /// Clears all data from the Current Session's HashMap instantly.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.clear();
+    /// ```
+    ///
+    #[inline]
+    pub fn clear(&self) {
+        self.store.clear_session_data(self.id.inner());
+    }
+
+    /// Returns a view of the Session whose Keys are stored as `prefix:key`, so crates
+    /// sharing a Session can each use their own namespace without Keys colliding.
+    /// Avoid using `:` within the prefix as nested names could overlap.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let cart = session.namespace("cart");
+    /// cart.set("items", vec![1, 2]);
+    /// ```
+    ///
+    #[inline]
+    pub fn namespace(&self, prefix: &str) -> SessionNamespace<S> {
+        SessionNamespace::new(self.clone(), prefix)
+    }
+
+    /// Returns a i64 count of how many Sessions exist.
+    ///
+    /// If the Session is persistant it will return all sessions within the database.
+    /// If the Session is not persistant it will return a count within SessionStore.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let count = session.count().await;
+    /// ```
+    ///
+    #[inline]
+    pub async fn count(&self) -> i64 {
+        self.store.count_sessions().await
+    }
+
+    /// Returns a i64 count of how many Sessions exist that have not yet expired.
+    ///
+    /// If the Session is persistant it will return all active sessions within the database.
+    /// If the Session is not persistant it will return an active count within SessionStore.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let count = session.count_active().await;
+    /// ```
+    ///
+    #[inline]
+    pub async fn count_active(&self) -> i64 {
+        self.store.count_active_sessions().await
+    }
+
+    /// Returns the SessionID for this Session.
+    ///
+    /// The SessionID contains the ID generated at the beginning of this Session.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let session_id = session.get_session_id();
+    /// ```
+    ///
+    #[inline]
+    pub fn get_session_id(&self) -> SessionID {
+        self.id.clone()
+    }
+
+    /// Returns the store for this Session.
+    ///
+    /// The store contains everything that all sessions need.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let store = session.get_store();
+    /// ```
+    ///
+    #[inline]
+    pub fn get_store(&self) -> &SessionStore<S> {
+        &self.store
+    }
+
+    /// Returns a mutable store for this Session.
+    ///
+    /// The store contains everything that all sessions need.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let store = session.get_store_mut();
+    /// ```
+    ///
+    #[inline]
+    pub fn get_mut_store(&mut self) -> &mut SessionStore<S> {
+        &mut self.store
+    }
+
+    /// Removes a Request from the request counter
+    /// used to deturmine if parallel requests exist.
+    /// prevents data deletion until requests == 0.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.remove_request();
+    /// ```
+    ///
+    #[inline]
+    pub(crate) fn remove_request(&self) {
+        self.store.remove_session_request(self.id.inner());
+    }
+
+    /// Removes a Request from the request counter
+    /// used to deturmine if parallel requests exist.
+    /// prevents data deletion until requests == 0.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set_request();
+    /// ```
+    ///
+    #[inline]
+    pub(crate) fn set_request(&self) {
+        self.store.set_session_request(self.id.inner());
+    }
+
+    /// checks if a session has more than one request.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.is_parallel();
+    /// ```
+    ///
+    #[inline]
+    pub(crate) fn is_parallel(&self) -> bool {
+        self.store.is_session_parallel(self.id.inner())
+    }
+
+    /// checks if a session exists and if it is outdated.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.verify();
+    /// ```
+    ///
+    #[cfg(feature = "advanced")]
+    #[inline]
+    pub fn verify(&self) -> Result<(), SessionError> {
+        self.store.verify(self.id.inner())
+    }
+
+    /// Updates the sessions stored database expire time.
+    /// Use this before forcing a update to the database store.
+    /// will update the database expires based on
+    /// if the session is longterm then configs max_lifespan.
+    /// if not then configs lifespan.
+    ///
+    /// THIS WILL NOT UPDATE THE DATABASE SIDE.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.update_database_expires();
+    /// ```
+    ///
+    #[cfg(feature = "advanced")]
+    #[inline]
+    pub fn update_database_expires(&self) -> Result<(), SessionError> {
+        self.store.update_database_expires(self.id.inner())
+    }
+
+    /// Updates the Sessions In memory auto remove timer.
+    /// Will prevent it from being removed for the configs set memory_lifespan.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.update_memory_expires();
+    /// ```
+    ///
+    #[cfg(feature = "advanced")]
+    #[inline]
+    pub fn update_memory_expires(&self) -> Result<(), SessionError> {
+        self.store.update_memory_expires(self.id.inner())
+    }
+
+    /// forces a update to the databases stored data for the session.
+    /// Make sure to update the databases expire time before running this or
+    /// the data could be unloaded by a request checking for outdated sessions.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.force_database_update().await;
+    /// ```
+    ///
+    #[cfg(feature = "advanced")]
+    #[inline]
+    pub async fn force_database_update(&self) -> Result<(), SessionError> {
+        self.store.force_database_update(self.id.inner()).await
+    }
+
+    /// Removes the session from the memory store if it is not parallel.
+    /// If it is parallel then each parallel session will need to call this once.
+    /// when all parallel sessions are dead this gets unloaded.
+    ///
+    /// THIS DOES NOT CLEAR THE KEY STORE.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.memory_remove_session();
+    /// ```
+    ///
+    #[cfg(feature = "advanced")]
+    #[inline]
+    pub fn memory_remove_session(&self) -> Result<(), SessionError> {
+        self.store.memory_remove_session(self.id.inner())
+    }
+
+    /// Removes the session from the Database store.
+    ///
+    /// THIS DOES NOT REMOVE THE KEY STORE.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.database_remove_session().await;
+    /// ```
+    ///
+    #[cfg(feature = "advanced")]
+    #[inline]
+    pub async fn database_remove_session(&self) -> Result<(), SessionError> {
+        self.store.database_remove_session(self.id.inner()).await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadOnlySession<T>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    pub(crate) store: SessionStore<T>,
+    pub(crate) id: SessionID,
+}
+
+impl<T> From<Session<T>> for ReadOnlySession<T>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    fn from(session: Session<T>) -> Self {
+        ReadOnlySession {
+            store: session.store,
+            id: session.id,
+        }
+    }
+}
+
+/// Adds FromRequestParts<B> for Session
+///
+/// Returns the Session from Axums request extensions state.
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ReadOnlySession<T>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+    S: Send + Sync,
+{
+    type Rejection = (http::StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let session = parts.extensions.get::<Session<T>>().cloned().ok_or((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Can't extract Axum `Session`. Is `SessionLayer` enabled?",
+        ))?;
+
+        Ok(session.into())
+    }
+}
+
+/// A read only Session whose Request does not count as Session activity.
+///
+/// Use this for health checks, prefetches or polling so they do not keep a Session alive.
+/// The Session's values can be read through `Deref` like a `ReadOnlySession`. The
+/// Session's last_accessed, expires and in memory timeout are left as they were before
+/// the Request, no Cookies or Headers are sent and the Session is not saved. See `Session::peek`.
+///
+/// # Examples
+/// ```rust ignore
+/// async fn poll(session: PeekSession<SessionPgPool>) -> String {
+///     session.get::<String>("status").unwrap_or_default()
+/// }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct PeekSession<T>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    pub(crate) session: ReadOnlySession<T>,
+}
+
+impl<T> Deref for PeekSession<T>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    type Target = ReadOnlySession<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.session
+    }
+}
+
+/// Adds FromRequestParts<B> for PeekSession
+///
+/// Returns the Session from Axums request extensions state marking the Request as a peek.
+#[async_trait]
+impl<T, S> FromRequestParts<S> for PeekSession<T>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+    S: Send + Sync,
+{
+    type Rejection = (http::StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let session = parts.extensions.get::<Session<T>>().cloned().ok_or((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Can't extract Axum `Session`. Is `SessionLayer` enabled?",
+        ))?;
+
+        session.peek();
+        Ok(PeekSession {
+            session: session.into(),
+        })
+    }
+}
+
+impl<S> ReadOnlySession<S>
+where
+    S: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    /// Returns when the Session was first created.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let created_at = session.created_at();
+    /// ```
+    ///
+    #[inline]
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.store.created_at(self.id.inner())
+    }
+
+    /// Returns when the Session was last accessed. This is updated at the start of every request.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let last_accessed = session.last_accessed();
+    /// ```
+    ///
+    #[inline]
+    pub fn last_accessed(&self) -> DateTime<Utc> {
+        self.store.last_accessed(self.id.inner())
+    }
+
+    /// Returns when the Session will expire within the database unless it is accessed again.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let expires = session.expires();
+    /// ```
+    ///
+    #[inline]
+    pub fn expires(&self) -> DateTime<Utc> {
+        self.store.expires(self.id.inner())
+    }
+
+    /// Returns how long until the Session expires. Returns a zero Duration if already expired.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// if session.remaining() < Duration::minutes(5) {
+    ///     println!("Your session expires soon");
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn remaining(&self) -> Duration {
+        (self.expires() - self.store.config.now()).max(Duration::zero())
+    }
+
+    /// Returns all of the Session's values as a Json Map without changing the Session.
+    /// Pass it to `Session::merge` to carry values over to another Session, like a guest's
+    /// cart into a new account. Every key set by the app is included, so filter out the
+    /// ones that should not be carried over. The CSRF token is kept outside of the values
+    /// and is never included.
+    ///
+    /// Values that can not be read back as Json, like ones stored using bincode, are
+    /// returned as the stored String.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let cart = guest_session.export();
+    /// ```
+    ///
+    #[inline]
+    pub fn export(&self) -> serde_json::Map<String, serde_json::Value> {
+        self.store.export_data(self.id.inner())
+    }
+
+    /// Returns the approximate size in bytes of the Session's keys and serialized values.
+    /// This is the size checked against the configs max_session_size.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let bytes = session.data_len();
+    /// ```
+    ///
+    #[inline]
+    pub fn data_len(&self) -> usize {
+        self.store.data_len(self.id.inner())
+    }
+
+    /// Checks the presented token against the Session's anti-CSRF token in constant time.
+    ///
+    /// Returns false if no token was generated for the Session yet.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// if !session.verify_csrf(&form.csrf_token) {
+    ///     return StatusCode::FORBIDDEN;
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn verify_csrf(&self, presented: &str) -> bool {
+        self.store.verify_csrf(self.id.inner(), presented)
+    }
+
+    /// Checks the presented secret against the String stored under the key in constant time,
+    /// like for session bound API tokens. Use this over comparing with `==` which can leak
+    /// the secret through timing.
+    ///
+    /// Returns false if the key does not exist or is not a String.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// if !session.verify_secret("api-token", &presented) {
+    ///     return StatusCode::UNAUTHORIZED;
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn verify_secret(&self, key: &str, presented: &str) -> bool {
+        self.store.verify_secret(self.id.inner(), key, presented)
+    }
+
+    /// Checks the presented secret against the Argon2 hash stored under the key
+    /// using `Session::set_hashed_secret`. Hashing runs on a blocking thread.
+    ///
+    /// Returns false if the key does not exist, does not hold a valid hash or the
+    /// hashing thread failed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// if !session.verify_hashed_secret("api-token", &presented).await {
+    ///     return StatusCode::UNAUTHORIZED;
+    /// }
+    /// ```
+    ///
+    #[cfg(feature = "argon2")]
+    pub async fn verify_hashed_secret(&self, key: &str, presented: &str) -> bool {
+        verify_hashed(self.get::<String>(key), presented).await
+    }
+
+    /// Gets data from the Session's HashMap
+    ///
+    /// Provides an Option<T> that returns the requested data from the Sessions store.
+    /// Returns None if Key does not exist or if serdes_json failed to deserialize.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let id = session.get("user-id").unwrap_or(0);
+    /// ```
+    ///
+    ///Used to get data stored within SessionDatas hashmap from a key value.
+    ///
+    #[inline]
+    pub fn get<T: serde::de::DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.store.get(self.id.inner(), key)
+    }
+
+    /// Checks if the Key exists within the Session without deserializing its value,
+    /// like checking if a user is logged in without reading their whole profile.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let logged_in = session.contains_key("user");
+    /// ```
+    ///
+    #[inline]
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.store.contains_key(self.id.inner(), key)
+    }
+
+    /// Returns all of the Session's Keys without deserializing their values.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let keys = session.keys();
+    /// ```
+    ///
+    #[inline]
+    pub fn keys(&self) -> Vec<String> {
+        self.store.keys(self.id.inner())
+    }
+
+    /// Returns the Keys starting with the prefix, like every `cart:item:` Key, so callers
+    /// do not need to keep their own index of Keys. The Keys are returned with the prefix.
+    ///
+    /// The prefix is matched as a plain string like `remove_prefix`.
+    /// This is a linear scan of the Session's Keys.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let items = session.keys_with_prefix("cart:item:");
+    /// ```
+    ///
+    #[inline]
+    pub fn keys_with_prefix(&self, prefix: &str) -> Vec<String> {
+        self.store.keys_with_prefix(self.id.inner(), prefix)
+    }
+
+    /// Returns the length in bytes of the Key's serialized value without deserializing it.
+    /// Returns None if the Key does not exist.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let bytes = session.value_len("profile").unwrap_or(0);
+    /// ```
+    ///
+    #[inline]
+    pub fn value_len(&self, key: &str) -> Option<usize> {
+        self.store.value_len(self.id.inner(), key)
+    }
+
+    /// Returns a i64 count of how many Sessions exist.
+    ///
+    /// If the Session is persistant it will return all sessions within the database.
+    /// If the Session is not persistant it will return a count within SessionStore.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let count = session.count().await;
+    /// ```
+    ///
+    #[inline]
+    pub async fn count(&self) -> i64 {
+        self.store.count_sessions().await
+    }
+
+    /// Returns a i64 count of how many Sessions exist that have not yet expired.
+    ///
+    /// If the Session is persistant it will return all active sessions within the database.
+    /// If the Session is not persistant it will return an active count within SessionStore.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let count = session.count_active().await;
+    /// ```
+    ///
+    #[inline]
+    pub async fn count_active(&self) -> i64 {
+        self.store.count_active_sessions().await
+    }
+}
+
+/// Hashes the secret with Argon2 using a random salt returning the PHC string.
+#[cfg(feature = "argon2")]
+fn hash_secret(secret: &str) -> Result<String, SessionError> {
+    use argon2::{
+        password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
+        Argon2,
+    };
+
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default()
+        .hash_password(secret.as_bytes(), &salt)?
+        .to_string())
+}
+
+/// Checks the presented secret against the hash on a blocking thread for the Sessions'
+/// `verify_hashed_secret`. Returns false if there is no hash or the thread failed.
+#[cfg(feature = "argon2")]
+async fn verify_hashed(hash: Option<String>, presented: &str) -> bool {
+    let Some(hash) = hash else {
+        return false;
+    };
+
+    let presented = presented.to_owned();
+
+    match tokio::task::spawn_blocking(move || verify_hash(&hash, &presented)).await {
+        Ok(verified) => verified,
+        Err(err) => {
+            tracing::error!("Secret verifying task failed: {}", err);
+            false
+        }
+    }
+}
+
+/// Checks the secret against an Argon2 PHC string.
+#[cfg(feature = "argon2")]
+fn verify_hash(hash: &str, secret: &str) -> bool {
+    use argon2::{
+        password_hash::{PasswordHash, PasswordVerifier},
+        Argon2,
+    };
+
+    PasswordHash::new(hash).is_ok_and(|hash| {
+        Argon2::default()
+            .verify_password(secret.as_bytes(), &hash)
+            .is_ok()
+    })
 }
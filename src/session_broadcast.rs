@@ -0,0 +1,197 @@
+use crate::SessionError;
+use async_trait::async_trait;
+use futures::stream::{BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use tokio::sync::broadcast;
+
+/// What an `InvalidationEvent` tells the other app nodes to evict from memory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum InvalidationTarget {
+    /// The Session with the ID was destroyed.
+    Session(String),
+    /// Every Session was cleared from the database.
+    All,
+}
+
+/// Sent through a `SessionBroadcast` when a Session is destroyed or the store is cleared
+/// so other app nodes evict it from their memory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InvalidationEvent {
+    /// ID of the app node that sent the event so it can ignore its own events.
+    pub node_id: String,
+    /// What to evict.
+    pub target: InvalidationTarget,
+}
+
+/// Shares `InvalidationEvent`s between the `SessionStore`s of every app node.
+///
+/// Set it with `SessionStore::with_broadcast` and start applying the events of the other
+/// nodes with `SessionStore::spawn_broadcast_listener`. Delivery is best effort so events
+/// sent while a node is not subscribed are lost.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::{InvalidationEvent, SessionBroadcast, SessionError};
+/// use futures::stream::BoxStream;
+///
+/// #[derive(Debug)]
+/// struct NatsBroadcast { .. }
+///
+/// #[async_trait]
+/// impl SessionBroadcast for NatsBroadcast {
+///     async fn publish(&self, event: InvalidationEvent) -> Result<(), SessionError> { .. }
+///
+///     async fn subscribe(&self) -> Result<BoxStream<'static, InvalidationEvent>, SessionError> { .. }
+/// }
+/// ```
+///
+#[async_trait]
+pub trait SessionBroadcast: Debug + Send + Sync {
+    /// Sends the event to every subscribed app node, including this one.
+    async fn publish(&self, event: InvalidationEvent) -> Result<(), SessionError>;
+
+    /// Returns a stream of the events published by every app node.
+    async fn subscribe(&self) -> Result<BoxStream<'static, InvalidationEvent>, SessionError>;
+}
+
+/// In process `SessionBroadcast` using a `tokio::sync::broadcast` channel.
+/// Clones share the channel so it can be given to several stores, like within tests.
+///
+/// # Examples
+/// ```rust ignore
+/// let broadcast = SessionLocalBroadcast::new();
+/// let node_a = SessionStore::new(Some(pool.clone()), config.clone()).await?.with_broadcast(broadcast.clone());
+/// let node_b = SessionStore::new(Some(pool), config).await?.with_broadcast(broadcast);
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct SessionLocalBroadcast {
+    sender: broadcast::Sender<InvalidationEvent>,
+}
+
+impl SessionLocalBroadcast {
+    /// Creates a SessionLocalBroadcast with its own channel.
+    ///
+    pub fn new() -> Self {
+        Self {
+            sender: broadcast::channel(64).0,
+        }
+    }
+}
+
+impl Default for SessionLocalBroadcast {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl SessionBroadcast for SessionLocalBroadcast {
+    async fn publish(&self, event: InvalidationEvent) -> Result<(), SessionError> {
+        // Sending only fails when nobody is subscribed.
+        let _ = self.sender.send(event);
+        Ok(())
+    }
+
+    async fn subscribe(&self) -> Result<BoxStream<'static, InvalidationEvent>, SessionError> {
+        let receiver = self.sender.subscribe();
+
+        Ok(
+            futures::stream::unfold(receiver, |mut receiver| async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(event) => return Some((event, receiver)),
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            tracing::warn!("Session broadcast missed {} events", skipped);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            })
+            .boxed(),
+        )
+    }
+}
+
+/// `SessionBroadcast` using Redis pub/sub on a channel.
+///
+/// Publishing shares one multiplexed connection while each subscription opens its own.
+///
+/// # Examples
+/// ```rust ignore
+/// let client = redis::Client::open("redis://127.0.0.1/")?;
+/// let broadcast = SessionRedisBroadcast::new(client, "session_invalidation");
+/// let session_store = SessionStore::new(Some(pool), config).await?.with_broadcast(broadcast);
+/// ```
+///
+#[cfg(feature = "redis-db")]
+#[derive(Clone)]
+pub struct SessionRedisBroadcast {
+    client: redis::Client,
+    channel: std::borrow::Cow<'static, str>,
+    connection: std::sync::Arc<tokio::sync::OnceCell<redis::aio::MultiplexedConnection>>,
+}
+
+#[cfg(feature = "redis-db")]
+impl SessionRedisBroadcast {
+    /// Creates a SessionRedisBroadcast publishing and subscribing on the channel.
+    ///
+    pub fn new(client: redis::Client, channel: impl Into<std::borrow::Cow<'static, str>>) -> Self {
+        Self {
+            client,
+            channel: channel.into(),
+            connection: Default::default(),
+        }
+    }
+}
+
+#[cfg(feature = "redis-db")]
+impl Debug for SessionRedisBroadcast {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionRedisBroadcast")
+            .field("channel", &self.channel)
+            .finish()
+    }
+}
+
+#[cfg(feature = "redis-db")]
+#[async_trait]
+impl SessionBroadcast for SessionRedisBroadcast {
+    async fn publish(&self, event: InvalidationEvent) -> Result<(), SessionError> {
+        let mut con = self
+            .connection
+            .get_or_try_init(|| self.client.get_multiplexed_async_connection())
+            .await?
+            .clone();
+        let payload = serde_json::to_string(&event)?;
+
+        redis::cmd("PUBLISH")
+            .arg(&*self.channel)
+            .arg(payload)
+            .query_async::<_, ()>(&mut con)
+            .await?;
+        Ok(())
+    }
+
+    async fn subscribe(&self) -> Result<BoxStream<'static, InvalidationEvent>, SessionError> {
+        let mut pubsub = self.client.get_async_connection().await?.into_pubsub();
+        pubsub.subscribe(&*self.channel).await?;
+
+        Ok(pubsub
+            .into_on_message()
+            .filter_map(|message| async move {
+                let payload = message.get_payload::<String>().ok()?;
+
+                match serde_json::from_str(&payload) {
+                    Ok(event) => Some(event),
+                    Err(err) => {
+                        tracing::warn!("Session broadcast sent an invalid event: {}", err);
+                        None
+                    }
+                }
+            })
+            .boxed())
+    }
+}
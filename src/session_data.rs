@@ -1,10 +1,16 @@
-use crate::SessionConfig;
+use crate::{
+    session_rate_limit::RateWindow, DataMigrator, RateLimitDecision, SchemaMigrator, SessionConfig,
+    SessionError, SessionSerializer,
+};
+use base64::{engine::general_purpose, Engine as _};
 use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     fmt::{self, Display, Formatter},
 };
+use subtle::ConstantTimeEq;
 use uuid::Uuid;
 
 /// The Store and Configured Data for a Session.
@@ -15,24 +21,44 @@ use uuid::Uuid;
 /// use uuid::Uuid;
 ///
 /// let config = SessionConfig::default();
-/// let token = Uuid::new_v4();
+/// let token = Uuid::new_v4().to_string();
 /// let session_data = SessionData::new(token, true, &config);
 /// ```
 ///
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SessionData {
     #[serde(skip)]
-    pub(crate) id: Uuid,
+    pub(crate) id: String,
     pub(crate) data: HashMap<String, String>,
     #[serde(skip)]
     pub(crate) expires: DateTime<Utc>,
     #[serde(skip)]
     pub(crate) autoremove: DateTime<Utc>,
+    #[serde(default = "Utc::now")]
+    pub(crate) created_at: DateTime<Utc>,
+    #[serde(default = "Utc::now")]
+    pub(crate) last_accessed: DateTime<Utc>,
+    /// When the Session ID was last renewed. None if it was never renewed.
+    #[serde(default)]
+    pub(crate) last_renewed: Option<DateTime<Utc>>,
     #[serde(skip)]
     pub(crate) destroy: bool,
     #[serde(skip)]
     pub(crate) renew: bool,
+    /// Set when the ID is renewed for being older than the configs id_renewal_interval.
+    #[serde(skip)]
+    pub(crate) renew_scheduled: bool,
     pub(crate) longterm: bool,
+    #[serde(default)]
+    pub(crate) expiry: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub(crate) csrf_token: Option<String>,
+    /// Version of the stored values' shapes given to the config's data and schema migrators.
+    #[serde(default)]
+    pub(crate) schema_version: u32,
+    /// Overrides if the configs `SessionMode` is treated as opt-in for this Session.
+    #[serde(default)]
+    pub(crate) opt_in: Option<bool>,
     #[serde(skip)]
     pub(crate) store: bool,
     #[serde(skip)]
@@ -41,6 +67,10 @@ pub struct SessionData {
     pub(crate) renew_key: bool,
     #[serde(skip)]
     pub(crate) requests: usize,
+    #[serde(skip)]
+    pub(crate) serializer: SessionSerializer,
+    #[serde(skip)]
+    pub(crate) max_size: Option<usize>,
 }
 
 impl SessionData {
@@ -52,24 +82,36 @@ impl SessionData {
     /// use uuid::Uuid;
     ///
     /// let config = SessionConfig::default();
-    /// let token = Uuid::new_v4();
+    /// let token = Uuid::new_v4().to_string();
     /// let session_data = SessionData::new(token, true, &config);
     /// ```
     ///
     #[inline]
-    pub(crate) fn new(id: Uuid, storable: bool, config: &SessionConfig) -> Self {
+    pub(crate) fn new(id: String, storable: bool, config: &SessionConfig) -> Self {
+        let now = config.now();
+
         Self {
             id,
             data: HashMap::new(),
-            expires: Utc::now() + config.lifespan,
+            expires: now + config.lifespan,
             destroy: false,
             renew: false,
+            renew_scheduled: false,
             renew_key: false,
-            autoremove: Utc::now() + config.memory_lifespan,
+            autoremove: now + config.memory_lifespan,
+            created_at: now,
+            last_accessed: now,
+            last_renewed: None,
             longterm: false,
+            expiry: None,
+            csrf_token: None,
+            schema_version: config.schema_migrators.len() as u32,
+            opt_in: None,
             store: storable,
             update: true,
             requests: 1,
+            serializer: config.serializer,
+            max_size: config.max_session_size,
         }
     }
 
@@ -81,14 +123,41 @@ impl SessionData {
     /// use uuid::Uuid;
     ///
     /// let config = SessionConfig::default();
-    /// let token = Uuid::new_v4();
+    /// let token = Uuid::new_v4().to_string();
     /// let session_data = SessionData::new(token, true, &config);
-    /// let expired = session_data.validate();
+    /// let expired = session_data.validate(config.now());
     /// ```
     ///
     #[inline]
-    pub(crate) fn validate(&self) -> bool {
-        self.expires >= Utc::now()
+    pub(crate) fn validate(&self, now: DateTime<Utc>) -> bool {
+        self.expires >= now
+    }
+
+    /// Updates when the Session expires. An explicit expiry set with `set_expiry`
+    /// is used over the configs lifespan or max_lifespan.
+    #[inline]
+    pub(crate) fn refresh_expires(&mut self, config: &SessionConfig) {
+        self.expires = self.expiry.unwrap_or_else(|| {
+            if self.longterm {
+                config.now() + config.max_lifespan
+            } else {
+                config.now() + config.lifespan
+            }
+        });
+    }
+
+    /// Schedules the Session ID to be renewed if it is older than the configs
+    /// id_renewal_interval, counting from when it was created or last renewed.
+    #[inline]
+    pub(crate) fn schedule_renewal(&mut self, config: &SessionConfig) {
+        if let Some(interval) = config.id_renewal_interval {
+            let issued = self.last_renewed.unwrap_or(self.created_at);
+
+            if config.now() - issued >= interval {
+                self.renew_scheduled = true;
+                self.update = true;
+            }
+        }
     }
 
     /// Validates and checks if the Session is to be destroyed.
@@ -101,22 +170,26 @@ impl SessionData {
     /// use uuid::Uuid;
     ///
     /// let config = SessionConfig::default();
-    /// let token = Uuid::new_v4();
+    /// let token = Uuid::new_v4().to_string();
     /// let mut session_data = SessionData::new(token, true, &config);
-    /// let expired = session_data.service_clear(Duration::days(5));
+    /// let expired = session_data.service_clear(&config);
     /// ```
     ///
     #[inline]
-    pub(crate) fn service_clear(&mut self, memory_lifespan: Duration, clear_check: bool) {
-        if clear_check && self.autoremove < Utc::now() {
+    pub(crate) fn service_clear(&mut self, config: &SessionConfig) {
+        let now = config.now();
+
+        if config.clear_check_on_load && self.autoremove < now {
             self.update = true;
 
-            if !self.validate() {
+            if !self.validate(now) {
+                tracing::debug!("Session expired, clearing its data");
                 self.data.clear();
             }
         }
 
-        self.autoremove = Utc::now() + memory_lifespan;
+        self.autoremove = now + config.memory_lifespan;
+        self.last_accessed = now;
     }
 
     /// Sets the Session to renew its Session ID.
@@ -135,6 +208,7 @@ impl SessionData {
     pub fn renew(&mut self) {
         self.renew = true;
         self.update = true;
+        self.csrf_token = None;
     }
 
     /// Sets the Session to renew its Session Key ID and Encryption Key.
@@ -173,6 +247,20 @@ impl SessionData {
         self.update = true;
     }
 
+    /// Marks the Session as accessed now and sets it to update the database
+    /// so the refreshed activity is kept.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.touch(config.now());
+    /// ```
+    ///
+    #[inline]
+    pub(crate) fn touch(&mut self, now: DateTime<Utc>) {
+        self.last_accessed = now;
+        self.update = true;
+    }
+
     /// Sets the Current Session to be Destroyed.
     /// This will Deleted the Session and Cookies upon Response Phase.
     ///
@@ -184,6 +272,53 @@ impl SessionData {
     #[inline]
     pub fn destroy(&mut self) {
         self.destroy = true;
+        self.data.clear();
+        self.csrf_token = None;
+    }
+
+    /// Returns the Session's CSRF token generating and storing a new one if none exists.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let token = session.csrf_token();
+    /// ```
+    ///
+    #[inline]
+    pub(crate) fn csrf_token(&mut self) -> String {
+        if let Some(token) = &self.csrf_token {
+            return token.clone();
+        }
+
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let token = general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+
+        self.csrf_token = Some(token.clone());
+        self.update = true;
+        token
+    }
+
+    /// Checks the presented token against the Session's CSRF token in constant time.
+    /// Returns false if the Session has no CSRF token.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let valid = session.verify_csrf(&token);
+    /// ```
+    ///
+    #[inline]
+    pub(crate) fn verify_csrf(&self, presented: &str) -> bool {
+        match &self.csrf_token {
+            Some(token) => constant_time_eq(token, presented),
+            None => false,
+        }
+    }
+
+    /// Checks the presented secret against the String stored under the key in constant time.
+    /// Returns false if the key does not exist or is not a String.
+    pub(crate) fn verify_secret(&self, key: &str, presented: &str) -> bool {
+        self.get::<String>(key)
+            .is_some_and(|secret| constant_time_eq(&secret, presented))
     }
 
     /// Sets the Current Session to a long term expiration. Useful for Remember Me setups.
@@ -200,6 +335,27 @@ impl SessionData {
         self.update = true;
     }
 
+    /// Sets an explicit expiry for the Session which is used over the configs lifespans.
+    /// If the expiry is not in the future the Session is destroyed.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set_expiry(Utc::now() + Duration::days(30), Utc::now());
+    /// ```
+    ///
+    #[inline]
+    pub(crate) fn set_expiry(&mut self, expiry: DateTime<Utc>, now: DateTime<Utc>) {
+        if expiry <= now {
+            self.destroy();
+            return;
+        }
+
+        self.expiry = Some(expiry);
+        self.expires = expiry;
+        self.update = true;
+    }
+
     /// Sets the Current Session to be storable.
     /// This will also update the database on Response Phase.
     ///
@@ -218,10 +374,56 @@ impl SessionData {
         self.update = true;
     }
 
+    /// Returns when the Session was last accessed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let last_accessed = session_data.last_accessed();
+    /// ```
+    ///
+    #[inline]
+    pub fn last_accessed(&self) -> DateTime<Utc> {
+        self.last_accessed
+    }
+
+    /// Returns when the Session will expire unless it is accessed again.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let expires = session_data.expires();
+    /// ```
+    ///
+    #[inline]
+    pub fn expires(&self) -> DateTime<Utc> {
+        self.expires
+    }
+
+    /// Overrides if this Session only stores once `set_store(true)` is called, over the
+    /// configs `SessionMode`. False stores the Session like `SessionMode::Persistent`.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set_opt_in_override(true);
+    /// ```
+    ///
+    #[inline]
+    pub fn set_opt_in_override(&mut self, opt_in: bool) {
+        self.opt_in = Some(opt_in);
+        self.update = true;
+    }
+
+    /// Returns if the Session only stores once `set_store(true)` is called.
+    ///
+    #[inline]
+    pub(crate) fn is_opt_in(&self, config: &SessionConfig) -> bool {
+        self.opt_in
+            .unwrap_or_else(|| config.session_mode.is_opt_in())
+    }
+
     /// Gets data from the Session's HashMap
     ///
     /// Provides an Option<T> that returns the requested data from the Sessions store.
-    /// Returns None if Key does not exist or if serdes_json failed to deserialize.
+    /// Returns None if Key does not exist or if the serializer failed to deserialize.
     ///
     /// # Examples
     /// ```rust ignore
@@ -232,15 +434,120 @@ impl SessionData {
     ///
     #[inline]
     pub fn get<T: serde::de::DeserializeOwned>(&self, key: &str) -> Option<T> {
+        if self.destroy {
+            tracing::warn!("Session data read after the Session was destroyed");
+            return None;
+        }
+
         let string = self.data.get(key)?;
-        serde_json::from_str(string).ok()
+        self.serializer.deserialize(string).ok()
+    }
+
+    /// Checks if the Key exists without deserializing its value.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let logged_in = session.contains_key("user");
+    /// ```
+    ///
+    #[inline]
+    pub fn contains_key(&self, key: &str) -> bool {
+        !self.destroy && self.data.contains_key(key)
+    }
+
+    /// Returns all of the Session's Keys.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let keys = session.keys();
+    /// ```
+    ///
+    #[inline]
+    pub fn keys(&self) -> Vec<String> {
+        if self.destroy {
+            return Vec::new();
+        }
+
+        self.data.keys().cloned().collect()
+    }
+
+    /// Returns the length in bytes of the Key's serialized value without deserializing it.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let bytes = session.value_len("profile").unwrap_or(0);
+    /// ```
+    ///
+    #[inline]
+    pub fn value_len(&self, key: &str) -> Option<usize> {
+        if self.destroy {
+            return None;
+        }
+
+        self.data.get(key).map(String::len)
+    }
+
+    /// Gets data like `get` but runs the migrator if the stored value fails to deserialize,
+    /// storing the migrated value so it is only migrated once.
+    pub(crate) fn get_or_migrate<T: serde::de::DeserializeOwned>(
+        &mut self,
+        key: &str,
+        migrator: &DataMigrator,
+    ) -> Option<T> {
+        if self.destroy {
+            tracing::warn!("Session data read after the Session was destroyed");
+            return None;
+        }
+
+        let string = self.data.get(key)?;
+
+        if let Ok(value) = self.serializer.deserialize(string) {
+            return Some(value);
+        }
+
+        let migrated = migrator(key, self.schema_version, string.clone())?;
+
+        match self.serializer.deserialize(&migrated) {
+            Ok(value) => {
+                self.data.insert(key.to_owned(), migrated);
+                self.update = true;
+                Some(value)
+            }
+            Err(err) => {
+                tracing::warn!(
+                    "Session data {} failed to deserialize after migrating: {}",
+                    key,
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    /// Runs the schema migrators from the Session's schema version onward, marking the
+    /// Session to be saved if any ran. Returns true if the Session was migrated.
+    pub(crate) fn migrate_schema(&mut self, migrators: &[SchemaMigrator]) -> bool {
+        let from = self.schema_version;
+
+        for (version, migrator) in migrators.iter().enumerate().skip(from as usize) {
+            migrator(&mut self.data, version as u32);
+        }
+
+        if (from as usize) < migrators.len() {
+            self.schema_version = migrators.len() as u32;
+            self.update = true;
+            return true;
+        }
+
+        false
     }
 
     /// Removes a Key from the Current Session's HashMap returning it.
-    /// This will also update the database on Response Phase.
+    /// This will also update the database on Response Phase if the Key was removed.
     ///
     /// Provides an Option<T> that returns the requested data from the Sessions store.
-    /// Returns None if Key does not exist or if serdes_json failed to deserialize.
+    /// Returns None if Key does not exist or if the serializer failed to deserialize.
+    /// A value that fails to deserialize is kept so it can still be read as another type.
     ///
     /// # Examples
     /// ```rust ignore
@@ -251,24 +558,383 @@ impl SessionData {
     ///
     #[inline]
     pub fn get_remove<T: serde::de::DeserializeOwned>(&mut self, key: &str) -> Option<T> {
-        let string = self.data.remove(key)?;
+        if self.destroy {
+            tracing::warn!("Session data read after the Session was destroyed");
+            return None;
+        }
+
+        let value = match self.serializer.deserialize(self.data.get(key)?) {
+            Ok(value) => value,
+            Err(err) => {
+                tracing::warn!(
+                    "Session data {} failed to deserialize, keeping it: {}",
+                    key,
+                    err
+                );
+                return None;
+            }
+        };
+
+        let _ = self.data.remove(key);
+        self.update = true;
+        Some(value)
+    }
+
+    /// Removes a Key from the Current Session's HashMap returning it.
+    /// This will also update the database on Response Phase.
+    ///
+    /// Unlike `get_remove` a value that fails to deserialize is returned as an error
+    /// instead of None. The value is removed either way.
+    ///
+    /// # Errors
+    /// - ['SessionError::SessionDestroyed'] is returned if the Session was destroyed.
+    /// - ['SessionError::SerdeJson'] or the matching serializer error is returned if the value failed to deserialize.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let nonce: Option<String> = session.take("reset-nonce")?;
+    /// ```
+    ///
+    #[inline]
+    pub fn take<T: serde::de::DeserializeOwned>(
+        &mut self,
+        key: &str,
+    ) -> Result<Option<T>, SessionError> {
+        if self.destroy {
+            return Err(SessionError::SessionDestroyed);
+        }
+
+        let Some(string) = self.data.remove(key) else {
+            return Ok(None);
+        };
+
         self.update = true;
-        serde_json::from_str(&string).ok()
+        self.serializer.deserialize(&string).map(Some)
     }
 
     /// Sets data to the Current Session's HashMap.
     /// This will also update the database on Response Phase.
     ///
+    /// Failures are logged as errors with the Key and the Session is left unchanged.
+    ///
+    /// # Errors
+    /// - ['SessionError::SizeLimitExceeded'] is returned if the Session would grow larger than max_size.
+    /// - ['SessionError::SerdeJson'] is returned if the value failed to Serialize.
+    /// - ['SessionError::SessionDestroyed'] is returned if the Session was destroyed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set("user-id", 1)?;
+    /// ```
+    ///
+    #[inline]
+    pub fn set(&mut self, key: &str, value: impl Serialize) -> Result<(), SessionError> {
+        self.try_set(key, value).map_err(|err| {
+            tracing::error!("Session data for key {:?} failed to set: {}", key, err);
+            err
+        })
+    }
+
+    /// Sets data to the Current Session's HashMap returning any Serialize errors.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Errors
+    /// - ['SessionError::SizeLimitExceeded'] is returned if the Session would grow larger than max_size.
+    /// - ['SessionError::SerdeJson'] is returned if the value failed to Serialize.
+    /// - ['SessionError::SessionDestroyed'] is returned if the Session was destroyed.
+    ///
     /// # Examples
     /// ```rust ignore
-    /// session.set("user-id", 1);
+    /// session.try_set("user-id", 1)?;
     /// ```
     ///
     #[inline]
-    pub fn set(&mut self, key: &str, value: impl Serialize) {
-        let value = serde_json::to_string(&value).unwrap_or_else(|_| "".to_string());
+    pub fn try_set(&mut self, key: &str, value: impl Serialize) -> Result<(), SessionError> {
+        let value = self.serializer.serialize(&value)?;
+        self.insert(key, value)
+    }
+
+    /// Adds to the number stored under the Key returning the new value and if it hit the max.
+    /// A missing or non numeric value counts as 0 and the result is clamped to the max.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Errors
+    /// - ['SessionError::SizeLimitExceeded'] is returned if the Session would grow larger than max_size.
+    /// - ['SessionError::SessionDestroyed'] is returned if the Session was destroyed.
+    ///
+    #[inline]
+    pub(crate) fn increment(
+        &mut self,
+        key: &str,
+        by: i64,
+        max: Option<i64>,
+    ) -> Result<(i64, bool), SessionError> {
+        let mut value = self.get::<i64>(key).unwrap_or(0).saturating_add(by);
+        let hit = max.is_some_and(|max| value >= max);
+
+        if let Some(max) = max {
+            value = value.min(max);
+        }
+
+        self.try_set(key, value)?;
+        Ok((value, hit))
+    }
+
+    /// Counts an attempt against the fixed window rate limit stored under the Key.
+    /// A missing, ended or non rate limit value starts a new window of the given length.
+    /// Denied attempts are not counted and leave the Session unchanged.
+    ///
+    /// # Errors
+    /// - ['SessionError::SizeLimitExceeded'] is returned if the Session would grow larger than max_size.
+    /// - ['SessionError::SerdeJson'] is returned if the window failed to Serialize.
+    /// - ['SessionError::SessionDestroyed'] is returned if the Session was destroyed.
+    ///
+    pub(crate) fn rate_limit(
+        &mut self,
+        key: &str,
+        max: u32,
+        window: Duration,
+        now: DateTime<Utc>,
+    ) -> Result<RateLimitDecision, SessionError> {
+        let (window, decision) = RateWindow::attempt(self.get(key), max, window, now);
+
+        if decision.allowed {
+            self.try_set(key, window)?;
+        }
+
+        Ok(decision)
+    }
+
+    /// Appends the value to the list stored under the Key returning the list's new length.
+    /// A missing or non list value counts as an empty list and the oldest values are
+    /// dropped once the list is longer than max_len.
+    ///
+    /// # Errors
+    /// - ['SessionError::SizeLimitExceeded'] is returned if the Session would grow larger than max_size.
+    /// - ['SessionError::SerdeJson'] is returned if the list failed to Serialize.
+    /// - ['SessionError::SessionDestroyed'] is returned if the Session was destroyed.
+    ///
+    pub(crate) fn push<T>(
+        &mut self,
+        key: &str,
+        value: T,
+        max_len: usize,
+    ) -> Result<usize, SessionError>
+    where
+        T: Serialize + serde::de::DeserializeOwned,
+    {
+        let mut list = self.get::<Vec<T>>(key).unwrap_or_default();
+        list.push(value);
+
+        if list.len() > max_len {
+            list.drain(..list.len() - max_len);
+        }
+
+        self.try_set(key, &list)?;
+        Ok(list.len())
+    }
+
+    /// Removes and returns the newest value of the list stored under the Key.
+    /// Returns None if the list is empty, missing or failed to deserialize.
+    ///
+    /// # Errors
+    /// - ['SessionError::SerdeJson'] is returned if the shortened list failed to Serialize.
+    /// - ['SessionError::SessionDestroyed'] is returned if the Session was destroyed.
+    ///
+    pub(crate) fn pop<T>(&mut self, key: &str) -> Result<Option<T>, SessionError>
+    where
+        T: Serialize + serde::de::DeserializeOwned,
+    {
+        let Some(mut list) = self.get::<Vec<T>>(key) else {
+            return Ok(None);
+        };
+
+        let value = list.pop();
+
+        if value.is_some() {
+            self.try_set(key, &list)?;
+        }
+
+        Ok(value)
+    }
+
+    /// Deserializes the Key's value letting f change it, storing it back only if it changed.
+    /// Returns what f returned and if the value changed, or None if the Key does not exist
+    /// or failed to deserialize.
+    ///
+    /// # Errors
+    /// - ['SessionError::SizeLimitExceeded'] is returned if the Session would grow larger than max_size.
+    /// - ['SessionError::SerdeJson'] is returned if the changed value failed to Serialize.
+    /// - ['SessionError::SessionDestroyed'] is returned if the Session was destroyed.
+    ///
+    pub(crate) fn with<T, R, F>(
+        &mut self,
+        key: &str,
+        f: F,
+    ) -> Result<Option<(R, bool)>, SessionError>
+    where
+        T: Serialize + serde::de::DeserializeOwned,
+        F: FnOnce(&mut T) -> R,
+    {
+        if self.destroy {
+            return Err(SessionError::SessionDestroyed);
+        }
+
+        let Some(Ok(mut value)) = self
+            .data
+            .get(key)
+            .map(|string| self.serializer.deserialize::<T>(string))
+        else {
+            return Ok(None);
+        };
+
+        let result = f(&mut value);
+        let value = self.serializer.serialize(&value)?;
+
+        if self.data.get(key) == Some(&value) {
+            return Ok(Some((result, false)));
+        }
+
+        self.insert(key, value)?;
+        Ok(Some((result, true)))
+    }
+
+    /// Inserts the serialized value if it keeps the Session within max_size.
+    ///
+    #[inline]
+    fn insert(&mut self, key: &str, value: String) -> Result<(), SessionError> {
+        if self.destroy {
+            return Err(SessionError::SessionDestroyed);
+        }
+
+        if let Some(max_size) = self.max_size {
+            let replaced = self.data.get(key).map_or(0, |old| key.len() + old.len());
+
+            if self.size() - replaced + key.len() + value.len() > max_size {
+                return Err(SessionError::SizeLimitExceeded(max_size));
+            }
+        }
+
         let _ = self.data.insert(key.to_string(), value);
         self.update = true;
+        Ok(())
+    }
+
+    /// Sets every entry of the Map to the Current Session's HashMap overwriting existing keys.
+    /// Nothing is set if any value fails or the Session would grow too large.
+    /// This will also update the database on Response Phase if anything was set.
+    ///
+    /// # Errors
+    /// - ['SessionError::SizeLimitExceeded'] is returned if the Session would grow larger than max_size.
+    /// - ['SessionError::SerdeJson'] is returned if a value failed to Serialize.
+    /// - ['SessionError::SessionDestroyed'] is returned if the Session was destroyed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let mut map = serde_json::Map::new();
+    /// map.insert("theme".to_owned(), serde_json::json!("dark"));
+    /// session.merge(map)?;
+    /// ```
+    ///
+    pub fn merge(
+        &mut self,
+        map: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<(), SessionError> {
+        if self.destroy {
+            return Err(SessionError::SessionDestroyed);
+        }
+
+        let values = map
+            .into_iter()
+            .map(|(key, value)| Ok((key, self.serializer.serialize(&value)?)))
+            .collect::<Result<Vec<(String, String)>, SessionError>>()?;
+
+        self.insert_many(values)
+    }
+
+    /// Sets every Key and value at once overwriting existing keys.
+    /// Nothing is set if any value fails to Serialize or the Session would grow too large.
+    /// This will also update the database on Response Phase if anything was set.
+    ///
+    /// # Errors
+    /// - ['SessionError::SizeLimitExceeded'] is returned if the Session would grow larger than max_size.
+    /// - ['SessionError::SerdeJson'] is returned if a value failed to Serialize.
+    /// - ['SessionError::SessionDestroyed'] is returned if the Session was destroyed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set_many([("step", 2), ("total", 5)])?;
+    /// ```
+    ///
+    pub fn set_many<K: Into<String>, V: Serialize>(
+        &mut self,
+        values: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<(), SessionError> {
+        if self.destroy {
+            return Err(SessionError::SessionDestroyed);
+        }
+
+        let values = values
+            .into_iter()
+            .map(|(key, value)| Ok((key.into(), self.serializer.serialize(&value)?)))
+            .collect::<Result<Vec<(String, String)>, SessionError>>()?;
+
+        self.insert_many(values)
+    }
+
+    /// Inserts the serialized values if they keep the Session within max_size.
+    ///
+    fn insert_many(&mut self, values: Vec<(String, String)>) -> Result<(), SessionError> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(max_size) = self.max_size {
+            let size = values.iter().fold(self.size(), |size, (key, value)| {
+                let replaced = self.data.get(key).map_or(0, |old| key.len() + old.len());
+                size - replaced + key.len() + value.len()
+            });
+
+            if size > max_size {
+                return Err(SessionError::SizeLimitExceeded(max_size));
+            }
+        }
+
+        self.data.extend(values);
+        self.update = true;
+        Ok(())
+    }
+
+    /// Returns all of the Session's values as a Json Map without changing the Session.
+    /// Values that can not be read back as Json, like ones stored using bincode, are
+    /// returned as the stored String.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let values = session.export();
+    /// ```
+    ///
+    pub fn export(&self) -> serde_json::Map<String, serde_json::Value> {
+        self.data
+            .iter()
+            .map(|(key, value)| {
+                let value = self
+                    .serializer
+                    .deserialize(value)
+                    .unwrap_or_else(|_| serde_json::Value::String(value.clone()));
+                (key.clone(), value)
+            })
+            .collect()
+    }
+
+    /// Returns the approximate size in bytes of the Session's keys and serialized values.
+    ///
+    #[inline]
+    pub(crate) fn size(&self) -> usize {
+        self.data
+            .iter()
+            .map(|(key, value)| key.len() + value.len())
+            .sum()
     }
 
     /// Removes a Key from the Current Session's HashMap.
@@ -300,6 +966,66 @@ impl SessionData {
         self.update = true;
     }
 
+    /// Returns the Keys starting with the prefix. This is a linear scan of the Keys.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let items = session.keys_with_prefix("cart:item:");
+    /// ```
+    ///
+    #[inline]
+    pub fn keys_with_prefix(&self, prefix: &str) -> Vec<String> {
+        if self.destroy {
+            return Vec::new();
+        }
+
+        self.data
+            .keys()
+            .filter(|key| key.starts_with(prefix))
+            .cloned()
+            .collect()
+    }
+
+    /// Removes each of the Keys returning how many existed.
+    /// Only updates the database on Response Phase if a Key was removed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let removed = session.remove_many(&["step", "total"]);
+    /// ```
+    ///
+    pub fn remove_many(&mut self, keys: &[&str]) -> usize {
+        let removed = keys
+            .iter()
+            .filter(|key| self.data.remove(**key).is_some())
+            .count();
+
+        if removed > 0 {
+            self.update = true;
+        }
+
+        removed
+    }
+
+    /// Removes every Key starting with the prefix returning the removed Keys.
+    /// The prefix is matched as is, so `"a"` matches `"a"` and `"ab"` but not `"ba"`.
+    /// Only updates the database on Response Phase if a Key was removed.
+    ///
+    #[inline]
+    pub(crate) fn remove_prefix(&mut self, prefix: &str) -> Vec<String> {
+        let keys = self.keys_with_prefix(prefix);
+
+        for key in &keys {
+            let _ = self.data.remove(key);
+        }
+
+        if !keys.is_empty() {
+            self.update = true;
+        }
+
+        keys
+    }
+
     /// Removes a Request from the request counter
     /// used to deturmine if parallel requests exist.
     /// prevents data deletion until requests == 0.
@@ -341,10 +1067,11 @@ impl SessionData {
     }
 }
 
-/// Contains the UUID the Session.
+/// Contains the ID of the Session.
 ///
 /// This is used to store and find the Session.
-/// Used to pass the UUID between Cookies, the Database, and Session.
+/// Used to pass the ID between Cookies, the Database, and Session.
+/// The ID is an opaque string created by the configs id generator which defaults to a UUIDv4.
 ///
 /// # Examples
 /// ```rust ignore
@@ -352,15 +1079,15 @@ impl SessionData {
 /// use uuid::Uuid;
 ///
 ///
-/// let token = Uuid::new_v4();
+/// let token = Uuid::new_v4().to_string();
 /// let id = SessionID::new(token);
 /// ```
 ///
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
-pub struct SessionID(pub(crate) Uuid);
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SessionID(pub(crate) String);
 
 impl SessionID {
-    /// Constructs a new SessionID hold a UUID.
+    /// Constructs a new SessionID holding an ID string.
     ///
     /// # Examples
     /// ```rust ignore
@@ -368,16 +1095,16 @@ impl SessionID {
     /// use uuid::Uuid;
     ///
     ///
-    /// let token = Uuid::new_v4();
+    /// let token = Uuid::new_v4().to_string();
     /// let id = SessionID::new(token);
     /// ```
     ///
     #[inline]
-    pub(crate) fn new(uuid: Uuid) -> SessionID {
-        SessionID(uuid)
+    pub(crate) fn new(id: String) -> SessionID {
+        SessionID(id)
     }
 
-    /// Returns the inner UUID as a string.
+    /// Returns the inner ID as a string.
     ///
     /// # Examples
     /// ```rust ignore
@@ -385,17 +1112,19 @@ impl SessionID {
     /// use uuid::Uuid;
     ///
     ///
-    /// let token = Uuid::new_v4();
+    /// let token = Uuid::new_v4().to_string();
     /// let id = SessionID::new(token);
     /// let str_id = id.inner();
     /// ```
     ///
     #[inline]
     pub fn inner(&self) -> String {
-        self.0.to_string()
+        self.0.clone()
     }
 
-    /// Returns the inner UUID.
+    /// Returns the inner ID as a UUID.
+    ///
+    /// Returns None if a custom id generator was used that does not create UUID's.
     ///
     /// # Examples
     /// ```rust ignore
@@ -403,20 +1132,20 @@ impl SessionID {
     /// use uuid::Uuid;
     ///
     ///
-    /// let token = Uuid::new_v4();
+    /// let token = Uuid::new_v4().to_string();
     /// let id = SessionID::new(token);
     /// let uuid = id.uuid();
     /// ```
     ///
     #[inline]
-    pub fn uuid(&self) -> Uuid {
-        self.0
+    pub fn uuid(&self) -> Option<Uuid> {
+        Uuid::parse_str(&self.0).ok()
     }
 }
 
 impl Display for SessionID {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        f.write_str(&self.0.to_string())
+        f.write_str(&self.0)
     }
 }
 
@@ -428,4 +1157,12 @@ impl Display for SessionID {
 pub(crate) struct SessionTimers {
     pub(crate) last_expiry_sweep: DateTime<Utc>,
     pub(crate) last_database_expiry_sweep: DateTime<Utc>,
+    pub(crate) last_database_cleanup: Option<DateTime<Utc>>,
+    pub(crate) last_snapshot: DateTime<Utc>,
+}
+
+/// Compares the Strings in constant time so their contents are not leaked through timing.
+/// Only the length is leaked when they differ in length.
+pub(crate) fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.as_bytes().ct_eq(b.as_bytes()).into()
 }
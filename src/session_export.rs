@@ -0,0 +1,223 @@
+use crate::{
+    DatabasePool, MemoryStore, SessionData, SessionError, SessionID, SessionKey, SessionStore,
+};
+use chrono::{DateTime, Utc};
+#[cfg(feature = "key-store")]
+use fastbloom_rs::Membership;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Debug,
+    io::{BufRead, BufReader, Read, Write},
+    marker::{Send, Sync},
+};
+
+/// Version of the export format. Bump this and migrate older versions within
+/// `SessionStore::import` when `SessionData` changes in an incompatible way.
+pub(crate) const EXPORT_VERSION: u32 = 1;
+
+/// Name written into the export header so other files are rejected.
+const EXPORT_FORMAT: &str = "axum_session";
+
+/// First line of every export.
+#[derive(Serialize, Deserialize)]
+struct ExportHeader {
+    format: String,
+    version: u32,
+}
+
+/// Each line after the header holds one Session or Per-Session Key.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ExportRecord {
+    Session {
+        id: String,
+        expires: DateTime<Utc>,
+        /// Boxed as SessionData is far larger than a Key.
+        data: Box<SessionData>,
+    },
+    /// The Key encrypted with the config's database key.
+    Key { id: String, key: String },
+}
+
+impl<T> SessionStore<T>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    /// Writes all Sessions in memory that have not expired as Json lines so they can be
+    /// imported again after a restart using `import`. Useful to keep users logged in across
+    /// deploys when using the `SessionNullPool`.
+    ///
+    /// Per-Session Keys are only written if a database key is set and are encrypted with it.
+    /// Returns the number of Sessions written.
+    ///
+    /// # Errors
+    /// - ['SessionError::IO'] is returned if writing failed.
+    /// - ['SessionError::SerdeJson'] is returned if a Session failed to serialize.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let file = std::fs::File::create("sessions.jsonl")?;
+    /// let count = session_store.export(std::io::BufWriter::new(file))?;
+    /// ```
+    ///
+    pub fn export(&self, mut writer: impl Write) -> Result<u64, SessionError> {
+        serde_json::to_writer(
+            &mut writer,
+            &ExportHeader {
+                format: EXPORT_FORMAT.to_owned(),
+                version: EXPORT_VERSION,
+            },
+        )?;
+        writeln!(writer)?;
+
+        let mut count = 0;
+        let mut sessions = Vec::new();
+
+        // Copied out first so the writer never runs while the memory is locked.
+        let now = self.config.now();
+
+        self.inner.for_each(|_, session| {
+            if !session.destroy && session.validate(now) {
+                sessions.push(session.clone());
+            }
+        });
+
+        for session in sessions {
+            serde_json::to_writer(
+                &mut writer,
+                &ExportRecord::Session {
+                    id: session.id.clone(),
+                    expires: session.expires,
+                    data: Box::new(session),
+                },
+            )?;
+            writeln!(writer)?;
+            count += 1;
+        }
+
+        if let Some(database_key) = &self.config.database_key {
+            for key in self.keys.iter() {
+                serde_json::to_writer(
+                    &mut writer,
+                    &ExportRecord::Key {
+                        id: key.id.inner(),
+                        key: key.encrypt(database_key.clone()),
+                    },
+                )?;
+                writeln!(writer)?;
+            }
+        }
+
+        writer.flush()?;
+        Ok(count)
+    }
+
+    /// Loads Sessions written by `export` into memory.
+    ///
+    /// Expired Sessions, ID's that fail the configs id validator and ID's already in memory
+    /// are skipped. Keys are skipped if they fail to decrypt with the config's database key.
+    /// Returns the number of Sessions loaded.
+    ///
+    /// # Errors
+    /// - ['SessionError::IO'] is returned if reading failed.
+    /// - ['SessionError::SerdeJson'] is returned if a line is not valid.
+    /// - ['SessionError::InvalidExportHeader'] is returned if the header is missing.
+    /// - ['SessionError::UnsupportedExportVersion'] is returned if the export is from a newer version.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// if let Ok(file) = std::fs::File::open("sessions.jsonl") {
+    ///     let count = session_store.import(file).await?;
+    /// }
+    /// ```
+    ///
+    pub async fn import(&self, reader: impl Read) -> Result<u64, SessionError> {
+        let mut lines = BufReader::new(reader).lines();
+
+        let header: ExportHeader = match lines.next() {
+            Some(line) => {
+                serde_json::from_str(&line?).map_err(|_| SessionError::InvalidExportHeader)?
+            }
+            None => return Err(SessionError::InvalidExportHeader),
+        };
+
+        if header.format != EXPORT_FORMAT {
+            return Err(SessionError::InvalidExportHeader);
+        }
+
+        if header.version != EXPORT_VERSION {
+            return Err(SessionError::UnsupportedExportVersion(header.version));
+        }
+
+        let mut count = 0;
+        let now = self.config.now();
+
+        for line in lines {
+            let line = line?;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let id = match serde_json::from_str(&line)? {
+                ExportRecord::Session {
+                    id,
+                    expires,
+                    mut data,
+                } => {
+                    if expires <= now
+                        || !(self.config.id_validator)(&id)
+                        || self.inner.contains_key(&id)
+                    {
+                        continue;
+                    }
+
+                    data.id = id.clone();
+                    data.expires = expires;
+                    data.autoremove = now + self.config.memory_lifespan;
+                    data.store = true;
+                    data.serializer = self.config.serializer;
+                    data.max_size = self.config.max_session_size;
+                    self.inner.insert(id.clone(), *data);
+                    count += 1;
+                    id
+                }
+                ExportRecord::Key { id, key } => {
+                    let Some(database_key) = self.config.database_key.clone() else {
+                        continue;
+                    };
+
+                    if self.keys.contains_key(&id) {
+                        continue;
+                    }
+
+                    match SessionKey::decrypt(
+                        SessionID::new(id.clone()),
+                        &key,
+                        database_key,
+                        now + self.config.memory_lifespan,
+                    ) {
+                        Ok(key) => {
+                            self.keys.insert(id.clone(), key);
+                            id
+                        }
+                        Err(err) => {
+                            tracing::warn!("Session key failed to import: {}", err);
+                            continue;
+                        }
+                    }
+                }
+            };
+
+            #[cfg(feature = "key-store")]
+            if self.config.use_bloom_filters {
+                self.filter.write().await.add(id.as_bytes());
+            }
+
+            #[cfg(not(feature = "key-store"))]
+            let _ = id;
+        }
+
+        Ok(count)
+    }
+}
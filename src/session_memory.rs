@@ -0,0 +1,171 @@
+use crate::{config::is_shard_amount, SessionConfig, SessionData};
+use dashmap::{
+    mapref::one::{Ref as ShardRef, RefMut as ShardRefMut},
+    DashMap,
+};
+use std::{
+    fmt::Debug,
+    ops::{Deref, DerefMut},
+};
+
+/// `MemoryStore` the `SessionStore` keeps its loaded Sessions in.
+/// Point this at another implementation to swap the memory backend.
+pub(crate) type SessionMemory = ShardedMemory;
+
+/// Structure holding the Sessions loaded into memory, keyed by Session ID.
+///
+/// The references returned hold a lock on the Session until they are dropped, so they
+/// must never be held across an await or while calling back into the store.
+pub(crate) trait MemoryStore: Debug + Send + Sync + 'static {
+    type Ref<'a>: Deref<Target = SessionData>
+    where
+        Self: 'a;
+    type RefMut<'a>: DerefMut<Target = SessionData>
+    where
+        Self: 'a;
+
+    /// Creates an empty memory store using the config's memory settings.
+    fn new(config: &SessionConfig) -> Self
+    where
+        Self: Sized;
+
+    /// Creates an empty memory store only holding the Sessions of a single Request.
+    fn scoped() -> Self
+    where
+        Self: Sized;
+
+    fn get(&self, id: &str) -> Option<Self::Ref<'_>>;
+
+    fn get_mut(&self, id: &str) -> Option<Self::RefMut<'_>>;
+
+    fn contains_key(&self, id: &str) -> bool;
+
+    /// Inserts the Session returning the one it replaced.
+    fn insert(&self, id: String, session: SessionData) -> Option<SessionData>;
+
+    /// Inserts the Session unless the ID is already in memory.
+    fn insert_if_absent(&self, id: String, session: SessionData);
+
+    fn remove(&self, id: &str) -> Option<(String, SessionData)>;
+
+    /// Removes the Session only if f returns true for it.
+    fn remove_if(
+        &self,
+        id: &str,
+        f: impl FnOnce(&String, &SessionData) -> bool,
+    ) -> Option<(String, SessionData)>;
+
+    /// Keeps only the Sessions f returns true for.
+    fn retain(&self, f: impl FnMut(&String, &mut SessionData) -> bool);
+
+    /// Calls f with every Session in memory.
+    fn for_each(&self, f: impl FnMut(&String, &SessionData));
+
+    fn len(&self) -> usize;
+
+    fn clear(&self);
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Counts the Sessions f returns true for.
+    fn count(&self, mut f: impl FnMut(&SessionData) -> bool) -> usize {
+        let mut count = 0;
+
+        self.for_each(|_, session| {
+            if f(session) {
+                count += 1;
+            }
+        });
+
+        count
+    }
+}
+
+/// Default `MemoryStore` splitting the Sessions between DashMap shards that each have
+/// their own lock.
+#[derive(Debug, Default)]
+pub(crate) struct ShardedMemory(DashMap<String, SessionData>);
+
+impl MemoryStore for ShardedMemory {
+    type Ref<'a> = ShardRef<'a, String, SessionData>;
+    type RefMut<'a> = ShardRefMut<'a, String, SessionData>;
+
+    fn new(config: &SessionConfig) -> Self {
+        // Invalid amounts only reach here with lenient validation which already warned.
+        match config
+            .memory_shards
+            .filter(|shards| is_shard_amount(*shards))
+        {
+            Some(shards) => Self(DashMap::with_shard_amount(shards)),
+            None => Self::default(),
+        }
+    }
+
+    fn scoped() -> Self {
+        // Only ever holds a Session or two so the fewest shards are used.
+        Self(DashMap::with_shard_amount(2))
+    }
+
+    #[inline]
+    fn get(&self, id: &str) -> Option<Self::Ref<'_>> {
+        self.0.get(id)
+    }
+
+    #[inline]
+    fn get_mut(&self, id: &str) -> Option<Self::RefMut<'_>> {
+        self.0.get_mut(id)
+    }
+
+    #[inline]
+    fn contains_key(&self, id: &str) -> bool {
+        self.0.contains_key(id)
+    }
+
+    #[inline]
+    fn insert(&self, id: String, session: SessionData) -> Option<SessionData> {
+        self.0.insert(id, session)
+    }
+
+    #[inline]
+    fn insert_if_absent(&self, id: String, session: SessionData) {
+        self.0.entry(id).or_insert(session);
+    }
+
+    #[inline]
+    fn remove(&self, id: &str) -> Option<(String, SessionData)> {
+        self.0.remove(id)
+    }
+
+    #[inline]
+    fn remove_if(
+        &self,
+        id: &str,
+        f: impl FnOnce(&String, &SessionData) -> bool,
+    ) -> Option<(String, SessionData)> {
+        self.0.remove_if(id, f)
+    }
+
+    #[inline]
+    fn retain(&self, f: impl FnMut(&String, &mut SessionData) -> bool) {
+        self.0.retain(f)
+    }
+
+    #[inline]
+    fn for_each(&self, mut f: impl FnMut(&String, &SessionData)) {
+        self.0
+            .iter()
+            .for_each(|entry| f(entry.key(), entry.value()))
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    fn clear(&self) {
+        self.0.clear()
+    }
+}
@@ -0,0 +1,208 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// How many seconds the database count used by `SessionStore::stats` is cached for.
+pub(crate) const STATS_COUNT_INTERVAL: i64 = 30;
+
+/// When the database count was last taken and its value. The value is None if the count failed.
+pub(crate) type CountCache = Option<(DateTime<Utc>, Option<i64>)>;
+
+/// Counters tracking how the SessionStore is used. Shared by all clones of the SessionStore.
+///
+/// When the `metrics` feature is enabled every update is also reported through the
+/// `metrics` crate so any installed exporter picks them up.
+#[derive(Debug, Default)]
+pub(crate) struct SessionMetrics {
+    created: AtomicU64,
+    loaded_memory: AtomicU64,
+    loaded_db: AtomicU64,
+    saved: AtomicU64,
+    destroyed: AtomicU64,
+    evicted: AtomicU64,
+    decrypt_failures: AtomicU64,
+    invalid_ids: AtomicU64,
+    rejected: AtomicU64,
+    db_errors: AtomicU64,
+    db_calls: AtomicU64,
+    db_time_micros: AtomicU64,
+}
+
+impl SessionMetrics {
+    #[inline]
+    fn increment(counter: &AtomicU64, _name: &'static str) {
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        #[cfg(feature = "metrics")]
+        ::metrics::counter!(_name).increment(1);
+    }
+
+    #[inline]
+    pub(crate) fn created(&self) {
+        Self::increment(&self.created, "axum_session_created_total");
+    }
+
+    #[inline]
+    pub(crate) fn loaded_memory(&self) {
+        Self::increment(&self.loaded_memory, "axum_session_loaded_memory_total");
+    }
+
+    #[inline]
+    pub(crate) fn loaded_db(&self) {
+        Self::increment(&self.loaded_db, "axum_session_loaded_db_total");
+    }
+
+    #[inline]
+    pub(crate) fn saved(&self) {
+        Self::increment(&self.saved, "axum_session_saved_total");
+    }
+
+    #[inline]
+    pub(crate) fn destroyed(&self) {
+        Self::increment(&self.destroyed, "axum_session_destroyed_total");
+    }
+
+    #[inline]
+    pub(crate) fn evicted(&self) {
+        Self::increment(&self.evicted, "axum_session_evicted_total");
+    }
+
+    #[inline]
+    pub(crate) fn decrypt_failure(&self) {
+        Self::increment(
+            &self.decrypt_failures,
+            "axum_session_decrypt_failures_total",
+        );
+    }
+
+    #[inline]
+    pub(crate) fn invalid_id(&self) {
+        Self::increment(&self.invalid_ids, "axum_session_invalid_ids_total");
+    }
+
+    #[inline]
+    pub(crate) fn rejected(&self) {
+        Self::increment(&self.rejected, "axum_session_rejected_total");
+    }
+
+    /// Records a database call's duration and if it failed.
+    #[inline]
+    pub(crate) fn db_call(&self, elapsed: Duration, failed: bool) {
+        self.db_calls.fetch_add(1, Ordering::Relaxed);
+        self.db_time_micros.fetch_add(
+            u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+
+        #[cfg(feature = "metrics")]
+        ::metrics::histogram!("axum_session_db_call_seconds").record(elapsed.as_secs_f64());
+
+        if failed {
+            Self::increment(&self.db_errors, "axum_session_db_errors_total");
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            created: self.created.load(Ordering::Relaxed),
+            loaded_memory: self.loaded_memory.load(Ordering::Relaxed),
+            loaded_db: self.loaded_db.load(Ordering::Relaxed),
+            saved: self.saved.load(Ordering::Relaxed),
+            destroyed: self.destroyed.load(Ordering::Relaxed),
+            evicted: self.evicted.load(Ordering::Relaxed),
+            decrypt_failures: self.decrypt_failures.load(Ordering::Relaxed),
+            invalid_ids: self.invalid_ids.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            db_errors: self.db_errors.load(Ordering::Relaxed),
+            db_calls: self.db_calls.load(Ordering::Relaxed),
+            db_time: Duration::from_micros(self.db_time_micros.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// A point in time copy of the SessionStore's counters.
+///
+/// # Examples
+/// ```rust ignore
+/// let metrics = session_store.metrics();
+/// println!("{} sessions created", metrics.created);
+/// ```
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// Sessions newly created.
+    pub created: u64,
+    /// Requests where the Session was already in memory.
+    pub loaded_memory: u64,
+    /// Sessions loaded from the database.
+    pub loaded_db: u64,
+    /// Sessions saved to the database.
+    pub saved: u64,
+    /// Sessions destroyed.
+    pub destroyed: u64,
+    /// Sessions evicted from memory due to the memory limit.
+    pub evicted: u64,
+    /// Cookies or Headers that failed to decrypt.
+    pub decrypt_failures: u64,
+    /// Cookies or Headers holding a Session ID that failed the configs id validator.
+    pub invalid_ids: u64,
+    /// Requests answered with 401 due to `SessionConfig::with_reject_invalid_tokens`.
+    pub rejected: u64,
+    /// Database calls that returned an error or timed out.
+    pub db_errors: u64,
+    /// Total database calls made.
+    pub db_calls: u64,
+    /// Total time spent within database calls.
+    pub db_time: Duration,
+}
+
+/// Gauges describing the current state of the SessionStore.
+///
+/// Implements Serialize so it can be returned from an admin endpoint.
+///
+/// # Examples
+/// ```rust ignore
+/// let stats = session_store.stats().await;
+/// println!("{} sessions in memory", stats.memory_sessions);
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoreStats {
+    /// Sessions currently loaded in memory.
+    pub memory_sessions: usize,
+    /// Sessions in memory that expired but have not been purged yet.
+    pub expired_in_memory: usize,
+    /// Sessions within the database. None without a database or if the count failed.
+    pub database_sessions: Option<i64>,
+    /// If the last database count succeeded. None without a database.
+    pub database_healthy: Option<bool>,
+    /// When expired Sessions were last purged from the database.
+    pub last_database_cleanup: Option<DateTime<Utc>>,
+    /// When these stats were taken.
+    pub sampled_at: DateTime<Utc>,
+}
+
+impl StoreStats {
+    /// Exports the stats as `metrics` crate gauges.
+    #[cfg(feature = "metrics")]
+    pub fn record(&self) {
+        ::metrics::gauge!("axum_session_memory_sessions").set(self.memory_sessions as f64);
+        ::metrics::gauge!("axum_session_expired_in_memory").set(self.expired_in_memory as f64);
+
+        if let Some(count) = self.database_sessions {
+            ::metrics::gauge!("axum_session_database_sessions").set(count as f64);
+        }
+
+        if let Some(healthy) = self.database_healthy {
+            ::metrics::gauge!("axum_session_database_healthy").set(f64::from(u8::from(healthy)));
+        }
+
+        if let Some(cleanup) = self.last_database_cleanup {
+            ::metrics::gauge!("axum_session_last_database_cleanup_seconds")
+                .set(cleanup.timestamp() as f64);
+        }
+    }
+}
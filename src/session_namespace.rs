@@ -0,0 +1,135 @@
+use crate::{DatabasePool, Session};
+use serde::Serialize;
+use std::{
+    fmt::Debug,
+    marker::{Send, Sync},
+};
+
+/// A view of a Session that keeps its Keys apart from the rest of the Session.
+///
+/// Created using `Session::namespace`. Keys are stored within the Session as
+/// `prefix:key` so libraries sharing a Session can each take their own namespace
+/// without their Keys colliding. Changes are persisted with the Session as usual.
+///
+/// # Examples
+/// ```rust ignore
+/// let cart = session.namespace("cart");
+/// let wishlist = session.namespace("wishlist");
+///
+/// cart.set("items", vec![1, 2]);
+/// wishlist.set("items", vec![3]);
+///
+/// assert_eq!(cart.get::<Vec<u32>>("items"), Some(vec![1, 2]));
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct SessionNamespace<T>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    pub(crate) session: Session<T>,
+    /// The namespace's prefix including the `:` separator.
+    pub(crate) prefix: String,
+}
+
+impl<T> SessionNamespace<T>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    pub(crate) fn new(session: Session<T>, prefix: &str) -> Self {
+        Self {
+            session,
+            prefix: format!("{prefix}:"),
+        }
+    }
+
+    #[inline]
+    fn key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key)
+    }
+
+    /// Returns the namespace's name without the separator.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// assert_eq!(session.namespace("cart").name(), "cart");
+    /// ```
+    ///
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.prefix[..self.prefix.len() - 1]
+    }
+
+    /// Gets data from the namespace.
+    ///
+    /// Provides an Option<T> that returns the requested data from the Sessions store.
+    /// Returns None if Key does not exist or if serde_json failed to deserialize.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let items: Vec<u32> = session.namespace("cart").get("items").unwrap_or_default();
+    /// ```
+    ///
+    #[inline]
+    pub fn get<N: serde::de::DeserializeOwned>(&self, key: &str) -> Option<N> {
+        self.session.get(&self.key(key))
+    }
+
+    /// Sets data to the namespace.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.namespace("cart").set("items", vec![1, 2]);
+    /// ```
+    ///
+    #[inline]
+    pub fn set(&self, key: &str, value: impl Serialize) {
+        self.session.set(&self.key(key), value);
+    }
+
+    /// Removes a Key from the namespace.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.namespace("cart").remove("items");
+    /// ```
+    ///
+    #[inline]
+    pub fn remove(&self, key: &str) {
+        self.session.remove(&self.key(key));
+    }
+
+    /// Returns the namespace's Keys without the prefix.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let keys = session.namespace("cart").keys();
+    /// ```
+    ///
+    #[inline]
+    pub fn keys(&self) -> Vec<String> {
+        self.session
+            .store
+            .keys_with_prefix(self.session.id.inner(), &self.prefix)
+            .into_iter()
+            .map(|key| key[self.prefix.len()..].to_owned())
+            .collect()
+    }
+
+    /// Removes every Key within the namespace leaving the rest of the Session untouched.
+    /// This will also update the database on Response Phase if anything was removed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.namespace("cart").clear();
+    /// ```
+    ///
+    #[inline]
+    pub fn clear(&self) {
+        self.session
+            .store
+            .remove_prefix(self.session.id.inner(), &self.prefix);
+    }
+}
@@ -0,0 +1,74 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Outcome of `Session::rate_limit`.
+///
+/// # Examples
+/// ```rust ignore
+/// let decision = session.rate_limit("login", 5, Duration::minutes(15));
+///
+/// if !decision.allowed {
+///     return Err(StatusCode::TOO_MANY_REQUESTS);
+/// }
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitDecision {
+    /// If the action is allowed. Denied attempts are not counted.
+    pub allowed: bool,
+    /// How many more attempts are allowed within the current window.
+    pub remaining: u32,
+    /// When the current window ends and the attempts are allowed again.
+    pub reset: DateTime<Utc>,
+    /// How long until reset, which is what `Retry-After` should be set to when denied.
+    pub retry_after: Duration,
+}
+
+impl RateLimitDecision {
+    /// Gets retry_after in whole seconds rounded up, as used by the `Retry-After` Header.
+    pub fn retry_after_secs(&self) -> u64 {
+        let millis = self.retry_after.num_milliseconds().max(0) as u64;
+        millis.div_ceil(1000)
+    }
+}
+
+/// Fixed window counter stored under the rate limit's Key.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct RateWindow {
+    /// Attempts allowed within the window so far.
+    pub(crate) count: u32,
+    /// When the window ends.
+    pub(crate) reset: DateTime<Utc>,
+}
+
+impl RateWindow {
+    /// Counts an attempt at now, starting a new window if the stored one ended.
+    pub(crate) fn attempt(
+        window: Option<Self>,
+        max: u32,
+        length: Duration,
+        now: DateTime<Utc>,
+    ) -> (Self, RateLimitDecision) {
+        let mut window = window
+            .filter(|window| window.reset > now)
+            .unwrap_or(RateWindow {
+                count: 0,
+                reset: now + length,
+            });
+
+        let allowed = window.count < max;
+
+        if allowed {
+            window.count += 1;
+        }
+
+        let decision = RateLimitDecision {
+            allowed,
+            remaining: max.saturating_sub(window.count),
+            reset: window.reset,
+            retry_after: window.reset - now,
+        };
+
+        (window, decision)
+    }
+}
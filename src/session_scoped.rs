@@ -0,0 +1,140 @@
+use crate::{DatabasePool, Session};
+use async_trait::async_trait;
+use axum_core::extract::FromRequestParts;
+use http::{self, request::Parts, StatusCode};
+use std::{
+    fmt::{self, Debug, Formatter},
+    marker::{PhantomData, Send, Sync},
+    ops::Deref,
+};
+
+/// Inserts the Session into the Request's extensions for the extractors to find.
+pub(crate) type SessionInserter<T> = fn(&mut http::Extensions, Session<T>);
+
+/// Inserts the Session so it can be extracted as `Session<T>`.
+pub(crate) fn insert_session<T>(extensions: &mut http::Extensions, session: Session<T>)
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    extensions.insert(session);
+}
+
+/// Inserts the Session so it can be extracted as `SessionScoped<T, Scope>`.
+pub(crate) fn insert_scoped<T, Scope>(extensions: &mut http::Extensions, session: Session<T>)
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+    Scope: 'static,
+{
+    extensions.insert(SessionScoped::<T, Scope> {
+        session,
+        scope: PhantomData,
+    });
+}
+
+/// A Session from a `SessionLayer` set up with `SessionLayer::with_scope`.
+///
+/// Lets more than one `SessionLayer` run on the same Request, like a short lived admin
+/// Session nested within the public site's Session. Each scope is a marker type and the
+/// Session is used through `Deref` like a normal `Session<T>`. Each layer's config must
+/// use its own Cookie or Header names.
+///
+/// # Examples
+/// ```rust ignore
+/// struct Admin;
+///
+/// let admin = Router::new()
+///     .route("/", get(|session: SessionScoped<SessionPgPool, Admin>| async move {
+///         session.set("last_admin_page", "/admin");
+///     }))
+///     .layer(SessionLayer::new(admin_store).with_scope::<Admin>());
+///
+/// let app = Router::new()
+///     .route("/", get(|session: Session<SessionPgPool>| async move { "public" }))
+///     .nest("/admin", admin)
+///     .layer(SessionLayer::new(public_store));
+/// ```
+///
+pub struct SessionScoped<T, Scope>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    pub(crate) session: Session<T>,
+    /// Uses a fn pointer so the marker type does not need to be Send, Sync or Clone.
+    pub(crate) scope: PhantomData<fn() -> Scope>,
+}
+
+impl<T, Scope> SessionScoped<T, Scope>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    /// Returns the inner Session.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let session: Session<SessionPgPool> = scoped.into_inner();
+    /// ```
+    ///
+    #[inline]
+    pub fn into_inner(self) -> Session<T> {
+        self.session
+    }
+}
+
+impl<T, Scope> Clone for SessionScoped<T, Scope>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
+            session: self.session.clone(),
+            scope: PhantomData,
+        }
+    }
+}
+
+impl<T, Scope> Debug for SessionScoped<T, Scope>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionScoped")
+            .field("scope", &std::any::type_name::<Scope>())
+            .field("session", &self.session)
+            .finish()
+    }
+}
+
+impl<T, Scope> Deref for SessionScoped<T, Scope>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    type Target = Session<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.session
+    }
+}
+
+/// Adds FromRequestParts<B> for SessionScoped
+///
+/// Returns the Session of the layer using the same Scope from Axums request extensions state.
+#[async_trait]
+impl<T, Scope, S> FromRequestParts<S> for SessionScoped<T, Scope>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+    Scope: 'static,
+    S: Send + Sync,
+{
+    type Rejection = (http::StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<SessionScoped<T, Scope>>()
+            .cloned()
+            .ok_or((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Can't extract Axum `SessionScoped`. Is `SessionLayer::with_scope` enabled?",
+            ))
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{SessionData, SessionError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{io::ErrorKind, path::Path};
+
+/// Version written to snapshot files. Files with another version are ignored.
+pub(crate) const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    version: u32,
+    sessions: Vec<SnapshotSession>,
+}
+
+/// Read first so files from another version are ignored even if they no longer parse.
+#[derive(Deserialize)]
+struct SnapshotVersion {
+    version: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotSession {
+    id: String,
+    expires: DateTime<Utc>,
+    session: SessionData,
+}
+
+/// Writes the Sessions to the snapshot file replacing it atomically.
+///
+/// # Errors
+/// - ['SessionError::SerdeJson'] is returned if a Session failed to Serialize.
+/// - ['SessionError::IO'] is returned if the file could not be written or renamed.
+///
+pub(crate) async fn write_snapshot(
+    path: &Path,
+    sessions: Vec<SessionData>,
+) -> Result<(), SessionError> {
+    let snapshot = Snapshot {
+        version: SNAPSHOT_VERSION,
+        sessions: sessions
+            .into_iter()
+            .map(|session| SnapshotSession {
+                id: session.id.clone(),
+                expires: session.expires,
+                session,
+            })
+            .collect(),
+    };
+    let bytes = serde_json::to_vec(&snapshot)?;
+
+    // Written next to the snapshot first so a crash while writing never leaves half a file.
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+
+    tokio::fs::write(&temp, bytes).await?;
+    tokio::fs::rename(&temp, path).await?;
+    Ok(())
+}
+
+/// Reads the Sessions unexpired at now from the snapshot file. Missing files return no Sessions
+/// while unreadable, corrupt or other version files are ignored with a warning.
+pub(crate) async fn read_snapshot(path: &Path, now: DateTime<Utc>) -> Vec<SessionData> {
+    let bytes = match tokio::fs::read(path).await {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Vec::new(),
+        Err(err) => {
+            tracing::warn!("Session snapshot could not be read, ignoring it: {}", err);
+            return Vec::new();
+        }
+    };
+
+    match serde_json::from_slice::<SnapshotVersion>(&bytes) {
+        Ok(SnapshotVersion { version }) if version == SNAPSHOT_VERSION => {}
+        Ok(SnapshotVersion { version }) => {
+            tracing::warn!(
+                "Session snapshot has version {} instead of {}, ignoring it",
+                version,
+                SNAPSHOT_VERSION
+            );
+            return Vec::new();
+        }
+        Err(err) => {
+            tracing::warn!("Session snapshot is corrupt, ignoring it: {}", err);
+            return Vec::new();
+        }
+    }
+
+    let snapshot: Snapshot = match serde_json::from_slice(&bytes) {
+        Ok(snapshot) => snapshot,
+        Err(err) => {
+            tracing::warn!("Session snapshot is corrupt, ignoring it: {}", err);
+            return Vec::new();
+        }
+    };
+
+    snapshot
+        .sessions
+        .into_iter()
+        .filter(|entry| entry.expires > now)
+        .map(|entry| {
+            let mut session = entry.session;
+            session.id = entry.id;
+            session.expires = entry.expires;
+            session
+        })
+        .collect()
+}
@@ -1,24 +1,68 @@
+#[cfg(feature = "zstd")]
+use crate::compression;
 use crate::{
-    DatabasePool, Session, SessionConfig, SessionData, SessionError, SessionID, SessionKey,
-    SessionTimers,
+    headers,
+    session_metrics::{CountCache, STATS_COUNT_INTERVAL},
+    session_snapshot,
+    session_watcher::EVENT_CAPACITY,
+    ConfigError, DatabasePool, InvalidationEvent, InvalidationTarget, MemoryStore, MetricsSnapshot,
+    RateLimitDecision, Session, SessionBroadcast, SessionConfig, SessionData, SessionError,
+    SessionEvent, SessionID, SessionKey, SessionMemory, SessionMetrics, SessionTimers,
+    SessionWatcher, StoreStats,
 };
 use async_trait::async_trait;
 use axum_core::extract::FromRequestParts;
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use dashmap::DashMap;
 #[cfg(feature = "key-store")]
 use fastbloom_rs::Deletable;
 #[cfg(feature = "key-store")]
 use fastbloom_rs::{CountingBloomFilter, FilterBuilder, Membership};
+use futures::StreamExt;
 use http::{self, request::Parts, StatusCode};
 use serde::Serialize;
 use std::{
     fmt::Debug,
+    future::Future,
     marker::{Send, Sync},
-    sync::Arc,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
 };
-use tokio::sync::RwLock;
-use uuid::Uuid;
+use tokio::sync::{broadcast, watch, Mutex, RwLock, Semaphore};
+
+/// Prefixed to Session data encrypted with `encrypt_at_rest` so unencrypted rows still load.
+/// Json and Base64 data can never start with this.
+pub(crate) const ENCRYPTED_MAGIC: &str = "enc:";
+
+/// Maximum number of deferred saves from `SessionConfig::with_deferred_save` running at once.
+pub(crate) const MAX_DEFERRED_SAVES: usize = 64;
+
+/// Sessions written or removed on the primary mapped to when the replica can serve them again.
+/// None marks a removed Session.
+pub(crate) type RecentWrites = DashMap<String, (DateTime<Utc>, Option<SessionData>)>;
+
+/// Result of loading a Session's stored data from the database.
+pub(crate) enum LoadedSession {
+    /// Boxed as SessionData is far larger than the other variants.
+    Found(Box<SessionData>),
+    /// Nothing is stored for the Session or it expired.
+    Missing,
+    /// Data is stored but could not be decoded or is larger than max_session_size.
+    Corrupt,
+}
+
+/// Orders the deferred saves of one Session so an older copy never lands after a newer one.
+#[derive(Debug, Default)]
+pub(crate) struct DeferredOrder {
+    /// Generation of the newest save queued for the Session.
+    queued: AtomicU64,
+    /// Held while a save runs so they reach the database one at a time.
+    writing: Mutex<()>,
+}
 
 /// Contains the main Services storage for all session's and database access for persistant Sessions.
 ///
@@ -30,26 +74,81 @@ use uuid::Uuid;
 /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
 /// ```
 ///
+/// Everything is kept behind one shared allocation so cloning the store for each request
+/// only bumps a reference count. Its fields, like `config` and `client`, are read through
+/// `Deref` to `SessionStoreInner`.
 #[derive(Clone, Debug)]
 pub struct SessionStore<T>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    pub(crate) shared: Arc<SessionStoreInner<T>>,
+}
+
+impl<T> Deref for SessionStore<T>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    type Target = SessionStoreInner<T>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.shared
+    }
+}
+
+/// The pool, config and memory shared by all clones of a `SessionStore`.
+#[derive(Clone, Debug)]
+pub struct SessionStoreInner<T>
 where
     T: DatabasePool + Clone + Debug + Sync + Send + 'static,
 {
     /// Client for the database.
     pub client: Option<T>,
-    /// locked Hashmap containing UserID and their session data.
-    pub(crate) inner: Arc<DashMap<String, SessionData>>,
+    /// Read replica used for loads and counts. Set with `with_read_pool`.
+    pub(crate) read_client: Option<T>,
+    /// How long written or removed Sessions are served from recent_writes instead of the replica.
+    pub(crate) replica_lag: Duration,
+    /// Sessions written or removed within the replica lag.
+    pub(crate) recent_writes: Arc<RecentWrites>,
+    /// Sessions loaded into memory by their ID.
+    pub(crate) inner: Arc<SessionMemory>,
+    /// Session ID's renewed by the id_renewal_interval mapped to their new ID and until
+    /// when they still lead to it.
+    pub(crate) renamed: Arc<DashMap<String, (String, DateTime<Utc>)>>,
     /// locked Hashmap containing KeyID and their Key data.
     pub(crate) keys: Arc<DashMap<String, SessionKey>>,
     /// Session Configuration.
     pub config: SessionConfig,
     /// Session Timers used for Clearing Memory and Database.
     pub(crate) timers: Arc<RwLock<SessionTimers>>,
+    /// Counters tracking Session usage.
+    pub(crate) metrics: Arc<SessionMetrics>,
+    /// Last database count taken by `stats` or `count` and when it was taken. None if the count failed.
+    pub(crate) count_cache: Arc<RwLock<CountCache>>,
+    /// Channels used to tell `SessionWatcher`s when their Session was renewed or destroyed.
+    pub(crate) watchers: Arc<DashMap<String, watch::Sender<Option<String>>>>,
+    /// Channels used to send `SessionEvent`s to the Session's subscribers.
+    pub(crate) events: Arc<DashMap<String, broadcast::Sender<SessionEvent>>>,
+    /// Limits how many deferred saves run at once.
+    pub(crate) deferred_saves: Arc<Semaphore>,
+    /// Orders the deferred saves of each Session by its ID.
+    pub(crate) deferred_order: Arc<DashMap<String, Arc<DeferredOrder>>>,
+    /// Set while the database is failing and Sessions are served from memory.
+    pub(crate) degraded: Arc<AtomicBool>,
     #[cfg(feature = "key-store")]
     /// Filter used to keep track of what uuid's exist.
     pub(crate) filter: Arc<RwLock<CountingBloomFilter>>,
+    /// Shares Session invalidations with other app nodes. Set with `with_broadcast`.
+    pub(crate) broadcast: Option<Arc<dyn SessionBroadcast>>,
+    /// ID of this app node so it ignores its own invalidation notifications.
+    pub(crate) node_id: Arc<str>,
 }
 
+/// Adds FromRequestParts<B> for SessionStore
+///
+/// Returns the SessionStore of the current Session so handlers can use store level
+/// functions like `destroy_session` or `count`.
 #[async_trait]
 impl<T, S> FromRequestParts<S> for SessionStore<T>
 where
@@ -73,7 +172,13 @@ where
     T: DatabasePool + Clone + Debug + Sync + Send + 'static,
 {
     /// Constructs a New `SessionStore` and Creates the Database Table
-    /// needed for the Session if it does not exist if client is not `None`.
+    /// needed for the Session if it does not exist if client is not `None`
+    /// and `SessionConfig::with_auto_migrate` is not disabled.
+    ///
+    /// # Errors
+    /// - ['SessionError::Config'] is returned if the config fails `SessionConfig::validate`
+    ///   or the client does not accept the table name, unless lenient validation is enabled.
+    /// - ['SessionError::Sqlx'] is returned if the table could not be created.
     ///
     /// # Examples
     /// ```rust ignore
@@ -85,7 +190,26 @@ where
     ///
     #[inline]
     pub async fn new(client: Option<T>, config: SessionConfig) -> Result<Self, SessionError> {
-        if let Some(client) = &client {
+        if config.lenient_validation {
+            config.validate_lenient();
+        } else {
+            config.validate()?;
+        }
+
+        if client
+            .as_ref()
+            .is_some_and(|client| !client.accepts_table_name(&config.table_name))
+        {
+            let err = ConfigError::InvalidTableName(config.table_name.to_string());
+
+            if config.lenient_validation {
+                tracing::warn!("Session config is invalid: {}", err);
+            } else {
+                return Err(err.into());
+            }
+        }
+
+        if let Some(client) = client.as_ref().filter(|_| config.auto_migrate) {
             client.initiate(&config.table_name).await?
         }
 
@@ -94,20 +218,253 @@ where
         #[cfg(feature = "key-store")]
         let filter = Self::create_filter(&client, &config).await?;
 
-        Ok(Self {
+        let snapshot_interval = config
+            .snapshot
+            .as_ref()
+            .map_or(Duration::zero(), |(_, interval)| *interval);
+        let now = config.now();
+
+        let shared = Arc::new(SessionStoreInner {
             client,
-            inner: Default::default(),
+            read_client: None,
+            replica_lag: Duration::zero(),
+            recent_writes: Default::default(),
+            inner: Arc::new(SessionMemory::new(&config)),
+            renamed: Default::default(),
             keys: Default::default(),
             config,
             timers: Arc::new(RwLock::new(SessionTimers {
                 // the first expiry sweep is scheduled one lifetime from start-up
-                last_expiry_sweep: Utc::now() + Duration::hours(1),
+                last_expiry_sweep: now + Duration::hours(1),
                 // the first expiry sweep is scheduled one lifetime from start-up
-                last_database_expiry_sweep: Utc::now() + Duration::hours(6),
+                last_database_expiry_sweep: now + Duration::hours(6),
+                last_database_cleanup: None,
+                // the first snapshot is scheduled one interval from start-up
+                last_snapshot: now + snapshot_interval,
             })),
+            count_cache: Default::default(),
+            metrics: Default::default(),
+            watchers: Default::default(),
+            events: Default::default(),
+            deferred_saves: Arc::new(Semaphore::new(MAX_DEFERRED_SAVES)),
+            deferred_order: Default::default(),
+            degraded: Default::default(),
             #[cfg(feature = "key-store")]
             filter: Arc::new(RwLock::new(filter)),
-        })
+            broadcast: None,
+            node_id: uuid::Uuid::new_v4().to_string().into(),
+        });
+        let store = Self { shared };
+
+        if !store.is_persistent() {
+            store.load_snapshot().await;
+        }
+
+        Ok(store)
+    }
+
+    /// Writes the unexpired Sessions in memory to the snapshot file set with
+    /// `SessionConfig::with_snapshot`. This already runs every interval but can be called
+    /// during a graceful shutdown so no changes are lost.
+    ///
+    /// Does nothing if no snapshot file is set.
+    ///
+    /// # Errors
+    /// - ['SessionError::IO'] is returned if the file could not be written.
+    /// - ['SessionError::SerdeJson'] is returned if a Session failed to Serialize.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session_store.snapshot().await.unwrap();
+    /// ```
+    ///
+    pub async fn snapshot(&self) -> Result<(), SessionError> {
+        let Some((path, _)) = &self.config.snapshot else {
+            return Ok(());
+        };
+
+        let now = self.config.now();
+        let mut sessions = Vec::new();
+
+        self.inner.for_each(|_, session| {
+            if session.store && !session.destroy && session.expires > now {
+                sessions.push(session.clone());
+            }
+        });
+
+        session_snapshot::write_snapshot(path, sessions).await
+    }
+
+    /// Loads the Sessions from the snapshot file into memory if one is set.
+    async fn load_snapshot(&self) {
+        let Some((path, _)) = &self.config.snapshot else {
+            return;
+        };
+
+        let sessions = session_snapshot::read_snapshot(path, self.config.now()).await;
+        tracing::debug!(count = sessions.len(), "Sessions loaded from the snapshot");
+
+        #[cfg(feature = "key-store")]
+        let mut filter = self.filter.write().await;
+
+        for mut session in sessions {
+            session.serializer = self.config.serializer;
+            session.max_size = self.config.max_session_size;
+            session.autoremove = self.config.now() + self.config.memory_lifespan;
+            session.store = true;
+            session.migrate_schema(&self.config.schema_migrators);
+
+            #[cfg(feature = "key-store")]
+            if self.config.use_bloom_filters {
+                filter.add(session.id.as_bytes());
+            }
+
+            self.inner.insert(session.id.clone(), session);
+        }
+    }
+
+    /// Sends Session loads and counts to a read replica while stores and deletes go to the
+    /// primary client. This is opt-in and does nothing without a primary client.
+    ///
+    /// Replicas lag behind the primary so Sessions stored or removed within max_lag are served
+    /// from memory instead of the replica. Set max_lag to the longest lag you expect. After it
+    /// has passed, loads read whatever the replica has, so a replica lagging further can return
+    /// stale Sessions or ones that were destroyed. Writes made by other app nodes are not known
+    /// to this store, and counts always come from the replica so they can lag behind.
+    /// Per-Session keys are still loaded from the primary.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionConfig, SessionStore};
+    /// use chrono::Duration;
+    ///
+    /// let session_store = SessionStore::new(Some(primary.into()), SessionConfig::default())
+    ///     .await
+    ///     .unwrap()
+    ///     .with_read_pool(replica.into(), Duration::seconds(5));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_read_pool(mut self, pool: T, max_lag: Duration) -> Self {
+        let shared = Arc::make_mut(&mut self.shared);
+        shared.read_client = Some(pool);
+        shared.replica_lag = max_lag;
+        self
+    }
+
+    /// Shares Session destroys, ID renewals and store clears with the other app nodes using the
+    /// broadcast, so they evict those Sessions from memory instead of serving them until they expire.
+    /// Call `spawn_broadcast_listener` to apply the events sent by the other nodes.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionConfig, SessionRedisBroadcast, SessionStore};
+    ///
+    /// let broadcast = SessionRedisBroadcast::new(client, "session_invalidation");
+    /// let session_store = SessionStore::new(Some(pool.into()), SessionConfig::default())
+    ///     .await
+    ///     .unwrap()
+    ///     .with_broadcast(broadcast);
+    /// let listener = session_store.spawn_broadcast_listener().await.unwrap();
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_broadcast(mut self, broadcast: impl SessionBroadcast + 'static) -> Self {
+        Arc::make_mut(&mut self.shared).broadcast = Some(Arc::new(broadcast));
+        self
+    }
+
+    /// Spawns a task that applies the `InvalidationEvent`s sent by the other app nodes to this
+    /// node's memory. Destroyed Sessions are evicted even if a request is using them so it
+    /// will not save them again.
+    ///
+    /// Returns None if no broadcast is set.
+    ///
+    /// # Errors
+    /// - ['SessionError'] is returned if the broadcast failed to subscribe.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let listener = session_store.spawn_broadcast_listener().await.unwrap();
+    /// ```
+    ///
+    pub async fn spawn_broadcast_listener(
+        &self,
+    ) -> Result<Option<tokio::task::JoinHandle<()>>, SessionError> {
+        let Some(broadcast) = &self.broadcast else {
+            return Ok(None);
+        };
+
+        let mut events = broadcast.subscribe().await?;
+        let store = self.clone();
+
+        Ok(Some(tokio::spawn(async move {
+            while let Some(event) = events.next().await {
+                store.apply_invalidation(event).await;
+            }
+        })))
+    }
+
+    /// Publishes the invalidation to the other app nodes. Failures are only logged as the
+    /// change was already made.
+    pub(crate) async fn publish_invalidation(&self, target: InvalidationTarget) {
+        if let Some(broadcast) = &self.broadcast {
+            let event = InvalidationEvent {
+                node_id: self.node_id.to_string(),
+                target,
+            };
+
+            if let Err(err) = broadcast.publish(event).await {
+                tracing::warn!("Session invalidation failed to publish: {}", err);
+            }
+        }
+    }
+
+    /// Evicts what another app node invalidated from memory, ignoring this node's own events.
+    pub(crate) async fn apply_invalidation(&self, event: InvalidationEvent) {
+        if event.node_id == *self.node_id {
+            return;
+        }
+
+        match event.target {
+            InvalidationTarget::Session(id) => {
+                self.inner.remove(&id);
+                self.keys.remove(&id);
+                self.recent_writes.remove(&id);
+                self.emit(&id, || SessionEvent::Destroyed);
+                self.notify_destroyed(&id);
+
+                #[cfg(feature = "key-store")]
+                if self.config.use_bloom_filters {
+                    let mut filter = self.filter.write().await;
+                    filter.remove(id.as_bytes());
+                }
+            }
+            InvalidationTarget::All => {
+                self.inner.clear();
+                self.keys.clear();
+                self.recent_writes.clear();
+            }
+        }
+    }
+
+    /// Returns the client used for loads and counts, which is the read replica if one is set.
+    #[inline]
+    fn read_client(&self) -> Option<&T> {
+        self.client
+            .as_ref()
+            .map(|client| self.read_client.as_ref().unwrap_or(client))
+    }
+
+    /// Remembers a Session stored or removed on the primary so loads do not read it from a
+    /// replica that has not caught up yet.
+    fn record_write(&self, id: &str, session: Option<&SessionData>) {
+        if self.read_client.is_some() && self.config.memory {
+            self.recent_writes.insert(
+                id.to_owned(),
+                (self.config.now() + self.replica_lag, session.cloned()),
+            );
+        }
     }
 
     /// Used to create and Fill the Filter.
@@ -134,6 +491,95 @@ where
         Ok(filter)
     }
 
+    /// Returns a snapshot of the SessionStore's usage counters.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+    /// let created = session_store.metrics().created;
+    /// ```
+    ///
+    #[inline]
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.metrics.snapshot()
+    }
+
+    /// Returns the current gauges of the SessionStore.
+    ///
+    /// The database count is cached and only refreshed once every 30 seconds
+    /// so frequent polling does not hammer the database.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+    /// let stats = session_store.stats().await;
+    /// ```
+    ///
+    pub async fn stats(&self) -> StoreStats {
+        let now = self.config.now();
+
+        let database_sessions = if self.is_persistent() {
+            let mut cache = self.count_cache.write().await;
+
+            match *cache {
+                Some((taken, count)) if now - taken < Duration::seconds(STATS_COUNT_INTERVAL) => {
+                    count
+                }
+                _ => {
+                    let count = self.database_count().await.ok();
+                    *cache = Some((now, count));
+                    count
+                }
+            }
+        } else {
+            None
+        };
+
+        let expired_in_memory = self
+            .inner
+            .count(|session| session.autoremove < now || !session.validate(now));
+
+        StoreStats {
+            memory_sessions: self.inner.len(),
+            expired_in_memory,
+            database_sessions,
+            database_healthy: self.is_persistent().then_some(database_sessions.is_some()),
+            last_database_cleanup: self.timers.read().await.last_database_cleanup,
+            sampled_at: now,
+        }
+    }
+
+    /// Spawns a task that samples `stats` on every interval and exports them as
+    /// `metrics` crate gauges.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let sampler = session_store.spawn_stats_sampler(std::time::Duration::from_secs(15));
+    /// ```
+    ///
+    #[cfg(feature = "metrics")]
+    pub fn spawn_stats_sampler(
+        &self,
+        interval: std::time::Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        let store = self.clone();
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+
+            loop {
+                ticker.tick().await;
+                store.stats().await.record();
+            }
+        })
+    }
+
     /// Checks if the database is in persistent mode.
     ///
     /// Returns true if client is Some().
@@ -152,6 +598,77 @@ where
         self.client.is_some()
     }
 
+    /// Checks if the database is failing and Sessions are served from memory.
+    /// Only ever true with `SessionConfig::with_fallback_to_memory`.
+    ///
+    /// Returns true from a failed load or save until one succeeds again.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    ///
+    /// let config = SessionConfig::default().with_fallback_to_memory(true);
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+    /// let is_degraded = session_store.is_degraded();
+    /// ```
+    ///
+    #[inline]
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Relaxed)
+    }
+
+    /// Marks the database as failing so Sessions are served from memory until it recovers.
+    pub(crate) fn database_failed(&self, err: &SessionError) {
+        self.degraded.store(true, Ordering::Relaxed);
+        tracing::warn!(
+            "Session database failed, serving Sessions from memory: {}",
+            err
+        );
+    }
+
+    /// Marks the database as working again after a successful load or save.
+    pub(crate) fn database_recovered(&self) {
+        if self.degraded.swap(false, Ordering::Relaxed) {
+            tracing::info!("Session database recovered, saving Sessions again");
+        }
+    }
+
+    /// Unwraps the result of a database call made while serving a Request. With
+    /// fallback_to_memory a failure marks the database as failing and returns the default
+    /// instead of panicking.
+    pub(crate) fn or_fallback<R: Default>(&self, result: Result<R, SessionError>) -> R {
+        match result {
+            Err(err) if self.config.fallback_to_memory && self.is_persistent() => {
+                self.database_failed(&err);
+                R::default()
+            }
+            result => result.unwrap(),
+        }
+    }
+
+    /// Returns if the Session must stay in memory because its changes failed to save
+    /// while the database was failing.
+    #[inline]
+    pub(crate) fn keeps_unsaved(&self, session: &SessionData) -> bool {
+        self.config.fallback_to_memory && self.is_persistent() && session.store && session.update
+    }
+
+    /// Keeps a Session that failed to save in memory marked for an update so its next
+    /// Request saves it once the database recovers, instead of it being lost.
+    pub(crate) fn keep_unsaved(&self, mut session: SessionData, err: &SessionError) {
+        self.database_failed(err);
+
+        if let Some(mut sess) = self.inner.get_mut(&session.id) {
+            sess.update = true;
+            return;
+        }
+
+        // It was already unloaded after its Request.
+        session.update = true;
+        session.requests = 0;
+        self.inner.insert_if_absent(session.id.clone(), session);
+    }
+
     /// Cleans Expired sessions from the Database based on Utc::now().
     ///
     /// If client is None it will return Ok(()).
@@ -173,15 +690,44 @@ where
     #[inline]
     pub async fn cleanup(&self) -> Result<Vec<String>, SessionError> {
         if let Some(client) = &self.client {
-            Ok(client.delete_by_expiry(&self.config.table_name).await?)
+            self.timeout(client.delete_by_expiry(&self.config.table_name))
+                .await
         } else {
             Ok(Vec::new())
         }
     }
 
+    /// Creates the Session table named by the config if it does not exist.
+    /// This is safe to call more than once.
+    ///
+    /// `SessionStore::new` already does this unless `SessionConfig::with_auto_migrate` is disabled.
+    /// If client is None it will return Ok(()).
+    ///
+    /// # Errors
+    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let config = SessionConfig::default().with_auto_migrate(false);
+    /// let session_store = SessionStore::<SessionPgPool>::new(Some(pool.into()), config).await?;
+    /// session_store.migrate().await?;
+    /// ```
+    ///
+    #[inline]
+    pub async fn migrate(&self) -> Result<(), SessionError> {
+        if let Some(client) = &self.client {
+            self.timeout(client.initiate(&self.config.table_name))
+                .await?;
+        }
+
+        Ok(())
+    }
+
     /// Returns count of existing sessions within database.
     ///
     /// If client is None it will return Ok(0).
+    /// If `SessionConfig::with_count_cache_ttl` is set the count is cached and may lag
+    /// behind the database by up to the TTL.
     ///
     /// # Errors
     /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
@@ -199,63 +745,67 @@ where
     ///
     #[inline]
     pub async fn count(&self) -> Result<i64, SessionError> {
-        if let Some(client) = &self.client {
-            let count = client.count(&self.config.table_name).await?;
-            return Ok(count);
+        let Some(ttl) = self.config.count_cache_ttl else {
+            return self.database_count().await;
+        };
+
+        // Held while counting so only one caller refreshes the cache at a time.
+        let mut cache = self.count_cache.write().await;
+
+        // Failed counts cached by `stats` are counted again.
+        if let Some((taken, Some(count))) = *cache {
+            if self.config.now() - taken < ttl {
+                return Ok(count);
+            }
+        }
+
+        let count = self.database_count().await?;
+        *cache = Some((self.config.now(), Some(count)));
+        Ok(count)
+    }
+
+    /// private internal function that counts the Sessions within the database skipping the cache.
+    async fn database_count(&self) -> Result<i64, SessionError> {
+        if let Some(client) = self.read_client() {
+            return self.retry(|| client.count(&self.config.table_name)).await;
         }
 
         Ok(0)
     }
 
-    /// private internal function that loads a session's data from the database using a UUID string.
+    /// Returns count of sessions within the database that have not yet expired.
     ///
-    /// If client is None it will return Ok(None).
+    /// If client is None it will return Ok(0).
     ///
     /// # Errors
     /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
-    /// - ['SessionError::SerdeJson'] is returned if it failed to deserialize the sessions data.
     ///
     /// # Examples
     /// ```rust ignore
     /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
-    /// use uuid::Uuid;
     ///
     /// let config = SessionConfig::default();
     /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
-    /// let token = Uuid::new_v4();
     /// async {
-    ///     let session_data = session_store.load_session(token.to_string()).await.unwrap();
+    ///     let count = session_store.count_active().await.unwrap();
     /// };
     /// ```
     ///
-    pub(crate) async fn load_session(
-        &self,
-        cookie_value: String,
-    ) -> Result<Option<SessionData>, SessionError> {
-        if let Some(client) = &self.client {
-            let result: Option<String> =
-                client.load(&cookie_value, &self.config.table_name).await?;
-
-            if let Ok(uuid) = Uuid::parse_str(&cookie_value) {
-                if let Some(mut session) = result
-                    .map(|session| serde_json::from_str::<SessionData>(&session))
-                    .transpose()?
-                {
-                    session.id = uuid;
-                    return Ok(Some(session));
-                }
-            }
+    #[inline]
+    pub async fn count_active(&self) -> Result<i64, SessionError> {
+        if let Some(client) = self.read_client() {
+            return self
+                .retry(|| client.count_active(&self.config.table_name))
+                .await;
         }
 
-        Ok(None)
+        Ok(0)
     }
 
-    /// private internal function that loads an encryption key for the session's cookie from the database using a UUID string.
-    ///
-    /// If client is None it will return Ok(None).
+    /// Checks if a Session exists for the given ID and that it has not yet expired.
     ///
-    /// # Errors
-    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
+    /// Checks Memory first and then the Database if client is Some().
+    /// Returns false if the database lookup fails.
     ///
     /// # Examples
     /// ```rust ignore
@@ -264,33 +814,346 @@ where
     ///
     /// let config = SessionConfig::default();
     /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
-    /// let token = Uuid::new_v4();
-    /// let key = Key::generate();
+    /// let token = Uuid::new_v4().to_string();
     /// async {
-    ///     let session_key = session_store.load_key(token.to_string(), key).await.unwrap();
+    ///     let exists = session_store.exists(&token).await;
     /// };
     /// ```
     ///
-    pub(crate) async fn load_key(
-        &self,
-        cookie_value: String,
-    ) -> Result<Option<SessionKey>, SessionError> {
-        if let Some(client) = &self.client {
-            let result: Option<String> =
-                client.load(&cookie_value, &self.config.table_name).await?;
+    pub async fn exists(&self, id: &str) -> bool {
+        if let Some(instance) = self.inner.get(id) {
+            return instance.validate(self.config.now());
+        }
 
-            let uuid = SessionID::new(Uuid::parse_str(cookie_value.as_str())?);
-            if let Some(value) = result {
-                return Ok(Some(SessionKey::decrypt(
-                    uuid,
-                    &value,
-                    self.config.database_key.clone().unwrap(),
-                    self.config.memory_lifespan,
-                )?));
-            }
+        if let Some(client) = &self.client {
+            return self
+                .retry(|| client.exists(id, &self.config.table_name))
+                .await
+                .unwrap_or(false);
         }
 
-        Ok(None)
+        false
+    }
+
+    /// Returns a copy of the Session's data for the given ID without loading it into memory.
+    ///
+    /// Checks Memory first and then the Database if client is Some().
+    /// Returns Ok(None) if the Session does not exist or has expired.
+    ///
+    /// # Errors
+    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+    /// async {
+    ///     if let Some(data) = session_store.get_session_data(&id).await.unwrap() {
+    ///         let user_id: Option<i64> = data.get("user_id");
+    ///     }
+    /// };
+    /// ```
+    ///
+    pub async fn get_session_data(&self, id: &str) -> Result<Option<SessionData>, SessionError> {
+        if let Some(instance) = self.inner.get(id) {
+            return Ok(instance
+                .validate(self.config.now())
+                .then(|| instance.clone()));
+        }
+
+        self.load_session(id.to_owned()).await
+    }
+
+    /// Destroys the Session for the given ID removing it from memory and the database.
+    /// Useful to log out a user from another Session like an admin panel.
+    ///
+    /// Any request currently using the Session will not save it again.
+    /// Other app nodes evict it from memory too if a broadcast is set with `with_broadcast`.
+    ///
+    /// # Errors
+    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+    /// async {
+    ///     session_store.destroy_session(&id).await.unwrap();
+    /// };
+    /// ```
+    ///
+    pub async fn destroy_session(&self, id: &str) -> Result<(), SessionError> {
+        if self.inner.remove(id).is_some() {
+            self.metrics.destroyed();
+        }
+
+        self.emit(id, || SessionEvent::Destroyed);
+        self.notify_destroyed(id);
+
+        #[cfg(feature = "key-store")]
+        if self.config.use_bloom_filters {
+            let mut filter = self.filter.write().await;
+            filter.remove(id.as_bytes());
+        }
+
+        self.database_remove_session(id.to_owned()).await
+    }
+
+    /// Loads the Session for the given ID or creates a new one if none exists, for use outside
+    /// of a request like within a background job.
+    ///
+    /// The Session is kept in memory so the next request using the ID sees any changes.
+    /// With `SessionConfig::with_memory` disabled it is only held by the returned Session
+    /// instead. Use `Session::save` to persist the changes to the database.
+    ///
+    /// # Errors
+    /// - ['SessionError::InvalidSessionID'] is returned if the ID fails the configs id validator.
+    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+    /// async {
+    ///     let session = session_store.load_or_create(&user_session_id).await.unwrap();
+    ///     session.set("notification", true);
+    ///     session.save().await.unwrap();
+    /// };
+    /// ```
+    ///
+    pub async fn load_or_create(&self, id: &str) -> Result<Session<T>, SessionError> {
+        if !(self.config.id_validator)(id) {
+            return Err(SessionError::InvalidSessionID);
+        }
+
+        let store = if self.config.memory {
+            self.clone()
+        } else {
+            self.scoped()
+        };
+        let (mut session, _) = Session::new(store.clone(), Some(id.to_owned())).await;
+
+        if let Some(mut inner) = store.inner.get_mut(id) {
+            inner.service_clear(&self.config);
+            self.metrics.loaded_memory();
+            return Ok(session);
+        }
+
+        let mut sess = match self.load_session(id.to_owned()).await? {
+            Some(sess) => sess,
+            None => {
+                session.is_new = true;
+                self.metrics.created();
+                SessionData::new(id.to_owned(), true, &self.config)
+            }
+        };
+
+        sess.autoremove = self.config.now() + self.config.memory_lifespan;
+        sess.last_accessed = self.config.now();
+        sess.store = true;
+        sess.update = true;
+        sess.requests = 0;
+
+        // A request could have loaded the Session while we were waiting on the database.
+        store.inner.insert_if_absent(id.to_owned(), sess);
+        Ok(session)
+    }
+
+    /// Calls f with the ID and data of every unexpired Session held in memory, like to
+    /// list the active Sessions in an admin view.
+    ///
+    /// Each shard of the memory store is only locked while its own Sessions are visited so
+    /// Requests are not held up for the whole walk. This means the Sessions seen are not a
+    /// consistent snapshot, as ones created, changed or removed while iterating may or may
+    /// not be seen. f must not use the store or any Session or it may deadlock.
+    ///
+    /// Only memory only stores hold every Session in memory. Persistent stores only keep a
+    /// Session in memory for `memory_lifespan` after it was last used, 60 minutes by default,
+    /// so a walk would miss the rest. List those from the database table instead.
+    ///
+    /// # Errors
+    /// - ['SessionError::GenericNotSupportedError'] is returned if the store is persistent.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+    /// let mut active = Vec::new();
+    ///
+    /// session_store.for_each_session(|id, data| {
+    ///     // Only part of the ID is kept so full Session ID's do not end up in the admin view.
+    ///     let short_id: String = id.chars().take(8).collect();
+    ///     active.push((short_id, data.last_accessed()));
+    /// })?;
+    /// ```
+    ///
+    pub fn for_each_session<F>(&self, mut f: F) -> Result<(), SessionError>
+    where
+        F: FnMut(&str, &SessionData),
+    {
+        if self.is_persistent() {
+            return Err(SessionError::GenericNotSupportedError(
+                "for_each_session is only supported by memory only stores.".to_owned(),
+            ));
+        }
+
+        let now = self.config.now();
+
+        self.inner.for_each(|id, session| {
+            if session.validate(now) {
+                f(id, session);
+            }
+        });
+
+        Ok(())
+    }
+
+    /// private internal function that loads a session's data from the database using an ID string.
+    ///
+    /// If client is None it will return Ok(None).
+    /// If the sessions data fails to deserialize it is logged and Ok(None) is returned.
+    ///
+    /// # Errors
+    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    /// use uuid::Uuid;
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+    /// let token = Uuid::new_v4();
+    /// async {
+    ///     let session_data = session_store.load_session(token.to_string()).await.unwrap();
+    /// };
+    /// ```
+    ///
+    pub(crate) async fn load_session(
+        &self,
+        cookie_value: String,
+    ) -> Result<Option<SessionData>, SessionError> {
+        Ok(match self.try_load_session(cookie_value).await? {
+            LoadedSession::Found(session) => Some(*session),
+            LoadedSession::Missing | LoadedSession::Corrupt => None,
+        })
+    }
+
+    /// private internal function that loads a session like `load_session` but tells
+    /// Sessions that do not exist apart from ones whose stored data could not be read.
+    pub(crate) async fn try_load_session(
+        &self,
+        cookie_value: String,
+    ) -> Result<LoadedSession, SessionError> {
+        if let Some(client) = self.read_client() {
+            if let Some(entry) = self
+                .recent_writes
+                .get(&cookie_value)
+                .filter(|entry| entry.0 > self.config.now())
+            {
+                return Ok(entry.1.clone().map_or(LoadedSession::Missing, |session| {
+                    LoadedSession::Found(Box::new(session))
+                }));
+            }
+
+            let result: Option<String> = self
+                .retry(|| client.load(&cookie_value, &self.config.table_name))
+                .await?;
+            self.database_recovered();
+
+            if let Some(session) = result {
+                // Sessions that fail to decode, like ones stored with a different serializer,
+                // are reported as corrupt instead of failing.
+                match self.decode_session(&cookie_value, session) {
+                    Ok(session)
+                        if self
+                            .config
+                            .max_session_size
+                            .is_some_and(|max_size| session.size() > max_size) =>
+                    {
+                        tracing::warn!(
+                            "Session data larger than max_session_size, treating it as corrupt"
+                        );
+                        return Ok(LoadedSession::Corrupt);
+                    }
+                    Ok(mut session) => {
+                        self.metrics.loaded_db();
+                        session.id = cookie_value;
+                        session.serializer = self.config.serializer;
+                        session.max_size = self.config.max_session_size;
+
+                        let from = session.schema_version;
+                        if session.migrate_schema(&self.config.schema_migrators) {
+                            tracing::debug!(
+                                from,
+                                to = session.schema_version,
+                                "Session data migrated to the current schema version"
+                            );
+                        }
+
+                        return Ok(LoadedSession::Found(Box::new(session)));
+                    }
+                    Err(err) => {
+                        tracing::warn!("Session data failed to deserialize: {}", err);
+                        return Ok(LoadedSession::Corrupt);
+                    }
+                }
+            }
+        }
+
+        Ok(LoadedSession::Missing)
+    }
+
+    /// private internal function that loads an encryption key for the session's cookie from the database using an ID string.
+    ///
+    /// If client is None it will return Ok(None).
+    ///
+    /// # Errors
+    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    /// use uuid::Uuid;
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+    /// let token = Uuid::new_v4();
+    /// let key = Key::generate();
+    /// async {
+    ///     let session_key = session_store.load_key(token.to_string(), key).await.unwrap();
+    /// };
+    /// ```
+    ///
+    pub(crate) async fn load_key(
+        &self,
+        cookie_value: String,
+    ) -> Result<Option<SessionKey>, SessionError> {
+        if let Some(client) = &self.client {
+            let result: Option<String> = self
+                .retry(|| client.load(&cookie_value, &self.config.table_name))
+                .await?;
+
+            let id = SessionID::new(cookie_value);
+            if let Some(value) = result {
+                return Ok(Some(SessionKey::decrypt(
+                    id,
+                    &value,
+                    self.config.database_key.clone().unwrap(),
+                    self.config.now() + self.config.memory_lifespan,
+                )?));
+            }
+        }
+
+        Ok(None)
     }
 
     /// private internal function that stores a session's data to the database.
@@ -299,7 +1162,7 @@ where
     ///
     /// # Errors
     /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
-    /// - ['SessionError::SerdeJson'] is returned if it failed to serialize the sessions data.
+    /// - ['SessionError::SerdeJson'] is returned if the serializer failed to serialize the sessions data.
     ///
     /// # Examples
     /// ```rust ignore
@@ -318,19 +1181,273 @@ where
     ///
     pub(crate) async fn store_session(&self, session: &SessionData) -> Result<(), SessionError> {
         if let Some(client) = &self.client {
-            client
-                .store(
-                    &session.id.to_string(),
-                    &serde_json::to_string(session)?,
-                    session.expires.timestamp(),
-                    &self.config.table_name,
-                )
+            let data = self.encode_session(session)?;
+            tracing::Span::current().record("payload_size", data.len());
+
+            let id = session.id.to_string();
+            let expires = session.expires.timestamp();
+
+            self.retry(|| client.store(&id, &data, expires, &self.config.table_name))
                 .await?;
+            self.metrics.saved();
+            self.database_recovered();
+            self.record_write(&id, Some(session));
+
+            #[cfg(any(feature = "postgres-rustls", feature = "postgres-native"))]
+            self.notify_invalidation(&session.id).await;
         }
 
         Ok(())
     }
 
+    /// Creates a watcher for the Session reusing its channel if it is already watched.
+    pub(crate) fn watch(&self, id: String) -> SessionWatcher<T> {
+        let receiver = self
+            .watchers
+            .entry(id.clone())
+            .or_insert_with(|| watch::channel(Some(id)).0)
+            .subscribe();
+
+        SessionWatcher {
+            store: self.clone(),
+            receiver,
+        }
+    }
+
+    /// Subscribes to the changes made to the Session for the given ID on this app node.
+    ///
+    /// Receivers that fall more than 64 events behind get `RecvError::Lagged` and skip
+    /// to the oldest event still held. Once the Session is destroyed the receiver
+    /// gets `SessionEvent::Destroyed` followed by `RecvError::Closed`.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+    /// let mut events = session_store.subscribe(&id);
+    ///
+    /// while let Ok(event) = events.recv().await {
+    ///     println!("{:?}", event);
+    /// }
+    /// ```
+    ///
+    pub fn subscribe(&self, id: &str) -> broadcast::Receiver<SessionEvent> {
+        self.events
+            .entry(id.to_owned())
+            .or_insert_with(|| broadcast::channel(EVENT_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// Sends the event to the Session's subscribers. The event is only built if there are any.
+    pub(crate) fn emit(&self, id: &str, event: impl FnOnce() -> SessionEvent) {
+        if let Some(sender) = self.events.get(id) {
+            let _ = sender.send(event());
+        }
+    }
+
+    /// Wakes the Session's watchers letting them know it was destroyed.
+    pub(crate) fn notify_destroyed(&self, id: &str) {
+        if let Some((_, sender)) = self.watchers.remove(id) {
+            sender.send_replace(None);
+        }
+
+        // Dropping the sender closes the subscribers after the Destroyed event.
+        let _ = self.events.remove(id);
+    }
+
+    /// Moves the Session's watchers and subscribers over to its renewed ID.
+    pub(crate) fn notify_renewed(&self, old_id: &str, new_id: String) {
+        if let Some((_, sender)) = self.watchers.remove(old_id) {
+            sender.send_replace(Some(new_id.clone()));
+            self.watchers.insert(new_id.clone(), sender);
+        }
+
+        if let Some((_, sender)) = self.events.remove(old_id) {
+            self.events.insert(new_id, sender);
+        }
+    }
+
+    /// Lets the old ID of a Session renewed by the id_renewal_interval lead to the new ID
+    /// for the grace window.
+    pub(crate) fn record_renamed(&self, old_id: String, new_id: String) {
+        if self.config.id_renewal_grace > Duration::zero() {
+            self.renamed.insert(
+                old_id,
+                (new_id, self.config.now() + self.config.id_renewal_grace),
+            );
+        }
+    }
+
+    /// Gets the ID a renewed Session ID leads to while within its grace window.
+    pub(crate) fn renamed_to(&self, id: &str) -> Option<String> {
+        self.renamed
+            .get(id)
+            .filter(|renamed| renamed.1 > self.config.now())
+            .map(|renamed| renamed.0.clone())
+    }
+
+    /// Queues a deferred save of the Session behind the ones queued before it. The save is
+    /// skipped if a newer one was queued while it waited, as that one writes a newer copy.
+    pub(crate) fn queue_save<F>(&self, id: String, save: F) -> impl Future<Output = ()>
+    where
+        F: Future<Output = ()>,
+    {
+        // The generation is taken under the entry's lock so it can not be removed in between.
+        let (order, generation) = {
+            let order = self.deferred_order.entry(id.clone()).or_default();
+            let generation = order.queued.fetch_add(1, Ordering::SeqCst) + 1;
+            (order.clone(), generation)
+        };
+        let deferred_order = self.deferred_order.clone();
+
+        async move {
+            let writing = order.writing.lock().await;
+
+            if order.queued.load(Ordering::SeqCst) == generation {
+                save.await;
+            }
+
+            drop(writing);
+            deferred_order.remove_if(&id, |_, order| {
+                order.queued.load(Ordering::SeqCst) == generation
+            });
+        }
+    }
+
+    /// Tells other app nodes listening on the invalidation channel to evict the Session.
+    /// Failures are only logged as the Session was already written.
+    #[cfg(any(feature = "postgres-rustls", feature = "postgres-native"))]
+    pub(crate) async fn notify_invalidation(&self, id: &str) {
+        if let (Some(client), Some(channel)) = (&self.client, &self.config.invalidation_channel) {
+            let payload = format!("{}:{}", self.node_id, id);
+
+            if let Err(err) = self.timeout(client.notify(channel, &payload)).await {
+                tracing::warn!("Session invalidation failed to notify: {}", err);
+            }
+        }
+    }
+
+    /// Evicts the Session or Key named in an invalidation payload from memory unless it was
+    /// sent by this node. Sessions still in use by a request are evicted too, so the request
+    /// does not save its stale copy over the newer one and the next request reloads it.
+    #[cfg(any(feature = "postgres-rustls", feature = "postgres-native"))]
+    pub(crate) fn evict_invalidated(&self, payload: &str) {
+        if let Some((node_id, id)) = payload.split_once(':') {
+            if node_id != &*self.node_id {
+                self.inner.remove(id);
+                self.keys.remove(id);
+                self.recent_writes.remove(id);
+            }
+        }
+    }
+
+    /// private internal function that fails a database call with `SessionError::Timeout`
+    /// if it takes longer than db_timeout. The call is recorded within the metrics.
+    pub(crate) async fn timeout<R>(
+        &self,
+        call: impl Future<Output = Result<R, SessionError>>,
+    ) -> Result<R, SessionError> {
+        let start = Instant::now();
+
+        let result = if let Some(duration) = self.config.db_timeout {
+            let duration = duration.to_std().unwrap_or_default();
+
+            tokio::time::timeout(duration, call)
+                .await
+                .unwrap_or(Err(SessionError::Timeout))
+        } else {
+            call.await
+        };
+
+        self.metrics.db_call(start.elapsed(), result.is_err());
+        result
+    }
+
+    /// private internal function that runs a database call through `timeout`, retrying it with
+    /// exponential backoff while it fails with a transient error if a RetryPolicy is set.
+    /// The last error is returned once every attempt has failed.
+    pub(crate) async fn retry<R, Fut>(
+        &self,
+        mut call: impl FnMut() -> Fut,
+    ) -> Result<R, SessionError>
+    where
+        Fut: Future<Output = Result<R, SessionError>>,
+    {
+        let Some(policy) = self.config.retry else {
+            return self.timeout(call()).await;
+        };
+
+        let mut attempt = 1;
+
+        loop {
+            match self.timeout(call()).await {
+                Err(err) if err.is_transient() && attempt < policy.max_attempts => {
+                    let delay = policy.delay(attempt);
+                    tracing::warn!(
+                        "Session database call failed, retrying in {:?}: {}",
+                        delay,
+                        err
+                    );
+
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// private internal function that serializes, compresses and encrypts if enabled a session's data for storage.
+    pub(crate) fn encode_session(&self, session: &SessionData) -> Result<String, SessionError> {
+        let data = self.config.serializer.serialize(session)?;
+
+        #[cfg(feature = "zstd")]
+        let data = match self.config.compression {
+            Some((level, threshold)) => compression::compress(data, level, threshold)?,
+            None => data,
+        };
+
+        if self.config.encrypt_at_rest {
+            let key = self
+                .config
+                .current_key()
+                .ok_or(ConfigError::EncryptAtRestWithoutKey)?;
+
+            // The ID is used as the associated data so rows can not be swapped between Sessions.
+            let sealed = headers::encrypt(&session.id, &data, &key, self.config.cipher);
+            return Ok(format!("{ENCRYPTED_MAGIC}{sealed}"));
+        }
+
+        Ok(data)
+    }
+
+    /// private internal function that decrypts and decompresses if needed and deserializes a session's stored data.
+    pub(crate) fn decode_session(
+        &self,
+        id: &str,
+        data: String,
+    ) -> Result<SessionData, SessionError> {
+        let data = match data.strip_prefix(ENCRYPTED_MAGIC) {
+            Some(sealed) => {
+                let key = self
+                    .config
+                    .current_key()
+                    .ok_or(ConfigError::EncryptAtRestWithoutKey)?;
+                // Rows are only sealed with the current key once saved again after a rotation.
+                let previous = self.config.previous_key();
+                headers::decrypt_rotated(id, sealed, &key, previous.as_ref(), self.config.cipher)?
+            }
+            None => data,
+        };
+
+        #[cfg(feature = "zstd")]
+        let data = compression::decompress(data)?;
+
+        self.config.serializer.deserialize(&data)
+    }
+
     /// private internal function that stores a keys data to the database as a session.
     ///
     /// If client is None it will return Ok(()).
@@ -361,187 +1478,692 @@ where
     ) -> Result<(), SessionError> {
         if let Some(client) = &self.client {
             let value = key.encrypt(self.config.database_key.clone().unwrap());
-            client
-                .store(
-                    &key.id.to_string(),
-                    &value,
-                    expires,
-                    &self.config.table_name,
-                )
+            let id = key.id.to_string();
+
+            self.retry(|| client.store(&id, &value, expires, &self.config.table_name))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Deletes all sessions in the database.
+    /// Other app nodes clear their memory too if a broadcast is set with `with_broadcast`.
+    ///
+    /// If client is None it will return Ok(()).
+    ///
+    /// # Errors
+    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    /// use uuid::Uuid;
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config.clone()).await.unwrap();
+    ///
+    /// async {
+    ///     let _ = session_store.clear_store().await.unwrap();
+    /// };
+    /// ```
+    ///
+    #[inline]
+    pub async fn clear_store(&self) -> Result<(), SessionError> {
+        if let Some(client) = &self.client {
+            self.timeout(client.delete_all(&self.config.table_name))
                 .await?;
+            self.recent_writes.clear();
+            self.publish_invalidation(InvalidationTarget::All).await;
+        }
+
+        Ok(())
+    }
+
+    /// Deletes all sessions in Memory.
+    /// This will also Clear those keys from the filter cache if a persistent database does not exist.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    /// use uuid::Uuid;
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config.clone()).await.unwrap();
+    ///
+    /// async {
+    ///     let _ = session_store.clear().await.unwrap();
+    /// };
+    /// ```
+    ///
+    #[inline]
+    pub async fn clear(&mut self) {
+        #[cfg(feature = "key-store")]
+        if self.client.is_none() {
+            let mut filter = self.filter.write().await;
+            self.inner.for_each(|id, _| filter.remove(id.as_bytes()));
+            self.keys
+                .iter()
+                .for_each(|value| filter.remove(value.key().as_bytes()));
+        }
+
+        self.inner.clear();
+        self.keys.clear();
+    }
+
+    /// Clones the store with its own empty memory, used when `SessionConfig::with_memory`
+    /// is disabled so a Session is only held by the Request or `Session` using it and never
+    /// reaches the shared memory. Everything else is still shared with this store.
+    pub(crate) fn scoped(&self) -> Self {
+        let shared = Arc::new(SessionStoreInner {
+            inner: Arc::new(SessionMemory::scoped()),
+            ..(*self.shared).clone()
+        });
+
+        Self { shared }
+    }
+
+    /// Attempts to load check and clear Data.
+    ///
+    /// If no session is found returns false.
+    pub(crate) fn service_session_data(&self, session: &Session<T>) -> bool {
+        if let Some(mut inner) = self.inner.get_mut(&session.id.inner()) {
+            inner.service_clear(&self.config);
+            inner.set_request();
+            self.metrics.loaded_memory();
+            return true;
+        }
+
+        false
+    }
+
+    /// Unloads the least recently accessed Sessions once memory_limit is exceeded, down to
+    /// 90% of the limit so the scan for them runs once per batch instead of every request.
+    ///
+    /// Sessions within a request are never unloaded.
+    pub(crate) async fn evict_memory(&self) {
+        let Some(limit) = self.config.memory_limit else {
+            return;
+        };
+
+        if self.inner.len() <= limit {
+            return;
+        }
+
+        let mut idle: Vec<(String, DateTime<Utc>)> = Vec::new();
+
+        self.inner.for_each(|id, session| {
+            if !session.is_parallel() {
+                idle.push((id.clone(), session.last_accessed));
+            }
+        });
+
+        // Only the oldest Sessions are picked out instead of sorting them all.
+        let low_water = limit - limit / 10;
+        let count = self.inner.len().saturating_sub(low_water).min(idle.len());
+
+        if count == 0 {
+            return;
+        }
+
+        if count < idle.len() {
+            idle.select_nth_unstable_by_key(count - 1, |(_, last_accessed)| *last_accessed);
+        }
+
+        for (id, _) in idle.into_iter().take(count) {
+            if self
+                .inner
+                .remove_if(&id, |_, session| !session.is_parallel())
+                .is_none()
+            {
+                continue;
+            }
+
+            self.metrics.evicted();
+
+            if !self.is_persistent() {
+                // The ID is the bearer token so only its start is logged.
+                let short_id: String = id.chars().take(8).collect();
+                tracing::warn!("Session {} evicted from memory and lost", short_id);
+
+                #[cfg(feature = "key-store")]
+                if self.config.use_bloom_filters {
+                    let mut filter = self.filter.write().await;
+                    filter.remove(id.as_bytes());
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub(crate) fn renew(&self, id: String) {
+        if let Some(mut instance) = self.inner.get_mut(&id) {
+            instance.renew();
+        } else {
+            tracing::warn!("Session data unexpectedly missing");
+        }
+    }
+
+    #[inline]
+    pub(crate) fn renew_key(&self, id: String) {
+        if let Some(mut instance) = self.inner.get_mut(&id) {
+            instance.renew_key();
+        } else {
+            tracing::warn!("Session data unexpectedly missing");
+        }
+    }
+
+    #[inline]
+    pub(crate) fn destroy(&self, id: String) {
+        if let Some(mut instance) = self.inner.get_mut(&id) {
+            instance.destroy();
+            self.emit(&id, || SessionEvent::Destroyed);
+        } else {
+            tracing::warn!("Session data unexpectedly missing");
+        }
+    }
+
+    #[inline]
+    pub(crate) fn is_destroyed(&self, id: String) -> bool {
+        // Destroyed Sessions are often already unloaded so a missing one is not warned about.
+        self.inner.get(&id).is_some_and(|instance| instance.destroy)
+    }
+
+    #[inline]
+    pub(crate) fn set_longterm(&self, id: String, longterm: bool) {
+        if let Some(mut instance) = self.inner.get_mut(&id) {
+            instance.set_longterm(longterm);
+        } else {
+            tracing::warn!("Session data unexpectedly missing");
+        }
+    }
+
+    #[inline]
+    pub(crate) fn set_expiry(&self, id: String, expiry: DateTime<Utc>) {
+        if let Some(mut instance) = self.inner.get_mut(&id) {
+            instance.set_expiry(expiry, self.config.now());
+        } else {
+            tracing::warn!("Session data unexpectedly missing");
+        }
+    }
+
+    #[inline]
+    pub(crate) fn extend_expiry(&self, id: String, duration: Duration) {
+        if let Some(mut instance) = self.inner.get_mut(&id) {
+            let expiry = instance.expiry.unwrap_or(instance.expires) + duration;
+            instance.set_expiry(expiry, self.config.now());
+        } else {
+            tracing::warn!("Session data unexpectedly missing");
+        }
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[inline]
+    pub(crate) fn explicit_expiry(&self, id: String) -> Option<DateTime<Utc>> {
+        self.inner.get(&id).and_then(|instance| instance.expiry)
+    }
+
+    #[inline]
+    pub(crate) fn set_store(&self, id: String, storable: bool) {
+        if let Some(mut instance) = self.inner.get_mut(&id) {
+            instance.set_store(storable);
+        } else {
+            tracing::warn!("Session data unexpectedly missing");
+        }
+    }
+
+    #[inline]
+    pub(crate) fn set_opt_in_override(&self, id: String, opt_in: bool) {
+        if let Some(mut instance) = self.inner.get_mut(&id) {
+            instance.set_opt_in_override(opt_in);
+        } else {
+            tracing::warn!("Session data unexpectedly missing");
+        }
+    }
+
+    #[inline]
+    pub(crate) fn update(&self, id: String) {
+        if let Some(mut instance) = self.inner.get_mut(&id) {
+            instance.update();
+        } else {
+            tracing::warn!("Session data unexpectedly missing");
+        }
+    }
+
+    #[inline]
+    pub(crate) fn touch(&self, id: String) {
+        self.tap(&id, |instance| instance.touch(self.config.now()));
+    }
+
+    /// Runs the closure with write access to the Session's data returning None if it is missing.
+    #[inline]
+    pub(crate) fn tap<R>(&self, id: &str, f: impl FnOnce(&mut SessionData) -> R) -> Option<R> {
+        let result = self.inner.get_mut(id).map(|mut instance| f(&mut instance));
+
+        if result.is_none() {
+            tracing::warn!("Session data unexpectedly missing");
         }
 
-        Ok(())
+        result
     }
 
-    /// Deletes all sessions in the database.
-    ///
-    /// If client is None it will return Ok(()).
-    ///
-    /// # Errors
-    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
-    ///
-    /// # Examples
-    /// ```rust ignore
-    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
-    /// use uuid::Uuid;
-    ///
-    /// let config = SessionConfig::default();
-    /// let session_store = SessionStore::<SessionNullPool>::new(None, config.clone()).await.unwrap();
-    ///
-    /// async {
-    ///     let _ = session_store.clear_store().await.unwrap();
-    /// };
-    /// ```
-    ///
+    /// Runs the closure with read access to the Session's data returning None if it is missing.
+    /// Only takes a shared lock so reads of Sessions within the same shard do not block each other.
     #[inline]
-    pub async fn clear_store(&self) -> Result<(), SessionError> {
-        if let Some(client) = &self.client {
-            client.delete_all(&self.config.table_name).await?;
+    pub(crate) fn tap_ref<R>(&self, id: &str, f: impl FnOnce(&SessionData) -> R) -> Option<R> {
+        let result = self.inner.get(id).map(|instance| f(&instance));
+
+        if result.is_none() {
+            tracing::warn!("Session data unexpectedly missing");
         }
 
-        Ok(())
+        result
     }
 
-    /// Deletes all sessions in Memory.
-    /// This will also Clear those keys from the filter cache if a persistent database does not exist.
-    ///
-    /// # Examples
-    /// ```rust ignore
-    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
-    /// use uuid::Uuid;
-    ///
-    /// let config = SessionConfig::default();
-    /// let session_store = SessionStore::<SessionNullPool>::new(None, config.clone()).await.unwrap();
-    ///
-    /// async {
-    ///     let _ = session_store.clear().await.unwrap();
-    /// };
-    /// ```
-    ///
     #[inline]
-    pub async fn clear(&mut self) {
-        #[cfg(feature = "key-store")]
-        if self.client.is_none() {
-            let mut filter = self.filter.write().await;
-            self.inner
-                .iter()
-                .for_each(|value| filter.remove(value.key().as_bytes()));
-            self.keys
-                .iter()
-                .for_each(|value| filter.remove(value.key().as_bytes()));
+    pub(crate) fn contains_key(&self, id: String, key: &str) -> bool {
+        self.tap_ref(&id, |instance| instance.contains_key(key))
+            .unwrap_or(false)
+    }
+
+    #[inline]
+    pub(crate) fn keys(&self, id: String) -> Vec<String> {
+        self.tap_ref(&id, SessionData::keys).unwrap_or_default()
+    }
+
+    #[inline]
+    pub(crate) fn value_len(&self, id: String, key: &str) -> Option<usize> {
+        self.tap_ref(&id, |instance| instance.value_len(key))
+            .flatten()
+    }
+
+    #[inline]
+    pub(crate) fn created_at(&self, id: String) -> DateTime<Utc> {
+        self.tap_ref(&id, |instance| instance.created_at)
+            .unwrap_or_else(|| self.config.now())
+    }
+
+    #[inline]
+    pub(crate) fn last_accessed(&self, id: String) -> DateTime<Utc> {
+        self.tap_ref(&id, |instance| instance.last_accessed)
+            .unwrap_or_else(|| self.config.now())
+    }
+
+    #[inline]
+    pub(crate) fn expires(&self, id: String) -> DateTime<Utc> {
+        self.tap_ref(&id, |instance| instance.expires)
+            .unwrap_or_else(|| self.config.now())
+    }
+
+    #[inline]
+    pub(crate) fn export_data(&self, id: String) -> serde_json::Map<String, serde_json::Value> {
+        self.tap_ref(&id, SessionData::export).unwrap_or_default()
+    }
+
+    #[inline]
+    pub(crate) fn data_len(&self, id: String) -> usize {
+        self.tap_ref(&id, SessionData::size).unwrap_or(0)
+    }
+
+    #[inline]
+    pub(crate) fn csrf_token(&self, id: String) -> String {
+        if let Some(mut instance) = self.inner.get_mut(&id) {
+            instance.csrf_token()
+        } else {
+            tracing::warn!("Session data unexpectedly missing");
+            String::new()
         }
+    }
 
-        self.inner.clear();
-        self.keys.clear();
+    #[inline]
+    pub(crate) fn verify_csrf(&self, id: String, presented: &str) -> bool {
+        self.tap_ref(&id, |instance| instance.verify_csrf(presented))
+            .unwrap_or(false)
     }
 
-    /// Attempts to load check and clear Data.
-    ///
-    /// If no session is found returns false.
-    pub(crate) fn service_session_data(&self, session: &Session<T>) -> bool {
-        if let Some(mut inner) = self.inner.get_mut(&session.id.inner()) {
-            inner.service_clear(self.config.memory_lifespan, self.config.clear_check_on_load);
-            inner.set_request();
-            return true;
+    #[inline]
+    pub(crate) fn verify_secret(&self, id: String, key: &str, presented: &str) -> bool {
+        self.tap_ref(&id, |instance| instance.verify_secret(key, presented))
+            .unwrap_or(false)
+    }
+
+    #[inline]
+    pub(crate) fn get<N: serde::de::DeserializeOwned>(&self, id: String, key: &str) -> Option<N> {
+        let value = self.tap_ref(&id, |instance| instance.get(key))?;
+
+        if value.is_some() {
+            return value;
         }
 
-        false
+        // Migrating stores the new value so it needs write access.
+        let migrator = self.config.data_migrator.as_ref()?;
+        self.inner.get_mut(&id)?.get_or_migrate(key, migrator)
     }
 
     #[inline]
-    pub(crate) fn renew(&self, id: String) {
+    pub(crate) fn schema_version(&self, id: String) -> u32 {
+        self.tap_ref(&id, |instance| instance.schema_version)
+            .unwrap_or(0)
+    }
+
+    #[inline]
+    pub(crate) fn set_schema_version(&self, id: String, version: u32) {
         if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.renew();
+            instance.schema_version = version;
+            instance.update = true;
         } else {
             tracing::warn!("Session data unexpectedly missing");
         }
     }
 
     #[inline]
-    pub(crate) fn renew_key(&self, id: String) {
+    pub(crate) fn get_remove<N: serde::de::DeserializeOwned>(
+        &self,
+        id: String,
+        key: &str,
+    ) -> Option<N> {
         if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.renew_key();
+            let value = instance.get_remove(key);
+
+            if value.is_some() {
+                self.emit(&id, || SessionEvent::Updated(key.to_owned()));
+            }
+
+            value
         } else {
             tracing::warn!("Session data unexpectedly missing");
+            None
         }
     }
 
     #[inline]
-    pub(crate) fn destroy(&self, id: String) {
+    pub(crate) fn take<N: serde::de::DeserializeOwned>(
+        &self,
+        id: String,
+        key: &str,
+    ) -> Result<Option<N>, SessionError> {
         if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.destroy();
+            let value = instance.take(key);
+            self.emit(&id, || SessionEvent::Updated(key.to_owned()));
+            value
         } else {
             tracing::warn!("Session data unexpectedly missing");
+            Err(SessionError::NoSessionError)
         }
     }
 
     #[inline]
-    pub(crate) fn set_longterm(&self, id: String, longterm: bool) {
+    pub(crate) fn merge(
+        &self,
+        id: String,
+        map: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<(), SessionError> {
         if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.set_longterm(longterm);
+            let keys: Vec<String> = map.keys().cloned().collect();
+            instance.merge(map)?;
+
+            for key in keys {
+                self.emit(&id, || SessionEvent::Updated(key));
+            }
+
+            Ok(())
         } else {
             tracing::warn!("Session data unexpectedly missing");
+            Err(SessionError::NoSessionError)
         }
     }
 
     #[inline]
-    pub(crate) fn set_store(&self, id: String, storable: bool) {
+    pub(crate) fn set(&self, id: String, key: &str, value: impl Serialize) {
         if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.set_store(storable);
+            // Failures are already logged by SessionData::set.
+            if instance.set(key, value).is_ok() {
+                self.emit(&id, || SessionEvent::Updated(key.to_owned()));
+            }
         } else {
             tracing::warn!("Session data unexpectedly missing");
         }
     }
 
     #[inline]
-    pub(crate) fn update(&self, id: String) {
+    pub(crate) fn try_set(
+        &self,
+        id: String,
+        key: &str,
+        value: impl Serialize,
+    ) -> Result<(), SessionError> {
         if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.update();
+            instance.try_set(key, value)?;
+            self.emit(&id, || SessionEvent::Updated(key.to_owned()));
+            Ok(())
         } else {
             tracing::warn!("Session data unexpectedly missing");
+            Err(SessionError::NoSessionError)
         }
     }
 
     #[inline]
-    pub(crate) fn get<N: serde::de::DeserializeOwned>(&self, id: String, key: &str) -> Option<N> {
-        if let Some(instance) = self.inner.get(&id) {
-            instance.get(key)
+    pub(crate) fn increment(
+        &self,
+        id: String,
+        key: &str,
+        by: i64,
+        max: Option<i64>,
+    ) -> (i64, bool) {
+        // The entry stays locked between reading and writing so concurrent increments are not lost.
+        if let Some(mut instance) = self.inner.get_mut(&id) {
+            match instance.increment(key, by, max) {
+                Ok(result) => {
+                    self.emit(&id, || SessionEvent::Updated(key.to_owned()));
+                    result
+                }
+                Err(err) => {
+                    tracing::warn!("Session data failed to increment: {}", err);
+                    (instance.get::<i64>(key).unwrap_or(0), false)
+                }
+            }
         } else {
             tracing::warn!("Session data unexpectedly missing");
-            None
+            (0, false)
         }
     }
 
     #[inline]
-    pub(crate) fn get_remove<N: serde::de::DeserializeOwned>(
+    pub(crate) fn with<V, R, F>(&self, id: String, key: &str, f: F) -> Option<R>
+    where
+        V: Serialize + serde::de::DeserializeOwned,
+        F: FnOnce(&mut V) -> R,
+    {
+        // The entry stays locked while f runs so concurrent changes are not lost.
+        let Some(mut instance) = self.inner.get_mut(&id) else {
+            tracing::warn!("Session data unexpectedly missing");
+            return None;
+        };
+
+        match instance.with(key, f) {
+            Ok(Some((result, changed))) => {
+                if changed {
+                    self.emit(&id, || SessionEvent::Updated(key.to_owned()));
+                }
+
+                Some(result)
+            }
+            Ok(None) => None,
+            Err(err) => {
+                tracing::error!("Session data for key {:?} failed to set: {}", key, err);
+                None
+            }
+        }
+    }
+
+    #[inline]
+    pub(crate) fn rate_limit(
         &self,
         id: String,
         key: &str,
-    ) -> Option<N> {
+        max: u32,
+        window: Duration,
+    ) -> RateLimitDecision {
+        // The window stays locked between reading and writing so concurrent attempts are all counted.
+        match self.tap(&id, |instance| {
+            instance.rate_limit(key, max, window, self.config.now())
+        }) {
+            Some(Ok(decision)) => {
+                if decision.allowed {
+                    self.emit(&id, || SessionEvent::Updated(key.to_owned()));
+                }
+
+                decision
+            }
+            result => {
+                if let Some(Err(err)) = result {
+                    tracing::error!(
+                        "Session data for key {:?} failed to rate limit: {}",
+                        key,
+                        err
+                    );
+                }
+
+                // Attempts that could not be counted are denied so the limit can not be skipped.
+                let reset = self.config.now() + window;
+
+                RateLimitDecision {
+                    allowed: false,
+                    remaining: 0,
+                    reset,
+                    retry_after: window,
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub(crate) fn push<V>(&self, id: String, key: &str, value: V)
+    where
+        V: Serialize + serde::de::DeserializeOwned,
+    {
+        let max_len = self.config.max_list_len;
+
+        // The list stays locked between reading and writing so concurrent pushes are not lost.
+        match self.tap(&id, |instance| instance.push(key, value, max_len)) {
+            Some(Ok(_)) => self.emit(&id, || SessionEvent::Updated(key.to_owned())),
+            Some(Err(err)) => {
+                tracing::error!("Session data for key {:?} failed to push: {}", key, err)
+            }
+            None => {}
+        }
+    }
+
+    #[inline]
+    pub(crate) fn pop<V>(&self, id: String, key: &str) -> Option<V>
+    where
+        V: Serialize + serde::de::DeserializeOwned,
+    {
+        match self.tap(&id, |instance| instance.pop(key))? {
+            Ok(value) => {
+                if value.is_some() {
+                    self.emit(&id, || SessionEvent::Updated(key.to_owned()));
+                }
+
+                value
+            }
+            Err(err) => {
+                tracing::error!("Session data for key {:?} failed to pop: {}", key, err);
+                None
+            }
+        }
+    }
+
+    #[inline]
+    pub(crate) fn list<V: serde::de::DeserializeOwned>(&self, id: String, key: &str) -> Vec<V> {
+        self.tap_ref(&id, |instance| instance.get::<Vec<V>>(key))
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    pub(crate) fn list_len(&self, id: String, key: &str) -> usize {
+        self.tap_ref(&id, |instance| {
+            instance
+                .get::<Vec<serde::de::IgnoredAny>>(key)
+                .map_or(0, |list| list.len())
+        })
+        .unwrap_or(0)
+    }
+
+    #[inline]
+    pub(crate) fn remove(&self, id: String, key: &str) {
         if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.get_remove(key)
+            instance.remove(key);
+            self.emit(&id, || SessionEvent::Updated(key.to_owned()));
         } else {
             tracing::warn!("Session data unexpectedly missing");
-            None
         }
     }
 
     #[inline]
-    pub(crate) fn set(&self, id: String, key: &str, value: impl Serialize) {
+    pub(crate) fn keys_with_prefix(&self, id: String, prefix: &str) -> Vec<String> {
+        self.tap_ref(&id, |instance| instance.keys_with_prefix(prefix))
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    pub(crate) fn remove_prefix(&self, id: String, prefix: &str) -> usize {
         if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.set(key, value);
+            let keys = instance.remove_prefix(prefix);
+            let removed = keys.len();
+
+            for key in keys {
+                self.emit(&id, || SessionEvent::Updated(key));
+            }
+
+            removed
         } else {
             tracing::warn!("Session data unexpectedly missing");
+            0
         }
     }
 
     #[inline]
-    pub(crate) fn remove(&self, id: String, key: &str) {
+    pub(crate) fn remove_many(&self, id: String, keys: &[&str]) -> usize {
         if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.remove(key);
+            let removed = instance.remove_many(keys);
+
+            if removed > 0 {
+                for key in keys {
+                    self.emit(&id, || SessionEvent::Updated((*key).to_owned()));
+                }
+            }
+
+            removed
+        } else {
+            tracing::warn!("Session data unexpectedly missing");
+            0
+        }
+    }
+
+    #[inline]
+    pub(crate) fn set_many<K: Into<String>, V: Serialize>(
+        &self,
+        id: String,
+        values: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<(), SessionError> {
+        if let Some(mut instance) = self.inner.get_mut(&id) {
+            let values: Vec<(String, V)> = values
+                .into_iter()
+                .map(|(key, value)| (key.into(), value))
+                .collect();
+            let keys: Vec<String> = values.iter().map(|(key, _)| key.clone()).collect();
+            instance.set_many(values)?;
+
+            for key in keys {
+                self.emit(&id, || SessionEvent::Updated(key));
+            }
+
+            Ok(())
         } else {
             tracing::warn!("Session data unexpectedly missing");
+            Err(SessionError::NoSessionError)
         }
     }
 
@@ -549,6 +2171,7 @@ where
     pub(crate) fn clear_session_data(&self, id: String) {
         if let Some(mut instance) = self.inner.get_mut(&id) {
             instance.clear();
+            self.emit(&id, || SessionEvent::Cleared);
         } else {
             tracing::warn!("Session data unexpectedly missing");
         }
@@ -574,12 +2197,8 @@ where
 
     #[inline]
     pub(crate) fn is_session_parallel(&self, id: String) -> bool {
-        if let Some(instance) = self.inner.get(&id) {
-            instance.is_parallel()
-        } else {
-            tracing::warn!("Session data unexpectedly missing");
-            false
-        }
+        self.tap_ref(&id, |instance| instance.is_parallel())
+            .unwrap_or(false)
     }
 
     #[inline]
@@ -591,6 +2210,16 @@ where
         }
     }
 
+    #[inline]
+    pub(crate) async fn count_active_sessions(&self) -> i64 {
+        if self.is_persistent() {
+            self.count_active().await.unwrap_or(0i64)
+        } else {
+            let now = self.config.now();
+            self.inner.count(|session| session.validate(now)) as i64
+        }
+    }
+
     #[inline]
     pub(crate) fn auto_handles_expiry(&self) -> bool {
         if let Some(client) = &self.client {
@@ -600,11 +2229,36 @@ where
         }
     }
 
+    /// private internal function that updates a Session's expiry and persists it to the database.
+    /// Destroyed Sessions are removed instead.
+    pub(crate) async fn save(&self, id: String) -> Result<(), SessionError> {
+        let session = if let Some(mut instance) = self.inner.get_mut(&id) {
+            if instance.destroy {
+                None
+            } else {
+                instance.refresh_expires(&self.config);
+                instance.update = false;
+                Some(instance.clone())
+            }
+        } else {
+            return Err(SessionError::NoSessionError);
+        };
+
+        if let Some(session) = session {
+            self.store_session(&session).await?;
+            self.emit(&id, || SessionEvent::Saved);
+            return Ok(());
+        }
+
+        let _ = self.inner.remove(&id);
+        self.database_remove_session(id).await
+    }
+
     #[cfg(feature = "advanced")]
     #[inline]
     pub(crate) fn verify(&self, id: String) -> Result<(), SessionError> {
         if let Some(instance) = self.inner.get(&id) {
-            if instance.expires < Utc::now() {
+            if instance.expires < self.config.now() {
                 Err(SessionError::OldSessionError)
             } else {
                 Ok(())
@@ -618,12 +2272,7 @@ where
     #[inline]
     pub(crate) fn update_database_expires(&self, id: String) -> Result<(), SessionError> {
         if let Some(mut instance) = self.inner.get_mut(&id) {
-            if instance.longterm {
-                instance.expires = Utc::now() + self.config.max_lifespan;
-            } else {
-                instance.expires = Utc::now() + self.config.lifespan;
-            }
-
+            instance.refresh_expires(&self.config);
             Ok(())
         } else {
             Err(SessionError::NoSessionError)
@@ -634,7 +2283,7 @@ where
     #[inline]
     pub(crate) fn update_memory_expires(&self, id: String) -> Result<(), SessionError> {
         if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.autoremove = Utc::now() + self.config.memory_lifespan;
+            instance.autoremove = self.config.now() + self.config.memory_lifespan;
 
             Ok(())
         } else {
@@ -674,11 +2323,17 @@ where
     #[inline]
     pub(crate) async fn database_remove_session(&self, id: String) -> Result<(), SessionError> {
         if let Some(client) = &self.client {
-            client
-                .delete_one_by_id(&id, &self.config.table_name)
+            self.retry(|| client.delete_one_by_id(&id, &self.config.table_name))
                 .await?;
+            self.record_write(&id, None);
+
+            #[cfg(any(feature = "postgres-rustls", feature = "postgres-native"))]
+            self.notify_invalidation(&id).await;
         }
 
+        // Covers Sessions destroyed or renewed by a Request as well as by destroy_session.
+        self.publish_invalidation(InvalidationTarget::Session(id))
+            .await;
         Ok(())
     }
 }
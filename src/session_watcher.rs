@@ -0,0 +1,114 @@
+use crate::{DatabasePool, SessionStore};
+use std::{
+    fmt::Debug,
+    marker::{Send, Sync},
+};
+use tokio::sync::watch;
+
+/// Number of events a subscriber can fall behind before it starts missing them.
+pub(crate) const EVENT_CAPACITY: usize = 64;
+
+/// Changes made to a Session, sent to receivers from `Session::subscribe`.
+///
+/// Events are only sent for changes made on this app node, apart from `Destroyed` which is
+/// also sent when another node destroys the Session through a `SessionBroadcast`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SessionEvent {
+    /// The value of the key was set or removed.
+    Updated(String),
+    /// All of the Session's values were removed.
+    Cleared,
+    /// The Session was saved to the database using `Session::save`.
+    Saved,
+    /// The Session was destroyed. No more events will be sent.
+    Destroyed,
+}
+
+/// A handle that resolves once a Session is destroyed or expires.
+///
+/// Gotten from `Session::watch` and can be moved into long running tasks like a
+/// websocket's `on_upgrade` closure to close the socket when the user logs out.
+/// The watcher follows the Session if its ID is renewed.
+///
+/// # Examples
+/// ```rust ignore
+/// async fn ws_handler(ws: WebSocketUpgrade, session: Session<SessionPgPool>) -> Response {
+///     let mut watcher = session.watch();
+///
+///     ws.on_upgrade(move |socket| async move {
+///         tokio::select! {
+///             _ = handle_socket(socket, session) => {}
+///             _ = watcher.destroyed() => {}
+///         }
+///     })
+/// }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct SessionWatcher<T>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    pub(crate) store: SessionStore<T>,
+    /// Holds the Session's current ID or None once it was destroyed.
+    pub(crate) receiver: watch::Receiver<Option<String>>,
+}
+
+impl<T> SessionWatcher<T>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    /// Waits till the Session is destroyed or expires.
+    ///
+    /// Returns right away if the Session no longer exists. The expiry is checked again
+    /// each time it is reached as requests using the Session will push it back.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// watcher.destroyed().await;
+    /// ```
+    ///
+    pub async fn destroyed(&mut self) {
+        loop {
+            let Some(id) = self.receiver.borrow_and_update().clone() else {
+                return;
+            };
+
+            let expires = match self.store.get_session_data(&id).await {
+                Ok(Some(session)) => session.expires,
+                Ok(None) => return,
+                Err(err) => {
+                    // Database errors should not close long running tasks so try again later.
+                    tracing::warn!("Session watcher failed to load the Session: {}", err);
+                    self.store.config.now() + chrono::Duration::seconds(1)
+                }
+            };
+
+            let wait = (expires - self.store.config.now())
+                .to_std()
+                .unwrap_or_default();
+
+            tokio::select! {
+                changed = self.receiver.changed() => {
+                    // The store dropped the Session's channel.
+                    if changed.is_err() {
+                        return;
+                    }
+                }
+                _ = tokio::time::sleep(wait) => {}
+            }
+        }
+    }
+
+    /// Gets the ID of the Session being watched or None if it was destroyed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let id = watcher.session_id();
+    /// ```
+    ///
+    pub fn session_id(&self) -> Option<String> {
+        self.receiver.borrow().clone()
+    }
+}
@@ -0,0 +1,779 @@
+use crate::{
+    config::SecurityMode,
+    headers::{set_headers, CookieAttrs},
+    Clock, DatabasePool, MemoryStore, Session, SessionConfig, SessionData, SessionError,
+    SessionKey, SessionLayer, SessionNullPool, SessionStore,
+};
+use async_trait::async_trait;
+use axum_core::{body::Body, response::Response};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+#[cfg(feature = "rest_mode")]
+use http::header::HeaderName;
+#[cfg(not(feature = "rest_mode"))]
+use http::header::{COOKIE, SET_COOKIE};
+use http::{request::Builder, HeaderMap, Request};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Creates a memory only SessionStore with the default config for use within tests.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::test_util::test_store;
+///
+/// let store = test_store().await;
+/// ```
+///
+pub async fn test_store() -> SessionStore<SessionNullPool> {
+    SessionStore::new(None, SessionConfig::default())
+        .await
+        .expect("the default SessionConfig is valid")
+}
+
+/// `Clock` that only moves when told to so time based behavior can be tested without
+/// sleeping. Clones share the same time.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::{test_util::MockClock, SessionConfig};
+///
+/// let clock = MockClock::new();
+/// let config = SessionConfig::default().with_clock(Arc::new(clock.clone()));
+/// clock.advance(chrono::Duration::hours(1));
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl MockClock {
+    /// Creates a MockClock starting at the current system time.
+    ///
+    pub fn new() -> Self {
+        Self::at(Utc::now())
+    }
+
+    /// Creates a MockClock starting at the time.
+    ///
+    pub fn at(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    /// Moves the clock forward by the duration.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// clock.advance(chrono::Duration::minutes(15));
+    /// ```
+    ///
+    pub fn advance(&self, duration: chrono::Duration) {
+        *self.now.lock().expect("MockClock lock poisoned") += duration;
+    }
+
+    /// Sets the clock to the time.
+    ///
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().expect("MockClock lock poisoned") = now;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().expect("MockClock lock poisoned")
+    }
+}
+
+impl<T> Session<T>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    /// Creates a new Session with its data within the store without needing a request.
+    /// Useful for calling handlers that take a `Session` directly within tests.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{test_util::test_store, Session};
+    ///
+    /// let session = Session::new_test(test_store().await).await;
+    /// session.set("user_id", 1);
+    /// assert_eq!(my_handler(session.clone()).await, "1");
+    /// ```
+    ///
+    pub async fn new_test(store: SessionStore<T>) -> Session<T> {
+        let (mut session, _) = Session::new(store, None).await;
+        let mut sess = SessionData::new(session.id.inner(), true, &session.store.config);
+
+        // The Session is not within a request so it must not block unloading.
+        sess.requests = 0;
+        session.store.inner.insert(session.id.inner(), sess);
+        session.store.metrics.created();
+        session.is_new = true;
+        session
+    }
+
+    /// Creates a new Session like `new_test` with every entry of the Map already set.
+    ///
+    /// # Errors
+    /// - ['SessionError::SerdeJson'] is returned if a value failed to Serialize.
+    /// - ['SessionError::SizeLimitExceeded'] is returned if the data is larger than max_session_size.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{test_util::test_store, Session};
+    ///
+    /// let serde_json::Value::Object(data) = serde_json::json!({ "user_id": 42 }) else {
+    ///     unreachable!()
+    /// };
+    /// let session = Session::new_test_with_data(test_store().await, data).await?;
+    /// ```
+    ///
+    pub async fn new_test_with_data(
+        store: SessionStore<T>,
+        data: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<Session<T>, SessionError> {
+        let session = Session::new_test(store).await;
+        session.merge(data)?;
+        Ok(session)
+    }
+}
+
+impl<T> SessionStore<T>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    /// Creates a Session holding the data within the store and the database if persistent,
+    /// returning the Cookies or Headers a client holding the Session would send.
+    ///
+    /// The values are sealed the same way the `SessionLayer` seals them, including the
+    /// Session's own key with `SecurityMode::PerSession`. Clones of the store share the
+    /// Session so the token works with the layer given a clone of this store.
+    ///
+    /// # Errors
+    /// - ['SessionError::SerdeJson'] is returned if a value failed to Serialize.
+    /// - ['SessionError::SizeLimitExceeded'] is returned if the data is larger than max_session_size.
+    /// - ['SessionError::InvalidHeader'] is returned in rest_mode if a Header could not be built.
+    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let token = session_store
+    ///     .create_test_session(HashMap::from([("user_id", 42)]))
+    ///     .await?;
+    /// let request = token.apply(Request::builder().uri("/account")).body(Body::empty())?;
+    /// ```
+    ///
+    pub async fn create_test_session<K: Into<String>>(
+        &self,
+        data: HashMap<K, impl Serialize>,
+    ) -> Result<TestSessionToken, SessionError> {
+        let session = Session::new_test(self.clone()).await;
+
+        for (key, value) in data {
+            session.try_set(&key.into(), value)?;
+        }
+
+        let session_key = match self.config.security_mode {
+            SecurityMode::PerSession => SessionKey::get_or_create(self, None).await,
+            SecurityMode::Simple => SessionKey::new(),
+        };
+
+        let (sess, opt_in) = {
+            let mut sess = self
+                .inner
+                .get_mut(&session.id.inner())
+                .ok_or(SessionError::NoSessionError)?;
+            sess.set_store(true);
+            sess.update = false;
+            (sess.clone(), sess.is_opt_in(&self.config))
+        };
+
+        self.store_session(&sess).await?;
+
+        if self.config.security_mode == SecurityMode::PerSession {
+            self.store_key(&session_key, sess.expires.timestamp())
+                .await?;
+        }
+
+        let mut response_headers = HeaderMap::new();
+        set_headers(
+            &session,
+            &session_key,
+            &mut response_headers,
+            false,
+            true,
+            opt_in,
+            false,
+            &CookieAttrs {
+                domain: None,
+                secure: self.config.cookie_secure,
+            },
+        )?;
+
+        Ok(TestSessionToken {
+            session_id: session.id.inner(),
+            headers: request_headers(response_headers),
+        })
+    }
+}
+
+/// Turns the Session's Response Headers into the Headers a client would send back.
+#[cfg(not(feature = "rest_mode"))]
+fn request_headers(response_headers: HeaderMap) -> HeaderMap {
+    let cookies = response_headers
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|value| cookie::Cookie::parse(value.to_owned()).ok())
+        .filter(|cookie| !cookie.value().is_empty())
+        .map(|cookie| format!("{}={}", cookie.name(), cookie.value()))
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    let mut headers = HeaderMap::new();
+
+    if let Ok(value) = cookies.parse() {
+        headers.insert(COOKIE, value);
+    }
+
+    headers
+}
+
+/// Turns the Session's Response Headers into the Headers a client would send back.
+#[cfg(feature = "rest_mode")]
+fn request_headers(response_headers: HeaderMap) -> HeaderMap {
+    response_headers
+}
+
+/// The Cookies or Headers of a Session created with `SessionStore::create_test_session`.
+///
+/// # Examples
+/// ```rust ignore
+/// let request = token
+///     .apply(Request::builder().uri("/account"))
+///     .body(Body::empty())?;
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct TestSessionToken {
+    session_id: String,
+    headers: HeaderMap,
+}
+
+impl TestSessionToken {
+    /// Returns the ID of the created Session.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let data = session_store.get_session_data(token.session_id()).await?;
+    /// ```
+    ///
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Returns the Cookie Header, or the Session Headers in rest_mode, a client would send.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let cookie = token.headers().get(header::COOKIE);
+    /// ```
+    ///
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Adds the Session's Headers to the Request builder.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let request = token
+    ///     .apply(Request::builder().uri("/account"))
+    ///     .body(Body::empty())?;
+    /// ```
+    ///
+    pub fn apply(&self, mut builder: Builder) -> Builder {
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+
+        builder
+    }
+
+    /// Adds the Session's Headers to an already built Request.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// token.attach(&mut request);
+    /// ```
+    ///
+    pub fn attach<B>(&self, request: &mut Request<B>) {
+        for (name, value) in &self.headers {
+            request.headers_mut().append(name, value.clone());
+        }
+    }
+}
+
+/// In memory `DatabasePool` for tests that can be told to fail, slow down or corrupt its data.
+///
+/// Clones share the same data and switches, so keep a clone to flip the switches after
+/// giving the pool to a `SessionStore`. Failed calls return a connection reset IO error,
+/// which counts as transient for `RetryPolicy`, and do not change the stored data.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::{test_util::TestPool, SessionConfig, SessionStore};
+///
+/// let pool = TestPool::new();
+/// let store = SessionStore::new(Some(pool.clone()), SessionConfig::default()).await?;
+/// pool.fail_next_stores(1);
+/// ```
+///
+#[derive(Debug, Clone, Default)]
+pub struct TestPool {
+    sessions: Arc<DashMap<String, (String, i64)>>,
+    latency: Arc<Mutex<Option<Duration>>>,
+    fail_stores: Arc<AtomicUsize>,
+    fail_loads: Arc<AtomicUsize>,
+    corrupt_loads: Arc<AtomicBool>,
+    clock: Option<Arc<dyn Clock>>,
+}
+
+impl TestPool {
+    /// Creates an empty TestPool with no latency or failures.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let pool = TestPool::new();
+    /// ```
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks expiry against the clock instead of the system time, like a `MockClock`
+    /// shared with the store's config.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let pool = TestPool::new().with_clock(Arc::new(clock.clone()));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Sets how long every call waits before running. None removes the latency.
+    /// Useful with `SessionConfig::with_db_timeout` to test timeouts.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// pool.set_latency(Some(Duration::from_millis(50)));
+    /// ```
+    ///
+    pub fn set_latency(&self, latency: Option<Duration>) {
+        *self.latency.lock().expect("TestPool latency lock poisoned") = latency;
+    }
+
+    /// Makes the next count `store` calls fail.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// pool.fail_next_stores(1);
+    /// ```
+    ///
+    pub fn fail_next_stores(&self, count: usize) {
+        self.fail_stores.store(count, Ordering::SeqCst);
+    }
+
+    /// Makes the next count `load` calls fail.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// pool.fail_next_loads(1);
+    /// ```
+    ///
+    pub fn fail_next_loads(&self, count: usize) {
+        self.fail_loads.store(count, Ordering::SeqCst);
+    }
+
+    /// Makes `load` return data that can not be decoded while set, like a row
+    /// written by a different serializer.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// pool.corrupt_loads(true);
+    /// ```
+    ///
+    pub fn corrupt_loads(&self, corrupt: bool) {
+        self.corrupt_loads.store(corrupt, Ordering::SeqCst);
+    }
+
+    /// Returns the stored data for the ID even if it expired.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let stored = pool.stored(&session_id);
+    /// ```
+    ///
+    pub fn stored(&self, id: &str) -> Option<String> {
+        self.sessions.get(id).map(|entry| entry.0.clone())
+    }
+
+    /// Returns how many rows are stored including Session keys and expired rows.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// assert_eq!(pool.len(), 1);
+    /// ```
+    ///
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Returns true if nothing is stored.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// assert!(pool.is_empty());
+    /// ```
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    fn now(&self) -> i64 {
+        self.clock
+            .as_ref()
+            .map_or_else(Utc::now, |clock| clock.now())
+            .timestamp()
+    }
+
+    async fn delay(&self) {
+        let latency = *self.latency.lock().expect("TestPool latency lock poisoned");
+
+        if let Some(latency) = latency {
+            tokio::time::sleep(latency).await;
+        }
+    }
+}
+
+/// Counts down one injected failure returning true if the call should fail.
+fn take_failure(counter: &AtomicUsize) -> bool {
+    counter
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+            count.checked_sub(1)
+        })
+        .is_ok()
+}
+
+/// Error returned by injected failures, like a dropped database connection.
+fn injected_failure(message: &str) -> SessionError {
+    SessionError::IO(std::io::Error::new(
+        std::io::ErrorKind::ConnectionReset,
+        message,
+    ))
+}
+
+#[async_trait]
+impl DatabasePool for TestPool {
+    async fn initiate(&self, _table_name: &str) -> Result<(), SessionError> {
+        self.delay().await;
+        Ok(())
+    }
+
+    async fn delete_by_expiry(&self, _table_name: &str) -> Result<Vec<String>, SessionError> {
+        self.delay().await;
+        let now = self.now();
+        let expired: Vec<String> = self
+            .sessions
+            .iter()
+            .filter(|entry| entry.1 < now)
+            .map(|entry| entry.key().clone())
+            .collect();
+
+        for id in &expired {
+            self.sessions.remove(id);
+        }
+
+        Ok(expired)
+    }
+
+    async fn count(&self, _table_name: &str) -> Result<i64, SessionError> {
+        self.delay().await;
+        Ok(self.sessions.len() as i64)
+    }
+
+    async fn count_active(&self, _table_name: &str) -> Result<i64, SessionError> {
+        self.delay().await;
+        let now = self.now();
+        Ok(self.sessions.iter().filter(|entry| entry.1 >= now).count() as i64)
+    }
+
+    async fn store(
+        &self,
+        id: &str,
+        session: &str,
+        expires: i64,
+        _table_name: &str,
+    ) -> Result<(), SessionError> {
+        self.delay().await;
+
+        if take_failure(&self.fail_stores) {
+            return Err(injected_failure("TestPool store failure"));
+        }
+
+        self.sessions
+            .insert(id.to_owned(), (session.to_owned(), expires));
+        Ok(())
+    }
+
+    async fn load(&self, id: &str, _table_name: &str) -> Result<Option<String>, SessionError> {
+        self.delay().await;
+
+        if take_failure(&self.fail_loads) {
+            return Err(injected_failure("TestPool load failure"));
+        }
+
+        let now = self.now();
+        let session = self
+            .sessions
+            .get(id)
+            .filter(|entry| entry.1 >= now)
+            .map(|entry| entry.0.clone());
+
+        if session.is_some() && self.corrupt_loads.load(Ordering::SeqCst) {
+            return Ok(Some("\u{0}corrupted".to_owned()));
+        }
+
+        Ok(session)
+    }
+
+    async fn delete_one_by_id(&self, id: &str, _table_name: &str) -> Result<(), SessionError> {
+        self.delay().await;
+        self.sessions.remove(id);
+        Ok(())
+    }
+
+    async fn exists(&self, id: &str, _table_name: &str) -> Result<bool, SessionError> {
+        self.delay().await;
+        let now = self.now();
+        Ok(self.sessions.get(id).is_some_and(|entry| entry.1 >= now))
+    }
+
+    async fn delete_all(&self, _table_name: &str) -> Result<(), SessionError> {
+        self.delay().await;
+        self.sessions.clear();
+        Ok(())
+    }
+
+    async fn get_ids(&self, _table_name: &str) -> Result<Vec<String>, SessionError> {
+        self.delay().await;
+        let now = self.now();
+        Ok(self
+            .sessions
+            .iter()
+            .filter(|entry| entry.1 >= now)
+            .map(|entry| entry.key().clone())
+            .collect())
+    }
+
+    fn auto_handles_expiry(&self) -> bool {
+        false
+    }
+}
+
+/// Drives requests through an app wrapped in a `SessionLayer`, carrying the Session
+/// Cookies, or Headers in rest_mode, from each Response over to the next Request
+/// like a browser would.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::test_util::{test_store, TestClient};
+///
+/// let mut client = TestClient::with_session_store(test_store().await, app);
+/// client.get("/login").await;
+/// assert_eq!(client.get("/account").await.status(), StatusCode::OK);
+/// ```
+///
+/// Clones start with the same held values, like another tab racing the first.
+///
+#[derive(Clone)]
+pub struct TestClient<S> {
+    service: S,
+    /// Session Cookies or Headers held by the client keyed by name.
+    held: HashMap<String, String>,
+    #[cfg(feature = "rest_mode")]
+    header_names: Vec<HeaderName>,
+}
+
+impl<S> TestClient<S>
+where
+    S: Service<Request<Body>, Response = Response, Error = Infallible> + Clone,
+{
+    /// Wraps the app with a `SessionLayer` using the store.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let app = Router::new().route("/", get(handler));
+    /// let mut client = TestClient::with_session_store(session_store, app);
+    /// ```
+    ///
+    pub fn with_session_store<T, A>(store: SessionStore<T>, app: A) -> Self
+    where
+        T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+        SessionLayer<T>: Layer<A, Service = S>,
+    {
+        #[cfg(feature = "rest_mode")]
+        let header_names = [
+            &store.config.session_name,
+            &store.config.key_name,
+            &store.config.store_name,
+        ]
+        .iter()
+        .filter_map(|name| HeaderName::from_bytes(name.as_bytes()).ok())
+        .collect();
+
+        Self {
+            service: SessionLayer::new(store).layer(app),
+            held: HashMap::new(),
+            #[cfg(feature = "rest_mode")]
+            header_names,
+        }
+    }
+
+    /// Sends a GET Request for the uri. See `send`.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let response = client.get("/").await;
+    /// ```
+    ///
+    pub async fn get(&mut self, uri: &str) -> Response {
+        let request = Request::builder()
+            .uri(uri)
+            .body(Body::empty())
+            .expect("uri is not valid");
+
+        self.send(request).await
+    }
+
+    /// Sends the Request with the held Session Cookies or Headers and keeps the ones
+    /// set by the Response. Removal Cookies stop the client from sending the Cookie.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let response = client
+    ///     .send(Request::post("/logout").body(Body::empty()).unwrap())
+    ///     .await;
+    /// ```
+    ///
+    pub async fn send(&mut self, mut request: Request<Body>) -> Response {
+        #[cfg(not(feature = "rest_mode"))]
+        if !self.held.is_empty() {
+            let cookies = self
+                .held
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join("; ");
+
+            if let Ok(value) = cookies.parse() {
+                request.headers_mut().append(COOKIE, value);
+            }
+        }
+
+        #[cfg(feature = "rest_mode")]
+        for (name, value) in &self.held {
+            if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name.as_bytes()), value.parse())
+            {
+                request.headers_mut().insert(name, value);
+            }
+        }
+
+        let mut service = self.service.clone();
+        let response = match futures::future::poll_fn(|cx| service.poll_ready(cx)).await {
+            Ok(()) => service.call(request).await,
+            Err(err) => Err(err),
+        };
+        let response = match response {
+            Ok(response) => response,
+            Err(err) => match err {},
+        };
+
+        #[cfg(not(feature = "rest_mode"))]
+        for value in response.headers().get_all(SET_COOKIE) {
+            let Some(cookie) = value
+                .to_str()
+                .ok()
+                .and_then(|value| cookie::Cookie::parse(value.to_owned()).ok())
+            else {
+                continue;
+            };
+
+            if cookie.value().is_empty() || cookie.max_age() == Some(cookie::time::Duration::ZERO) {
+                self.held.remove(cookie.name());
+            } else {
+                self.held
+                    .insert(cookie.name().to_owned(), cookie.value().to_owned());
+            }
+        }
+
+        #[cfg(feature = "rest_mode")]
+        for name in &self.header_names {
+            if let Some(value) = response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+            {
+                self.held.insert(name.to_string(), value.to_owned());
+            }
+        }
+
+        response
+    }
+
+    /// Returns the held Session Cookie or Header value for the name.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let session_id = client.held("session");
+    /// ```
+    ///
+    pub fn held(&self, name: &str) -> Option<&str> {
+        self.held.get(name).map(String::as_str)
+    }
+
+    /// Forgets every held Session Cookie or Header, like a new browser.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// client.clear();
+    /// ```
+    ///
+    pub fn clear(&mut self) {
+        self.held.clear();
+    }
+}